@@ -7,7 +7,6 @@ use tracing_subscriber::layer::SubscriberExt as _;
 use tracing_subscriber::util::SubscriberInitExt as _;
 
 static BIOS: &[u8] = include_bytes!("../../bios/SCPH1001.BIN");
-const BIN_PATH: &str = "games\\Crash Bandicoot (USA)\\Crash Bandicoot (USA).bin";
 
 fn main() {
     let targets = tracing_subscriber::filter::Targets::new()
@@ -17,7 +16,8 @@ fn main() {
     tracing_subscriber::registry().with(fmt_layer).init();
 
     let mut psx = Psx::new(BIOS);
-    let bin_buffer = std::fs::read(BIN_PATH).expect("Failed to read BIN file");
+    let bin_path = std::env::args().nth(1).expect("Usage: psx-bench <disc.bin>");
+    let bin_buffer = std::fs::read(bin_path).expect("Failed to read BIN file");
     psx.load_cdrom(bin_buffer);
 
     let mut instruction_count = 0u64;
@@ -0,0 +1,36 @@
+//! Drives the CD-ROM controller through a sequence of register accesses, with time passing in between.
+//!
+//! The first byte decides whether a small blank disc is inserted, then each 2 byte chunk is an operation
+//! (register index in the low bits) and its operand.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use psx_core::cdrom::{CDROM_ADDR_START, Cdrom};
+use psx_core::mmu::bus::Bus8;
+
+const SECTOR_SIZE: usize = 2352;
+const DISC_SECTORS: usize = 32;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&disc, ops)) = data.split_first() else {
+        return;
+    };
+
+    let mut cdrom = Cdrom::new();
+    if disc & 1 != 0 {
+        cdrom.insert_disk(vec![0; SECTOR_SIZE * DISC_SECTORS]);
+    }
+
+    for op in ops.chunks_exact(2) {
+        let address = CDROM_ADDR_START + (op[0] & 0b11) as u32;
+
+        match op[0] >> 6 {
+            0 | 1 => cdrom.write_u8(address, op[1]),
+            2 => {
+                cdrom.read_u8(address);
+            }
+            _ => cdrom.tick(op[1] as usize * 0x1000),
+        }
+    }
+});
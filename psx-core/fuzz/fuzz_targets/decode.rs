@@ -0,0 +1,13 @@
+//! Decodes and disassembles every word of the input, whether or not it is a valid opcode.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use psx_core::cpu::decoder::Instruction;
+
+fuzz_target!(|data: &[u8]| {
+    for word in data.chunks_exact(4) {
+        let instruction = Instruction::decode(u32::from_le_bytes(word.try_into().unwrap()));
+        let _ = instruction.to_string();
+    }
+});
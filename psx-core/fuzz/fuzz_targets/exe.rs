@@ -0,0 +1,13 @@
+//! Parses the input as a PS-X EXE, malformed ones have to be rejected with an error.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use psx_core::exe::Exe;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(exe) = Exe::parse(data.to_vec()) {
+        assert!(exe.data.len() <= data.len());
+        let _ = (exe.sp(), exe.fp());
+    }
+});
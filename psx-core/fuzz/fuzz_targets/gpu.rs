@@ -0,0 +1,30 @@
+//! Feeds a stream of GP0/GP1 writes and GPUREAD/GPUSTAT reads to the GPU, the way the CPU and DMA reach it.
+//!
+//! Each 5 byte chunk is an operation selector followed by a little-endian word.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use psx_core::gpu::{GP0_ADDRESS_START, GP1_ADDRESS_START, Gpu};
+use psx_core::mmu::bus::Bus32;
+
+fuzz_target!(|data: &[u8]| {
+    let mut gpu = Gpu::new();
+
+    for chunk in data.chunks_exact(5) {
+        let word = u32::from_le_bytes(chunk[1..5].try_into().unwrap());
+
+        match chunk[0] & 0b11 {
+            0 => gpu.write_u32(GP0_ADDRESS_START, word),
+            1 => gpu.write_u32(GP1_ADDRESS_START, word),
+            2 => {
+                gpu.read_u32(GP0_ADDRESS_START);
+            }
+            _ => {
+                gpu.read_u32(GP1_ADDRESS_START);
+            }
+        }
+    }
+
+    let _ = gpu.display_frame();
+});
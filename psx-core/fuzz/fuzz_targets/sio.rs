@@ -0,0 +1,56 @@
+//! Talks to the controller and memory card ports through the SIO registers.
+//!
+//! The first 3 bytes pick the controller on each port and which ports have a multitap or memory card, then each
+//! 4 byte chunk is an operation, a register offset and a halfword operand.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use psx_core::mmu::bus::{Bus8, Bus16};
+use psx_core::sio::joy::ControllerType;
+use psx_core::sio::memcard::MemoryCard;
+use psx_core::sio::{SIO_ADDR_END, SIO_ADDR_START, Sio};
+
+const CONTROLLER_TYPES: [ControllerType; 6] = [
+    ControllerType::Digital,
+    ControllerType::DualShock,
+    ControllerType::Mouse,
+    ControllerType::Negcon,
+    ControllerType::Guncon,
+    ControllerType::Justifier,
+];
+
+fuzz_target!(|data: &[u8]| {
+    let Some((setup, ops)) = data.split_first_chunk::<3>() else {
+        return;
+    };
+
+    let mut sio = Sio::new();
+    for port in 0..2 {
+        sio.connect_multitap(port, setup[2] & (0b01 << port) != 0);
+        sio.connect_controller(port, 0, CONTROLLER_TYPES[setup[port] as usize % CONTROLLER_TYPES.len()]);
+        if setup[2] & (0b100 << port) != 0 {
+            sio.insert_memory_card(port, 0, MemoryCard::new());
+        }
+    }
+
+    let mut now = 0;
+    for op in ops.chunks_exact(4) {
+        let address = SIO_ADDR_START + (op[1] as u32 % (SIO_ADDR_END - SIO_ADDR_START + 1));
+        let value = u16::from_le_bytes([op[2], op[3]]);
+
+        match op[0] & 0b11 {
+            0 => sio.write_u16(address & !0b1, value),
+            1 => {
+                sio.read_u16(address & !0b1);
+            }
+            2 => {
+                sio.read_u8(address);
+            }
+            _ => {
+                now += value as u64;
+                sio.sync(now);
+            }
+        }
+    }
+});
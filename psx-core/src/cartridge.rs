@@ -0,0 +1,378 @@
+use crate::mmu::bus::Bus8;
+use std::path::PathBuf;
+
+crate::define_addr!(EXPANSION1_ADDR, 0x1F00_0000, 0, 0x80_0000, 0x80_0000);
+
+// Largest image that fits into expansion region 1
+pub const CARTRIDGE_MAX_SIZE: usize = 0x80_0000;
+
+// JEDEC command addresses, decoded on the low 15 address lines
+const FLASH_COMMAND_MASK: u32 = 0x7FFF;
+const FLASH_UNLOCK_ADDR_1: u32 = 0x5555;
+const FLASH_UNLOCK_ADDR_2: u32 = 0x2AAA;
+
+// Atmel AT29C010A as found on Action Replay and Xplorer carts
+const FLASH_MANUFACTURER_ID: u8 = 0x1F;
+const FLASH_DEVICE_ID: u8 = 0xD5;
+const FLASH_PAGE_SIZE: u32 = 128;
+
+// Vblanks without flash writes before the image is saved, so a firmware update is written once and not per frame
+const FLASH_FLUSH_IDLE_FRAMES: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlashMode {
+    Read,
+    Id,
+    PageLoad(Option<u32>), // Page being loaded, the load ends on a read or a write to another page
+}
+
+/// Cheat/development cartridge in expansion region 1, a ROM image on flash that can be reprogrammed
+///
+/// PSX-SPX: the BIOS calls into it when "Licensed by Sony Computer Entertainment Inc." is found at 1F000084h
+pub struct Cartridge {
+    data: Vec<u8>,
+    path: Option<PathBuf>,
+    mode: FlashMode,
+    unlock_step: u8, // Bytes of the AAh, 55h unlock sequence seen so far
+    erase_setup: bool,
+    protected: bool, // Software data protection, page writes need the unlock sequence while it is on
+    dirty: bool,
+    idle_frames: u32,
+}
+
+impl Cartridge {
+    /// Open the ROM image at `path`, flash writes are saved back to it
+    pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let mut cartridge = Self::from_image(std::fs::read(&path)?)?;
+        cartridge.path = Some(path);
+        Ok(cartridge)
+    }
+
+    /// Cartridge from a raw ROM image that only lives in memory
+    pub fn from_image(image: Vec<u8>) -> std::io::Result<Self> {
+        if image.is_empty() || image.len() > CARTRIDGE_MAX_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Cartridge image must be between 1 and {} bytes, got {} bytes",
+                    CARTRIDGE_MAX_SIZE,
+                    image.len()
+                ),
+            ));
+        }
+
+        Ok(Self {
+            data: image,
+            path: None,
+            mode: FlashMode::Read,
+            unlock_step: 0,
+            erase_setup: false,
+            protected: true,
+            dirty: false,
+            idle_frames: 0,
+        })
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Write the image back to its file if the flash was modified since the last flush
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.dirty = false;
+        match &self.path {
+            Some(path) => std::fs::write(path, &self.data),
+            None => Ok(()),
+        }
+    }
+
+    /// Called once per frame, flushes the image once the flash has not been written for a while
+    pub fn vblank(&mut self) -> std::io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.idle_frames += 1;
+        if self.idle_frames < FLASH_FLUSH_IDLE_FRAMES {
+            return Ok(());
+        }
+
+        self.flush()
+    }
+
+    fn erase(&mut self, start: u32, length: u32) {
+        let start = (start as usize).min(self.data.len());
+        let end = (start + length as usize).min(self.data.len());
+        self.data[start..end].fill(0xFF);
+        self.dirty = true;
+        self.idle_frames = 0;
+    }
+
+    /// Latch a byte of a page write. The chip rewrites the whole page, bytes that are not loaded read as FFh.
+    fn load(&mut self, offset: u32, value: u8) {
+        let page = offset / FLASH_PAGE_SIZE;
+        if self.mode != FlashMode::PageLoad(Some(page)) {
+            self.mode = FlashMode::PageLoad(Some(page));
+            self.erase(page * FLASH_PAGE_SIZE, FLASH_PAGE_SIZE);
+        }
+
+        if let Some(byte) = self.data.get_mut(offset as usize) {
+            *byte = value;
+        }
+        self.idle_frames = 0;
+    }
+
+    fn command(&mut self, offset: u32, value: u8) {
+        let address = offset & FLASH_COMMAND_MASK;
+
+        match self.unlock_step {
+            0 if address == FLASH_UNLOCK_ADDR_1 && value == 0xAA => self.unlock_step = 1,
+            1 if address == FLASH_UNLOCK_ADDR_2 && value == 0x55 => self.unlock_step = 2,
+            2 if address == FLASH_UNLOCK_ADDR_1 => {
+                self.unlock_step = 0;
+
+                if std::mem::take(&mut self.erase_setup) {
+                    match value {
+                        0x10 => {
+                            self.erase(0, self.data.len() as u32);
+                            tracing::debug!(target: "psx_core::cart", "Flash chip erased");
+                        }
+                        0x20 => self.protected = false,
+                        _ => {
+                            tracing::warn!(target: "psx_core::cart", value = %format!("{:02X}", value), "Unknown flash erase command")
+                        }
+                    }
+                    return;
+                }
+
+                match value {
+                    0x90 => self.mode = FlashMode::Id,
+                    0xF0 => self.mode = FlashMode::Read,
+                    // Also turns software data protection back on
+                    0xA0 => {
+                        self.protected = true;
+                        self.mode = FlashMode::PageLoad(None);
+                    }
+                    0x80 => self.erase_setup = true,
+                    _ => {
+                        tracing::warn!(target: "psx_core::cart", value = %format!("{:02X}", value), "Unknown flash command")
+                    }
+                }
+            }
+            _ => {
+                self.unlock_step = 0;
+                self.erase_setup = false;
+
+                // Without data protection any other write starts a page write, the command bytes are not stored
+                if !self.protected {
+                    self.load(offset, value);
+                    return;
+                }
+
+                tracing::debug!(
+                    target: "psx_core::cart",
+                    offset = %format!("{:06X}", offset),
+                    value = %format!("{:02X}", value),
+                    "Ignored write to protected flash"
+                );
+            }
+        }
+    }
+}
+
+impl Bus8 for Cartridge {
+    fn read_u8(&mut self, address: u32) -> u8 {
+        let offset = address - EXPANSION1_ADDR_START;
+
+        match self.mode {
+            FlashMode::Id => match offset {
+                0 => return FLASH_MANUFACTURER_ID,
+                1 => return FLASH_DEVICE_ID,
+                _ => {}
+            },
+            FlashMode::PageLoad(_) => self.mode = FlashMode::Read,
+            FlashMode::Read => {}
+        }
+
+        // Nothing drives the bus past the end of the image
+        self.data.get(offset as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_u8(&mut self, address: u32, value: u8) {
+        let offset = address - EXPANSION1_ADDR_START;
+
+        if let FlashMode::PageLoad(page) = self.mode {
+            if page.is_none_or(|page| page == offset / FLASH_PAGE_SIZE) {
+                self.load(offset, value);
+                return;
+            }
+
+            self.mode = FlashMode::Read;
+        }
+
+        self.command(offset, value);
+    }
+}
+
+impl Drop for Cartridge {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::error!(target: "psx_core::cart", error = %e, "Failed to flush cartridge image");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAGE_SIZE: usize = 0x2_0000;
+
+    fn cartridge() -> Cartridge {
+        Cartridge::from_image(vec![0; IMAGE_SIZE]).unwrap()
+    }
+
+    fn read(cartridge: &mut Cartridge, offset: u32) -> u8 {
+        cartridge.read_u8(EXPANSION1_ADDR_START + offset)
+    }
+
+    fn write(cartridge: &mut Cartridge, offset: u32, value: u8) {
+        cartridge.write_u8(EXPANSION1_ADDR_START + offset, value);
+    }
+
+    /// Unlock sequence followed by `value`
+    fn command(cartridge: &mut Cartridge, value: u8) {
+        write(cartridge, FLASH_UNLOCK_ADDR_1, 0xAA);
+        write(cartridge, FLASH_UNLOCK_ADDR_2, 0x55);
+        write(cartridge, FLASH_UNLOCK_ADDR_1, value);
+    }
+
+    #[test]
+    fn test_product_id() {
+        let mut cartridge = cartridge();
+
+        command(&mut cartridge, 0x90);
+        assert_eq!(read(&mut cartridge, 0), FLASH_MANUFACTURER_ID);
+        assert_eq!(read(&mut cartridge, 1), FLASH_DEVICE_ID);
+
+        command(&mut cartridge, 0xF0);
+        assert_eq!(read(&mut cartridge, 0), 0);
+        assert_eq!(read(&mut cartridge, 1), 0);
+    }
+
+    #[test]
+    fn test_protected_writes_are_ignored() {
+        let mut cartridge = cartridge();
+
+        write(&mut cartridge, 0x100, 0x12);
+        // Broken unlock sequence
+        write(&mut cartridge, FLASH_UNLOCK_ADDR_1, 0xAA);
+        write(&mut cartridge, 0x1234, 0x55);
+        write(&mut cartridge, FLASH_UNLOCK_ADDR_1, 0xA0);
+        write(&mut cartridge, 0x100, 0x12);
+
+        assert!(cartridge.data().iter().all(|&byte| byte == 0));
+        assert!(!cartridge.dirty);
+    }
+
+    #[test]
+    fn test_page_write_erases_bytes_not_loaded() {
+        let mut cartridge = cartridge();
+
+        command(&mut cartridge, 0xA0);
+        write(&mut cartridge, 0x105, 0x12);
+        write(&mut cartridge, 0x106, 0x34);
+        assert_eq!(read(&mut cartridge, 0x105), 0x12);
+
+        let page = &cartridge.data()[0x100..0x180];
+        assert_eq!(page[5..7], [0x12, 0x34]);
+        assert!(page[..5].iter().chain(&page[7..]).all(|&byte| byte == 0xFF));
+        assert_eq!(cartridge.data()[0x0FF], 0);
+        assert_eq!(cartridge.data()[0x180], 0);
+
+        // The read ended the page write
+        write(&mut cartridge, 0x107, 0x56);
+        assert_eq!(read(&mut cartridge, 0x107), 0xFF);
+    }
+
+    #[test]
+    fn test_page_write_ends_on_another_page() {
+        let mut cartridge = cartridge();
+
+        command(&mut cartridge, 0xA0);
+        write(&mut cartridge, 0x100, 0x12);
+        write(&mut cartridge, 0x200, 0x34);
+
+        assert_eq!(read(&mut cartridge, 0x100), 0x12);
+        assert_eq!(read(&mut cartridge, 0x200), 0);
+    }
+
+    #[test]
+    fn test_chip_erase() {
+        let mut cartridge = cartridge();
+
+        command(&mut cartridge, 0x80);
+        command(&mut cartridge, 0x10);
+
+        assert!(cartridge.data().iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn test_data_protection_off_and_on() {
+        let mut cartridge = cartridge();
+
+        command(&mut cartridge, 0x80);
+        command(&mut cartridge, 0x20);
+        write(&mut cartridge, 0x300, 0x12);
+        assert_eq!(read(&mut cartridge, 0x300), 0x12);
+        assert_eq!(read(&mut cartridge, 0x301), 0xFF);
+
+        // A protected page write turns the protection back on
+        command(&mut cartridge, 0xA0);
+        write(&mut cartridge, 0x400, 0x34);
+        assert_eq!(read(&mut cartridge, 0x400), 0x34);
+
+        write(&mut cartridge, 0x500, 0x56);
+        assert_eq!(read(&mut cartridge, 0x500), 0);
+    }
+
+    #[test]
+    fn test_flush_waits_for_idle_frames() {
+        let path = std::env::temp_dir().join(format!("psx-cartridge-{}.rom", std::process::id()));
+        std::fs::write(&path, vec![0; IMAGE_SIZE]).unwrap();
+        let saved = |offset: usize| std::fs::read(&path).unwrap()[offset];
+
+        let mut cartridge = Cartridge::open(&path).unwrap();
+        command(&mut cartridge, 0xA0);
+        write(&mut cartridge, 0x100, 0x12);
+
+        for _ in 1..FLASH_FLUSH_IDLE_FRAMES {
+            cartridge.vblank().unwrap();
+        }
+        assert_eq!(saved(0x100), 0);
+
+        // Another write restarts the wait
+        write(&mut cartridge, 0x101, 0x34);
+        cartridge.vblank().unwrap();
+        assert_eq!(saved(0x100), 0);
+
+        for _ in 1..FLASH_FLUSH_IDLE_FRAMES {
+            cartridge.vblank().unwrap();
+        }
+        assert_eq!(saved(0x100), 0x12);
+        assert_eq!(saved(0x101), 0x34);
+
+        // Dropping the cartridge saves what was not flushed yet
+        command(&mut cartridge, 0xA0);
+        write(&mut cartridge, 0x200, 0x56);
+        drop(cartridge);
+        assert_eq!(saved(0x200), 0x56);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
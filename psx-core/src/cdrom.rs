@@ -6,6 +6,7 @@ use crate::cdrom::reg::{
     AddressRegister, AdpCtlRegister, HChpCtl, HClrCtl, HIntMaskRegister, HIntSts, REG_ADDRESS_ADDR, REG_ADPCTL_ADDR, REG_ATV0_ADDR, REG_ATV1_ADDR, REG_ATV2_ADDR, REG_ATV3_ADDR, REG_CI_ADDR, REG_COMMAND_ADDR, REG_HCHPCTL_ADDR, REG_HCLRCTL_ADDR, REG_HINTMSK_ADDR_R, REG_HINTMSK_ADDR_W, REG_HINTSTS_ADDR, REG_HSTS_ADDR, REG_PARAMETER_ADDR, REG_RDDATA_ADDR, REG_RESULT_ADDR, SetModeRegister, StatusCode
 };
 use crate::mmu::bus::Bus8;
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
 use proc_bitfield::with_bits;
 use std::collections::VecDeque;
 
@@ -100,6 +101,8 @@ pub struct Cdrom {
     data_ready: bool,          // True when a sector has been fully read
 
     last_command: u8, // Last executed command (0x06=ReadN, 0x1B=ReadS, etc.)
+
+    last_sync: u64, // Scheduler timestamp the drive was last ticked at
 }
 
 impl Cdrom {
@@ -129,6 +132,33 @@ impl Cdrom {
             sector_lba_current: 0,
             data_ready: false,
             last_command: 0,
+            last_sync: 0,
+        }
+    }
+
+    /// Catch the drive up to the scheduler timestamp `now`
+    pub fn sync(&mut self, now: u64) {
+        let cycles = now.saturating_sub(self.last_sync) as usize;
+        self.last_sync = now;
+        self.tick(cycles);
+    }
+
+    /// CPU cycles until the drive needs to be ticked again, either to finish a seek or to fire the next interrupt
+    pub fn cycles_until_event(&self) -> Option<u64> {
+        let seek = match self.state {
+            DriveState::Seeking { cycles_left } => Some(cycles_left as u64),
+            _ => None,
+        };
+
+        // The next interrupt only starts counting down once the current one was acknowledged
+        let interrupt = match self.hintsts.irq_flags() {
+            DiskIrq::NoIrq => self.interrupt_queue.front().map(|pending| pending.cycles_until_fire as u64),
+            _ => None,
+        };
+
+        match (seek, interrupt) {
+            (Some(seek), Some(interrupt)) => Some(seek.min(interrupt)),
+            (seek, interrupt) => seek.or(interrupt),
         }
     }
 
@@ -225,6 +255,11 @@ impl Cdrom {
         }
     }
 
+    /// DREQ for DMA3, set while sector data is waiting in the data FIFO
+    pub fn data_request(&self) -> bool {
+        self.address.data_request()
+    }
+
     pub fn check_and_clear_irq(&mut self) -> bool {
         let irq = self.hintsts.irq_flags();
         if irq != DiskIrq::NoIrq && self.hintmsk.enable_irq_on_intsts() & irq as u8 != 0 {
@@ -363,6 +398,23 @@ impl Cdrom {
         // Track the command for later reference (e.g., ReadS vs ReadN)
         self.last_command = command;
 
+        if self.parameter_fifo.len() < Self::required_parameters(command) {
+            tracing::error!(
+                target: "psx_core::cdrom",
+                command = format!("{:02X}", command),
+                parameters = self.parameter_fifo.len(),
+                "CDROM command is missing parameters",
+            );
+
+            let mut error_stat = self.status();
+            error_stat.set_error(true);
+            let response = vec![error_stat.0, ERROR_WRONG_NUMBER_OF_PARAMETERS];
+            self.queue_interrupt(DiskIrq::DiskError, response, FIRST_RESP_GENERIC_DELAY, false);
+
+            self.parameter_fifo.clear();
+            return;
+        }
+
         match command {
             // 0x01 	GetStat 	INT3: status
             0x01 => {
@@ -468,6 +520,17 @@ impl Cdrom {
         self.parameter_fifo.clear();
     }
 
+    /// Number of parameter bytes a command pops from the parameter FIFO.
+    fn required_parameters(command: u8) -> usize {
+        match command {
+            // Setloc
+            0x02 => 3,
+            // Setmode, Test
+            0x0E | 0x19 => 1,
+            _ => 0,
+        }
+    }
+
     fn execute_subcommand(&mut self, subcommand: u8) {
         match subcommand {
             // 20h      -   INT3(yy,mm,dd,ver) ;Get cdrom BIOS date/version (yy,mm,dd,ver)
@@ -614,7 +677,8 @@ impl Cdrom {
         let seconds = ((seconds >> 4) * 10) + (seconds & 0x0F);
         let frames = ((frames >> 4) * 10) + (frames & 0x0F);
 
-        let block_addr = (((minutes * 60) + seconds) * 75 + frames) - 150;
+        // Positions inside the 2 second pregap have no data sector, clamp them to the first one
+        let block_addr = (((minutes * 60) + seconds) * 75 + frames).saturating_sub(150);
 
         self.sector_lba = block_addr;
         self.sector_lba_current = block_addr + SETLOC_CURRENT_LBA_OFFSET;
@@ -915,6 +979,11 @@ impl Cdrom {
     fn disk_inserted(&self) -> bool {
         !self.cdrom_bin.is_empty()
     }
+
+    /// Identifies the inserted disc image, None if the drive is empty
+    pub(crate) fn disk_checksum(&self) -> Option<u64> {
+        self.disk_inserted().then(|| crate::savestate::checksum(&self.cdrom_bin))
+    }
 }
 
 impl Bus8 for Cdrom {
@@ -1111,3 +1180,97 @@ impl Bus8 for Cdrom {
         }
     }
 }
+
+impl State for PendingInterrupt {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write::<u8>(&self.irq.into());
+        writer.write(&self.response);
+        writer.write(&self.cycles_until_fire);
+        writer.write(&self.is_read);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        Ok(Self {
+            irq: DiskIrq::from(reader.read::<u8>()?),
+            response: reader.read()?,
+            cycles_until_fire: reader.read()?,
+            is_read: reader.read()?,
+        })
+    }
+}
+
+impl State for DriveState {
+    fn write(&self, writer: &mut StateWriter) {
+        match self {
+            DriveState::Idle => writer.write(&0u8),
+            DriveState::Seeking { cycles_left } => {
+                writer.write(&1u8);
+                writer.write(cycles_left);
+            }
+            DriveState::Reading => writer.write(&2u8),
+            DriveState::Playing => writer.write(&3u8),
+        }
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<u8>()? {
+            0 => Ok(DriveState::Idle),
+            1 => Ok(DriveState::Seeking {
+                cycles_left: reader.read()?,
+            }),
+            2 => Ok(DriveState::Reading),
+            3 => Ok(DriveState::Playing),
+            _ => Err(SavestateError::Invalid("drive state out of range")),
+        }
+    }
+}
+
+/// The inserted disc is media and stays in the drive
+impl Savestate for Cdrom {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.address.0);
+        writer.write(&self.adpctl.0);
+        writer.write(&self.hintmsk.0);
+        writer.write(&self.hclrctl.0);
+        writer.write(&self.hintsts.0);
+        writer.write(&self.hchpctl.0);
+        writer.write(&[self.atv0, self.atv1, self.atv2, self.atv3, self.ci]);
+        writer.write(&self.parameter_fifo);
+        writer.write(&self.result_fifo);
+        writer.write(&self.interrupt_queue);
+        writer.write(&self.read_in_progress);
+        writer.write(&self.state);
+        writer.write(&self.mode.0);
+        writer.write(&self.sector_offset);
+        writer.write(&self.subheader);
+        writer.write(&self.sector_lba);
+        writer.write(&self.sector_lba_current);
+        writer.write(&self.data_ready);
+        writer.write(&self.last_command);
+        writer.write(&self.last_sync);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.address.0 = reader.read()?;
+        self.adpctl.0 = reader.read()?;
+        self.hintmsk.0 = reader.read()?;
+        self.hclrctl.0 = reader.read()?;
+        self.hintsts.0 = reader.read()?;
+        self.hchpctl.0 = reader.read()?;
+        [self.atv0, self.atv1, self.atv2, self.atv3, self.ci] = reader.read()?;
+        self.parameter_fifo = reader.read()?;
+        self.result_fifo = reader.read()?;
+        self.interrupt_queue = reader.read()?;
+        self.read_in_progress = reader.read()?;
+        self.state = reader.read()?;
+        self.mode.0 = reader.read()?;
+        self.sector_offset = reader.read()?;
+        self.subheader = reader.read()?;
+        self.sector_lba = reader.read()?;
+        self.sector_lba_current = reader.read()?;
+        self.data_ready = reader.read()?;
+        self.last_command = reader.read()?;
+        self.last_sync = reader.read()?;
+        Ok(())
+    }
+}
@@ -4,11 +4,13 @@ pub mod interpreter;
 pub mod internal;
 pub mod lut;
 
-use crate::cpu::cop::cop0::{Cop0, Exception};
+use crate::cpu::cop::cop0::{Cop0, Exception, HardwareBreakpoint};
 use crate::cpu::cop::cop2::Cop2;
 use crate::cpu::decoder::Instruction;
 use crate::mmu::Mmu;
 use crate::mmu::bus::{Bus8 as _, Bus16 as _, Bus32 as _};
+use crate::pcdrv::Pcdrv;
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
 
 pub struct Cpu {
     pub pc: u32,
@@ -21,8 +23,11 @@ pub struct Cpu {
     pub cop0: Cop0,                       // COP0 registers
     pub cop2: Cop2,                       // COP2 registers
     pub mmu: Mmu,
-    pub cycles: usize,      // Number of cycles executed
-    exception_raised: bool, // Indicates if an exception has been raised
+    pub cycles: usize,                                   // Number of cycles executed
+    exception_raised: bool,                              // Indicates if an exception has been raised
+    pub hardware_breakpoint: Option<HardwareBreakpoint>, // Last COP0 breakpoint that trapped, taken by debuggers
+    pending_data_break: Option<HardwareBreakpoint>,      // A load/store of this instruction hit a data breakpoint
+    pub pcdrv: Option<Pcdrv>,                            // Serves PCdrv BREAKs from the host when set
 }
 
 impl Cpu {
@@ -39,12 +44,16 @@ impl Cpu {
             cop2: Cop2::new(),
             mmu: Mmu::new(),
             exception_raised: false,
+            pending_data_break: None,
+            hardware_breakpoint: None,
+            pcdrv: None,
             cycles: 0,
         }
     }
 
     pub fn tick(&mut self) -> Result<Instruction, ()> {
         self.check_interrupts();
+        self.check_execution_breakpoint();
 
         if let Some(handler) = internal::cpu_hooks().get(&self.pc) {
             handler(self);
@@ -72,7 +81,13 @@ impl Cpu {
             // Process pending load, or mark it for the next instruction
             self.process_pending_load();
 
-            self.pc = branch_target; // Set PC to the scheduled branch address
+            // An exception of the delay slot wins over the branch, EPC already points at the branch
+            self.dispatch_data_breakpoint(true);
+            if self.exception_raised {
+                self.exception_raised = false;
+            } else {
+                self.pc = branch_target; // Set PC to the scheduled branch address
+            }
 
             return if failed {
                 Err(())
@@ -95,6 +110,8 @@ impl Cpu {
         // Process pending load, or mark it for the next instruction
         self.process_pending_load();
 
+        self.dispatch_data_breakpoint(instr.is_delay_slot);
+
         // Do not progress PC if we just raised an exception
         if self.exception_raised {
             self.exception_raised = false;
@@ -110,6 +127,18 @@ impl Cpu {
     }
 
     pub fn cause_exception(&mut self, exception: Exception, is_delay_slot: bool) {
+        self.raise_exception(exception, is_delay_slot, false);
+    }
+
+    /// COP0 breakpoints raise a Breakpoint exception through the debug vector instead of the general one
+    fn cause_debug_exception(&mut self, breakpoint: HardwareBreakpoint, is_delay_slot: bool) {
+        tracing::debug!(target: "psx_core::cpu", pc = %format!("{:08X}", self.pc), %breakpoint, "COP0 breakpoint hit");
+
+        self.hardware_breakpoint = Some(breakpoint);
+        self.raise_exception(Exception::Breakpoint, is_delay_slot, true);
+    }
+
+    fn raise_exception(&mut self, exception: Exception, is_delay_slot: bool, is_debug: bool) {
         tracing::debug!(target: "psx_core::cpu", "Exception occurred: {}", &exception);
 
         self.exception_raised = true;
@@ -145,11 +174,12 @@ impl Cpu {
         //   UTLB Miss     80000000h     BFC00100h   (Virtual memory, none such in PSX)
         //   COP0 Break    80000040h     BFC00140h   (Debug Break)
         //   General       80000080h     BFC00180h   (General Interrupts & Exceptions)
-        if !self.cop0.sr.boot_exception_vector_location() {
-            self.pc = 0x8000_0080;
-        } else {
-            self.pc = 0xBFC0_0180;
-        }
+        self.pc = match (self.cop0.sr.boot_exception_vector_location(), is_debug) {
+            (false, false) => 0x8000_0080,
+            (true, false) => 0xBFC0_0180,
+            (false, true) => 0x8000_0040,
+            (true, true) => 0xBFC0_0140,
+        };
     }
 
     pub fn restore_from_exception(&mut self) {
@@ -181,7 +211,49 @@ impl Cpu {
         }
     }
 
+    /// Trap before fetching the instruction at PC if it matches BPC
+    fn check_execution_breakpoint(&mut self) {
+        // An interrupt was just taken, the breakpoint is checked again once the handler returns
+        if self.exception_raised || !self.cop0.check_execution_breakpoint(self.pc) {
+            return;
+        }
+
+        let in_delay_slot = self.delay_slot.is_some();
+        self.cause_debug_exception(HardwareBreakpoint::Execute(self.pc), in_delay_slot);
+
+        // The handler is fetched right away, so it has to advance normally
+        self.exception_raised = false;
+    }
+
+    #[inline(always)]
+    fn check_data_breakpoint(&mut self, address: u32, is_write: bool) {
+        if self.cop0.check_data_breakpoint(address, is_write) && self.pending_data_break.is_none() {
+            self.pending_data_break = Some(if is_write {
+                HardwareBreakpoint::Write(address)
+            } else {
+                HardwareBreakpoint::Read(address)
+            });
+        }
+    }
+
+    /// Data breakpoints trap once the instruction that accessed memory has finished
+    fn dispatch_data_breakpoint(&mut self, is_delay_slot: bool) -> bool {
+        let Some(breakpoint) = self.pending_data_break.take() else {
+            return false;
+        };
+
+        // Another exception of the same instruction takes precedence
+        if self.exception_raised {
+            return false;
+        }
+
+        self.cause_debug_exception(breakpoint, is_delay_slot);
+        true
+    }
+
     pub fn write_u8(&mut self, address: u32, value: u8) {
+        self.check_data_breakpoint(address, true);
+
         if self.cop0.sr.isolate_cache() {
             return;
         }
@@ -190,6 +262,8 @@ impl Cpu {
     }
 
     pub fn write_u16(&mut self, address: u32, value: u16) {
+        self.check_data_breakpoint(address, true);
+
         if self.cop0.sr.isolate_cache() {
             return;
         }
@@ -198,6 +272,8 @@ impl Cpu {
     }
 
     pub fn write_u32(&mut self, address: u32, value: u32) {
+        self.check_data_breakpoint(address, true);
+
         if self.cop0.sr.isolate_cache() {
             return;
         }
@@ -206,14 +282,17 @@ impl Cpu {
     }
 
     pub fn read_u8(&mut self, address: u32) -> u8 {
+        self.check_data_breakpoint(address, false);
         self.mmu.read_u8(address)
     }
 
     pub fn read_u16(&mut self, address: u32) -> u16 {
+        self.check_data_breakpoint(address, false);
         self.mmu.read_u16(address)
     }
 
     pub fn read_u32(&mut self, address: u32) -> u32 {
+        self.check_data_breakpoint(address, false);
         self.mmu.read_u32(address)
     }
 
@@ -298,6 +377,49 @@ impl Cpu {
     }
 }
 
+/// The bus is saved separately, PCdrv keeps its host files open across loads
+impl Savestate for Cpu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.pc);
+        writer.write(&self.registers);
+        writer.write(&self.hi);
+        writer.write(&self.lo);
+        writer.write(&self.load_delay);
+        writer.write(&self.load_delay_pending);
+        writer.write(&self.delay_slot.map(|(instruction, branch_target)| (instruction.raw, branch_target)));
+        self.cop0.save_state(writer);
+        self.cop2.save_state(writer);
+        writer.write(&self.cycles);
+        writer.write(&self.exception_raised);
+        writer.write(&self.hardware_breakpoint);
+        writer.write(&self.pending_data_break);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.pc = reader.read()?;
+        self.registers = reader.read()?;
+        self.hi = reader.read()?;
+        self.lo = reader.read()?;
+        self.load_delay = reader.read()?;
+        self.load_delay_pending = reader.read()?;
+        self.delay_slot = reader
+            .read::<Option<(u32, u32)>>()?
+            .map(|(raw, branch_target)| (Instruction::decode(raw), branch_target));
+        self.cop0.load_state(reader)?;
+        self.cop2.load_state(reader)?;
+        self.cycles = reader.read()?;
+        self.exception_raised = reader.read()?;
+        self.hardware_breakpoint = reader.read()?;
+        self.pending_data_break = reader.read()?;
+
+        if self.load_delay.is_some_and(|(register, _)| register >= 32) {
+            return Err(SavestateError::Invalid("load delay register"));
+        }
+
+        Ok(())
+    }
+}
+
 impl std::fmt::Display for Cpu {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
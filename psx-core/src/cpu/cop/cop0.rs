@@ -1,5 +1,6 @@
 use crate::cpu::cop::Cop;
-use crate::cpu::cop::registers::{CauseRegister, StatusRegister};
+use crate::cpu::cop::registers::{CauseRegister, DebugControlRegister, StatusRegister};
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
 
 pub const COP0_BPC: u32 = 3; // Breakpoint Program Counter
 pub const COP0_BDA: u32 = 5; // Breakpoint Data Address
@@ -72,19 +73,60 @@ impl std::fmt::Display for Exception {
     }
 }
 
+/// A COP0 breakpoint that matched, along with the address it matched on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HardwareBreakpoint {
+    Execute(u32),
+    Read(u32),
+    Write(u32),
+}
+
+impl std::fmt::Display for HardwareBreakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HardwareBreakpoint::Execute(address) => write!(f, "Execution breakpoint at {:08X}", address),
+            HardwareBreakpoint::Read(address) => write!(f, "Data read breakpoint at {:08X}", address),
+            HardwareBreakpoint::Write(address) => write!(f, "Data write breakpoint at {:08X}", address),
+        }
+    }
+}
+
+impl State for HardwareBreakpoint {
+    fn write(&self, writer: &mut StateWriter) {
+        let (kind, address) = match *self {
+            HardwareBreakpoint::Execute(address) => (0u8, address),
+            HardwareBreakpoint::Read(address) => (1, address),
+            HardwareBreakpoint::Write(address) => (2, address),
+        };
+        writer.write(&kind);
+        writer.write(&address);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        let kind = reader.read::<u8>()?;
+        let address = reader.read()?;
+        match kind {
+            0 => Ok(HardwareBreakpoint::Execute(address)),
+            1 => Ok(HardwareBreakpoint::Read(address)),
+            2 => Ok(HardwareBreakpoint::Write(address)),
+            _ => Err(SavestateError::Invalid("COP0 breakpoint kind")),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Cop0 {
-    pub bpc: u32,             // Breakpoint Program Counter
-    pub bda: u32,             // Breakpoint Data Address
-    pub tar: u32,             // Target Address
-    pub dcic: u32,            // Debug and Cache Invalidate Control
-    pub bad_a: u32,           // Bad Address
-    pub bdam: u32,            // Breakpoint Data Address Mask
-    pub bdcm: u32,            // Breakpoint Program Counter Mask
-    pub sr: StatusRegister,   // Status Register
-    pub cause: CauseRegister, // Cause Register
-    pub epc: u32,             // Exception Program Counter
-    pub prid: u32,            // Processor Revision ID
+    pub bpc: u32,                   // Breakpoint Program Counter
+    pub bda: u32,                   // Breakpoint Data Address
+    pub tar: u32,                   // Target Address
+    pub dcic: DebugControlRegister, // Debug and Cache Invalidate Control
+    pub bad_a: u32,                 // Bad Address
+    pub bdam: u32,                  // Breakpoint Data Address Mask
+    pub bdcm: u32,                  // Breakpoint Program Counter Mask
+    pub sr: StatusRegister,         // Status Register
+    pub cause: CauseRegister,       // Cause Register
+    pub epc: u32,                   // Exception Program Counter
+    pub prid: u32,                  // Processor Revision ID
 }
 
 impl Cop0 {
@@ -93,7 +135,7 @@ impl Cop0 {
             bpc: 0,
             bda: 0,
             tar: 0,
-            dcic: 0,
+            dcic: DebugControlRegister(0),
             bad_a: 0,
             bdam: 0,
             bdcm: 0,
@@ -103,6 +145,39 @@ impl Cop0 {
             prid: 0,
         }
     }
+
+    /// PSX-SPX: "Execution breakpoint: (PC XOR BPC) AND BPCM = 0", returns true if the CPU has to trap
+    pub fn check_execution_breakpoint(&mut self, pc: u32) -> bool {
+        if !self.dcic.execution_breakpoints_enabled() || (pc ^ self.bpc) & self.bdcm != 0 {
+            return false;
+        }
+
+        self.dcic.set_any_break(true);
+        self.dcic.set_code_break(true);
+        true
+    }
+
+    /// PSX-SPX: "Data breakpoint: (addr XOR BDA) AND BDAM = 0", returns true if the CPU has to trap
+    pub fn check_data_breakpoint(&mut self, address: u32, is_write: bool) -> bool {
+        let enabled = if is_write {
+            self.dcic.data_write_breakpoints_enabled()
+        } else {
+            self.dcic.data_read_breakpoints_enabled()
+        };
+
+        if !enabled || (address ^ self.bda) & self.bdam != 0 {
+            return false;
+        }
+
+        self.dcic.set_any_break(true);
+        self.dcic.set_data_break(true);
+        if is_write {
+            self.dcic.set_data_write_break(true);
+        } else {
+            self.dcic.set_data_read_break(true);
+        }
+        true
+    }
 }
 
 impl Cop for Cop0 {
@@ -112,7 +187,7 @@ impl Cop for Cop0 {
             3 => self.bpc,
             5 => self.bda,
             6 => self.tar,
-            7 => self.dcic,
+            7 => self.dcic.0,
             8 => self.bad_a,
             9 => self.bdam,
             11 => self.bdcm,
@@ -130,7 +205,7 @@ impl Cop for Cop0 {
             3 => self.bpc = value,
             5 => self.bda = value,
             6 => self.tar = value,
-            7 => self.dcic = value,
+            7 => self.dcic.0 = value,
             8 => self.bad_a = value,
             9 => self.bdam = value,
             11 => self.bdcm = value,
@@ -142,3 +217,34 @@ impl Cop for Cop0 {
         }
     }
 }
+
+impl Savestate for Cop0 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.bpc);
+        writer.write(&self.bda);
+        writer.write(&self.tar);
+        writer.write(&self.dcic.0);
+        writer.write(&self.bad_a);
+        writer.write(&self.bdam);
+        writer.write(&self.bdcm);
+        writer.write(&self.sr.0);
+        writer.write(&self.cause.0);
+        writer.write(&self.epc);
+        writer.write(&self.prid);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.bpc = reader.read()?;
+        self.bda = reader.read()?;
+        self.tar = reader.read()?;
+        self.dcic.0 = reader.read()?;
+        self.bad_a = reader.read()?;
+        self.bdam = reader.read()?;
+        self.bdcm = reader.read()?;
+        self.sr.0 = reader.read()?;
+        self.cause.0 = reader.read()?;
+        self.epc = reader.read()?;
+        self.prid = reader.read()?;
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use crate::cpu::cop::Cop;
 use crate::gteidx;
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
 
 #[derive(Clone, Copy)]
 pub struct Cop2 {
@@ -854,3 +855,14 @@ impl Cop for Cop2 {
         }
     }
 }
+
+impl Savestate for Cop2 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.registers);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.registers = reader.read()?;
+        Ok(())
+    }
+}
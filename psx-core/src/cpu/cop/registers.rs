@@ -31,3 +31,48 @@ bitfield! {
         pub branch_delay: bool @ 31,
     }
 }
+
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct DebugControlRegister(pub u32): Debug, FromStorage, IntoStorage, DerefStorage {
+        pub any_break: bool @ 0,
+        pub code_break: bool @ 1,
+        pub data_break: bool @ 2,
+        pub data_read_break: bool @ 3,
+        pub data_write_break: bool @ 4,
+        pub jump_break: bool @ 5,
+        pub super_master_enable_1: bool @ 23,
+        pub execution_breakpoint_enable: bool @ 24,
+        pub data_breakpoint_enable: bool @ 25,
+        pub break_on_data_read: bool @ 26,
+        pub break_on_data_write: bool @ 27,
+        pub break_on_jump: bool @ 28,
+        pub jump_master_enable: bool @ 29,
+        pub master_enable: bool @ 30,
+        pub super_master_enable_2: bool @ 31,
+    }
+}
+
+impl DebugControlRegister {
+    /// PSX-SPX: "23 Super-Master Enable 1 for bit24-29", "30 Master Enable for Bit24-27",
+    /// "31 Super-Master Enable 2 for bit24-29"
+    #[inline(always)]
+    fn breakpoints_enabled(&self) -> bool {
+        self.super_master_enable_1() && self.super_master_enable_2() && self.master_enable()
+    }
+
+    #[inline(always)]
+    pub fn execution_breakpoints_enabled(&self) -> bool {
+        self.breakpoints_enabled() && self.execution_breakpoint_enable()
+    }
+
+    #[inline(always)]
+    pub fn data_read_breakpoints_enabled(&self) -> bool {
+        self.breakpoints_enabled() && self.data_breakpoint_enable() && self.break_on_data_read()
+    }
+
+    #[inline(always)]
+    pub fn data_write_breakpoints_enabled(&self) -> bool {
+        self.breakpoints_enabled() && self.data_breakpoint_enable() && self.break_on_data_write()
+    }
+}
@@ -50,7 +50,11 @@ fn bios_function_call_c(cpu: &mut Cpu) {
 }
 
 fn bios_putchar(cpu: &mut Cpu) {
-    let mut value = cpu.read_register(crate::regidx("$a0")) as u8 as char;
+    tty_putchar(cpu.read_register(crate::regidx("$a0")) as u8 as char);
+}
+
+/// Append a character to the TTY, complete lines are logged
+pub(crate) fn tty_putchar(mut value: char) {
     if value == '\r' {
         value = '\n';
     }
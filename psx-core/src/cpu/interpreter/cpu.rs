@@ -483,6 +483,18 @@ pub fn system_call(instr: &Instruction, cpu: &mut Cpu) {
 }
 
 pub fn debug_break(instr: &Instruction, cpu: &mut Cpu) {
+    // PCdrv calls are BREAKs with a code, they return like a function call instead of trapping
+    if let Some(mut pcdrv) = cpu.pcdrv.take() {
+        let code = (instr.raw >> 6) & 0xF_FFFF;
+        let handled = pcdrv.handle(code, cpu);
+        cpu.pcdrv = Some(pcdrv);
+
+        if handled {
+            cpu.add_cycles(1);
+            return;
+        }
+    }
+
     cpu.cause_exception(Exception::Breakpoint, instr.is_delay_slot);
     cpu.add_cycles(1);
 }
@@ -47,15 +47,9 @@ fn gte_divide(cpu: &mut Cpu, numerator: u32, denominator: u32) -> u32 {
     let r3 = ((0x80u32.wrapping_sub(r2 * ((r1 as u32) + 0x8000))) >> 8) & 0x1FFFF;
     let reciprocal = ((r2 * r3) + 0x80) >> 8;
 
-    let mut res = ((reciprocal as u64 * ((numerator << shift) as u64) + 0x8000) >> 16) as u32;
+    let res = ((reciprocal as u64 * ((numerator << shift) as u64) + 0x8000) >> 16) as u32;
 
     // Some divisions result in values > 0x1FFFF but are saturated without setting FLAG
-    if res > 0x1FFFF {
-        let flag = cpu.cop2.flag();
-        cpu.cop2.set_flag(flag | (1 << 17));
-        res = 0x1FFFF;
-    }
-
     res.min(0x1FFFF)
 }
 
@@ -92,13 +86,30 @@ fn set_mac_flag(cpu: &mut Cpu, mac_index: usize, result: i64) {
     cpu.cop2.set_flag(new_flag);
 }
 
+// Wrap a MAC1-3 partial sum to the 44 bits the hardware keeps
+fn sign_extend_44(value: i64) -> i64 {
+    (value << 20) >> 20
+}
+
+// Add up terms like MAC1-3 do, flagging overflow and wrapping after every addition
+fn accumulate(cpu: &mut Cpu, mac_index: usize, initial: i64, terms: &[i64]) -> i64 {
+    let mut sum = initial;
+    for &term in terms {
+        sum += term;
+        set_mac_flag(cpu, mac_index, sum);
+        sum = sign_extend_44(sum);
+    }
+    sum
+}
+
 // Limit A: MAC1-3 to IR1-3 with saturation
 fn lim_a(cpu: &mut Cpu, result: i64, saturation_bit: u32, lm: bool) -> i16 {
     let flag = cpu.cop2.flag();
     let limit = if lm { 0 } else { 1 };
 
     let mut new_flag = flag;
-    let mut value = result;
+    // IR is saturated from MAC, which only keeps 32 bits
+    let mut value = result as i32 as i64;
 
     if value < -0x8000 * limit {
         value = -0x8000 * limit;
@@ -112,33 +123,17 @@ fn lim_a(cpu: &mut Cpu, result: i64, saturation_bit: u32, lm: bool) -> i16 {
     value as i16
 }
 
-// Limit A with shift fraction checking
+// Limit A for IR3 of RTPS/RTPT, saturates MAC3 but sets the flag from the unshifted sum SAR 12
 fn lim_a_sf(cpu: &mut Cpu, result: i64, saturation_bit: u32, lm: bool, sf: u32) -> i16 {
     let flag = cpu.cop2.flag();
     let limit = if lm { 0 } else { 1 };
-    let mut new_flag = flag;
 
-    // Check overflow before shift
-    if (result >> 12) < -0x8000 {
-        new_flag |= 1 << saturation_bit;
-    } else if (result >> 12) > 0x7FFF {
-        new_flag |= 1 << saturation_bit;
+    let z = result >> 12;
+    if !(-0x8000..=0x7FFF).contains(&z) {
+        cpu.cop2.set_flag(flag | (1 << saturation_bit));
     }
 
-    let mut value = result;
-
-    if value < -0x8000 * limit {
-        value = -0x8000 * limit;
-        if sf != 0 {
-            new_flag |= 1 << saturation_bit;
-        }
-    } else if value > 0x7FFF {
-        value = 0x7FFF;
-        new_flag |= 1 << saturation_bit;
-    }
-
-    cpu.cop2.set_flag(new_flag);
-    value as i16
+    clamp_i64((result >> sf) as i32 as i64, -0x8000 * limit, 0x7FFF) as i16
 }
 
 // Limit B: MAC0 to IR0 (0-255)
@@ -381,7 +376,7 @@ impl Matrix {
     }
 
     fn from_reserved(cpu: &Cpu) -> Self {
-        let (_, r13) = cpu.cop2.r13r21();
+        let (r13, _) = cpu.cop2.r13r21();
         let (r22, _) = cpu.cop2.r22r23();
         let (r, _, _, _) = cpu.cop2.rgbc();
         let ir0 = cpu.cop2.ir0();
@@ -402,31 +397,9 @@ impl Matrix {
     }
 
     fn multiply(&self, v: &Vec3, tr: &Vec3, cpu: &mut Cpu) -> Vec3 {
-        let sum_x = tr.x + self.m[0] * v.x + self.m[1] * v.y + self.m[2] * v.z;
-        let sum_y = tr.y + self.m[3] * v.x + self.m[4] * v.y + self.m[5] * v.z;
-        let sum_z = tr.z + self.m[6] * v.x + self.m[7] * v.y + self.m[8] * v.z;
-
-        // Set MAC flags for intermediate calculations
-        let mac1_1 = tr.x + self.m[0] * v.x;
-        let mac1_2 = mac1_1 + self.m[1] * v.y;
-        let mac1_3 = mac1_2 + self.m[2] * v.z;
-        set_mac_flag(cpu, 1, mac1_1);
-        set_mac_flag(cpu, 1, mac1_2);
-        set_mac_flag(cpu, 1, mac1_3);
-
-        let mac2_1 = tr.y + self.m[3] * v.x;
-        let mac2_2 = mac2_1 + self.m[4] * v.y;
-        let mac2_3 = mac2_2 + self.m[5] * v.z;
-        set_mac_flag(cpu, 2, mac2_1);
-        set_mac_flag(cpu, 2, mac2_2);
-        set_mac_flag(cpu, 2, mac2_3);
-
-        let mac3_1 = tr.z + self.m[6] * v.x;
-        let mac3_2 = mac3_1 + self.m[7] * v.y;
-        let mac3_3 = mac3_2 + self.m[8] * v.z;
-        set_mac_flag(cpu, 3, mac3_1);
-        set_mac_flag(cpu, 3, mac3_2);
-        set_mac_flag(cpu, 3, mac3_3);
+        let sum_x = accumulate(cpu, 1, tr.x, &[self.m[0] * v.x, self.m[1] * v.y, self.m[2] * v.z]);
+        let sum_y = accumulate(cpu, 2, tr.y, &[self.m[3] * v.x, self.m[4] * v.y, self.m[5] * v.z]);
+        let sum_z = accumulate(cpu, 3, tr.z, &[self.m[6] * v.x, self.m[7] * v.y, self.m[8] * v.z]);
 
         Vec3::new(sum_x, sum_y, sum_z)
     }
@@ -453,11 +426,7 @@ pub fn gte_rtps(instr: &Instruction, cpu: &mut Cpu) {
 
     let ir1 = lim_a(cpu, mac1 as i64, 24, lm);
     let ir2 = lim_a(cpu, mac2 as i64, 23, lm);
-    let ir3 = lim_a_sf(cpu, mac3 as i64, 22, lm, sf);
-
-    set_mac_flag(cpu, 1, vector.x);
-    set_mac_flag(cpu, 2, vector.y);
-    set_mac_flag(cpu, 3, vector.z);
+    let ir3 = lim_a_sf(cpu, vector.z, 22, lm, sf);
 
     let sz = lim_c(cpu, (result.z >> (12 - sf)) as i32);
     let division = gte_divide(cpu, cpu.cop2.h() as u32, sz as u32);
@@ -482,7 +451,7 @@ pub fn gte_rtps(instr: &Instruction, cpu: &mut Cpu) {
     cpu.cop2.set_ir2(ir2);
     cpu.cop2.set_ir3(ir3);
 
-    cpu.cop2.set_mac0(sx as i32);
+    cpu.cop2.set_mac0(p as i32);
     cpu.cop2.set_mac1(mac1);
     cpu.cop2.set_mac2(mac2);
     cpu.cop2.set_mac3(mac3);
@@ -535,7 +504,7 @@ pub fn gte_op(instr: &Instruction, cpu: &mut Cpu) {
     let ir3 = cpu.cop2.ir3() as i64;
 
     let (r11, _) = cpu.cop2.r11r12();
-    let (_, r22) = cpu.cop2.r22r23();
+    let (r22, _) = cpu.cop2.r22r23();
     let r33 = cpu.cop2.r33();
 
     let d1 = r11 as i64;
@@ -606,9 +575,9 @@ pub fn gte_dpcs(instr: &Instruction, cpu: &mut Cpu) {
     let (_, _, _, code) = cpu.cop2.rgbc();
     push_color_fifo(
         cpu,
-        (limit.x >> 4) as i32,
-        (limit.y >> 4) as i32,
-        (limit.z >> 4) as i32,
+        (limit.x as i32) >> 4,
+        (limit.y as i32) >> 4,
+        (limit.z as i32) >> 4,
         code,
     );
 
@@ -664,9 +633,9 @@ pub fn gte_intpl(instr: &Instruction, cpu: &mut Cpu) {
     let (_, _, _, code) = cpu.cop2.rgbc();
     push_color_fifo(
         cpu,
-        (limit.x >> 4) as i32,
-        (limit.y >> 4) as i32,
-        (limit.z >> 4) as i32,
+        (limit.x as i32) >> 4,
+        (limit.y as i32) >> 4,
+        (limit.z as i32) >> 4,
         code,
     );
 
@@ -712,29 +681,17 @@ pub fn gte_mvmva(instr: &Instruction, cpu: &mut Cpu) {
 
     // Special buggy behavior for tx=2
     if tx == 2 {
-        let result_x = t.x + m.m[0] * v.x;
-        let result_y = t.y + m.m[3] * v.x;
-        let result_z = t.z + m.m[6] * v.x;
-
-        set_mac_flag(cpu, 1, result_x);
-        set_mac_flag(cpu, 2, result_y);
-        set_mac_flag(cpu, 3, result_z);
+        let result_x = accumulate(cpu, 1, t.x, &[m.m[0] * v.x]);
+        let result_y = accumulate(cpu, 2, t.y, &[m.m[3] * v.x]);
+        let result_z = accumulate(cpu, 3, t.z, &[m.m[6] * v.x]);
 
         lim_a(cpu, result_x >> sf, 24, false);
         lim_a(cpu, result_y >> sf, 23, false);
         lim_a(cpu, result_z >> sf, 22, false);
 
-        let mut result_x = m.m[1] * v.y;
-        result_x = result_x + m.m[2] * v.z;
-        set_mac_flag(cpu, 1, result_x);
-
-        let mut result_y = m.m[4] * v.y;
-        result_y = result_y + m.m[5] * v.z;
-        set_mac_flag(cpu, 2, result_y);
-
-        let mut result_z = m.m[7] * v.y;
-        result_z = result_z + m.m[8] * v.z;
-        set_mac_flag(cpu, 3, result_z);
+        let result_x = accumulate(cpu, 1, 0, &[m.m[1] * v.y, m.m[2] * v.z]);
+        let result_y = accumulate(cpu, 2, 0, &[m.m[4] * v.y, m.m[5] * v.z]);
+        let result_z = accumulate(cpu, 3, 0, &[m.m[7] * v.y, m.m[8] * v.z]);
 
         let result_x = result_x >> sf;
         let result_y = result_y >> sf;
@@ -845,9 +802,9 @@ pub fn gte_ncds(instr: &Instruction, cpu: &mut Cpu) {
     let (_, _, _, code) = cpu.cop2.rgbc();
     push_color_fifo(
         cpu,
-        (limit.x >> 4) as i32,
-        (limit.y >> 4) as i32,
-        (limit.z >> 4) as i32,
+        (limit.x as i32) >> 4,
+        (limit.y as i32) >> 4,
+        (limit.z as i32) >> 4,
         code,
     );
 }
@@ -921,9 +878,9 @@ pub fn gte_cdp(instr: &Instruction, cpu: &mut Cpu) {
     let (_, _, _, code) = cpu.cop2.rgbc();
     push_color_fifo(
         cpu,
-        (limit.x >> 4) as i32,
-        (limit.y >> 4) as i32,
-        (limit.z >> 4) as i32,
+        (limit.x as i32) >> 4,
+        (limit.y as i32) >> 4,
+        (limit.z as i32) >> 4,
         code,
     );
 }
@@ -1018,9 +975,9 @@ pub fn gte_ncdt(instr: &Instruction, cpu: &mut Cpu) {
         let (_, _, _, code) = cpu.cop2.rgbc();
         push_color_fifo(
             cpu,
-            (limit.x >> 4) as i32,
-            (limit.y >> 4) as i32,
-            (limit.z >> 4) as i32,
+            (limit.x as i32) >> 4,
+            (limit.y as i32) >> 4,
+            (limit.z as i32) >> 4,
             code,
         );
     }
@@ -1075,9 +1032,9 @@ pub fn gte_nccs(instr: &Instruction, cpu: &mut Cpu) {
     let (_, _, _, code) = cpu.cop2.rgbc();
     push_color_fifo(
         cpu,
-        (limit.x >> 4) as i32,
-        (limit.y >> 4) as i32,
-        (limit.z >> 4) as i32,
+        (limit.x as i32) >> 4,
+        (limit.y as i32) >> 4,
+        (limit.z as i32) >> 4,
         code,
     );
 }
@@ -1122,9 +1079,9 @@ pub fn gte_cc(instr: &Instruction, cpu: &mut Cpu) {
     let (_, _, _, code) = cpu.cop2.rgbc();
     push_color_fifo(
         cpu,
-        (limit.x >> 4) as i32,
-        (limit.y >> 4) as i32,
-        (limit.z >> 4) as i32,
+        (limit.x as i32) >> 4,
+        (limit.y as i32) >> 4,
+        (limit.z as i32) >> 4,
         code,
     );
 }
@@ -1165,9 +1122,9 @@ pub fn gte_ncs(instr: &Instruction, cpu: &mut Cpu) {
     let (_, _, _, code) = cpu.cop2.rgbc();
     push_color_fifo(
         cpu,
-        (bk_light_result.x >> 4) as i32,
-        (bk_light_result.y >> 4) as i32,
-        (bk_light_result.z >> 4) as i32,
+        (bk_light_result.x as i32) >> 4,
+        (bk_light_result.y as i32) >> 4,
+        (bk_light_result.z as i32) >> 4,
         code,
     );
 }
@@ -1215,9 +1172,9 @@ pub fn gte_nct(instr: &Instruction, cpu: &mut Cpu) {
         let (_, _, _, code) = cpu.cop2.rgbc();
         push_color_fifo(
             cpu,
-            (bk_light_result.x >> 4) as i32,
-            (bk_light_result.y >> 4) as i32,
-            (bk_light_result.z >> 4) as i32,
+            (bk_light_result.x as i32) >> 4,
+            (bk_light_result.y as i32) >> 4,
+            (bk_light_result.z as i32) >> 4,
             code,
         );
     }
@@ -1289,9 +1246,9 @@ pub fn gte_dcpl(instr: &Instruction, cpu: &mut Cpu) {
     let (_, _, _, code) = cpu.cop2.rgbc();
     push_color_fifo(
         cpu,
-        (limit.x >> 4) as i32,
-        (limit.y >> 4) as i32,
-        (limit.z >> 4) as i32,
+        (limit.x as i32) >> 4,
+        (limit.y as i32) >> 4,
+        (limit.z as i32) >> 4,
         code,
     );
 
@@ -1344,9 +1301,9 @@ pub fn gte_dpct(instr: &Instruction, cpu: &mut Cpu) {
         let (_, _, _, code) = cpu.cop2.rgbc();
         push_color_fifo(
             cpu,
-            (limit.x >> 4) as i32,
-            (limit.y >> 4) as i32,
-            (limit.z >> 4) as i32,
+            (limit.x as i32) >> 4,
+            (limit.y as i32) >> 4,
+            (limit.z as i32) >> 4,
             code,
         );
 
@@ -1421,11 +1378,7 @@ pub fn gte_rtpt(instr: &Instruction, cpu: &mut Cpu) {
 
         let ir1 = lim_a(cpu, mac1 as i64, 24, lm);
         let ir2 = lim_a(cpu, mac2 as i64, 23, lm);
-        let ir3 = lim_a_sf(cpu, mac3 as i64, 22, lm, sf);
-
-        set_mac_flag(cpu, 1, vector.x);
-        set_mac_flag(cpu, 2, vector.y);
-        set_mac_flag(cpu, 3, vector.z);
+        let ir3 = lim_a_sf(cpu, vector.z, 22, lm, sf);
 
         let sz = lim_c(cpu, (result.z >> (12 - sf)) as i32);
         let division = gte_divide(cpu, cpu.cop2.h() as u32, sz as u32);
@@ -1446,16 +1399,17 @@ pub fn gte_rtpt(instr: &Instruction, cpu: &mut Cpu) {
         let sx2 = clamp_i64(sx >> 16, -0x400, 0x3FF) as i16;
         let sy2 = clamp_i64(sy >> 16, -0x400, 0x3FF) as i16;
 
+        // Only the last vector gets the depth cue
         if i == 2 {
             let ir0 = lim_e(cpu, p >> 12);
             cpu.cop2.set_ir0(ir0 as i16);
+            cpu.cop2.set_mac0(p as i32);
         }
 
         cpu.cop2.set_ir1(ir1);
         cpu.cop2.set_ir2(ir2);
         cpu.cop2.set_ir3(ir3);
 
-        cpu.cop2.set_mac0(sx as i32);
         cpu.cop2.set_mac1(mac1);
         cpu.cop2.set_mac2(mac2);
         cpu.cop2.set_mac3(mac3);
@@ -1500,9 +1454,9 @@ pub fn gte_gpf(instr: &Instruction, cpu: &mut Cpu) {
     let (_, _, _, code) = cpu.cop2.rgbc();
     push_color_fifo(
         cpu,
-        (limit.x >> 4) as i32,
-        (limit.y >> 4) as i32,
-        (limit.z >> 4) as i32,
+        (limit.x as i32) >> 4,
+        (limit.y as i32) >> 4,
+        (limit.z as i32) >> 4,
         code,
     );
 
@@ -1542,9 +1496,9 @@ pub fn gte_gpl(instr: &Instruction, cpu: &mut Cpu) {
     let (_, _, _, code) = cpu.cop2.rgbc();
     push_color_fifo(
         cpu,
-        (limit.x >> 4) as i32,
-        (limit.y >> 4) as i32,
-        (limit.z >> 4) as i32,
+        (limit.x as i32) >> 4,
+        (limit.y as i32) >> 4,
+        (limit.z as i32) >> 4,
         code,
     );
 
@@ -1612,9 +1566,9 @@ pub fn gte_ncct(instr: &Instruction, cpu: &mut Cpu) {
         let (_, _, _, code) = cpu.cop2.rgbc();
         push_color_fifo(
             cpu,
-            (limit.x >> 4) as i32,
-            (limit.y >> 4) as i32,
-            (limit.z >> 4) as i32,
+            (limit.x as i32) >> 4,
+            (limit.y as i32) >> 4,
+            (limit.z as i32) >> 4,
             code,
         );
     }
@@ -1,3 +1,42 @@
+use crate::mmu::RamSize;
+
+/// Size of the PS-X EXE header, the text section starts right after it
+pub(crate) const HEADER_SIZE: usize = 0x800;
+/// Header offset of the text section size
+pub(crate) const TEXT_SIZE_OFFSET: usize = 0x1C;
+const MAGIC: &[u8; 8] = b"PS-X EXE";
+/// No section can be larger than the RAM of a dev kit
+const MAX_SECTION_SIZE: u32 = RamSize::DevKit.bytes();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExeError {
+    TooShort(usize),
+    InvalidMagic,
+    Truncated { expected: usize, actual: usize },
+    TooLarge { section: &'static str, size: u32 },
+}
+
+impl std::fmt::Display for ExeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExeError::TooShort(size) => {
+                write!(f, "EXE is {} bytes, smaller than its {} byte header", size, HEADER_SIZE)
+            }
+            ExeError::InvalidMagic => write!(f, "Not a PS-X EXE"),
+            ExeError::Truncated { expected, actual } => write!(
+                f,
+                "EXE text section is {} bytes but only {} follow the header",
+                expected, actual
+            ),
+            ExeError::TooLarge { section, size } => {
+                write!(f, "EXE {} section of {} bytes does not fit in RAM", section, size)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExeError {}
+
 pub struct Exe {
     pub header: Vec<u8>,
     pub data: Vec<u8>,
@@ -8,25 +47,56 @@ pub struct Exe {
     pub initial_fp: u32,
     pub sp_offset: u32,
     pub fp_offset: u32,
+    pub bss_address: u32,
+    pub bss_size: u32,
     pub license: String,
 }
 
 impl Exe {
-    pub fn parse(exe_buffer: Vec<u8>) -> Self {
-        let entry_point = u32::from_le_bytes(exe_buffer[0x10..0x14].try_into().unwrap());
-        let map_address = u32::from_le_bytes(exe_buffer[0x18..0x1c].try_into().unwrap());
-        let initial_gp = u32::from_le_bytes(exe_buffer[0x14..0x18].try_into().unwrap());
-        let initial_sp_fp = u32::from_le_bytes(exe_buffer[0x30..0x34].try_into().unwrap());
-        let sp_fp_offset = u32::from_le_bytes(exe_buffer[0x34..0x38].try_into().unwrap());
-        let license = exe_buffer[0x4C..0x800]
+    pub fn parse(exe_buffer: Vec<u8>) -> Result<Self, ExeError> {
+        if exe_buffer.len() < HEADER_SIZE {
+            return Err(ExeError::TooShort(exe_buffer.len()));
+        }
+
+        if &exe_buffer[0x00..0x08] != MAGIC {
+            return Err(ExeError::InvalidMagic);
+        }
+
+        let read_u32 = |offset: usize| u32::from_le_bytes(exe_buffer[offset..offset + 4].try_into().unwrap());
+
+        let entry_point = read_u32(0x10);
+        let initial_gp = read_u32(0x14);
+        let map_address = read_u32(0x18);
+        let text_size = read_u32(TEXT_SIZE_OFFSET);
+        let bss_address = read_u32(0x28);
+        let bss_size = read_u32(0x2C);
+        let initial_sp_fp = read_u32(0x30);
+        let sp_fp_offset = read_u32(0x34);
+        let license = exe_buffer[0x4C..HEADER_SIZE]
             .iter()
             .take_while(|&&b| b != 0)
             .map(|&b| b as char)
             .collect::<String>();
 
-        Self {
-            header: exe_buffer[0x00..0x800].to_vec(),
-            data: exe_buffer[0x800..].to_vec(),
+        for (section, size) in [("text", text_size), ("BSS", bss_size)] {
+            if size > MAX_SECTION_SIZE {
+                return Err(ExeError::TooLarge { section, size });
+            }
+        }
+
+        // Anything after the text section is padding and never reaches RAM
+        let text_size = text_size as usize;
+        let available = exe_buffer.len() - HEADER_SIZE;
+        if text_size > available {
+            return Err(ExeError::Truncated {
+                expected: text_size,
+                actual: available,
+            });
+        }
+
+        Ok(Self {
+            header: exe_buffer[0x00..HEADER_SIZE].to_vec(),
+            data: exe_buffer[HEADER_SIZE..HEADER_SIZE + text_size].to_vec(),
             entry_point,
             map_address,
             initial_gp,
@@ -34,15 +104,17 @@ impl Exe {
             initial_fp: initial_sp_fp,
             sp_offset: sp_fp_offset,
             fp_offset: sp_fp_offset,
+            bss_address,
+            bss_size,
             license,
-        }
+        })
     }
 
     pub fn sp(&self) -> u32 {
-        self.initial_sp + self.sp_offset
+        self.initial_sp.wrapping_add(self.sp_offset)
     }
 
     pub fn fp(&self) -> u32 {
-        self.initial_fp + self.fp_offset
+        self.initial_fp.wrapping_add(self.fp_offset)
     }
 }
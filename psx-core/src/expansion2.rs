@@ -0,0 +1,87 @@
+use crate::cpu::internal;
+use crate::mmu::bus::Bus8;
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
+
+crate::define_addr!(DUART_ADDR, 0x1F80_2020, 0, 0x10, 0x10);
+crate::define_addr!(POST_ADDR, 0x1F80_2041, 0, 0x01, 0x01);
+
+// PSX-SPX: SCN2681 register offsets within the DUART block
+const DUART_STATUS_A: u32 = 0x1;
+const DUART_DATA_A: u32 = 0x3;
+const DUART_STATUS_B: u32 = 0x9;
+const DUART_DATA_B: u32 = 0xB;
+
+// PSX-SPX: "SRx.2 TxRDY, SRx.3 TxEMT", the transmitters are always idle since bytes leave instantly
+const DUART_STATUS_TX_IDLE: u8 = 0b1100;
+
+/// Expansion region 2 of dev units, the SCN2681 DUART debug terminal and the POST 7-segment display
+///
+/// Channel A is the TTY, its TX bytes go to the same buffer as the BIOS putchar. Nothing is ever received.
+pub struct Expansion2 {
+    registers: [u8; 0x10],
+    post: u8,
+}
+
+impl Expansion2 {
+    pub fn new() -> Self {
+        Self {
+            registers: [0; 0x10],
+            post: 0,
+        }
+    }
+
+    /// Last value written to the POST display
+    pub fn post(&self) -> u8 {
+        self.post
+    }
+}
+
+impl Default for Expansion2 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus8 for Expansion2 {
+    fn read_u8(&mut self, address: u32) -> u8 {
+        match address {
+            DUART_ADDR_START..=DUART_ADDR_END => match address - DUART_ADDR_START {
+                DUART_STATUS_A | DUART_STATUS_B => DUART_STATUS_TX_IDLE,
+                DUART_DATA_A | DUART_DATA_B => 0x00,
+                offset => self.registers[offset as usize],
+            },
+            POST_ADDR_START => self.post,
+            _ => unreachable!(),
+        }
+    }
+
+    fn write_u8(&mut self, address: u32, value: u8) {
+        match address {
+            DUART_ADDR_START..=DUART_ADDR_END => match address - DUART_ADDR_START {
+                DUART_DATA_A => internal::tty_putchar(value as char),
+                DUART_DATA_B => {
+                    tracing::debug!(target: "psx_core::exp2", value = %format!("{:02X}", value), "DUART channel B TX");
+                }
+                offset => self.registers[offset as usize] = value,
+            },
+            POST_ADDR_START => {
+                self.post = value;
+                tracing::info!(target: "psx_core::exp2", post = %format!("{:02X}", value), "POST");
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Savestate for Expansion2 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.registers);
+        writer.write(&self.post);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.registers = reader.read()?;
+        self.post = reader.read()?;
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ pub mod gp;
 pub mod rasterizer;
 pub mod rgb;
 pub mod status;
+pub mod timing;
 
 use crate::gpu::cmd::Gp0Command;
 use crate::gpu::cmd::poly::DrawPolygonCommand;
@@ -11,8 +12,10 @@ use crate::gpu::cmd::tex::{
     DrawModeSettingCommand, DrawingAreaBottomRightCommand, DrawingAreaTopLeftCommand, DrawingOffsetCommand,
     TextureWindowSettingCommand,
 };
-use crate::gpu::gp::{Gp, ParsedCommand};
+use crate::gpu::gp::{Gp, ParsedCommand, blit_position, blit_size};
+use crate::gpu::timing::VideoTiming;
 use crate::mmu::bus::Bus32;
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
 
 pub const VRAM_WIDTH: usize = 1024;
 pub const VRAM_HEIGHT: usize = 512;
@@ -22,11 +25,15 @@ crate::define_addr!(GP1_ADDRESS, 0x1F80_1810, 1, 0x04, 0x04);
 
 pub struct Gpu {
     pub gp: Gp,
+    pub timing: VideoTiming,
 }
 
 impl Gpu {
     pub fn new() -> Self {
-        let mut gpu = Self { gp: Gp::new() };
+        let mut gpu = Self {
+            gp: Gp::new(),
+            timing: VideoTiming::new(),
+        };
         
         gpu.gp.drawing_area_bottom_right =
             DrawingAreaBottomRightCommand((VRAM_HEIGHT as u32) << 10 | (VRAM_WIDTH as u32));
@@ -75,8 +82,9 @@ impl Gpu {
         buffer
     }
 
-    pub fn tick(&mut self) {
-        if let Some(parsed_cmd) = self.gp.pop_command() {
+    /// Execute every GP0 command that has received all of its parameters
+    pub fn process_commands(&mut self) {
+        while let Some(parsed_cmd) = self.gp.pop_command() {
             match parsed_cmd.cmd {
                 Gp0Command::RectanglePrimitive(cmd) => self.process_rectangle_primitive_cmd(parsed_cmd, cmd),
                 Gp0Command::PolygonPrimitive(cmd) => self.process_polygon_primitive_cmd(parsed_cmd, cmd),
@@ -97,9 +105,10 @@ impl Gpu {
         let y = ((outer_cmd.data[cmd.vertex_idx()] >> 16) & 0xFFFF) as i16;
 
         let (width, height) = match cmd.size() {
+            // Variable sized rectangles are limited to 1023x511
             0b00 => (
-                (outer_cmd.data[cmd.size_idx()] & 0xFFFF) as u16,
-                ((outer_cmd.data[cmd.size_idx()] >> 16) & 0xFFFF) as u16,
+                (outer_cmd.data[cmd.size_idx()] & 0x3FF) as u16,
+                ((outer_cmd.data[cmd.size_idx()] >> 16) & 0x1FF) as u16,
             ),
             0b01 => (1, 1),
             0b10 => (8, 8),
@@ -116,8 +125,8 @@ impl Gpu {
         );
 
         // Apply drawing offset
-        let x = x + self.gp.drawing_offset.x_offset_signed() as i16;
-        let y = y + self.gp.drawing_offset.y_offset_signed() as i16;
+        let x = x.wrapping_add(self.gp.drawing_offset.x_offset_signed() as i16);
+        let y = y.wrapping_add(self.gp.drawing_offset.y_offset_signed() as i16);
 
         if cmd.textured() {
             let uv = outer_cmd.data[cmd.uv_idx()];
@@ -179,8 +188,8 @@ impl Gpu {
         let vertices: Vec<(i16, i16)> = (0..cmd.vertex_count())
             .map(|i| {
                 let data = parsed_cmd.data[cmd.vertex_idx(i)];
-                let x = (data & 0xFFFF) as i16 + self.gp.drawing_offset.x_offset_signed() as i16;
-                let y = ((data >> 16) & 0xFFFF) as i16 + self.gp.drawing_offset.y_offset_signed() as i16;
+                let x = (data as i16).wrapping_add(self.gp.drawing_offset.x_offset_signed() as i16);
+                let y = ((data >> 16) as i16).wrapping_add(self.gp.drawing_offset.y_offset_signed() as i16);
                 (x, y)
             })
             .collect();
@@ -256,10 +265,8 @@ impl Gpu {
     }
 
     fn process_cpu_to_vram_blit_cmd(&mut self, parsed_cmd: ParsedCommand) {
-        let dest_x = (parsed_cmd.data[0] & 0xFFFF) as u16;
-        let dest_y = ((parsed_cmd.data[0] >> 16) & 0xFFFF) as u16;
-        let width = (parsed_cmd.data[1] & 0xFFFF) as usize;
-        let height = ((parsed_cmd.data[1] >> 16) & 0xFFFF) as usize;
+        let (dest_x, dest_y) = blit_position(parsed_cmd.data[0]);
+        let (width, height) = blit_size(parsed_cmd.data[1]);
 
         let total_pixels = width * height;
 
@@ -277,8 +284,8 @@ impl Gpu {
                 return;
             }
 
-            let x = dest_x as usize + (pixel_idx % width);
-            let y = dest_y as usize + (pixel_idx / width);
+            let x = dest_x + (pixel_idx % width);
+            let y = dest_y + (pixel_idx / width);
 
             // Wrap coordinates to VRAM dimensions
             let vram_x = x & (VRAM_WIDTH - 1);
@@ -304,10 +311,8 @@ impl Gpu {
     }
 
     fn process_vram_to_cpu_blit_cmd(&mut self, parsed_cmd: ParsedCommand) {
-        let src_x = (parsed_cmd.data[0] & 0xFFFF) as u16;
-        let src_y = ((parsed_cmd.data[0] >> 16) & 0xFFFF) as u16;
-        let width = (parsed_cmd.data[1] & 0xFFFF) as usize;
-        let height = ((parsed_cmd.data[1] >> 16) & 0xFFFF) as usize;
+        let (src_x, src_y) = blit_position(parsed_cmd.data[0]);
+        let (width, height) = blit_size(parsed_cmd.data[1]);
 
         tracing::debug!(
             target: "psx_core::gpu",
@@ -326,12 +331,9 @@ impl Gpu {
         // Word 2: Destination Coord (Yd << 16 | Xd)
         // Word 3: Width+Height (H << 16 | W)
 
-        let src_x = (parsed_cmd.data[0] & 0xFFFF) as u16;
-        let src_y = ((parsed_cmd.data[0] >> 16) & 0xFFFF) as u16;
-        let dst_x = (parsed_cmd.data[1] & 0xFFFF) as u16;
-        let dst_y = ((parsed_cmd.data[1] >> 16) & 0xFFFF) as u16;
-        let width = (parsed_cmd.data[2] & 0xFFFF) as usize;
-        let height = ((parsed_cmd.data[2] >> 16) & 0xFFFF) as usize;
+        let (src_x, src_y) = blit_position(parsed_cmd.data[0]);
+        let (dst_x, dst_y) = blit_position(parsed_cmd.data[1]);
+        let (width, height) = blit_size(parsed_cmd.data[2]);
 
         tracing::debug!(
             target: "psx_core::gpu",
@@ -346,8 +348,8 @@ impl Gpu {
         // Read from source
         for row in 0..height {
             for col in 0..width {
-                let vram_x = (src_x as usize + col) & (VRAM_WIDTH - 1);
-                let vram_y = (src_y as usize + row) & (VRAM_HEIGHT - 1);
+                let vram_x = (src_x + col) & (VRAM_WIDTH - 1);
+                let vram_y = (src_y + row) & (VRAM_HEIGHT - 1);
                 let vram_idx = (vram_y * VRAM_WIDTH + vram_x) * 2;
                 let byte0 = self.gp.vram[vram_idx];
                 let byte1 = self.gp.vram[vram_idx + 1];
@@ -360,8 +362,8 @@ impl Gpu {
         let mut pixel_idx = 0;
         for row in 0..height {
             for col in 0..width {
-                let vram_x = (dst_x as usize + col) & (VRAM_WIDTH - 1);
-                let vram_y = (dst_y as usize + row) & (VRAM_HEIGHT - 1);
+                let vram_x = (dst_x + col) & (VRAM_WIDTH - 1);
+                let vram_y = (dst_y + row) & (VRAM_HEIGHT - 1);
                 let vram_idx = (vram_y * VRAM_WIDTH + vram_x) * 2;
                 let bytes = temp_buffer[pixel_idx].to_le_bytes();
                 self.gp.vram[vram_idx] = bytes[0];
@@ -452,10 +454,12 @@ impl Gpu {
             // Quick Rectangle Fill
             0x02 => {
                 let color = parsed_cmd.raw & 0x00FF_FFFF;
-                let x = (parsed_cmd.data[0] & 0xFFFF) as i16;
-                let y = ((parsed_cmd.data[0] >> 16) & 0xFFFF) as i16;
-                let width = (parsed_cmd.data[1] & 0xFFFF) as u16;
-                let height = ((parsed_cmd.data[1] >> 16) & 0xFFFF) as u16;
+                // The fill position is in VRAM coordinates, with X rounded down to a multiple of 16
+                let x = (parsed_cmd.data[0] & 0x3F0) as u16;
+                let y = ((parsed_cmd.data[0] >> 16) & 0x1FF) as u16;
+                // The width is rounded up to a multiple of 16 like the position
+                let width = (((parsed_cmd.data[1] & 0x3FF) + 0xF) & !0xF) as u16;
+                let height = ((parsed_cmd.data[1] >> 16) & 0x1FF) as u16;
 
                 tracing::debug!(
                     target: "psx_core::gpu",
@@ -506,5 +510,23 @@ impl Bus32 for Gpu {
     #[inline(always)]
     fn write_u32(&mut self, address: u32, value: u32) {
         self.gp.write_u32(address, value);
+        self.process_commands();
+    }
+}
+
+impl Savestate for Gpu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.gp.save_state(writer);
+        writer.write(&self.timing.scanline);
+        writer.write(&self.timing.in_hblank);
+        writer.write(&self.timing.in_vblank);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.gp.load_state(reader)?;
+        self.timing.scanline = reader.read()?;
+        self.timing.in_hblank = reader.read()?;
+        self.timing.in_vblank = reader.read()?;
+        Ok(())
     }
 }
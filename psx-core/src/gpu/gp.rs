@@ -6,6 +6,7 @@ use crate::gpu::cmd::tex::{
 use crate::gpu::status::{DmaDirection, StatusRegister};
 use crate::gpu::{GP1_ADDRESS_END, GP1_ADDRESS_START, VRAM_HEIGHT, VRAM_WIDTH};
 use crate::mmu::bus::Bus32;
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
 use std::collections::VecDeque;
 
 pub struct ParsedCommand {
@@ -15,6 +16,20 @@ pub struct ParsedCommand {
     pub ready: bool,
 }
 
+/// Decodes the position word of a blit command, wrapped to VRAM dimensions.
+pub(crate) fn blit_position(word: u32) -> (usize, usize) {
+    let x = word as usize & (VRAM_WIDTH - 1);
+    let y = (word >> 16) as usize & (VRAM_HEIGHT - 1);
+    (x, y)
+}
+
+/// Decodes the size word of a blit command. A size of 0 selects the full VRAM extent.
+pub(crate) fn blit_size(word: u32) -> (usize, usize) {
+    let width = ((word as usize & 0xFFFF).wrapping_sub(1) & (VRAM_WIDTH - 1)) + 1;
+    let height = (((word >> 16) as usize).wrapping_sub(1) & (VRAM_HEIGHT - 1)) + 1;
+    (width, height)
+}
+
 #[derive(PartialEq, Eq)]
 enum State {
     WaitingForCommand,
@@ -54,13 +69,21 @@ impl Gp {
     }
 
     pub fn process_read(&mut self, address: u32) -> u32 {
-        if self.fifo.is_empty() {
+        let Some(last_cmd) = self.fifo.back_mut() else {
             // No active command, return the gpuread_latch
             // (set by GP1(10h) or previous reads)
             return self.gpuread_latch;
-        }
+        };
 
-        let last_cmd = self.fifo.back_mut().unwrap();
+        // Only a VRAM to CPU blit that finished collecting its parameters has data to hand out
+        if self.state != State::CollectingExtraData || last_cmd.cmd != Gp0Command::VramToCpuBlit {
+            tracing::error!(
+                target: "psx_core::gpu",
+                command = %last_cmd.cmd, address, self.expected_data,
+                "Reading from GP0 during illegal state"
+            );
+            return 0xFF;
+        }
 
         if self.expected_data > 0 {
             self.expected_data -= 1;
@@ -72,57 +95,34 @@ impl Gp {
             last_cmd.ready = true;
         }
 
-        match last_cmd.cmd {
-            Gp0Command::VramToCpuBlit => {
-                let (src_x, src_y) = {
-                    let word = last_cmd.data[0];
-                    let x = word & 0xFFFF;
-                    let y = word >> 16;
-                    (x as usize, y as usize)
-                };
-
-                let (width, _height) = {
-                    let word = last_cmd.data[1];
-                    let w = word & 0xFFFF;
-                    let h = word >> 16;
-                    (w as usize, h as usize)
-                };
-
-                // Calculate current pixel position based on words read (2 pixels per word)
-                let pixels_read = self.read_counter * 2; // 2 pixels per 32-bit word
-                let pixel0_x = src_x + (pixels_read % width);
-                let pixel0_y = src_y + (pixels_read / width);
-                let pixel1_x = src_x + ((pixels_read + 1) % width);
-                let pixel1_y = src_y + ((pixels_read + 1) / width);
-
-                // Read two 16-bit pixels and pack into 32-bit word
-                let idx0 = ((pixel0_y * 1024) + pixel0_x) * 2;
-                let idx1 = ((pixel1_y * 1024) + pixel1_x) * 2;
-
-                let pixel0 = u16::from_le_bytes([self.vram[idx0], self.vram[idx0 + 1]]);
-                let pixel1 = u16::from_le_bytes([self.vram[idx1], self.vram[idx1 + 1]]);
-
-                let word = (pixel1 as u32) << 16 | (pixel0 as u32);
+        let (src_x, src_y) = blit_position(last_cmd.data[0]);
+        let (width, _height) = blit_size(last_cmd.data[1]);
 
-                tracing::debug!(
-                    target: "psx_core::gpu",
-                    command = %last_cmd.cmd, vram_addr = %format!("{:08X}", address), self.expected_data, data = %format!("{:08X?}", last_cmd.data), src_x, src_y, pixel0_x, pixel0_y, pixel1_x, pixel1_y, word = %format!("{:08X}", word),
-                    "Reading from VRAM during GP0 VramToCpuBlit command"
-                );
+        // Calculate current pixel position based on words read (2 pixels per word)
+        let pixels_read = self.read_counter * 2; // 2 pixels per 32-bit word
+        let pixel0_x = (src_x + (pixels_read % width)) & (VRAM_WIDTH - 1);
+        let pixel0_y = (src_y + (pixels_read / width)) & (VRAM_HEIGHT - 1);
+        let pixel1_x = (src_x + ((pixels_read + 1) % width)) & (VRAM_WIDTH - 1);
+        let pixel1_y = (src_y + ((pixels_read + 1) / width)) & (VRAM_HEIGHT - 1);
 
-                self.read_counter += 1;
+        // Read two 16-bit pixels and pack into 32-bit word
+        let idx0 = ((pixel0_y * VRAM_WIDTH) + pixel0_x) * 2;
+        let idx1 = ((pixel1_y * VRAM_WIDTH) + pixel1_x) * 2;
 
-                word
-            }
-            _ => {
-                tracing::error!(
-                    target: "psx_core::gpu",
-                    command = %last_cmd.cmd, address, self.expected_data,
-                    "Reading from GP0 during illegal state"
-                );
-                0xFF
-            }
-        }
+        let pixel0 = u16::from_le_bytes([self.vram[idx0], self.vram[idx0 + 1]]);
+        let pixel1 = u16::from_le_bytes([self.vram[idx1], self.vram[idx1 + 1]]);
+
+        let word = (pixel1 as u32) << 16 | (pixel0 as u32);
+
+        tracing::debug!(
+            target: "psx_core::gpu",
+            command = %last_cmd.cmd, vram_addr = %format!("{:08X}", address), self.expected_data, data = %format!("{:08X?}", last_cmd.data), src_x, src_y, pixel0_x, pixel0_y, pixel1_x, pixel1_y, word = %format!("{:08X}", word),
+            "Reading from VRAM during GP0 VramToCpuBlit command"
+        );
+
+        self.read_counter += 1;
+
+        word
     }
 
     pub fn process_gp0_word(&mut self, value: u32) {
@@ -153,11 +153,7 @@ impl Gp {
                     // Is a variable amount of extra data expected?
                     match last_cmd.cmd {
                         Gp0Command::CpuToVramBlit | Gp0Command::VramToCpuBlit => {
-                            let (width, height) = {
-                                let width = (value & 0xFFFF) as usize;
-                                let height = (value >> 16) as usize;
-                                (width, height)
-                            };
+                            let (width, height) = blit_size(value);
                             self.expected_data = ((width * height) + 1) / 2;
                             self.state = State::CollectingExtraData;
 
@@ -411,3 +407,62 @@ impl Bus32 for Gp {
         }
     }
 }
+
+impl Savestate for Gp {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.vram);
+        writer.write(&self.gp1_status.0);
+        writer.write(&self.texture_window.0);
+        writer.write(&self.drawing_area_top_left.0);
+        writer.write(&self.drawing_area_bottom_right.0);
+        writer.write(&self.drawing_offset.0);
+
+        // The decoded command is rebuilt from the raw word on load
+        writer.write(&(self.fifo.len() as u32));
+        for command in &self.fifo {
+            writer.write(&command.raw);
+            writer.write(&command.data);
+            writer.write(&command.ready);
+        }
+
+        writer.write(&self.expected_data);
+        writer.write(&match self.state {
+            State::WaitingForCommand => 0u8,
+            State::CollectingParams => 1,
+            State::CollectingExtraData => 2,
+        });
+        writer.write(&self.read_counter);
+        writer.write(&self.gpuread_latch);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        reader.read_into(&mut self.vram)?;
+        self.gp1_status.0 = reader.read()?;
+        self.texture_window.0 = reader.read()?;
+        self.drawing_area_top_left.0 = reader.read()?;
+        self.drawing_area_bottom_right.0 = reader.read()?;
+        self.drawing_offset.0 = reader.read()?;
+
+        self.fifo.clear();
+        for _ in 0..reader.read::<u32>()? {
+            let raw = reader.read()?;
+            self.fifo.push_back(ParsedCommand {
+                raw,
+                cmd: Gp0Command::from(raw),
+                data: reader.read()?,
+                ready: reader.read()?,
+            });
+        }
+
+        self.expected_data = reader.read()?;
+        self.state = match reader.read::<u8>()? {
+            0 => State::WaitingForCommand,
+            1 => State::CollectingParams,
+            2 => State::CollectingExtraData,
+            _ => return Err(SavestateError::Invalid("GP0 state out of range")),
+        };
+        self.read_counter = reader.read()?;
+        self.gpuread_latch = reader.read()?;
+        Ok(())
+    }
+}
@@ -176,6 +176,13 @@ fn rasterize_triangle(
     let (x1, y1) = (vertices[1].0 as i32, vertices[1].1 as i32);
     let (x2, y2) = (vertices[2].0 as i32, vertices[2].1 as i32);
 
+    // The GPU refuses to draw polygons spanning more than 1023x511 pixels
+    if x0.max(x1).max(x2) - x0.min(x1).min(x2) >= VRAM_WIDTH as i32
+        || y0.max(y1).max(y2) - y0.min(y1).min(y2) >= VRAM_HEIGHT as i32
+    {
+        return;
+    }
+
     // Bounding box constrained to drawing area
     let min_x = x0.min(x1).min(x2).max(drawing_area_x1 as i32);
     let max_x = x0.max(x1).max(x2).min(drawing_area_x2 as i32);
@@ -118,11 +118,23 @@ impl StatusRegister {
         }
     }
 
+    /// Video clock cycles per dot for the current horizontal resolution
+    pub fn dot_clock_divider(&self) -> u32 {
+        match self.hres() {
+            256 => 10,
+            320 => 8,
+            368 => 7,
+            512 => 5,
+            640 => 4,
+            _ => unreachable!(),
+        }
+    }
+
     pub fn vres(&self) -> u32 {
+        // 480 lines needs interlacing, the vertical resolution bit alone has no effect
         match (self.vertical_resolution(), self.vertical_interlace()) {
-            (false, false) => 240,
+            (_, false) => 240,
             (_, true) => 480,
-            _ => unreachable!(),
         }
     }
 }
@@ -0,0 +1,106 @@
+use crate::gpu::status::VideoMode;
+
+// PSX-SPX: "Video Clock 53.222400MHz (NTSC) / 53.693175MHz (PAL)", CPU clock = Video clock * 7/11
+pub const NTSC_VIDEO_CLOCK: u64 = 53_222_400;
+pub const PAL_VIDEO_CLOCK: u64 = 53_693_175;
+pub const NTSC_VIDEO_CYCLES_PER_SCANLINE: u64 = 3413;
+pub const PAL_VIDEO_CYCLES_PER_SCANLINE: u64 = 3406;
+pub const NTSC_SCANLINES_PER_FRAME: u16 = 263;
+pub const PAL_SCANLINES_PER_FRAME: u16 = 314;
+pub const NTSC_VBLANK_START_SCANLINE: u16 = 240;
+pub const PAL_VBLANK_START_SCANLINE: u16 = 288;
+
+// PSX-SPX: Display range X1/X2 defaults are 260h..C60h (in video clock units)
+const HBLANK_END_VIDEO_CYCLE: u64 = 0x260;
+const HBLANK_START_VIDEO_CYCLE: u64 = 0xC60;
+
+// PSX-SPX: Display range Y1 defaults are 10h (NTSC) and 23h (PAL) scanlines after the vertical sync
+const NTSC_DISPLAY_START_SCANLINE: u16 = 0x10;
+const PAL_DISPLAY_START_SCANLINE: u16 = 0x23;
+
+/// Tracks the beam position of the video output, one scanline at a time
+pub struct VideoTiming {
+    pub scanline: u16,
+    pub in_hblank: bool,
+    pub in_vblank: bool,
+}
+
+impl VideoTiming {
+    pub fn new() -> Self {
+        Self {
+            scanline: 0,
+            in_hblank: false,
+            in_vblank: false,
+        }
+    }
+
+    /// CPU cycles per scanline
+    pub fn scanline_cycles(mode: VideoMode) -> u64 {
+        Self::video_to_cpu_cycles(match mode {
+            VideoMode::Ntsc => NTSC_VIDEO_CYCLES_PER_SCANLINE,
+            VideoMode::Pal => PAL_VIDEO_CYCLES_PER_SCANLINE,
+        })
+    }
+
+    /// CPU cycles from the start of a scanline to the start of its horizontal blank
+    pub fn hblank_start_cycles() -> u64 {
+        Self::video_to_cpu_cycles(HBLANK_START_VIDEO_CYCLE - HBLANK_END_VIDEO_CYCLE)
+    }
+
+    pub fn scanlines_per_frame(mode: VideoMode) -> u16 {
+        match mode {
+            VideoMode::Ntsc => NTSC_SCANLINES_PER_FRAME,
+            VideoMode::Pal => PAL_SCANLINES_PER_FRAME,
+        }
+    }
+
+    pub fn vblank_start_scanline(mode: VideoMode) -> u16 {
+        match mode {
+            VideoMode::Ntsc => NTSC_VBLANK_START_SCANLINE,
+            VideoMode::Pal => PAL_VBLANK_START_SCANLINE,
+        }
+    }
+
+    pub fn video_clock(mode: VideoMode) -> u64 {
+        match mode {
+            VideoMode::Ntsc => NTSC_VIDEO_CLOCK,
+            VideoMode::Pal => PAL_VIDEO_CLOCK,
+        }
+    }
+
+    /// Beam position of a point on the display, `x` and `y` range from 0.0 to 1.0
+    ///
+    /// Returns the video cycles since the start of the visible part of the scanline and the scanline
+    /// counted from the first visible one, like `scanline`
+    pub fn display_beam_position(mode: VideoMode, x: f32, y: f32) -> (u64, u16) {
+        let visible_cycles = HBLANK_START_VIDEO_CYCLE - HBLANK_END_VIDEO_CYCLE;
+        let visible_lines = Self::vblank_start_scanline(mode);
+
+        let video_cycle = (x.clamp(0.0, 1.0) * visible_cycles as f32) as u64;
+        let scanline = (y.clamp(0.0, 1.0) * visible_lines as f32) as u16;
+
+        (video_cycle.min(visible_cycles - 1), scanline.min(visible_lines - 1))
+    }
+
+    /// Beam position of a point on the display as counted from the horizontal and vertical sync
+    pub fn sync_beam_position(mode: VideoMode, x: f32, y: f32) -> (u64, u16) {
+        let (video_cycle, scanline) = Self::display_beam_position(mode, x, y);
+        let display_start_scanline = match mode {
+            VideoMode::Ntsc => NTSC_DISPLAY_START_SCANLINE,
+            VideoMode::Pal => PAL_DISPLAY_START_SCANLINE,
+        };
+
+        (HBLANK_END_VIDEO_CYCLE + video_cycle, display_start_scanline + scanline)
+    }
+
+    #[inline(always)]
+    pub fn video_to_cpu_cycles(video_cycles: u64) -> u64 {
+        video_cycles * 7 / 11
+    }
+}
+
+impl Default for VideoTiming {
+    fn default() -> Self {
+        Self::new()
+    }
+}
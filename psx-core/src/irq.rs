@@ -1,4 +1,5 @@
 use crate::mmu::bus::{Bus8, Bus16, Bus32};
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
 use proc_bitfield::bitfield;
 
 crate::define_addr!(I_STAT_ADDR, 0x1F80_1070, 0, 0x04, 0x04);
@@ -133,3 +134,16 @@ impl Bus32 for Irq {
         }
     }
 }
+
+impl Savestate for Irq {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.status.0);
+        writer.write(&self.mask.0);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.status.0 = reader.read()?;
+        self.mask.0 = reader.read()?;
+        Ok(())
+    }
+}
@@ -2,13 +2,21 @@
 #![feature(const_trait_impl)]
 #![feature(const_cmp)]
 
+pub mod cartridge;
 pub mod cdrom;
 pub mod cpu;
 pub mod exe;
+pub mod expansion2;
 pub mod gpu;
 pub mod irq;
+pub mod mdec;
 pub mod mmu;
+pub mod movie;
+pub mod pcdrv;
 pub mod psx;
+pub mod rewind;
+pub mod savestate;
+pub mod scheduler;
 pub mod spu;
 pub mod sio;
 pub mod timer;
@@ -0,0 +1,662 @@
+use crate::mmu::bus::Bus32;
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
+use proc_bitfield::bitfield;
+use std::collections::VecDeque;
+
+// PSX-SPX: "1F801820h - MDEC0 - MDEC Command/Parameter Register (W)" / "MDEC Data/Response Register (R)"
+crate::define_addr!(MDEC_DATA_ADDR, 0x1F80_1820, 0, 0x04, 0x04);
+// PSX-SPX: "1F801824h - MDEC1 - MDEC Control/Reset Register (W)" / "MDEC Status Register (R)"
+crate::define_addr!(MDEC_CONTROL_ADDR, 0x1F80_1824, 0, 0x04, 0x04);
+
+pub const MDEC_ADDR_START: u32 = MDEC_DATA_ADDR_START;
+pub const MDEC_ADDR_END: u32 = MDEC_CONTROL_ADDR_END;
+
+// PSX-SPX: "zigzag[0..63]"
+#[rustfmt::skip]
+const ZIGZAG: [usize; 64] = [
+    0,  1,  5,  6,  14, 15, 27, 28,
+    2,  4,  7,  13, 16, 26, 29, 42,
+    3,  8,  12, 17, 25, 30, 41, 43,
+    9,  11, 18, 24, 31, 40, 44, 53,
+    10, 19, 23, 32, 39, 45, 52, 54,
+    20, 22, 33, 38, 46, 51, 55, 60,
+    21, 34, 37, 47, 50, 56, 59, 61,
+    35, 36, 48, 49, 57, 58, 62, 63,
+];
+
+// PSX-SPX: "for i=0 to 63, zagzig[zigzag[i]]=i"
+const ZAGZIG: [usize; 64] = {
+    let mut zagzig = [0; 64];
+    let mut i = 0;
+    while i < 64 {
+        zagzig[ZIGZAG[i]] = i;
+        i += 1;
+    }
+    zagzig
+};
+
+const PADDING: u16 = 0xFE00;
+
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct MdecCommand(pub u32): Debug, FromStorage, IntoStorage, DerefStorage {
+        pub parameter_words: u16 @ 0..=15,
+        pub color: bool @ 0,
+        pub output_bit15: bool @ 25,
+        pub output_signed: bool @ 26,
+        pub output_depth: u8 @ 27..=28,
+        pub command: u8 @ 29..=31,
+    }
+}
+
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct MdecControl(pub u32): Debug, FromStorage, IntoStorage, DerefStorage {
+        pub data_out_request_enable: bool @ 29,
+        pub data_in_request_enable: bool @ 30,
+        pub reset: bool @ 31,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputDepth {
+    Mono4,
+    Mono8,
+    Rgb24,
+    Rgb15,
+}
+
+impl From<u8> for OutputDepth {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => OutputDepth::Mono4,
+            1 => OutputDepth::Mono8,
+            2 => OutputDepth::Rgb24,
+            3 => OutputDepth::Rgb15,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl std::fmt::Display for OutputDepth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputDepth::Mono4 => write!(f, "4-bit"),
+            OutputDepth::Mono8 => write!(f, "8-bit"),
+            OutputDepth::Rgb24 => write!(f, "24-bit"),
+            OutputDepth::Rgb15 => write!(f, "15-bit"),
+        }
+    }
+}
+
+pub struct Mdec {
+    control: MdecControl,
+    current_command: MdecCommand,
+    parameters_left: usize, // Words the current command still expects
+    parameters: Vec<u32>,   // Words received for the current command
+    output: VecDeque<u32>,  // Decoded data waiting to be read by the CPU or DMA1
+
+    luminance_quant_table: [u8; 64],
+    color_quant_table: [u8; 64],
+    scale_table: [i16; 64],
+}
+
+impl Mdec {
+    pub fn new() -> Self {
+        Self {
+            control: MdecControl(0),
+            current_command: MdecCommand(0),
+            parameters_left: 0,
+            parameters: Vec::new(),
+            output: VecDeque::new(),
+            luminance_quant_table: [0; 64],
+            color_quant_table: [0; 64],
+            scale_table: [0; 64],
+        }
+    }
+
+    /// DMA0 may push more command/parameter words
+    pub fn data_in_request(&self) -> bool {
+        self.control.data_in_request_enable()
+    }
+
+    /// DMA1 may pull decoded words
+    pub fn data_out_request(&self) -> bool {
+        self.control.data_out_request_enable() && !self.output.is_empty()
+    }
+
+    pub fn status(&self) -> u32 {
+        // 31    Data-Out Fifo Empty (0=No, 1=Empty)
+        // 30    Data-In Fifo Full   (0=No, 1=Full, or Last word received)
+        // 29    Command Busy  (0=Ready, 1=Busy receiving or processing parameters)
+        // 28    Data-In Request  (set when DMA0 enabled and ready to receive data)
+        // 27    Data-Out Request (set when DMA1 enabled and ready to send data)
+        // 26-25 Data Output Depth  (0=4bit, 1=8bit, 2=24bit, 3=15bit)
+        // 24    Data Output Signed (0=Unsigned, 1=Signed)
+        // 23    Data Output Bit15  (0=Clear, 1=Set) (for 15bit depth only)
+        // 15-0  Number of Parameter Words remaining minus 1  (FFFFh=None)
+        let mut status = 0;
+        status |= (self.output.is_empty() as u32) << 31;
+        status |= ((self.parameters_left > 0) as u32) << 29;
+        status |= (self.data_in_request() as u32) << 28;
+        status |= (self.data_out_request() as u32) << 27;
+        status |= (self.current_command.output_depth() as u32) << 25;
+        status |= (self.current_command.output_signed() as u32) << 24;
+        status |= (self.current_command.output_bit15() as u32) << 23;
+        status |= (self.parameters_left as u32).wrapping_sub(1) & 0xFFFF;
+        status
+    }
+
+    pub fn read_data(&mut self) -> u32 {
+        self.output.pop_front().unwrap_or_else(|| {
+            tracing::warn!(target: "psx_core::mdec", "Data-Out FIFO underflow on read");
+            0
+        })
+    }
+
+    pub fn write_data(&mut self, value: u32) {
+        if self.parameters_left > 0 {
+            self.parameters.push(value);
+            self.parameters_left -= 1;
+
+            if self.parameters_left == 0 {
+                self.execute_command();
+            }
+
+            return;
+        }
+
+        self.current_command = MdecCommand(value);
+        self.parameters.clear();
+        self.parameters_left = match self.current_command.command() {
+            1 => self.current_command.parameter_words() as usize,
+            // PSX-SPX: "64 unsigned parameter bytes for the Luminance Quant Table, ... 64 for the Color Quant Table"
+            2 if self.current_command.color() => 32,
+            2 => 16,
+            // PSX-SPX: "64 signed halfwords with 14bit fractional part"
+            3 => 32,
+            _ => 0,
+        };
+
+        tracing::debug!(
+            target: "psx_core::mdec",
+            command = format!("{:08X}", value),
+            parameter_words = self.parameters_left,
+            "MDEC command received"
+        );
+
+        if self.parameters_left == 0 {
+            self.execute_command();
+        }
+    }
+
+    pub fn write_control(&mut self, value: u32) {
+        self.control = MdecControl(value);
+
+        if self.control.reset() {
+            // PSX-SPX: "Reset MDEC (0=No change, 1=Abort any command, and set status=80040000h)"
+            self.current_command = MdecCommand(0);
+            self.parameters_left = 0;
+            self.parameters.clear();
+            self.output.clear();
+
+            tracing::debug!(target: "psx_core::mdec", "Reset");
+        }
+    }
+
+    fn execute_command(&mut self) {
+        match self.current_command.command() {
+            1 => self.decode_macroblocks(),
+            2 => {
+                let bytes: Vec<u8> = self.parameters.iter().flat_map(|word| word.to_le_bytes()).collect();
+                self.luminance_quant_table.copy_from_slice(&bytes[..64]);
+                if self.current_command.color() {
+                    self.color_quant_table.copy_from_slice(&bytes[64..128]);
+                }
+
+                tracing::debug!(target: "psx_core::mdec", color = self.current_command.color(), "Quant table set");
+            }
+            3 => {
+                for (i, word) in self.parameters.iter().enumerate() {
+                    self.scale_table[i * 2] = *word as i16;
+                    self.scale_table[i * 2 + 1] = (*word >> 16) as i16;
+                }
+
+                tracing::debug!(target: "psx_core::mdec", "Scale table set");
+            }
+            command => {
+                tracing::warn!(target: "psx_core::mdec", command, "Unknown MDEC command, ignoring");
+            }
+        }
+    }
+
+    fn decode_macroblocks(&mut self) {
+        let halfwords: Vec<u16> = self
+            .parameters
+            .iter()
+            .flat_map(|word| [*word as u16, (*word >> 16) as u16])
+            .collect();
+
+        let depth = OutputDepth::from(self.current_command.output_depth());
+        let mut src = halfwords.iter().copied().peekable();
+        let mut macroblocks = 0;
+
+        loop {
+            // Trailing padding isn't another macroblock
+            while src.next_if_eq(&PADDING).is_some() {}
+            if src.peek().is_none() {
+                break;
+            }
+
+            let complete = match depth {
+                OutputDepth::Mono4 | OutputDepth::Mono8 => self.decode_mono_macroblock(&mut src, depth),
+                OutputDepth::Rgb24 | OutputDepth::Rgb15 => self.decode_color_macroblock(&mut src, depth),
+            };
+
+            if !complete {
+                tracing::warn!(target: "psx_core::mdec", "Macroblock data ended mid block");
+                break;
+            }
+
+            macroblocks += 1;
+        }
+
+        tracing::debug!(
+            target: "psx_core::mdec",
+            macroblocks,
+            depth = %depth,
+            output_words = self.output.len(),
+            "Decoded macroblocks"
+        );
+    }
+
+    fn decode_color_macroblock(&mut self, src: &mut impl Iterator<Item = u16>, depth: OutputDepth) -> bool {
+        let mut cr = [0i32; 64];
+        let mut cb = [0i32; 64];
+        let mut y = [[0i32; 64]; 4];
+
+        let color_qt = self.color_quant_table;
+        let luminance_qt = self.luminance_quant_table;
+
+        if !self.decode_block(&mut cr, src, &color_qt) || !self.decode_block(&mut cb, src, &color_qt) {
+            return false;
+        }
+
+        for block in y.iter_mut() {
+            if !self.decode_block(block, src, &luminance_qt) {
+                return false;
+            }
+        }
+
+        let mut pixels = [(0u8, 0u8, 0u8); 256];
+        for (i, (xx, yy)) in [(0, 0), (8, 0), (0, 8), (8, 8)].into_iter().enumerate() {
+            self.yuv_to_rgb(&mut pixels, &cr, &cb, &y[i], xx, yy);
+        }
+
+        match depth {
+            OutputDepth::Rgb24 => {
+                let bytes: Vec<u8> = pixels.iter().flat_map(|&(r, g, b)| [r, g, b]).collect();
+                for chunk in bytes.chunks_exact(4) {
+                    self.output
+                        .push_back(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+                }
+            }
+            OutputDepth::Rgb15 => {
+                let bit15 = (self.current_command.output_bit15() as u16) << 15;
+                let to_rgb15 = |(r, g, b): (u8, u8, u8)| {
+                    (r as u16 >> 3) | ((g as u16 >> 3) << 5) | ((b as u16 >> 3) << 10) | bit15
+                };
+
+                for pair in pixels.chunks_exact(2) {
+                    self.output
+                        .push_back(to_rgb15(pair[0]) as u32 | ((to_rgb15(pair[1]) as u32) << 16));
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        true
+    }
+
+    fn decode_mono_macroblock(&mut self, src: &mut impl Iterator<Item = u16>, depth: OutputDepth) -> bool {
+        let mut y = [0i32; 64];
+        let luminance_qt = self.luminance_quant_table;
+
+        if !self.decode_block(&mut y, src, &luminance_qt) {
+            return false;
+        }
+
+        let signed = self.current_command.output_signed();
+        let pixels: Vec<u8> = y
+            .iter()
+            .map(|&luma| {
+                // PSX-SPX: "clip to 10bit range" before saturating
+                let luma = signed10bit(luma as u16).clamp(-128, 127) as u8;
+                if signed { luma } else { luma ^ 0x80 }
+            })
+            .collect();
+
+        match depth {
+            OutputDepth::Mono8 => {
+                for chunk in pixels.chunks_exact(4) {
+                    self.output
+                        .push_back(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+                }
+            }
+            OutputDepth::Mono4 => {
+                for chunk in pixels.chunks_exact(8) {
+                    let word = chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u32, |word, (i, &luma)| word | ((luma as u32 >> 4) << (i * 4)));
+                    self.output.push_back(word);
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        true
+    }
+
+    /// PSX-SPX: "rl_decode_block", followed by the IDCT. Returns false if the input ran out.
+    fn decode_block(&self, block: &mut [i32; 64], src: &mut impl Iterator<Item = u16>, qt: &[u8; 64]) -> bool {
+        block.fill(0);
+
+        let mut n = loop {
+            match src.next() {
+                Some(PADDING) => continue,
+                Some(n) => break n,
+                None => return false,
+            }
+        };
+
+        let mut k = 0;
+        let q_scale = ((n >> 10) & 0x3F) as i32;
+        let mut value = signed10bit(n) * qt[k] as i32;
+
+        loop {
+            if q_scale == 0 {
+                value = signed10bit(n) * 2;
+            }
+
+            value = value.clamp(-0x400, 0x3FF);
+
+            if q_scale > 0 {
+                block[ZAGZIG[k]] = value;
+            } else {
+                block[k] = value;
+            }
+
+            n = match src.next() {
+                Some(n) => n,
+                None => return false,
+            };
+
+            k += ((n >> 10) & 0x3F) as usize + 1;
+            if k > 63 {
+                break;
+            }
+
+            value = (signed10bit(n) * qt[k] as i32 * q_scale + 4) / 8;
+        }
+
+        self.idct(block);
+        true
+    }
+
+    /// PSX-SPX: "real_idct_core", one pass over the columns and one over the rows
+    fn idct(&self, block: &mut [i32; 64]) {
+        let mut temp = [0i32; 64];
+        self.idct_pass(block, &mut temp);
+        self.idct_pass(&temp, block);
+    }
+
+    fn idct_pass(&self, src: &[i32; 64], dst: &mut [i32; 64]) {
+        for x in 0..8 {
+            for y in 0..8 {
+                let sum: i64 = (0..8)
+                    .map(|z| src[y + z * 8] as i64 * (self.scale_table[x + z * 8] as i64 / 8))
+                    .sum();
+                dst[x + y * 8] = ((sum + 0xFFF) / 0x2000) as i32;
+            }
+        }
+    }
+
+    /// PSX-SPX: "yuv_to_rgb(xx,yy)"
+    fn yuv_to_rgb(
+        &self, pixels: &mut [(u8, u8, u8); 256], cr: &[i32; 64], cb: &[i32; 64], y: &[i32; 64], xx: usize, yy: usize,
+    ) {
+        let signed = self.current_command.output_signed();
+
+        for py in 0..8 {
+            for px in 0..8 {
+                let chroma_idx = ((px + xx) / 2) + ((py + yy) / 2) * 8;
+                let r = cr[chroma_idx] as f32;
+                let b = cb[chroma_idx] as f32;
+
+                let g = (-0.3437 * b) + (-0.7143 * r);
+                let r = 1.402 * r;
+                let b = 1.772 * b;
+
+                let luma = y[px + py * 8] as f32;
+                let convert = |component: f32| {
+                    let value = (luma + component).clamp(-128.0, 127.0) as i8 as u8;
+                    if signed { value } else { value ^ 0x80 }
+                };
+
+                pixels[(px + xx) + (py + yy) * 16] = (convert(r), convert(g), convert(b));
+            }
+        }
+    }
+}
+
+impl Default for Mdec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline(always)]
+fn signed10bit(value: u16) -> i32 {
+    ((value as i32) << 22) >> 22
+}
+
+impl Bus32 for Mdec {
+    fn read_u32(&mut self, address: u32) -> u32 {
+        match address {
+            MDEC_DATA_ADDR_START => self.read_data(),
+            MDEC_CONTROL_ADDR_START => self.status(),
+            _ => {
+                tracing::error!(target: "psx_core::mdec", address = %format!("{:08X}", address), "Unaligned MDEC read");
+                0
+            }
+        }
+    }
+
+    fn write_u32(&mut self, address: u32, value: u32) {
+        match address {
+            MDEC_DATA_ADDR_START => self.write_data(value),
+            MDEC_CONTROL_ADDR_START => self.write_control(value),
+            _ => {
+                tracing::error!(target: "psx_core::mdec", address = %format!("{:08X}", address), value = %format!("{:08X}", value), "Unaligned MDEC write");
+            }
+        }
+    }
+}
+
+impl Savestate for Mdec {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.control.0);
+        writer.write(&self.current_command.0);
+        writer.write(&self.parameters_left);
+        writer.write(&self.parameters);
+        writer.write(&self.output);
+        writer.write(&self.luminance_quant_table);
+        writer.write(&self.color_quant_table);
+        writer.write(&self.scale_table);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.control.0 = reader.read()?;
+        self.current_command.0 = reader.read()?;
+        self.parameters_left = reader.read()?;
+        self.parameters = reader.read()?;
+        self.output = reader.read()?;
+        self.luminance_quant_table = reader.read()?;
+        self.color_quant_table = reader.read()?;
+        self.scale_table = reader.read()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DECODE: u32 = 1 << 29;
+    const SET_QUANT_TABLE: u32 = 2 << 29;
+    const SET_SCALE_TABLE: u32 = 3 << 29;
+
+    const MONO4: u32 = 0 << 27;
+    const MONO8: u32 = 1 << 27;
+    const RGB15: u32 = 3 << 27;
+    const SIGNED: u32 = 1 << 26;
+    const BIT15: u32 = 1 << 25;
+
+    // First row of the IDCT matrix, enough to spread a DC coefficient evenly over its block
+    const DC_SCALE: u32 = 0x5A82_5A82;
+
+    // Quant scale 1 and DC 80h, then the end of block code
+    const LUMA_BLOCK: u32 = 0xFE00_0480;
+    const ZERO_BLOCK: u32 = 0xFE00_0400;
+
+    /// An MDEC with every quant table entry at 1 and an IDCT that only passes on the DC coefficient
+    fn mdec() -> Mdec {
+        let mut mdec = Mdec::new();
+
+        mdec.write_data(SET_QUANT_TABLE | 1);
+        for _ in 0..32 {
+            mdec.write_data(0x0101_0101);
+        }
+
+        mdec.write_data(SET_SCALE_TABLE);
+        for word in 0..32 {
+            mdec.write_data(if word < 4 { DC_SCALE } else { 0 });
+        }
+
+        mdec
+    }
+
+    fn decode(mdec: &mut Mdec, command: u32, data: &[u32]) -> Vec<u32> {
+        mdec.write_data(DECODE | command | data.len() as u32);
+        for &word in data {
+            mdec.write_data(word);
+        }
+        mdec.output.drain(..).collect()
+    }
+
+    #[test]
+    fn test_status_while_receiving_parameters() {
+        let mut mdec = Mdec::new();
+        assert_eq!(mdec.status(), 0x8000_FFFF);
+
+        mdec.write_data(DECODE | MONO8 | SIGNED | 3);
+        assert_eq!(mdec.status(), 0xA300_0002);
+
+        mdec.write_data(PADDING as u32 | (PADDING as u32) << 16);
+        assert_eq!(mdec.status() & 0xFFFF, 1);
+
+        // Reset aborts the command
+        mdec.write_control(0x8000_0000);
+        assert_eq!(mdec.status(), 0x8000_FFFF);
+        assert!(mdec.parameters.is_empty());
+    }
+
+    #[test]
+    fn test_quant_and_scale_tables() {
+        let mut mdec = Mdec::new();
+
+        mdec.write_data(SET_QUANT_TABLE);
+        for word in 0..16u32 {
+            mdec.write_data(u32::from_le_bytes([0, 1, 2, 3].map(|byte| (word * 4 + byte) as u8)));
+        }
+        assert_eq!(mdec.luminance_quant_table, std::array::from_fn(|i| i as u8));
+        assert_eq!(mdec.color_quant_table, [0; 64]);
+
+        mdec.write_data(SET_QUANT_TABLE | 1);
+        for _ in 0..16 {
+            mdec.write_data(0x0202_0202);
+        }
+        for _ in 0..16 {
+            mdec.write_data(0x0303_0303);
+        }
+        assert_eq!(mdec.luminance_quant_table, [2; 64]);
+        assert_eq!(mdec.color_quant_table, [3; 64]);
+
+        mdec.write_data(SET_SCALE_TABLE);
+        for word in 0..32u32 {
+            mdec.write_data(((word * 2 + 1) << 16) | (word * 2));
+        }
+        assert_eq!(mdec.scale_table, std::array::from_fn(|i| i as i16));
+        assert_eq!(mdec.status() & 0x2000_FFFF, 0xFFFF);
+    }
+
+    #[test]
+    fn test_decode_mono() {
+        let mut mdec = mdec();
+
+        // DC 80h comes out of the IDCT as 10h everywhere
+        assert_eq!(decode(&mut mdec, MONO8 | SIGNED, &[LUMA_BLOCK]), [0x1010_1010; 16]);
+        assert_eq!(decode(&mut mdec, MONO8, &[LUMA_BLOCK]), [0x9090_9090; 16]);
+        assert_eq!(decode(&mut mdec, MONO4, &[LUMA_BLOCK]), [0x9999_9999; 8]);
+
+        // Padding between and after macroblocks is skipped
+        let padding = PADDING as u32 | (PADDING as u32) << 16;
+        let output = decode(&mut mdec, MONO8, &[padding, LUMA_BLOCK, LUMA_BLOCK, padding]);
+        assert_eq!(output, [0x9090_9090; 32]);
+    }
+
+    #[test]
+    fn test_decode_color() {
+        let mut mdec = mdec();
+        let macroblock = [ZERO_BLOCK, ZERO_BLOCK, LUMA_BLOCK, LUMA_BLOCK, LUMA_BLOCK, LUMA_BLOCK];
+
+        // No chroma, so every pixel is the 90h grey of the luma blocks
+        let grey = 0x12 | (0x12 << 5) | (0x12 << 10);
+        assert_eq!(decode(&mut mdec, RGB15, &macroblock), [grey | grey << 16; 128]);
+
+        let grey = grey | 0x8000;
+        assert_eq!(decode(&mut mdec, RGB15 | BIT15, &macroblock), [grey | grey << 16; 128]);
+    }
+
+    #[test]
+    fn test_truncated_macroblock() {
+        let mut mdec = mdec();
+
+        // The end of block code never arrives
+        assert!(decode(&mut mdec, MONO8, &[0x0000_0480]).is_empty());
+        assert!(decode(&mut mdec, RGB15, &[ZERO_BLOCK, ZERO_BLOCK, LUMA_BLOCK]).is_empty());
+    }
+
+    #[test]
+    fn test_dma_requests() {
+        let mut mdec = mdec();
+        assert!(!mdec.data_in_request());
+        assert!(!mdec.data_out_request());
+
+        mdec.write_control(0x6000_0000);
+        assert!(mdec.data_in_request());
+        assert!(!mdec.data_out_request());
+
+        mdec.write_data(DECODE | MONO8 | 1);
+        mdec.write_data(LUMA_BLOCK);
+        assert!(mdec.data_out_request());
+        assert_eq!(mdec.status() & 0x8800_0000, 0x0800_0000);
+
+        for _ in 0..16 {
+            mdec.read_data();
+        }
+        assert!(!mdec.data_out_request());
+        assert_eq!(mdec.status() & 0x8800_0000, 0x8000_0000);
+    }
+}
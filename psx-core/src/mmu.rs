@@ -1,26 +1,71 @@
 pub mod bus;
 pub mod dma;
 
+use crate::cartridge::{Cartridge, EXPANSION1_ADDR_END, EXPANSION1_ADDR_START};
 use crate::cdrom::reg::REG_RDDATA_ADDR;
 use crate::cdrom::{CDROM_ADDR_END, CDROM_ADDR_START, Cdrom};
+use crate::expansion2::{DUART_ADDR_END, DUART_ADDR_START, Expansion2, POST_ADDR_START};
 use crate::gpu::status::DmaDirection;
 use crate::gpu::{GP0_ADDRESS_END, GP0_ADDRESS_START, GP1_ADDRESS_END, GP1_ADDRESS_START, Gpu};
 use crate::irq::{I_MASK_ADDR_END, I_MASK_ADDR_START, I_STAT_ADDR_END, I_STAT_ADDR_START, Irq};
+use crate::mdec::{MDEC_ADDR_END, MDEC_ADDR_START, Mdec};
 use crate::mmu::bus::{Bus8 as _, Bus32};
-use crate::mmu::dma::{Channel, DMA_INTERRUPT_REGISTER_ADDRESS_END, DMA0_ADDRESS_START, Dma, TransferMode};
+use crate::mmu::dma::{
+    CDROM_CHANNEL_ID, ChannelControl, DMA_INTERRUPT_REGISTER_ADDRESS_END, DMA0_ADDRESS_START, Dma, GPU_CHANNEL_ID,
+    MDEC_IN_CHANNEL_ID, MDEC_OUT_CHANNEL_ID, OTC_CHANNEL_ID, PIO_CHANNEL_ID, SPU_CHANNEL_ID, TransferMode,
+    with_channel,
+};
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
+use crate::scheduler::{Event, Scheduler};
 use crate::sio::{SIO_ADDR_END, SIO_ADDR_START, Sio};
 use crate::spu::Spu;
 use crate::timer::{TIMER0_COUNTER_ADDR_START, TIMER2_TARGET_ADDR_END, Timers};
 
+crate::define_addr!(RAM_SIZE_ADDR, 0x1F80_1060, 0, 4, 4);
+crate::define_addr!(SCRATCHPAD_ADDR, 0x1F80_0000, 0, 0x400, 0x400);
+crate::define_addr!(BIOS_ADDR, 0x1FC0_0000, 0, 0x8_0000, 0x8_0000);
+
+// The first 8 MiB of KUSEG/KSEG0/KSEG1 are decoded by RAM_SIZE
+const RAM_WINDOW_END: u32 = 0x007F_FFFF;
+
+// PSX-SPX: "00000B88h" is written by the BIOS, 8MB memory window
+const RAM_SIZE_DEFAULT: u32 = 0x0000_0B88;
+
+/// Amount of main RAM installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RamSize {
+    /// 2 MiB of retail consoles
+    #[default]
+    Retail,
+    /// 8 MiB of DTL-H2000 style dev kits
+    DevKit,
+}
+
+impl RamSize {
+    pub const fn bytes(&self) -> u32 {
+        match self {
+            RamSize::Retail => 2 * 1024 * 1024,
+            RamSize::DevKit => 8 * 1024 * 1024,
+        }
+    }
+}
+
 pub struct Mmu {
     pub memory: Box<[u8; 0xFFFF_FFFF]>, // 512 KB BIOS
     pub cdrom: Cdrom,
     pub spu: Spu,
+    pub mdec: Mdec,
     pub gpu: Gpu,
     pub dma: Dma,
     pub irq: Irq,
     pub sio: Sio,
     pub timers: Timers,
+    pub expansion2: Expansion2,
+    pub cartridge: Option<Cartridge>,
+    pub scheduler: Scheduler,
+    ram_size: RamSize,
+    ram_size_register: u32,
+    ram_window: u32, // Bytes of the 8 MiB window that hit memory, the rest is locked or HighZ
 }
 
 impl Mmu {
@@ -29,328 +74,599 @@ impl Mmu {
             memory: vec![0xFF; 0xFFFF_FFFF].try_into().unwrap(),
             cdrom: Cdrom::new(),
             spu: Spu::new(),
+            mdec: Mdec::new(),
             gpu: Gpu::new(),
             dma: Dma::new(),
             irq: Irq::new(),
             sio: Sio::new(),
             timers: Timers::new(),
+            expansion2: Expansion2::new(),
+            cartridge: None,
+            scheduler: Scheduler::new(),
+            ram_size: RamSize::Retail,
+            ram_size_register: RAM_SIZE_DEFAULT,
+            ram_window: RAM_WINDOW_END + 1,
         }
     }
 
-    pub fn perform_dma_transfers(&mut self) {
-        if self.dma.channels.0.channel_control.start_transfer() {
-            self.transfer_dma_channel(self.dma.channels.0);
+    pub fn ram_size(&self) -> RamSize {
+        self.ram_size
+    }
+
+    /// Install `ram_size` of main RAM, mirrored across whatever window RAM_SIZE selects
+    pub fn set_ram_size(&mut self, ram_size: RamSize) {
+        self.ram_size = ram_size;
+        self.update_ram_window();
+    }
+
+    /// PSX-SPX: "9-11 Define 8MB Memory Window (first 8MB of KUSEG,KSEG0,KSEG1)"
+    fn update_ram_window(&mut self) {
+        const MIB: u32 = 1024 * 1024;
+        self.ram_window = match (self.ram_size_register >> 9) & 0b111 {
+            0 | 2 => MIB,
+            1 | 3 => 4 * MIB,
+            4 | 6 => 2 * MIB,
+            _ => 8 * MIB,
+        };
+
+        tracing::debug!(
+            target: "psx_core::mmu",
+            window = %format!("{:X}", self.ram_window),
+            installed = %format!("{:X}", self.ram_size.bytes()),
+            "RAM window changed"
+        );
+    }
+
+    /// Physical RAM offset of `address`, None when the window leaves it undecoded
+    #[inline(always)]
+    fn ram_address(&self, address: u32) -> Option<usize> {
+        if address < self.ram_window {
+            Some((address & (self.ram_size.bytes() - 1)) as usize)
+        } else {
+            tracing::debug!(target: "psx_core::mmu", address = %format!("{:08X}", address), "Access outside of the RAM window");
+            None
         }
+    }
 
-        if self.dma.channels.1.channel_control.start_transfer() {
-            self.transfer_dma_channel(self.dma.channels.1);
+    /// Offset in `memory` of a byte of RAM or the scratchpad, None for everything else as it may be a device
+    pub(crate) fn memory_offset(&self, address: u32) -> Option<usize> {
+        let address = Self::canonicalize_virtual_address(address);
+        match address {
+            0..=RAM_WINDOW_END => self.ram_address(address),
+            SCRATCHPAD_ADDR_START..=SCRATCHPAD_ADDR_END => Some(address as usize),
+            _ => None,
         }
+    }
 
-        if self.dma.channels.2.channel_control.start_transfer() {
-            self.transfer_dma_channel(self.dma.channels.2);
-            if self.gpu.gp.gp1_status.dma_direction() != DmaDirection::CpuToGpu {
-                tracing::error!(
-                    target: "psx_core::mmu",
-                    channel_id = 2,
-                    dma_direction = %self.gpu.gp.gp1_status.dma_direction(),
-                    "DMA is ready, but GPUSTAT is in wrong state"
-                );
+    /// Byte of RAM, the scratchpad or the BIOS, read without any device seeing the access
+    pub(crate) fn peek(&self, address: u32) -> Option<u8> {
+        let offset = match Self::canonicalize_virtual_address(address) {
+            bios_address @ BIOS_ADDR_START..=BIOS_ADDR_END => bios_address as usize,
+            _ => self.memory_offset(address)?,
+        };
+        Some(self.memory[offset])
+    }
+
+    /// Store a byte in RAM or the scratchpad without any device seeing the access, false anywhere else
+    pub(crate) fn poke(&mut self, address: u32, value: u8) -> bool {
+        match self.memory_offset(address) {
+            Some(offset) => {
+                self.memory[offset] = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Identifies the BIOS image, save states only load on top of the BIOS they were made with
+    pub(crate) fn bios_checksum(&self) -> u64 {
+        crate::savestate::checksum(&self.memory[BIOS_ADDR_START as usize..=BIOS_ADDR_END as usize])
+    }
+
+    /// Catch the timers up to the current cycle and raise any IRQs they produced on the way
+    pub(crate) fn sync_timers(&mut self) {
+        let irqs = self.timers.sync(self.scheduler.now());
+        self.raise_timer_irqs(irqs);
+    }
+
+    pub(crate) fn schedule_timers(&mut self) {
+        for (timer_id, cycles) in self.timers.cycles_until_irq().into_iter().enumerate() {
+            let event = Event::Timer(timer_id as u8);
+            match cycles {
+                Some(cycles) => self.scheduler.schedule(event, cycles),
+                None => self.scheduler.cancel(event),
             }
         }
+    }
+
+    #[inline(always)]
+    fn raise_timer_irqs(&mut self, (tmr0_irq, tmr1_irq, tmr2_irq): (bool, bool, bool)) {
+        if tmr0_irq {
+            self.irq.status.set_tmr0(true);
+        }
+        if tmr1_irq {
+            self.irq.status.set_tmr1(true);
+        }
+        if tmr2_irq {
+            self.irq.status.set_tmr2(true);
+        }
+    }
+
+    /// Feed a blanking edge to the timers, counting up to this point with the previous levels
+    pub(crate) fn set_video_blanking(&mut self, in_hblank: bool, in_vblank: bool) {
+        self.sync_timers();
+
+        let irqs = self.timers.set_blanking(in_hblank, in_vblank);
+        self.raise_timer_irqs(irqs);
+        self.gpu.timing.in_hblank = in_hblank;
+        self.gpu.timing.in_vblank = in_vblank;
+
+        self.schedule_timers();
+    }
+
+    /// GP1 can change the horizontal resolution, which changes the dot clock timer 0 may be counting
+    fn write_gp1(&mut self, address: u32, value: u32) {
+        self.sync_timers();
+
+        self.gpu.write_u32(address, value);
+        self.timers.dot_clock_divider = self.gpu.gp.gp1_status.dot_clock_divider();
+
+        self.schedule_timers();
+
+        // GP1(04h) selects the DMA direction, which drives DREQ for channel 2
+        self.perform_dma_transfers();
+    }
 
-        if self.dma.channels.3.channel_control.start_transfer() {
-            self.transfer_dma_channel(self.dma.channels.3);
+    pub(crate) fn sync_cdrom(&mut self) {
+        self.cdrom.sync(self.scheduler.now());
+        if self.cdrom.check_and_clear_irq() {
+            self.irq.status.set_cdrom(true);
         }
+    }
 
-        if self.dma.channels.4.channel_control.start_transfer() {
-            self.transfer_dma_channel(self.dma.channels.4);
+    pub(crate) fn schedule_cdrom(&mut self) {
+        match self.cdrom.cycles_until_event() {
+            Some(cycles) => self.scheduler.schedule(Event::Cdrom, cycles),
+            None => self.scheduler.cancel(Event::Cdrom),
         }
+    }
 
-        if self.dma.channels.5.channel_control.start_transfer() {
-            self.transfer_dma_channel(self.dma.channels.5);
+    pub(crate) fn sync_sio(&mut self) {
+        self.sio.sync(self.scheduler.now());
+        if self.sio.should_trigger_irq() {
+            self.irq.status.set_controller_and_memory_card(true);
         }
+        if self.sio.check_and_clear_sio1_irq() {
+            self.irq.status.set_sio(true);
+        }
+    }
 
-        if self.dma.channels.6.channel_control.start_transfer() {
-            self.transfer_dma_channel(self.dma.channels.6);
+    pub(crate) fn schedule_sio(&mut self) {
+        match self.sio.cycles_until_event() {
+            Some(cycles) => self.scheduler.schedule(Event::Sio, cycles),
+            None => self.scheduler.cancel(Event::Sio),
         }
     }
 
     #[inline(always)]
-    pub fn transfer_dma_channel<const CHANNEL_ID: u8>(&mut self, channel: Channel<CHANNEL_ID>) {
+    fn access_timers<T>(&mut self, f: impl FnOnce(&mut Timers) -> T) -> T {
+        self.sync_timers();
+        let result = f(&mut self.timers);
+        self.schedule_timers();
+        result
+    }
+
+    #[inline(always)]
+    fn access_cdrom<T>(&mut self, f: impl FnOnce(&mut Cdrom) -> T) -> T {
+        self.sync_cdrom();
+        let result = f(&mut self.cdrom);
+        self.sync_cdrom();
+        self.schedule_cdrom();
+        self.perform_dma_transfers();
+        result
+    }
+
+    /// MDEC and SPU accesses can raise their DMA requests
+    #[inline(always)]
+    fn access_mdec<T>(&mut self, f: impl FnOnce(&mut Mdec) -> T) -> T {
+        let result = f(&mut self.mdec);
+        self.perform_dma_transfers();
+        result
+    }
+
+    #[inline(always)]
+    fn access_spu<T>(&mut self, f: impl FnOnce(&mut Spu) -> T) -> T {
+        let result = f(&mut self.spu);
+        self.perform_dma_transfers();
+        result
+    }
+
+    #[inline(always)]
+    fn access_sio<T>(&mut self, f: impl FnOnce(&mut Sio) -> T) -> T {
+        self.sync_sio();
+        let result = f(&mut self.sio);
+        self.sync_sio();
+        self.schedule_sio();
+        result
+    }
+
+    #[inline(always)]
+    fn access_dma<T>(&mut self, f: impl FnOnce(&mut Dma) -> T) -> T {
+        let result = f(&mut self.dma);
+        self.perform_dma_transfers();
+        result
+    }
+
+    /// Start every channel that is enabled, started and requested by its device, highest priority first
+    pub fn perform_dma_transfers(&mut self) {
+        // Device accesses made by a transfer must not start another transfer in the middle of it
+        if self.dma.running {
+            return;
+        }
+
+        self.dma.running = true;
+        while let Some(channel_id) = self.next_dma_channel() {
+            self.run_dma_channel(channel_id);
+        }
+        self.dma.running = false;
+    }
+
+    /// Continue a chopped transfer once the CPU had its window on the bus
+    pub(crate) fn resume_dma_channel(&mut self, channel_id: u8) {
+        let control = with_channel!(self.dma, channel_id, channel => channel.channel_control);
+
+        // Software may have stopped the channel in the meantime
+        let active = control.start_transfer() && self.dma.control.channel_enabled(channel_id);
+
+        if active && self.dma_request(channel_id) {
+            self.dma.running = true;
+            self.run_dma_channel(channel_id);
+            self.dma.running = false;
+        } else if active {
+            // The device dropped its request during the CPU window, look again after another one
+            self.scheduler
+                .schedule(Event::Dma(channel_id), control.chopping_cpu_window_cycles());
+        }
+
+        self.perform_dma_transfers();
+    }
+
+    fn next_dma_channel(&mut self) -> Option<u8> {
+        let mut next: Option<(u8, u8)> = None;
+
+        for channel_id in 0..7 {
+            if !self.is_dma_channel_ready(channel_id) {
+                continue;
+            }
+
+            // PSX-SPX: "if two or more channels have the same priority, then the higher channel number wins"
+            let priority = self.dma.control.channel_priority(channel_id);
+            if next.is_none_or(|(best_priority, _)| priority <= best_priority) {
+                next = Some((priority, channel_id));
+            }
+        }
+
+        next.map(|(_, channel_id)| channel_id)
+    }
+
+    fn is_dma_channel_ready(&mut self, channel_id: u8) -> bool {
+        let control = with_channel!(self.dma, channel_id, channel => channel.channel_control);
+
+        if !control.start_transfer() || !self.dma.control.channel_enabled(channel_id) {
+            return false;
+        }
+
+        // Chopped transfers continue from their own scheduler event
+        if self.scheduler.deadline(Event::Dma(channel_id)).is_some() {
+            return false;
+        }
+
+        // PSX-SPX: "Start/Trigger (0=Normal, 1=Manual Start; use for SyncMode=0)"
+        if control.transfer_mode() == TransferMode::Burst && !control.force_transfer_start() {
+            return false;
+        }
+
+        self.dma_request(channel_id)
+    }
+
+    /// State of the DREQ line between the device and its DMA channel
+    fn dma_request(&self, channel_id: u8) -> bool {
+        match channel_id {
+            MDEC_IN_CHANNEL_ID => self.mdec.data_in_request(),
+            MDEC_OUT_CHANNEL_ID => self.mdec.data_out_request(),
+            GPU_CHANNEL_ID => self.gpu.gp.gp1_status.dma_direction() != DmaDirection::Off,
+            CDROM_CHANNEL_ID => self.cdrom.data_request(),
+            SPU_CHANNEL_ID => self.spu.dma_request(),
+            PIO_CHANNEL_ID | OTC_CHANNEL_ID => true,
+            _ => unreachable!(),
+        }
+    }
+
+    fn run_dma_channel(&mut self, channel_id: u8) {
+        let (base_address, block_control, control) = with_channel!(self.dma, channel_id, channel => {
+            (channel.base_address(), channel.block_control, channel.channel_control)
+        });
+
         tracing::debug!(
             target: "psx_core::dma",
-            channel_id = CHANNEL_ID,
-            base_address = %format!("{:08X}", channel.base_address),
-            direction = if channel.channel_control.transfer_direction() {
+            channel_id,
+            base_address = %format!("{:08X}", base_address),
+            direction = if control.transfer_direction() {
                 "RAM to device"
             } else {
                 "Device to RAM"
             },
-            transfer_mode = %channel.channel_control.transfer_mode(),
-            block_control = %format!("{:08X}", channel.block_control()),
-            madr_step = if channel.channel_control.madr_increment_per_step() {
+            transfer_mode = %control.transfer_mode(),
+            block_control = %format!("{:08X}", block_control),
+            madr_step = if control.madr_increment_per_step() {
                 "-4"
             } else {
                 "+4"
             },
-            "DMA transfer started"
+            chopping = control.chopping_enable(),
+            "DMA transfer running"
         );
 
-        match CHANNEL_ID {
-            2 => self.perform_gpu_dma(),
-            3 => self.perform_cdrom_dma(),
-            6 => self.perform_otc_dma(),
-            _ => {
-                tracing::error!(target: "psx_core::dma", channel_id = CHANNEL_ID, "DMA transfer not implemented for this channel")
-            }
+        match control.transfer_mode() {
+            TransferMode::Burst => self.run_dma_burst(channel_id, base_address, block_control, control),
+            TransferMode::Slice => self.run_dma_slice(channel_id, base_address, block_control, control),
+            TransferMode::LinkedList => self.run_dma_linked_list(channel_id, base_address, control),
         }
+    }
 
-        // Clear the start_transfer bit after handling
-        match CHANNEL_ID {
-            0 => self.dma.channels.0.set_completed(),
-            1 => self.dma.channels.1.set_completed(),
-            2 => self.dma.channels.2.set_completed(),
-            3 => self.dma.channels.3.set_completed(),
-            4 => self.dma.channels.4.set_completed(),
-            5 => self.dma.channels.5.set_completed(),
-            6 => self.dma.channels.6.set_completed(),
-            _ => unreachable!(),
-        }
+    /// SyncMode 0: transfer all words at once, or one chopping window at a time
+    fn run_dma_burst(&mut self, channel_id: u8, mut address: u32, block_control: u32, control: ChannelControl) {
+        with_channel!(self.dma, channel_id, channel => channel.channel_control.set_force_transfer_start(false));
 
-        let old_master_flag = self.dma.interrupt.master_interrupt();
+        // PSX-SPX: "0-15 BC Number of words (0001h..FFFFh) (or 0=10000h words)"
+        let words_left = match block_control & 0xFFFF {
+            0 => 0x1_0000,
+            words => words,
+        };
 
-        let channel_mask_bit = (self.dma.interrupt.interrupt_mask() >> CHANNEL_ID) & 1;
-        if channel_mask_bit == 1 {
-            // Set the completion flag for this channel
-            let new_flags = self.dma.interrupt.interrupt_flags() | (1 << CHANNEL_ID);
-            self.dma.interrupt.set_interrupt_flags(new_flags);
+        let words = if control.chopping_enable() {
+            words_left.min(control.chopping_dma_window_words())
+        } else {
+            words_left
+        };
 
-            tracing::trace!(
-                target: "psx_core::dma",
-                channel_id = CHANNEL_ID,
-                "DMA channel completion flag set"
-            );
+        // PSX-SPX: OTC always runs backwards, regardless of the step bit
+        let step = if channel_id == OTC_CHANNEL_ID {
+            -4
+        } else {
+            control.madr_step()
+        };
+
+        for i in 0..words {
+            let is_last = words_left - i == 1;
+            self.transfer_dma_word(channel_id, address, control.transfer_direction(), is_last);
+            address = address.wrapping_add_signed(step);
         }
 
-        // PSX-SPX: "IF b15=1 OR (b23=1 AND (b16-22 AND b24-30)>0) THEN b31=1 ELSE b31=0"
-        // Recalculate the read-only master interrupt flag
-        let bus_error = self.dma.interrupt.bus_error();
-        let master_enable = self.dma.interrupt.master_interrupt_enable();
-        let masked_flags = self.dma.interrupt.interrupt_mask() & self.dma.interrupt.interrupt_flags();
+        let transfer_cycles = words as u64 * dma::cycles_per_word(channel_id);
+        self.dma.stall_cycles += transfer_cycles;
 
-        let new_master_flag = bus_error || (master_enable && masked_flags != 0);
-        self.dma.interrupt.set_master_interrupt(new_master_flag);
+        let words_left = words_left - words;
 
-        if !old_master_flag && new_master_flag {
-            self.irq.status.set_dma(true);
-            tracing::debug!(
-                target: "psx_core::dma",
-                channel_id = CHANNEL_ID,
-                "DMA IRQ raised"
+        // PSX-SPX: MADR and BCR only reflect the progress of SyncMode 0 transfers when chopping is enabled
+        if control.chopping_enable() {
+            with_channel!(self.dma, channel_id, channel => {
+                channel.base_address = address & 0x00FF_FFFF;
+                channel.block_control = (block_control & 0xFFFF_0000) | (words_left & 0xFFFF);
+            });
+        }
+
+        if words_left > 0 {
+            // The CPU gets the bus back for a while before the next window
+            self.scheduler.schedule(
+                Event::Dma(channel_id),
+                transfer_cycles + control.chopping_cpu_window_cycles(),
             );
+        } else {
+            self.complete_dma_transfer(channel_id);
         }
     }
 
-    /// Perform a DMA transfer for channel 6 (OTC)
-    /// Clears the linked list used for polygons
-    pub fn perform_otc_dma(&mut self) {
-        let channel = &self.dma.channels.6;
+    /// SyncMode 1: transfer blocks for as long as the device keeps requesting data
+    fn run_dma_slice(&mut self, channel_id: u8, mut address: u32, block_control: u32, control: ChannelControl) {
+        // PSX-SPX: "0-15 BS Blocksize (words)", "16-31 BA Amount of blocks"
+        let block_size = match block_control & 0xFFFF {
+            0 => 0x1_0000,
+            size => size,
+        };
+        let mut blocks = block_control >> 16;
 
-        let madr = channel.base_address();
-        let bcr = channel.bcr_word_count();
+        while blocks > 0 && self.dma_request(channel_id) {
+            for _ in 0..block_size {
+                self.transfer_dma_word(channel_id, address, control.transfer_direction(), false);
+                address = address.wrapping_add_signed(control.madr_step());
+            }
 
-        let mut write_u32 = |addr, value| {
-            self.write_u32(addr, value);
-        };
+            blocks -= 1;
+            self.dma.stall_cycles += block_size as u64 * dma::cycles_per_word(channel_id);
+        }
 
-        let mut current_addr = madr;
+        // PSX-SPX: MADR is incremented and BA decremented after each block
+        with_channel!(self.dma, channel_id, channel => {
+            channel.base_address = address & 0x00FF_FFFF;
+            channel.block_control = (blocks << 16) | (block_control & 0xFFFF);
+        });
 
-        if bcr == 1 {
-            write_u32(current_addr, 0xFFFFFFFF);
+        if blocks == 0 {
+            self.complete_dma_transfer(channel_id);
         } else {
-            for i in 0..bcr {
-                if i == bcr - 1 {
-                    // Last entry gets end marker
-                    write_u32(current_addr, 0xFFFFFFFF);
-                } else {
-                    // Each entry points to next entry
-                    write_u32(current_addr, current_addr.wrapping_sub(4));
-                }
-                current_addr = current_addr.wrapping_sub(4);
-            }
+            tracing::trace!(
+                target: "psx_core::dma",
+                channel_id,
+                blocks_left = blocks,
+                "DMA request dropped, pausing transfer"
+            );
         }
     }
 
-    pub fn perform_gpu_dma(&mut self) {
-        let channel = self.dma.channels.2;
-        let transfer_direction = channel.channel_control.transfer_direction();
-        let madr_step = channel.channel_control.madr_step();
-
-        match channel.channel_control.transfer_mode() {
-            TransferMode::Burst => {
-                let total_words = channel.bcr_word_count();
-                let mut addr = channel.base_address();
-
-                if transfer_direction {
-                    // RAM to GPU
-                    for _ in 0..total_words {
-                        let word = self.read_u32(addr);
-                        self.write_u32(GP0_ADDRESS_START, word);
-                        addr = addr.wrapping_add_signed(madr_step);
-                    }
-                } else {
-                    // GPU to RAM
-                    for _ in 0..total_words {
-                        let word = self.read_u32(GP0_ADDRESS_START);
-                        self.write_u32(addr, word);
-                        addr = addr.wrapping_add_signed(madr_step);
-                    }
-                }
-            }
-            TransferMode::Slice => {
-                let total_words = channel.bcr_block_total();
-                let mut addr = channel.base_address();
-
-                if transfer_direction {
-                    // RAM to GPU
-                    for _ in 0..total_words {
-                        let word = self.read_u32(addr);
-                        self.write_u32(GP0_ADDRESS_START, word);
-                        addr = addr.wrapping_add_signed(madr_step);
-                    }
-                } else {
-                    // GPU to RAM
-                    for _ in 0..total_words {
-                        let word = self.read_u32(GP0_ADDRESS_START);
-                        self.write_u32(addr, word);
-                        addr = addr.wrapping_add_signed(madr_step);
-                    }
-                }
+    /// SyncMode 2: walk a GPU command list until the end marker
+    fn run_dma_linked_list(&mut self, channel_id: u8, mut address: u32, control: ChannelControl) {
+        if channel_id != GPU_CHANNEL_ID || !control.transfer_direction() {
+            tracing::error!(
+                target: "psx_core::dma",
+                channel_id,
+                "LinkedList mode is only supported for RAM to GPU"
+            );
+            self.complete_dma_transfer(channel_id);
+            return;
+        }
+
+        // A list can't hold more words than RAM does, so walking further than that means it loops back on itself
+        let max_words = self.ram_size.bytes() as u64 / 4;
+        let mut transferred_words = 0u64;
+
+        loop {
+            if transferred_words >= max_words {
+                tracing::error!(
+                    target: "psx_core::dma",
+                    channel_id,
+                    address = format!("{:08X}", address),
+                    "DMA linked list has no end marker, stopping the transfer"
+                );
+                break;
             }
-            TransferMode::LinkedList => {
-                // Linked list is only for RAM to GPU
-                if !transfer_direction {
-                    tracing::error!(
-                        target: "psx_core::dma",
-                        "GPU DMA LinkedList mode only supports RAM to GPU"
-                    );
-                    return;
-                }
 
-                let mut src_addr = channel.base_address();
-
-                loop {
-                    let header = self.read_u32(src_addr);
-                    let next_addr = header & 0x00FFFFFF; // next node (24-bit address)
-                    let words = header >> 24; // number of words in this node
-
-                    tracing::trace!(
-                        target: "psx_core::dma",
-                        address = format!("{:08X}", src_addr),
-                        words = words,
-                        next_address = format!("{:08X}", next_addr),
-                        is_end = next_addr == 0x00FFFFFF,
-                        "Transfering DMA linked list node"
-                    );
-
-                    // Transfer all words from this node
-                    // Start from the word after the header
-                    let mut word_addr = src_addr.wrapping_add(4);
-                    for _ in 0..words {
-                        let word = self.read_u32(word_addr);
-                        self.write_u32(GP0_ADDRESS_START, word);
-
-                        tracing::trace!(
-                            target: "psx_core::dma",
-                            address = format!("{:08X}", word_addr),
-                            word = format!("{:08X}", word),
-                            "Sent word from linked list node to GP0"
-                        );
-
-                        word_addr = word_addr.wrapping_add(4);
-                    }
-
-                    // AFTER processing this node's data, check if this was the last node
-                    if next_addr == 0x00FFFFFF {
-                        break;
-                    }
-
-                    src_addr = next_addr;
-                }
+            let header = self.read_u32(address & 0x00FF_FFFC);
+            let next_address = header & 0x00FF_FFFF; // next node (24-bit address)
+            let words = header >> 24; // number of words in this node
+
+            tracing::trace!(
+                target: "psx_core::dma",
+                address = format!("{:08X}", address),
+                words = words,
+                next_address = format!("{:08X}", next_address),
+                is_end = next_address & 0x80_0000 != 0,
+                "Transfering DMA linked list node"
+            );
+
+            // Transfer all words from this node, starting from the word after the header
+            let mut word_address = address.wrapping_add(4);
+            for _ in 0..words {
+                self.transfer_dma_word(channel_id, word_address, true, false);
+                word_address = word_address.wrapping_add(4);
             }
-        }
-    }
 
-    pub fn perform_cdrom_dma(&mut self) {
-        let channel = self.dma.channels.3;
+            transferred_words += words as u64 + 1;
 
-        let mut dest_addr = channel.base_address();
-        let madr_step = channel.channel_control.madr_step();
+            // PSX-SPX: "The end marker is FFFFFFh, but actually only bit23 is checked"
+            if next_address & 0x80_0000 != 0 {
+                break;
+            }
 
-        tracing::debug!(
-            target: "psx_core::dma",
-            dest_addr = %format!("{:08X}", dest_addr),
-            transfer_mode = %channel.channel_control.transfer_mode(),
-            "CDROM DMA transfer starting"
-        );
+            address = next_address;
+        }
 
-        match channel.channel_control.transfer_mode() {
-            TransferMode::Burst => {
-                let total_words = channel.bcr_word_count();
+        self.dma.stall_cycles += transferred_words * dma::cycles_per_word(channel_id);
 
-                for _ in 0..total_words {
-                    // CDROM data port is 8-bit; pack 4 bytes into a word (little-endian)
-                    let b0 = self.read_u8(REG_RDDATA_ADDR) as u32;
-                    let b1 = self.read_u8(REG_RDDATA_ADDR) as u32;
-                    let b2 = self.read_u8(REG_RDDATA_ADDR) as u32;
-                    let b3 = self.read_u8(REG_RDDATA_ADDR) as u32;
-                    let word = b0 | (b1 << 8) | (b2 << 16) | (b3 << 24);
-                    self.write_u32(dest_addr, word);
+        with_channel!(self.dma, channel_id, channel => channel.base_address = 0x00FF_FFFF);
+        self.complete_dma_transfer(channel_id);
+    }
 
-                    tracing::trace!(
-                        target: "psx_core::dma",
-                        address = format!("{:08X}", dest_addr),
-                        word = format!("{:08X}", word),
-                        "CDROM DMA word transferred to RAM"
-                    );
+    /// Move a single word between RAM and the device behind `channel_id`
+    fn transfer_dma_word(&mut self, channel_id: u8, address: u32, to_device: bool, is_last: bool) {
+        let address = address & 0x00FF_FFFC;
 
-                    dest_addr = dest_addr.wrapping_add_signed(madr_step);
+        if to_device {
+            let word = self.read_u32(address);
+            match channel_id {
+                MDEC_IN_CHANNEL_ID => self.mdec.write_data(word),
+                GPU_CHANNEL_ID => self.gpu.write_u32(GP0_ADDRESS_START, word),
+                SPU_CHANNEL_ID => self.spu.dma_write(word),
+                PIO_CHANNEL_ID => self.write_u32(dma::PIO_ADDRESS, word),
+                _ => {
+                    tracing::error!(target: "psx_core::dma", channel_id, "DMA channel does not support RAM to device transfers");
                 }
             }
-            TransferMode::Slice => {
-                let total_words = channel.bcr_block_total();
 
-                for _ in 0..total_words {
+            tracing::trace!(
+                target: "psx_core::dma",
+                channel_id,
+                address = format!("{:08X}", address),
+                word = format!("{:08X}", word),
+                "DMA word transferred to device"
+            );
+        } else {
+            let word = match channel_id {
+                MDEC_OUT_CHANNEL_ID => self.mdec.read_data(),
+                GPU_CHANNEL_ID => self.gpu.read_u32(GP0_ADDRESS_START),
+                CDROM_CHANNEL_ID => {
                     // CDROM data port is 8-bit; pack 4 bytes into a word (little-endian)
                     let b0 = self.read_u8(REG_RDDATA_ADDR) as u32;
                     let b1 = self.read_u8(REG_RDDATA_ADDR) as u32;
                     let b2 = self.read_u8(REG_RDDATA_ADDR) as u32;
                     let b3 = self.read_u8(REG_RDDATA_ADDR) as u32;
-                    let word = b0 | (b1 << 8) | (b2 << 16) | (b3 << 24);
-                    self.write_u32(dest_addr, word);
+                    b0 | (b1 << 8) | (b2 << 16) | (b3 << 24)
+                }
+                SPU_CHANNEL_ID => self.spu.dma_read(),
+                PIO_CHANNEL_ID => self.read_u32(dma::PIO_ADDRESS),
+                // Each entry points to the previous one, the last entry gets the end marker
+                OTC_CHANNEL_ID if is_last => 0x00FF_FFFF,
+                OTC_CHANNEL_ID => address.wrapping_sub(4) & 0x00FF_FFFF,
+                _ => {
+                    tracing::error!(target: "psx_core::dma", channel_id, "DMA channel does not support device to RAM transfers");
+                    return;
+                }
+            };
 
-                    tracing::trace!(
-                        target: "psx_core::dma",
-                        address = format!("{:08X}", dest_addr),
-                        word = format!("{:08X}", word),
-                        "CDROM DMA word transferred to RAM (Slice mode)"
-                    );
+            self.write_u32(address, word);
 
-                    dest_addr = dest_addr.wrapping_add_signed(madr_step);
-                }
-            }
-            TransferMode::LinkedList => {
-                tracing::error!(
-                    target: "psx_core::dma",
-                    "CDROM DMA does not support LinkedList mode"
-                );
-            }
+            tracing::trace!(
+                target: "psx_core::dma",
+                channel_id,
+                address = format!("{:08X}", address),
+                word = format!("{:08X}", word),
+                "DMA word transferred to RAM"
+            );
+        }
+    }
+
+    /// Finish a transfer and raise the DMA IRQ if enabled
+    pub(crate) fn complete_dma_transfer(&mut self, channel_id: u8) {
+        // Clear the start_transfer bit after handling
+        with_channel!(self.dma, channel_id, channel => channel.set_completed());
+
+        let old_master_flag = self.dma.interrupt.master_interrupt();
+
+        let channel_mask_bit = (self.dma.interrupt.interrupt_mask() >> channel_id) & 1;
+        if channel_mask_bit == 1 {
+            // Set the completion flag for this channel
+            let new_flags = self.dma.interrupt.interrupt_flags() | (1 << channel_id);
+            self.dma.interrupt.set_interrupt_flags(new_flags);
+
+            tracing::trace!(
+                target: "psx_core::dma",
+                channel_id,
+                "DMA channel completion flag set"
+            );
+        }
+
+        // PSX-SPX: "IF b15=1 OR (b23=1 AND (b16-22 AND b24-30)>0) THEN b31=1 ELSE b31=0"
+        // Recalculate the read-only master interrupt flag
+        let bus_error = self.dma.interrupt.bus_error();
+        let master_enable = self.dma.interrupt.master_interrupt_enable();
+        let masked_flags = self.dma.interrupt.interrupt_mask() & self.dma.interrupt.interrupt_flags();
+
+        let new_master_flag = bus_error || (master_enable && masked_flags != 0);
+        self.dma.interrupt.set_master_interrupt(new_master_flag);
+
+        if !old_master_flag && new_master_flag {
+            self.irq.status.set_dma(true);
+            tracing::debug!(
+                target: "psx_core::dma",
+                channel_id,
+                "DMA IRQ raised"
+            );
         }
     }
 
     pub fn load(&mut self, address: u32, data: &[u8]) {
         for (i, &byte) in data.iter().enumerate() {
-            self.write_u8(address + i as u32, byte);
+            self.write_u8(address.wrapping_add(i as u32), byte);
         }
     }
 
@@ -372,22 +688,74 @@ impl Mmu {
     }
 }
 
+/// Main RAM, the scratchpad and every device. The BIOS, the disc and cartridge or memory card images are
+/// media and stay as they are
+impl Savestate for Mmu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.ram_size_register);
+        writer.write_bytes(&self.memory[..self.ram_size.bytes() as usize]);
+        writer.write_bytes(&self.memory[SCRATCHPAD_ADDR_START as usize..=SCRATCHPAD_ADDR_END as usize]);
+
+        self.cdrom.save_state(writer);
+        self.spu.save_state(writer);
+        self.mdec.save_state(writer);
+        self.gpu.save_state(writer);
+        self.dma.save_state(writer);
+        self.irq.save_state(writer);
+        self.sio.save_state(writer);
+        self.timers.save_state(writer);
+        self.expansion2.save_state(writer);
+        self.scheduler.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.ram_size_register = reader.read()?;
+        self.update_ram_window();
+        reader.read_into(&mut self.memory[..self.ram_size.bytes() as usize])?;
+        reader.read_into(&mut self.memory[SCRATCHPAD_ADDR_START as usize..=SCRATCHPAD_ADDR_END as usize])?;
+
+        self.cdrom.load_state(reader)?;
+        self.spu.load_state(reader)?;
+        self.mdec.load_state(reader)?;
+        self.gpu.load_state(reader)?;
+        self.dma.load_state(reader)?;
+        self.irq.load_state(reader)?;
+        self.sio.load_state(reader)?;
+        self.timers.load_state(reader)?;
+        self.expansion2.load_state(reader)?;
+        self.scheduler.load_state(reader)
+    }
+}
+
 impl bus::Bus8 for Mmu {
     #[inline(always)]
     fn read_u8(&mut self, address: u32) -> u8 {
         let address = Self::canonicalize_virtual_address(address);
         match address {
-            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => self.timers.read_u8(address),
-            SIO_ADDR_START..=SIO_ADDR_END => self.sio.read_u8(address),
+            0..=RAM_WINDOW_END => match self.ram_address(address) {
+                Some(offset) => self.memory[offset],
+                None => 0xFF,
+            },
+            RAM_SIZE_ADDR_START..=RAM_SIZE_ADDR_END => {
+                let offset = address - RAM_SIZE_ADDR_START;
+                (self.ram_size_register >> (offset * 8)) as u8
+            }
+            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => self.access_timers(|timers| timers.read_u8(address)),
+            SIO_ADDR_START..=SIO_ADDR_END => self.access_sio(|sio| sio.read_u8(address)),
             I_MASK_ADDR_START..=I_MASK_ADDR_END => self.irq.read_u8(address),
             I_STAT_ADDR_START..=I_STAT_ADDR_END => self.irq.read_u8(address),
             DMA0_ADDRESS_START..=DMA_INTERRUPT_REGISTER_ADDRESS_END => self.dma.read_u8(address),
-            CDROM_ADDR_START..=CDROM_ADDR_END => self.cdrom.read_u8(address),
+            CDROM_ADDR_START..=CDROM_ADDR_END => self.access_cdrom(|cdrom| cdrom.read_u8(address)),
             0x1F80_1C00..=0x1F80_1FFF => self.spu.read_u8(address),
             0x1F80_1000..=0x1F80_1FFF => {
                 tracing::error!(target: "psx_core::mmu", address = %format!("{:08X}", address), "Reading from unimplemented I/O port");
                 0xFF
             }
+            DUART_ADDR_START..=DUART_ADDR_END | POST_ADDR_START => self.expansion2.read_u8(address),
+            EXPANSION1_ADDR_START..=EXPANSION1_ADDR_END => match &mut self.cartridge {
+                Some(cartridge) => cartridge.read_u8(address),
+                None => self.memory[address as usize],
+            },
             _ => self.memory[address as usize],
         }
     }
@@ -396,16 +764,35 @@ impl bus::Bus8 for Mmu {
     fn write_u8(&mut self, address: u32, value: u8) {
         let address = Self::canonicalize_virtual_address(address);
         match address {
-            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => self.timers.write_u8(address, value),
-            SIO_ADDR_START..=SIO_ADDR_END => self.sio.write_u8(address, value),
+            0..=RAM_WINDOW_END => {
+                if let Some(offset) = self.ram_address(address) {
+                    self.memory[offset] = value;
+                }
+            }
+            RAM_SIZE_ADDR_START..=RAM_SIZE_ADDR_END => {
+                let shift = (address - RAM_SIZE_ADDR_START) * 8;
+                self.ram_size_register = (self.ram_size_register & !(0xFF << shift)) | ((value as u32) << shift);
+                self.update_ram_window();
+            }
+            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => {
+                self.access_timers(|timers| timers.write_u8(address, value))
+            },
+            SIO_ADDR_START..=SIO_ADDR_END => self.access_sio(|sio| sio.write_u8(address, value)),
             I_MASK_ADDR_START..=I_MASK_ADDR_END => self.irq.write_u8(address, value),
             I_STAT_ADDR_START..=I_STAT_ADDR_END => self.irq.write_u8(address, value),
-            DMA0_ADDRESS_START..=DMA_INTERRUPT_REGISTER_ADDRESS_END => self.dma.write_u8(address, value),
-            CDROM_ADDR_START..=CDROM_ADDR_END => self.cdrom.write_u8(address, value),
-            0x1F80_1C00..=0x1F80_1FFF => self.spu.write_u8(address, value),
+            DMA0_ADDRESS_START..=DMA_INTERRUPT_REGISTER_ADDRESS_END => {
+                self.access_dma(|dma| dma.write_u8(address, value))
+            },
+            CDROM_ADDR_START..=CDROM_ADDR_END => self.access_cdrom(|cdrom| cdrom.write_u8(address, value)),
+            0x1F80_1C00..=0x1F80_1FFF => self.access_spu(|spu| spu.write_u8(address, value)),
             0x1F80_1000..=0x1F80_1FFF => {
                 tracing::error!(target: "psx_core::mmu", address = %format!("{:08X}", address), value = %format!("{:02X}", value), "Writing to unimplemented I/O port");
             }
+            DUART_ADDR_START..=DUART_ADDR_END | POST_ADDR_START => self.expansion2.write_u8(address, value),
+            EXPANSION1_ADDR_START..=EXPANSION1_ADDR_END => match &mut self.cartridge {
+                Some(cartridge) => cartridge.write_u8(address, value),
+                None => self.memory[address as usize] = value,
+            },
             _ => self.memory[address as usize] = value,
         }
     }
@@ -416,8 +803,8 @@ impl bus::Bus16 for Mmu {
     fn read_u16(&mut self, address: u32) -> u16 {
         let address = Self::canonicalize_virtual_address(address);
         match address {
-            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => self.timers.read_u16(address),
-            SIO_ADDR_START..=SIO_ADDR_END => self.sio.read_u16(address),
+            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => self.access_timers(|timers| timers.read_u16(address)),
+            SIO_ADDR_START..=SIO_ADDR_END => self.access_sio(|sio| sio.read_u16(address)),
             I_MASK_ADDR_START..=I_MASK_ADDR_END => self.irq.read_u16(address),
             I_STAT_ADDR_START..=I_STAT_ADDR_END => self.irq.read_u16(address),
             DMA0_ADDRESS_START..=DMA_INTERRUPT_REGISTER_ADDRESS_END => self.dma.read_u16(address),
@@ -429,11 +816,15 @@ impl bus::Bus16 for Mmu {
     fn write_u16(&mut self, address: u32, value: u16) {
         let address = Self::canonicalize_virtual_address(address);
         match address {
-            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => self.timers.write_u16(address, value),
-            SIO_ADDR_START..=SIO_ADDR_END => self.sio.write_u16(address, value),
+            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => {
+                self.access_timers(|timers| timers.write_u16(address, value))
+            },
+            SIO_ADDR_START..=SIO_ADDR_END => self.access_sio(|sio| sio.write_u16(address, value)),
             I_MASK_ADDR_START..=I_MASK_ADDR_END => self.irq.write_u16(address, value),
             I_STAT_ADDR_START..=I_STAT_ADDR_END => self.irq.write_u16(address, value),
-            DMA0_ADDRESS_START..=DMA_INTERRUPT_REGISTER_ADDRESS_END => self.dma.write_u16(address, value),
+            DMA0_ADDRESS_START..=DMA_INTERRUPT_REGISTER_ADDRESS_END => {
+                self.access_dma(|dma| dma.write_u16(address, value))
+            },
             _ => {
                 self.write_u8(address, (value & 0xFF) as u8);
                 self.write_u8(address + 1, ((value >> 8) & 0xFF) as u8);
@@ -447,13 +838,14 @@ impl bus::Bus32 for Mmu {
     fn read_u32(&mut self, address: u32) -> u32 {
         let address = Self::canonicalize_virtual_address(address);
         match address {
-            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => self.timers.read_u32(address),
-            SIO_ADDR_START..=SIO_ADDR_END => self.sio.read_u32(address),
+            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => self.access_timers(|timers| timers.read_u32(address)),
+            SIO_ADDR_START..=SIO_ADDR_END => self.access_sio(|sio| sio.read_u32(address)),
             I_STAT_ADDR_START..=I_STAT_ADDR_END => self.irq.read_u32(address),
             I_MASK_ADDR_START..=I_MASK_ADDR_END => self.irq.read_u32(address),
             DMA0_ADDRESS_START..=DMA_INTERRUPT_REGISTER_ADDRESS_END => self.dma.read_u32(address),
             GP0_ADDRESS_START..=GP0_ADDRESS_END => self.gpu.read_u32(address),
             GP1_ADDRESS_START..=GP1_ADDRESS_END => self.gpu.read_u32(address),
+            MDEC_ADDR_START..=MDEC_ADDR_END => self.access_mdec(|mdec| mdec.read_u32(address)),
             _ => u32::from_le_bytes([
                 self.read_u8(address),
                 self.read_u8(address + 1),
@@ -467,13 +859,18 @@ impl bus::Bus32 for Mmu {
     fn write_u32(&mut self, address: u32, value: u32) {
         let address = Self::canonicalize_virtual_address(address);
         match address {
-            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => self.timers.write_u32(address, value),
-            SIO_ADDR_START..=SIO_ADDR_END => self.sio.write_u32(address, value),
+            TIMER0_COUNTER_ADDR_START..=TIMER2_TARGET_ADDR_END => {
+                self.access_timers(|timers| timers.write_u32(address, value))
+            },
+            SIO_ADDR_START..=SIO_ADDR_END => self.access_sio(|sio| sio.write_u32(address, value)),
             I_MASK_ADDR_START..=I_MASK_ADDR_END => self.irq.write_u32(address, value),
             I_STAT_ADDR_START..=I_STAT_ADDR_END => self.irq.write_u32(address, value),
-            DMA0_ADDRESS_START..=DMA_INTERRUPT_REGISTER_ADDRESS_END => self.dma.write_u32(address, value),
+            DMA0_ADDRESS_START..=DMA_INTERRUPT_REGISTER_ADDRESS_END => {
+                self.access_dma(|dma| dma.write_u32(address, value))
+            },
             GP0_ADDRESS_START..=GP0_ADDRESS_END => self.gpu.write_u32(address, value),
-            GP1_ADDRESS_START..=GP1_ADDRESS_END => self.gpu.write_u32(address, value),
+            GP1_ADDRESS_START..=GP1_ADDRESS_END => self.write_gp1(address, value),
+            MDEC_ADDR_START..=MDEC_ADDR_END => self.access_mdec(|mdec| mdec.write_u32(address, value)),
             _ => {
                 self.write_u8(address, (value & 0xFF) as u8);
                 self.write_u8(address + 1, ((value >> 8) & 0xFF) as u8);
@@ -483,3 +880,232 @@ impl bus::Bus32 for Mmu {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mmu::bus::Bus16 as _;
+    use crate::mmu::dma::ControlRegister;
+    use std::sync::{Mutex, PoisonError};
+
+    const DPCR: u32 = 0x1F80_10F0;
+    const DICR: u32 = 0x1F80_10F4;
+    const SPU_TRANSFER_ADDRESS: u32 = 0x1F80_1DA6;
+    const SPUCNT: u32 = 0x1F80_1DAA;
+
+    // CHCR bits
+    const TO_DEVICE: u32 = 1 << 0;
+    const CHOPPING: u32 = 1 << 8;
+    const SLICE: u32 = 1 << 9;
+    const LINKED_LIST: u32 = 2 << 9;
+    const START: u32 = 1 << 24;
+    const TRIGGER: u32 = 1 << 28;
+
+    // Two words per DMA window, eight cycles per CPU window
+    const CHOPPING_WINDOWS: u32 = CHOPPING | (1 << 16) | (3 << 20);
+
+    // Every MMU maps the whole 4 GiB address space, so the tests take turns on a single one
+    static MMU: Mutex<Option<Mmu>> = Mutex::new(None);
+
+    fn with_mmu(test: impl FnOnce(&mut Mmu)) {
+        let mut mmu = MMU.lock().unwrap_or_else(PoisonError::into_inner);
+        let mmu = mmu.get_or_insert_with(Mmu::new);
+
+        mmu.memory[..RamSize::Retail.bytes() as usize].fill(0);
+        mmu.dma = Dma::new();
+        mmu.spu = Spu::new();
+        mmu.gpu = Gpu::new();
+        mmu.irq = Irq::new();
+        mmu.scheduler = Scheduler::new();
+        test(mmu);
+    }
+
+    fn start_channel(mmu: &mut Mmu, channel_id: u8, base_address: u32, block_control: u32, control: u32) {
+        let registers = 0x1F80_1080 + channel_id as u32 * 0x10;
+        mmu.write_u32(registers, base_address);
+        mmu.write_u32(registers + 4, block_control);
+        mmu.write_u32(registers + 8, control);
+    }
+
+    /// Hand every chopped window to the channel like the scheduler would, returns how many there were
+    fn run_chopped_windows(mmu: &mut Mmu, channel_id: u8) -> usize {
+        let mut windows = 0;
+        while mmu.scheduler.deadline(Event::Dma(channel_id)).is_some() {
+            mmu.scheduler.cancel(Event::Dma(channel_id));
+            mmu.resume_dma_channel(channel_id);
+            windows += 1;
+        }
+        windows
+    }
+
+    #[test]
+    fn test_dma_channel_priority() {
+        with_mmu(|mmu| {
+            // Hold the transfers back, only the arbitration is of interest
+            mmu.dma.running = true;
+
+            // PIO and OTC are always requested by their "device"
+            mmu.dma.channels.5.channel_control = ChannelControl(START | SLICE);
+            mmu.dma.channels.6.channel_control = ChannelControl(START | TRIGGER);
+
+            let dpcr = |pio_priority: u32, otc_priority: u32| {
+                ControlRegister((pio_priority << 20) | (1 << 23) | (otc_priority << 24) | (1 << 27))
+            };
+
+            mmu.dma.control = dpcr(1, 2);
+            assert_eq!(mmu.next_dma_channel(), Some(PIO_CHANNEL_ID));
+
+            mmu.dma.control = dpcr(3, 2);
+            assert_eq!(mmu.next_dma_channel(), Some(OTC_CHANNEL_ID));
+
+            // Same priority, the higher channel wins
+            mmu.dma.control = dpcr(3, 3);
+            assert_eq!(mmu.next_dma_channel(), Some(OTC_CHANNEL_ID));
+
+            // A disabled channel doesn't take part
+            mmu.dma.control = ControlRegister(dpcr(7, 0).0 & !(1 << 27));
+            assert_eq!(mmu.next_dma_channel(), Some(PIO_CHANNEL_ID));
+
+            // Neither does a SyncMode 0 channel without its trigger
+            mmu.dma.control = dpcr(7, 0);
+            mmu.dma.channels.6.channel_control = ChannelControl(START);
+            assert_eq!(mmu.next_dma_channel(), Some(PIO_CHANNEL_ID));
+
+            mmu.dma.channels.5.channel_control = ChannelControl(SLICE);
+            assert_eq!(mmu.next_dma_channel(), None);
+        });
+    }
+
+    #[test]
+    fn test_dma_chopping() {
+        with_mmu(|mmu| {
+            mmu.write_u32(DPCR, 1 << 27);
+            mmu.write_u32(DICR, (1 << 23) | (1 << (16 + OTC_CHANNEL_ID)));
+
+            start_channel(mmu, OTC_CHANNEL_ID, 0x101C, 8, START | TRIGGER | CHOPPING_WINDOWS);
+
+            // The first window cleared two entries, then the CPU gets the bus back
+            assert_eq!(mmu.read_u32(0x101C), 0x1018);
+            assert_eq!(mmu.read_u32(0x1018), 0x1014);
+            assert_eq!(mmu.read_u32(0x1014), 0);
+            assert_eq!(mmu.dma.channels.6.base_address, 0x1014);
+            assert_eq!(mmu.dma.channels.6.block_control, 6);
+            assert_eq!(
+                mmu.scheduler.deadline(Event::Dma(OTC_CHANNEL_ID)),
+                Some(2 * dma::cycles_per_word(OTC_CHANNEL_ID) + 8)
+            );
+            assert!(!mmu.irq.status.dma());
+
+            assert_eq!(run_chopped_windows(mmu, OTC_CHANNEL_ID), 3);
+
+            for entry in 1..8 {
+                assert_eq!(mmu.read_u32(0x1000 + entry * 4), 0x1000 + (entry - 1) * 4);
+            }
+            assert_eq!(mmu.read_u32(0x1000), 0x00FF_FFFF);
+            assert!(!mmu.dma.channels.6.channel_control.start_transfer());
+            assert!(mmu.irq.status.dma());
+        });
+    }
+
+    #[test]
+    fn test_dma_chopping_waits_for_request() {
+        with_mmu(|mmu| {
+            for word in 0..8 {
+                mmu.write_u32(0x2000 + word * 4, 0x1111_1111 * (word + 1));
+            }
+
+            // Sound RAM address 80h, DMA write mode
+            mmu.write_u16(SPU_TRANSFER_ADDRESS, 0x0010);
+            mmu.write_u16(SPUCNT, 0x0020);
+            mmu.write_u32(DPCR, 1 << 19);
+
+            let control = START | TRIGGER | TO_DEVICE | CHOPPING_WINDOWS;
+            start_channel(mmu, SPU_CHANNEL_ID, 0x2000, 8, control);
+            assert_eq!(mmu.spu.sound_ram[0x80..0x84], 0x1111_1111u32.to_le_bytes());
+            assert_eq!(mmu.spu.sound_ram[0x88..0x8C], [0; 4]);
+
+            // The SPU stops requesting during the CPU window, the next window has to wait
+            mmu.write_u16(SPUCNT, 0x0000);
+            mmu.scheduler.cancel(Event::Dma(SPU_CHANNEL_ID));
+            mmu.resume_dma_channel(SPU_CHANNEL_ID);
+
+            assert_eq!(mmu.spu.sound_ram[0x88..0x8C], [0; 4]);
+            assert_eq!(mmu.dma.channels.4.base_address, 0x2008);
+            assert!(mmu.scheduler.deadline(Event::Dma(SPU_CHANNEL_ID)).is_some());
+            assert!(mmu.dma.channels.4.channel_control.start_transfer());
+
+            mmu.write_u16(SPUCNT, 0x0020);
+            assert_eq!(run_chopped_windows(mmu, SPU_CHANNEL_ID), 3);
+
+            for word in 0..8 {
+                let offset = 0x80 + word * 4;
+                let expected = (0x1111_1111 * (word as u32 + 1)).to_le_bytes();
+                assert_eq!(mmu.spu.sound_ram[offset..offset + 4], expected);
+            }
+            assert!(!mmu.dma.channels.4.channel_control.start_transfer());
+        });
+    }
+
+    #[test]
+    fn test_dma_linked_list() {
+        with_mmu(|mmu| {
+            // Two GP0 NOPs, an empty node, one more NOP and the end marker
+            mmu.write_u32(0x1000, 0x0200_1010);
+            mmu.write_u32(0x1010, 0x0000_1020);
+            mmu.write_u32(0x1020, 0x01FF_FFFF);
+
+            mmu.write_u32(GP1_ADDRESS_START, 0x0400_0002);
+            mmu.write_u32(DPCR, 1 << 11);
+            mmu.write_u32(DICR, (1 << 23) | (1 << (16 + GPU_CHANNEL_ID)));
+
+            start_channel(mmu, GPU_CHANNEL_ID, 0x1000, 0, START | LINKED_LIST | TO_DEVICE);
+
+            // Three headers and three words went over the bus
+            assert_eq!(mmu.dma.take_stall_cycles(), 6 * dma::cycles_per_word(GPU_CHANNEL_ID));
+            assert_eq!(mmu.dma.channels.2.base_address, 0x00FF_FFFF);
+            assert!(!mmu.dma.channels.2.channel_control.start_transfer());
+            assert!(mmu.irq.status.dma());
+        });
+    }
+
+    #[test]
+    fn test_dma_linked_list_without_end() {
+        with_mmu(|mmu| {
+            // Two empty nodes pointing at each other
+            mmu.write_u32(0x1000, 0x0000_1010);
+            mmu.write_u32(0x1010, 0x0000_1000);
+
+            mmu.write_u32(GP1_ADDRESS_START, 0x0400_0002);
+            mmu.write_u32(DPCR, 1 << 11);
+
+            start_channel(mmu, GPU_CHANNEL_ID, 0x1000, 0, START | LINKED_LIST | TO_DEVICE);
+
+            // Gave up after walking as many words as there are in RAM
+            let ram_words = RamSize::Retail.bytes() as u64 / 4;
+            assert_eq!(
+                mmu.dma.take_stall_cycles(),
+                ram_words * dma::cycles_per_word(GPU_CHANNEL_ID)
+            );
+            assert!(!mmu.dma.channels.2.channel_control.start_transfer());
+        });
+    }
+
+    #[test]
+    fn test_peek_poke() {
+        with_mmu(|mmu| {
+            // RAM through any segment, the scratchpad and the BIOS
+            assert!(mmu.poke(0xA000_1000, 0x5A));
+            assert_eq!(mmu.peek(0x8000_1000), Some(0x5A));
+            assert!(mmu.poke(0x1F80_0010, 0xA5));
+            assert_eq!(mmu.peek(0x9F80_0010), Some(0xA5));
+            assert_eq!(mmu.peek(0xBFC0_0000), Some(mmu.memory[BIOS_ADDR_START as usize]));
+
+            // Devices never see the access
+            assert!(!mmu.poke(I_MASK_ADDR_START, 0xFF));
+            assert_eq!(mmu.irq.mask.0, 0);
+            assert_eq!(mmu.peek(I_STAT_ADDR_START), None);
+            assert_eq!(mmu.peek(0x1F80_1810), None);
+            assert_eq!(mmu.peek(0x1F00_0000), None);
+        });
+    }
+}
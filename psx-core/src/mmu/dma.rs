@@ -1,6 +1,7 @@
 use proc_bitfield::bitfield;
 
 use crate::mmu::bus::{Bus8, Bus16, Bus32};
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
 
 crate::define_addr!(DMA0_ADDRESS, 0x1F80_1080, 0, 0xB, 0x10);
 crate::define_addr!(DMA1_ADDRESS, 0x1F80_1080, 1, 0xB, 0x10);
@@ -20,6 +21,58 @@ pub const SPU_CHANNEL_ID: u8 = 4;
 pub const PIO_CHANNEL_ID: u8 = 5;
 pub const OTC_CHANNEL_ID: u8 = 6;
 
+/// DMA5 talks to the expansion port 1 region
+pub const PIO_ADDRESS: u32 = 0x1F00_0000;
+
+/// PSX-SPX: "DMA Transfer Rates", in system clock cycles per word
+pub const fn cycles_per_word(channel_id: u8) -> u64 {
+    match channel_id {
+        CDROM_CHANNEL_ID => 24,
+        SPU_CHANNEL_ID => 4,
+        PIO_CHANNEL_ID => 20,
+        _ => 1,
+    }
+}
+
+/// Run `$body` with `$channel` bound to the channel with the runtime id `$id`
+macro_rules! with_channel {
+    ($dma:expr, $id:expr, $channel:ident => $body:expr) => {
+        match $id {
+            0 => {
+                let $channel = &mut $dma.channels.0;
+                $body
+            }
+            1 => {
+                let $channel = &mut $dma.channels.1;
+                $body
+            }
+            2 => {
+                let $channel = &mut $dma.channels.2;
+                $body
+            }
+            3 => {
+                let $channel = &mut $dma.channels.3;
+                $body
+            }
+            4 => {
+                let $channel = &mut $dma.channels.4;
+                $body
+            }
+            5 => {
+                let $channel = &mut $dma.channels.5;
+                $body
+            }
+            6 => {
+                let $channel = &mut $dma.channels.6;
+                $body
+            }
+            _ => unreachable!(),
+        }
+    };
+}
+
+pub(crate) use with_channel;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TransferMode {
     Burst,
@@ -59,7 +112,7 @@ bitfield! {
     pub struct ChannelControl(pub u32): Debug, FromStorage, IntoStorage, DerefStorage {
         pub transfer_direction: bool @ 0,
         pub madr_increment_per_step: bool @ 1,
-        pub idk_wtf_this_is: bool @ 8,
+        pub chopping_enable: bool @ 8,
         pub transfer_mode: u8 [get TransferMode, set TransferMode] @ 9..=10,
         pub chopping_dma_window_size: u8 @ 16..=18,
         pub chopping_cpu_window_size: u8 @ 20..=22,
@@ -74,6 +127,16 @@ impl ChannelControl {
     pub fn madr_step(&self) -> i32 {
         if self.madr_increment_per_step() { -4 } else { 4 }
     }
+
+    /// Words moved per chopping window before the CPU gets the bus back
+    pub fn chopping_dma_window_words(&self) -> u32 {
+        1 << self.chopping_dma_window_size()
+    }
+
+    /// CPU cycles between two chopping windows
+    pub fn chopping_cpu_window_cycles(&self) -> u64 {
+        1 << self.chopping_cpu_window_size()
+    }
 }
 
 bitfield! {
@@ -98,6 +161,18 @@ bitfield! {
     }
 }
 
+impl ControlRegister {
+    /// PSX-SPX: "DMA0..6 Master Enable (0=Disable, 1=Enable)"
+    pub fn channel_enabled(&self, channel_id: u8) -> bool {
+        (self.0 >> (channel_id * 4 + 3)) & 1 == 1
+    }
+
+    /// PSX-SPX: "DMA0..6 Priority (0..7; 0=Highest, 7=Lowest)"
+    pub fn channel_priority(&self, channel_id: u8) -> u8 {
+        ((self.0 >> (channel_id * 4)) & 0b111) as u8
+    }
+}
+
 bitfield! {
     #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct InterruptRegister(pub u32): Debug, FromStorage, IntoStorage, DerefStorage {
@@ -196,6 +271,7 @@ impl<const CHANNEL_ID: u8> Channel<CHANNEL_ID> {
     #[inline(always)]
     pub fn set_completed(&mut self) {
         self.channel_control.set_start_transfer(false);
+        self.channel_control.set_force_transfer_start(false);
     }
 }
 
@@ -263,6 +339,8 @@ pub struct Dma {
     ),
     pub control: ControlRegister,
     pub interrupt: InterruptRegister,
+    pub(crate) running: bool, // A transfer is being performed right now, guards against re-entrant starts
+    pub(crate) stall_cycles: u64, // Cycles the CPU has to wait for the bus because of finished transfers
 }
 
 impl Dma {
@@ -279,8 +357,15 @@ impl Dma {
             ),
             control: ControlRegister(0x07654321),
             interrupt: InterruptRegister(0),
+            running: false,
+            stall_cycles: 0,
         }
     }
+
+    /// Cycles the CPU was kept off the bus since the last call
+    pub fn take_stall_cycles(&mut self) -> u64 {
+        std::mem::take(&mut self.stall_cycles)
+    }
 }
 
 impl Bus8 for Dma {
@@ -427,3 +512,48 @@ impl Bus32 for Dma {
         }
     }
 }
+
+impl<const CHANNEL_ID: u8> Savestate for Channel<CHANNEL_ID> {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.base_address);
+        writer.write(&self.block_control);
+        writer.write(&self.channel_control.0);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.base_address = reader.read()?;
+        self.block_control = reader.read()?;
+        self.channel_control.0 = reader.read()?;
+        Ok(())
+    }
+}
+
+/// States are only taken between steps, so no transfer can be `running`
+impl Savestate for Dma {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.channels.0.save_state(writer);
+        self.channels.1.save_state(writer);
+        self.channels.2.save_state(writer);
+        self.channels.3.save_state(writer);
+        self.channels.4.save_state(writer);
+        self.channels.5.save_state(writer);
+        self.channels.6.save_state(writer);
+        writer.write(&self.control.0);
+        writer.write(&self.interrupt.0);
+        writer.write(&self.stall_cycles);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.channels.0.load_state(reader)?;
+        self.channels.1.load_state(reader)?;
+        self.channels.2.load_state(reader)?;
+        self.channels.3.load_state(reader)?;
+        self.channels.4.load_state(reader)?;
+        self.channels.5.load_state(reader)?;
+        self.channels.6.load_state(reader)?;
+        self.control.0 = reader.read()?;
+        self.interrupt.0 = reader.read()?;
+        self.stall_cycles = reader.read()?;
+        Ok(())
+    }
+}
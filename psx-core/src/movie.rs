@@ -0,0 +1,332 @@
+use crate::psx::Psx;
+use crate::savestate::{SavestateError, StateReader, StateWriter, checksum};
+use crate::sio::joy::ControllerState;
+
+pub const MOVIE_MAGIC: &[u8; 4] = b"PSMV";
+pub const MOVIE_VERSION: u16 = 2;
+
+/// Frames between two RAM/VRAM checksums, a desync is caught at most a second after it happened
+pub const MOVIE_DEFAULT_CHECKSUM_INTERVAL: u32 = 60;
+
+pub const MOVIE_PORT_COUNT: usize = 2;
+
+/// Controller state of both ports during one frame, multitaps and pointer devices are not recorded
+pub type MovieInput = [ControllerState; MOVIE_PORT_COUNT];
+
+#[derive(Debug)]
+pub enum MovieError {
+    Invalid(SavestateError),
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    BiosMismatch,
+    DiscMismatch,
+    RamSizeMismatch,
+    DeviceMismatch,
+    MemoryCardMismatch,
+    UnsupportedDevices,
+    Desync { frame: usize },
+}
+
+impl std::fmt::Display for MovieError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MovieError::Invalid(e) => write!(f, "Invalid movie: {}", e),
+            MovieError::InvalidMagic => write!(f, "Not a movie"),
+            MovieError::UnsupportedVersion(version) => write!(
+                f,
+                "Movie version {} is not supported, expected version {}",
+                version, MOVIE_VERSION
+            ),
+            MovieError::BiosMismatch => write!(f, "Movie was recorded with a different BIOS"),
+            MovieError::DiscMismatch => write!(f, "Movie was recorded with a different disc"),
+            MovieError::RamSizeMismatch => write!(f, "Movie was recorded with a different RAM size"),
+            MovieError::DeviceMismatch => {
+                write!(f, "Movie was recorded with different controllers or memory cards")
+            }
+            MovieError::MemoryCardMismatch => {
+                write!(f, "Movie was recorded with different memory card contents")
+            }
+            MovieError::UnsupportedDevices => {
+                write!(f, "Movies cannot record multitaps, mice or light guns")
+            }
+            MovieError::Desync { frame } => write!(f, "Playback desynced at frame {}", frame),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+impl From<SavestateError> for MovieError {
+    fn from(e: SavestateError) -> Self {
+        MovieError::Invalid(e)
+    }
+}
+
+/// Where a movie starts playing from
+pub enum MovieStart {
+    /// A console that was just created with the same media, before its first step
+    PowerOn,
+    /// Embedded save state
+    Savestate(Vec<u8>),
+}
+
+/// Recorded controller input together with everything needed to replay it deterministically
+pub struct Movie {
+    bios_checksum: u64,
+    disc_checksum: Option<u64>,
+    ram_size: u8,
+    device_layout: Vec<u8>,
+    memory_card_checksums: Vec<Option<u64>>, // Cards are not part of save states, so they are checked for any start
+    start: MovieStart,
+    checksum_interval: u32,
+    inputs: Vec<MovieInput>,
+    checksums: Vec<(u64, u64)>, // RAM and VRAM after every `checksum_interval` frames
+}
+
+impl Movie {
+    fn new(psx: &Psx, start: MovieStart, checksum_interval: u32) -> Self {
+        Self {
+            bios_checksum: psx.bios_checksum(),
+            disc_checksum: psx.disc_checksum(),
+            ram_size: psx.cpu.mmu.ram_size() as u8,
+            device_layout: psx.cpu.mmu.sio.device_layout(),
+            memory_card_checksums: psx.memory_card_checksums(),
+            start,
+            checksum_interval: checksum_interval.max(1),
+            inputs: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, MovieError> {
+        let mut reader = StateReader::new(data);
+        if reader.read_array::<4>()? != *MOVIE_MAGIC {
+            return Err(MovieError::InvalidMagic);
+        }
+
+        let version = reader.read::<u16>()?;
+        if version != MOVIE_VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+
+        let bios_checksum = reader.read()?;
+        let disc_checksum = reader.read()?;
+        let ram_size = reader.read()?;
+        let device_layout = reader.read()?;
+        let memory_card_checksums = reader.read()?;
+        let start = match reader.read::<bool>()? {
+            true => {
+                let length = reader.read::<u32>()? as usize;
+                MovieStart::Savestate(reader.read_bytes(length)?.to_vec())
+            }
+            false => MovieStart::PowerOn,
+        };
+        let checksum_interval = reader.read::<u32>()?.max(1);
+        let inputs = reader.read()?;
+        let checksums = reader.read()?;
+
+        if !reader.remaining().is_empty() {
+            return Err(MovieError::Invalid(SavestateError::Invalid("trailing data")));
+        }
+
+        Ok(Self {
+            bios_checksum,
+            disc_checksum,
+            ram_size,
+            device_layout,
+            memory_card_checksums,
+            start,
+            checksum_interval,
+            inputs,
+            checksums,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        writer.write_bytes(MOVIE_MAGIC);
+        writer.write(&MOVIE_VERSION);
+        writer.write(&self.bios_checksum);
+        writer.write(&self.disc_checksum);
+        writer.write(&self.ram_size);
+        writer.write(&self.device_layout);
+        writer.write(&self.memory_card_checksums);
+        match &self.start {
+            MovieStart::PowerOn => writer.write(&false),
+            MovieStart::Savestate(state) => {
+                writer.write(&true);
+                writer.write(&(state.len() as u32));
+                writer.write_bytes(state);
+            }
+        }
+        writer.write(&self.checksum_interval);
+        writer.write(&self.inputs);
+        writer.write(&self.checksums);
+        writer.into_inner()
+    }
+
+    /// Number of recorded frames
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    pub fn start(&self) -> &MovieStart {
+        &self.start
+    }
+
+    /// Checksum of RAM and VRAM, compared to catch a desync
+    fn frame_checksum(psx: &Psx) -> (u64, u64) {
+        let mmu = &psx.cpu.mmu;
+        (
+            checksum(&mmu.memory[..mmu.ram_size().bytes() as usize]),
+            checksum(&mmu.gpu.gp.vram),
+        )
+    }
+}
+
+/// Records the controller input of every frame
+pub struct MovieRecorder {
+    movie: Movie,
+}
+
+impl MovieRecorder {
+    /// Start recording on a console that was just created and had its media inserted
+    pub fn from_power_on(psx: &Psx) -> Result<Self, MovieError> {
+        Self::with_checksum_interval(psx, MovieStart::PowerOn, MOVIE_DEFAULT_CHECKSUM_INTERVAL)
+    }
+
+    /// Start recording right where the console is, a save state of it is embedded into the movie
+    pub fn from_savestate(psx: &Psx) -> Result<Self, MovieError> {
+        Self::with_checksum_interval(
+            psx,
+            MovieStart::Savestate(psx.save_state()),
+            MOVIE_DEFAULT_CHECKSUM_INTERVAL,
+        )
+    }
+
+    /// Fails unless both ports have a pad plugged in directly, the input of anything else would be lost
+    pub fn with_checksum_interval(psx: &Psx, start: MovieStart, checksum_interval: u32) -> Result<Self, MovieError> {
+        if !psx.cpu.mmu.sio.has_only_direct_pads() {
+            return Err(MovieError::UnsupportedDevices);
+        }
+
+        Ok(Self {
+            movie: Movie::new(psx, start, checksum_interval),
+        })
+    }
+
+    /// Call after every completed frame with the input that was applied before it started
+    pub fn record_frame(&mut self, psx: &Psx, input: MovieInput) {
+        self.movie.inputs.push(input);
+
+        if self
+            .movie
+            .inputs
+            .len()
+            .is_multiple_of(self.movie.checksum_interval as usize)
+        {
+            self.movie.checksums.push(Movie::frame_checksum(psx));
+        }
+    }
+
+    /// The movie recorded so far, recording can go on afterwards
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    pub fn finish(self) -> Movie {
+        self.movie
+    }
+}
+
+/// Feeds the input of a movie into a console and checks that it stays in sync
+pub struct MoviePlayer {
+    movie: Movie,
+    frame: usize,
+}
+
+impl MoviePlayer {
+    /// Prepare `psx` for playback, a power-on movie needs a console that was just created with the same media
+    pub fn new(movie: Movie, psx: &mut Psx) -> Result<Self, MovieError> {
+        if movie.bios_checksum != psx.bios_checksum() {
+            return Err(MovieError::BiosMismatch);
+        }
+
+        if movie.disc_checksum != psx.disc_checksum() {
+            return Err(MovieError::DiscMismatch);
+        }
+
+        if movie.ram_size != psx.cpu.mmu.ram_size() as u8 {
+            return Err(MovieError::RamSizeMismatch);
+        }
+
+        if movie.device_layout != psx.cpu.mmu.sio.device_layout() {
+            return Err(MovieError::DeviceMismatch);
+        }
+
+        if movie.memory_card_checksums != psx.memory_card_checksums() {
+            return Err(MovieError::MemoryCardMismatch);
+        }
+
+        if let MovieStart::Savestate(state) = &movie.start {
+            psx.load_state(state)?;
+        }
+
+        Ok(Self { movie, frame: 0 })
+    }
+
+    /// Apply the input of the next frame, returns false once the movie is over
+    pub fn start_frame(&mut self, psx: &mut Psx) -> bool {
+        let Some(input) = self.movie.inputs.get(self.frame) else {
+            return false;
+        };
+
+        for (port, state) in input.iter().enumerate() {
+            psx.set_controller_state(port, *state);
+        }
+
+        true
+    }
+
+    /// Call after every completed frame, fails if the console no longer matches the recording
+    pub fn end_frame(&mut self, psx: &Psx) -> Result<(), MovieError> {
+        self.frame += 1;
+
+        let interval = self.movie.checksum_interval as usize;
+        if self.frame.is_multiple_of(interval)
+            && let Some(expected) = self.movie.checksums.get(self.frame / interval - 1)
+            && Movie::frame_checksum(psx) != *expected
+        {
+            return Err(MovieError::Desync { frame: self.frame });
+        }
+
+        Ok(())
+    }
+
+    /// Play the rest of the movie without a frontend, returns the number of frames played
+    pub fn run(&mut self, psx: &mut Psx) -> Result<usize, MovieError> {
+        while self.start_frame(psx) {
+            psx.run_frame();
+            self.end_frame(psx)?;
+        }
+
+        Ok(self.frame)
+    }
+
+    /// Frames played so far
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.movie.len()
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+}
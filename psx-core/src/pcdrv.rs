@@ -0,0 +1,365 @@
+use crate::cpu::Cpu;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+// BREAK codes of the Psy-Q / PSn00bSDK PCdrv stubs
+const PCDRV_INIT: u32 = 0x101;
+const PCDRV_CREAT: u32 = 0x102;
+const PCDRV_OPEN: u32 = 0x103;
+const PCDRV_CLOSE: u32 = 0x104;
+const PCDRV_READ: u32 = 0x105;
+const PCDRV_WRITE: u32 = 0x106;
+const PCDRV_LSEEK: u32 = 0x107;
+
+// Upper bound for paths read from guest memory, guards against unterminated strings
+const PCDRV_MAX_PATH: u32 = 256;
+
+// Reads and writes go through a fixed buffer, whatever length the guest asks for
+const PCDRV_CHUNK_SIZE: usize = 0x1000;
+
+/// Host filesystem access for homebrew through PCdrv BREAKs, confined to a single directory
+///
+/// Arguments are passed in $a1..$a3, $v0 is 0 on success or -1 on failure and $v1 holds the result
+pub struct Pcdrv {
+    root: PathBuf,
+    files: HashMap<u32, File>,
+    next_handle: u32,
+}
+
+impl Pcdrv {
+    pub fn new(root: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            root: root.as_ref().canonicalize()?,
+            files: HashMap::new(),
+            next_handle: 1,
+        })
+    }
+
+    /// Serve the BREAK with `code`, returns false if it is not a PCdrv call
+    pub(crate) fn handle(&mut self, code: u32, cpu: &mut Cpu) -> bool {
+        let a1 = cpu.read_register(crate::regidx("$a1"));
+        let a2 = cpu.read_register(crate::regidx("$a2"));
+        let a3 = cpu.read_register(crate::regidx("$a3"));
+
+        let result = match code {
+            PCDRV_INIT => {
+                self.files.clear();
+                Ok(0)
+            }
+            PCDRV_CREAT => self.open(cpu, a1, None),
+            PCDRV_OPEN => self.open(cpu, a1, Some(a2)),
+            PCDRV_CLOSE => self.close(a1),
+            PCDRV_READ => self.read(cpu, a1, a2, a3),
+            PCDRV_WRITE => self.write(cpu, a1, a2, a3),
+            PCDRV_LSEEK => self.seek(a1, a2, a3),
+            _ => return false,
+        };
+
+        match result {
+            Ok(value) => {
+                cpu.write_register(crate::regidx("$v0"), 0);
+                cpu.write_register(crate::regidx("$v1"), value);
+            }
+            Err(error) => {
+                tracing::warn!(target: "psx_core::pcdrv", code = format!("{:03X}", code), %error, "PCdrv call failed");
+                cpu.write_register(crate::regidx("$v0"), u32::MAX);
+                cpu.write_register(crate::regidx("$v1"), u32::MAX);
+            }
+        }
+
+        true
+    }
+
+    /// PCcreat when `mode` is None, otherwise PCopen with 0 = read, 1 = write, 2 = read/write
+    fn open(&mut self, cpu: &Cpu, path_address: u32, mode: Option<u32>) -> io::Result<u32> {
+        let name = read_string(cpu, path_address);
+        let path = self.resolve(&name)?;
+
+        let mut options = OpenOptions::new();
+        match mode {
+            None => options.read(true).write(true).create(true).truncate(true),
+            Some(0) => options.read(true),
+            Some(1) => options.write(true),
+            Some(_) => options.read(true).write(true),
+        };
+
+        let file = options.open(&path)?;
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.files.insert(handle, file);
+
+        tracing::debug!(target: "psx_core::pcdrv", name, handle, create = mode.is_none(), "Opened file");
+        Ok(handle)
+    }
+
+    fn close(&mut self, handle: u32) -> io::Result<u32> {
+        self.files.remove(&handle).ok_or_else(invalid_handle)?;
+        tracing::debug!(target: "psx_core::pcdrv", handle, "Closed file");
+        Ok(0)
+    }
+
+    fn read(&mut self, cpu: &mut Cpu, handle: u32, length: u32, address: u32) -> io::Result<u32> {
+        let file = self.files.get_mut(&handle).ok_or_else(invalid_handle)?;
+        let length = guest_buffer_length(cpu, address, length)?;
+
+        let mut chunk = [0u8; PCDRV_CHUNK_SIZE];
+        let mut total = 0;
+        while total < length {
+            let size = (length - total).min(PCDRV_CHUNK_SIZE as u32) as usize;
+            let read = file.read(&mut chunk[..size])?;
+            if read == 0 {
+                break;
+            }
+
+            for (offset, &byte) in chunk[..read].iter().enumerate() {
+                if let Some(index) = cpu.mmu.memory_offset(address.wrapping_add(total + offset as u32)) {
+                    cpu.mmu.memory[index] = byte;
+                }
+            }
+
+            total += read as u32;
+        }
+
+        Ok(total)
+    }
+
+    fn write(&mut self, cpu: &mut Cpu, handle: u32, length: u32, address: u32) -> io::Result<u32> {
+        let file = self.files.get_mut(&handle).ok_or_else(invalid_handle)?;
+        let length = guest_buffer_length(cpu, address, length)?;
+
+        let mut chunk = [0u8; PCDRV_CHUNK_SIZE];
+        let mut total = 0;
+        while total < length {
+            let size = (length - total).min(PCDRV_CHUNK_SIZE as u32) as usize;
+
+            for (offset, byte) in chunk[..size].iter_mut().enumerate() {
+                if let Some(index) = cpu.mmu.memory_offset(address.wrapping_add(total + offset as u32)) {
+                    *byte = cpu.mmu.memory[index];
+                }
+            }
+
+            file.write_all(&chunk[..size])?;
+            total += size as u32;
+        }
+
+        Ok(total)
+    }
+
+    /// Whence is 0 = start, 1 = current position, 2 = end, the offset is signed
+    fn seek(&mut self, handle: u32, offset: u32, whence: u32) -> io::Result<u32> {
+        let file = self.files.get_mut(&handle).ok_or_else(invalid_handle)?;
+
+        let position = match whence {
+            0 => SeekFrom::Start(offset as u64),
+            1 => SeekFrom::Current(offset as i32 as i64),
+            2 => SeekFrom::End(offset as i32 as i64),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid seek origin")),
+        };
+
+        Ok(file.seek(position)? as u32)
+    }
+
+    /// Map a guest path onto the host directory, anything that would leave it is refused
+    fn resolve(&self, name: &str) -> io::Result<PathBuf> {
+        let escape = || {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("'{}' is outside the PCdrv root", name),
+            )
+        };
+
+        // Psy-Q tools commonly pass DOS style paths
+        let name = name.replace('\\', "/");
+
+        let mut relative = PathBuf::new();
+        for component in Path::new(&name).components() {
+            match component {
+                Component::Normal(part) => relative.push(part),
+                Component::ParentDir => {
+                    if !relative.pop() {
+                        return Err(escape());
+                    }
+                }
+                Component::CurDir | Component::RootDir => {}
+                Component::Prefix(_) => return Err(escape()),
+            }
+        }
+
+        let path = self.root.join(relative);
+
+        // Symlinks inside the root may still point elsewhere, check where the file or its directory really is
+        let existing = if path.exists() {
+            path.canonicalize()?
+        } else {
+            path.parent().unwrap_or(&self.root).canonicalize()?
+        };
+
+        if !existing.starts_with(&self.root) {
+            return Err(escape());
+        }
+
+        Ok(path)
+    }
+}
+
+fn read_string(cpu: &Cpu, address: u32) -> String {
+    (0..PCDRV_MAX_PATH)
+        .map_while(|offset| cpu.mmu.memory_offset(address.wrapping_add(offset)))
+        .map(|index| cpu.mmu.memory[index])
+        .take_while(|&byte| byte != 0)
+        .map(|byte| byte as char)
+        .collect()
+}
+
+/// Length of a transfer to or from the guest, at most the installed RAM and only within RAM or the scratchpad
+fn guest_buffer_length(cpu: &Cpu, address: u32, length: u32) -> io::Result<u32> {
+    let length = length.min(cpu.mmu.ram_size().bytes());
+
+    if (0..length).all(|offset| cpu.mmu.memory_offset(address.wrapping_add(offset)).is_some()) {
+        Ok(length)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("buffer {:08X}+{:X} is outside of RAM", address, length),
+        ))
+    }
+}
+
+fn invalid_handle() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "invalid file handle")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh directory under the system temp dir, removed again when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("psx-pcdrv-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn pcdrv(dir: &TempDir) -> Pcdrv {
+        std::fs::create_dir_all(dir.0.join("root/data")).unwrap();
+        std::fs::write(dir.0.join("root/data/level.bin"), b"level").unwrap();
+        std::fs::write(dir.0.join("secret.txt"), b"secret").unwrap();
+        Pcdrv::new(dir.0.join("root")).unwrap()
+    }
+
+    fn is_refused(result: io::Result<PathBuf>) -> bool {
+        matches!(result, Err(error) if error.kind() == io::ErrorKind::PermissionDenied)
+    }
+
+    /// Failing is fine too, e.g. when the directory does not exist under the root
+    fn stays_inside(pcdrv: &Pcdrv, name: &str) -> bool {
+        match pcdrv.resolve(name) {
+            Ok(path) => path.starts_with(&pcdrv.root),
+            Err(_) => true,
+        }
+    }
+
+    #[test]
+    fn test_resolves_paths_inside_the_root() {
+        let dir = TempDir::new("inside");
+        let pcdrv = pcdrv(&dir);
+
+        assert_eq!(
+            pcdrv.resolve("data/level.bin").unwrap(),
+            pcdrv.root.join("data/level.bin")
+        );
+        assert_eq!(
+            pcdrv.resolve("./data/../data/level.bin").unwrap(),
+            pcdrv.root.join("data/level.bin")
+        );
+        assert_eq!(pcdrv.resolve("new.sav").unwrap(), pcdrv.root.join("new.sav"));
+    }
+
+    #[test]
+    fn test_refuses_parent_directory_escapes() {
+        let dir = TempDir::new("parent");
+        let pcdrv = pcdrv(&dir);
+
+        assert!(is_refused(pcdrv.resolve("../secret.txt")));
+        assert!(is_refused(pcdrv.resolve("data/../../secret.txt")));
+        assert!(is_refused(pcdrv.resolve("..")));
+    }
+
+    #[test]
+    fn test_keeps_absolute_paths_inside_the_root() {
+        let dir = TempDir::new("absolute");
+        let pcdrv = pcdrv(&dir);
+
+        let outside = dir.0.join("secret.txt");
+        assert!(stays_inside(&pcdrv, outside.to_str().unwrap()));
+        assert_eq!(
+            pcdrv.resolve("/data/level.bin").unwrap(),
+            pcdrv.root.join("data/level.bin")
+        );
+        assert!(is_refused(pcdrv.resolve("/../secret.txt")));
+    }
+
+    #[test]
+    fn test_accepts_dos_separators() {
+        let dir = TempDir::new("dos");
+        let pcdrv = pcdrv(&dir);
+
+        assert_eq!(
+            pcdrv.resolve("data\\level.bin").unwrap(),
+            pcdrv.root.join("data/level.bin")
+        );
+        assert_eq!(
+            pcdrv.resolve("\\data\\level.bin").unwrap(),
+            pcdrv.root.join("data/level.bin")
+        );
+        assert!(is_refused(pcdrv.resolve("data\\..\\..\\secret.txt")));
+    }
+
+    #[test]
+    fn test_keeps_drive_prefixes_inside_the_root() {
+        let dir = TempDir::new("drive");
+        let pcdrv = pcdrv(&dir);
+
+        // Only Windows parses prefixes, elsewhere the drive is an ordinary directory name under the root
+        for name in [
+            "C:\\secret.txt",
+            "C:secret.txt",
+            "\\\\server\\share\\secret.txt",
+            "C:\\..\\secret.txt",
+        ] {
+            assert!(stays_inside(&pcdrv, name), "{}", name);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_refuses_symlinks_out_of_the_root() {
+        let dir = TempDir::new("symlink");
+        let pcdrv = pcdrv(&dir);
+
+        std::os::unix::fs::symlink(dir.0.join("secret.txt"), pcdrv.root.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(&dir.0, pcdrv.root.join("outside")).unwrap();
+        std::os::unix::fs::symlink(pcdrv.root.join("data"), pcdrv.root.join("inside")).unwrap();
+
+        assert!(is_refused(pcdrv.resolve("link.txt")));
+        assert!(is_refused(pcdrv.resolve("outside/secret.txt")));
+        assert!(is_refused(pcdrv.resolve("outside/new.sav")));
+        assert_eq!(
+            pcdrv.resolve("inside/level.bin").unwrap(),
+            pcdrv.root.join("inside/level.bin")
+        );
+    }
+}
@@ -1,69 +1,241 @@
+use crate::cartridge::Cartridge;
 use crate::cpu::Cpu;
 use crate::cpu::decoder::Instruction;
-use crate::exe::Exe;
-use crate::sio::joy::ControllerState;
+use crate::exe::{Exe, ExeError};
+use crate::gpu::timing::VideoTiming;
+use crate::mmu::RamSize;
+use crate::mmu::bus::Bus8;
+use crate::pcdrv::Pcdrv;
+use crate::savestate::{
+    SAVESTATE_MAGIC, SAVESTATE_VERSION, Savestate, SavestateError, StateReader, StateWriter, checksum,
+};
+use crate::scheduler::Event;
+use crate::sio::joy::{ControllerState, ControllerType, PointerState, RumbleCallback};
+use crate::sio::memcard::MemoryCard;
+use crate::sio::sio1::SerialBackend;
+use std::io;
+use std::path::Path;
 
 pub const PSX_RESET_ADDRESS: u32 = 0xBFC0_0000;
 pub const PSX_SIDELOAD_EXE_ADDRESS: u32 = 0x8003_0000;
 
 pub const CPU_CLOCK: usize = 33_868_800;
-pub const NTSC_VBLANK_CYCLES: usize = 33_868_800 / 60;
-pub const PAL_VBLANK_CYCLES: usize = 33_868_800 / 50;
-pub const NTSC_VBLANK_DURATION: usize = NTSC_VBLANK_CYCLES / 2; // ~282,240 cycles
-pub const PAL_VBLANK_DURATION: usize = PAL_VBLANK_CYCLES / 2; // ~338,688 cycles
 
 pub struct Psx {
     pub cpu: Cpu,
-    pub cycles: usize,
     sideload_exe: Option<Exe>,
 }
 
 impl Psx {
     pub fn new(bios: &[u8]) -> Self {
+        Self::with_ram_size(bios, RamSize::default())
+    }
+
+    /// Console with `ram_size` of main RAM, 8 MiB of the dev kits or 2 MiB of retail units
+    pub fn with_ram_size(bios: &[u8], ram_size: RamSize) -> Self {
         let mut cpu = Cpu::new();
+        cpu.mmu.set_ram_size(ram_size);
         cpu.mmu.load(PSX_RESET_ADDRESS, &bios);
         cpu.pc = PSX_RESET_ADDRESS;
 
+        // Kick off the video timing, every other device schedules itself once it has something to do
+        let video_mode = cpu.mmu.gpu.gp.gp1_status.video_mode();
+        cpu.mmu
+            .scheduler
+            .schedule(Event::HBlankStart, VideoTiming::hblank_start_cycles());
+        cpu.mmu
+            .scheduler
+            .schedule(Event::HBlankEnd, VideoTiming::scanline_cycles(video_mode));
+
         Self {
             cpu,
-            cycles: 0,
             sideload_exe: None,
         }
     }
 
-    pub fn sideload_exe(&mut self, exe_buffer: Vec<u8>) {
-        self.sideload_exe = Some(Exe::parse(exe_buffer));
+    /// Boot `exe_buffer` once the BIOS is done with its startup, instead of the shell or a disc
+    pub fn sideload_exe(&mut self, exe_buffer: Vec<u8>) -> Result<(), ExeError> {
+        self.sideload_exe = Some(Exe::parse(exe_buffer)?);
+        Ok(())
     }
 
     pub fn load_cdrom(&mut self, bin_buffer: Vec<u8>) {
         self.cpu.mmu.cdrom.insert_disk(bin_buffer);
     }
 
-    pub fn set_controller_state(&mut self, state: ControllerState) {
-        self.cpu.mmu.sio.set_controller_state(state);
+    /// Update the pad plugged into controller port 0 or 1
+    pub fn set_controller_state(&mut self, port: usize, state: ControllerState) {
+        self.cpu.mmu.sio.set_controller_state(port, 0, state);
     }
 
-    pub fn step(&mut self) -> Result<(Instruction, bool), ()> {
-        if let Some(exe) = &self.sideload_exe
-            && self.cpu.pc == PSX_SIDELOAD_EXE_ADDRESS
-        {
-            self.cpu.mmu.load(exe.map_address, &exe.data);
-            self.cpu.write_register(28, exe.initial_gp);
+    /// Plug a new controller of `controller_type` into port 0 or 1, any rumble callback has to be set again
+    pub fn connect_controller(&mut self, port: usize, controller_type: ControllerType) {
+        self.cpu.mmu.sio.connect_controller(port, 0, controller_type);
+    }
 
-            if exe.initial_sp != 0 {
-                self.cpu.write_register(29, exe.sp());
-            }
+    /// Feed host mouse input to a mouse plugged into port 0 or 1
+    pub fn set_pointer_state(&mut self, port: usize, state: PointerState) {
+        self.cpu.mmu.sio.set_pointer_state(port, 0, state);
+    }
 
-            if exe.initial_fp != 0 {
-                self.cpu.write_register(30, exe.fp());
-            }
+    /// `callback` receives the (small, large) motor values of the controller on port 0 or 1
+    pub fn set_rumble_callback(&mut self, port: usize, callback: impl FnMut(u8, u8) + Send + 'static) {
+        let callback: RumbleCallback = Box::new(callback);
+        self.cpu.mmu.sio.set_rumble_callback(port, 0, callback);
+    }
 
-            self.cpu.pc = exe.entry_point;
+    /// Insert `card` into the memory card slot of port 0 or 1
+    pub fn insert_memory_card(&mut self, port: usize, card: MemoryCard) {
+        self.cpu.mmu.sio.insert_memory_card(port, 0, card);
+    }
 
-            tracing::debug!(
-                target: "psx_core::psx",
-                "EXE license string: {}", exe.license
-            );
+    /// Plug a SCPH-1070 multitap into port 0 or 1, the pad and card already connected move to slot A
+    pub fn connect_multitap(&mut self, port: usize) {
+        self.cpu.mmu.sio.connect_multitap(port, true);
+    }
+
+    pub fn disconnect_multitap(&mut self, port: usize) {
+        self.cpu.mmu.sio.connect_multitap(port, false);
+    }
+
+    /// Update the pad in slot 0..3 (A..D) of the multitap on `port`
+    pub fn set_multitap_controller_state(&mut self, port: usize, slot: usize, state: ControllerState) {
+        self.cpu.mmu.sio.set_controller_state(port, slot, state);
+    }
+
+    /// Insert `card` into slot 0..3 (A..D) of the multitap on `port`
+    pub fn insert_multitap_memory_card(&mut self, port: usize, slot: usize, card: MemoryCard) {
+        self.cpu.mmu.sio.insert_memory_card(port, slot, card);
+    }
+
+    /// Plug `backend` into the serial port (SIO1), e.g. a link cable to another instance
+    pub fn connect_serial(&mut self, backend: Box<dyn SerialBackend>) {
+        self.cpu.mmu.sio.connect_serial(backend);
+        self.cpu.mmu.schedule_sio();
+    }
+
+    /// Plug `cartridge` into expansion region 1, the BIOS boots it if it carries the license header
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+        self.cpu.mmu.cartridge = Some(cartridge);
+    }
+
+    /// Serve PCdrv file access from `root`, paths outside of it are refused
+    pub fn enable_pcdrv(&mut self, root: impl AsRef<Path>) -> io::Result<()> {
+        self.cpu.pcdrv = Some(Pcdrv::new(root)?);
+        Ok(())
+    }
+
+    /// Copy `exe` into RAM and jump to its entry point right away, whatever the CPU was doing
+    pub fn load_exe(&mut self, exe: &Exe) {
+        self.cpu.delay_slot = None;
+        boot_exe(&mut self.cpu, exe);
+    }
+
+    /// Continue execution at `address`, dropping a branch that was in flight
+    pub fn jump(&mut self, address: u32) {
+        self.cpu.delay_slot = None;
+        self.cpu.pc = address;
+    }
+
+    /// Identifies the BIOS the console was created with
+    pub fn bios_checksum(&self) -> u64 {
+        self.cpu.mmu.bios_checksum()
+    }
+
+    /// Identifies the contents of the memory card in every slot, None for empty slots
+    pub fn memory_card_checksums(&self) -> Vec<Option<u64>> {
+        self.cpu.mmu.sio.memory_card_checksums()
+    }
+
+    /// Identifies the inserted disc image, hashing it takes a moment
+    pub fn disc_checksum(&self) -> Option<u64> {
+        self.cpu.mmu.cdrom.disk_checksum()
+    }
+
+    /// Snapshot of the whole console, media like the disc or memory cards are not part of it
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut body = StateWriter::new();
+        body.write(&(self.cpu.mmu.ram_size() as u8));
+        body.write(&self.cpu.mmu.bios_checksum());
+        body.write(&self.cpu.mmu.sio.device_layout());
+        self.save_devices(&mut body);
+        let body = body.into_inner();
+
+        let mut writer = StateWriter::new();
+        writer.write_bytes(SAVESTATE_MAGIC);
+        writer.write(&SAVESTATE_VERSION);
+        writer.write(&checksum(&body));
+        writer.write_bytes(&body);
+        writer.into_inner()
+    }
+
+    /// Resume from a snapshot made by `save_state`, the console is left untouched if it gets rejected
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), SavestateError> {
+        let mut reader = StateReader::new(state);
+        if reader.read_array::<4>()? != *SAVESTATE_MAGIC {
+            return Err(SavestateError::InvalidMagic);
+        }
+
+        let version = reader.read::<u16>()?;
+        if version != SAVESTATE_VERSION {
+            return Err(SavestateError::UnsupportedVersion(version));
+        }
+
+        let expected_checksum = reader.read::<u64>()?;
+        if checksum(reader.remaining()) != expected_checksum {
+            return Err(SavestateError::Corrupted);
+        }
+
+        if reader.read::<u8>()? != self.cpu.mmu.ram_size() as u8 {
+            return Err(SavestateError::RamSizeMismatch);
+        }
+
+        if reader.read::<u64>()? != self.cpu.mmu.bios_checksum() {
+            return Err(SavestateError::BiosMismatch);
+        }
+
+        if reader.read::<Vec<u8>>()? != self.cpu.mmu.sio.device_layout() {
+            return Err(SavestateError::DeviceMismatch);
+        }
+
+        // The devices are only checked while they are restored, so go back to where the console was when
+        // one of them turns out invalid half way through
+        let mut backup = StateWriter::new();
+        self.save_devices(&mut backup);
+        let backup = backup.into_inner();
+
+        if let Err(e) = self.load_devices(&mut reader) {
+            self.load_devices(&mut StateReader::new(&backup))
+                .expect("Snapshot of the console has to load back into it");
+            return Err(e);
+        }
+
+        tracing::info!(target: "psx_core::psx", pc = format!("{:08X}", self.cpu.pc), "Loaded save state");
+
+        Ok(())
+    }
+
+    fn save_devices(&self, writer: &mut StateWriter) {
+        self.cpu.save_state(writer);
+        self.cpu.mmu.save_state(writer);
+    }
+
+    fn load_devices(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.cpu.load_state(reader)?;
+        self.cpu.mmu.load_state(reader)?;
+
+        if !reader.remaining().is_empty() {
+            return Err(SavestateError::Invalid("trailing data"));
+        }
+
+        Ok(())
+    }
+
+    pub fn step(&mut self) -> Result<(Instruction, bool), ()> {
+        if let Some(exe) = &self.sideload_exe
+            && self.cpu.pc == PSX_SIDELOAD_EXE_ADDRESS
+        {
+            boot_exe(&mut self.cpu, exe);
 
             tracing::info!(
                 target: "psx_core::psx",
@@ -75,67 +247,120 @@ impl Psx {
 
         let instr = self.cpu.tick();
 
-        let cycles = self.cpu.drain_cycles();
-        self.cycles += cycles;
+        let mut cycles = self.cpu.drain_cycles() as u64;
+        let mut frame_complete = false;
 
-        for _ in 0..cycles {
-            self.cpu.mmu.gpu.tick();
-        }
+        // The CPU is stalled while DMA owns the bus, and devices keep running in the meantime
+        while cycles > 0 {
+            self.cpu.mmu.scheduler.advance(cycles as usize);
 
-        self.cpu.mmu.cdrom.tick(cycles);
-        if self.cpu.mmu.cdrom.check_and_clear_irq() {
-            self.cpu.mmu.irq.status.set_cdrom(true);
-        }
+            while let Some((event, timestamp)) = self.cpu.mmu.scheduler.pop_due() {
+                frame_complete |= self.handle_event(event, timestamp);
+            }
 
-        self.cpu.mmu.sio.tick(cycles);
-        if self.cpu.mmu.sio.should_trigger_irq() {
-            self.cpu.mmu.irq.status.set_controller_and_memory_card(true);
+            cycles = self.cpu.mmu.dma.take_stall_cycles();
         }
 
-        const CYCLES_PER_SCANLINE: usize = NTSC_VBLANK_CYCLES / 525;
-        const HBLANK_CYCLES: usize = CYCLES_PER_SCANLINE * 16 / 100;
-        let scanline_position = self.cycles % CYCLES_PER_SCANLINE;
-        let is_hblank = scanline_position >= (CYCLES_PER_SCANLINE - HBLANK_CYCLES);
-        let is_vblank = self.cycles >= NTSC_VBLANK_DURATION;
+        Ok((instr?, frame_complete))
+    }
 
-        let (tmr0_irq, tmr1_irq, tmr2_irq) = self.cpu.mmu.timers.tick(cycles, is_hblank, is_vblank);
-        if tmr0_irq {
-            self.cpu.mmu.irq.status.set_tmr0(true);
-        }
-        if tmr1_irq {
-            self.cpu.mmu.irq.status.set_tmr1(true);
+    /// Run until the next vertical blank starts, returns false if any instruction on the way failed
+    pub fn run_frame(&mut self) -> bool {
+        let mut ok = true;
+        loop {
+            match self.step() {
+                Ok((_, true)) => return ok,
+                Ok(_) => {}
+                Err(()) => ok = false,
+            }
         }
-        if tmr2_irq {
-            self.cpu.mmu.irq.status.set_tmr2(true);
+    }
+
+    /// Dispatch a due scheduler event, returns true if it started the vertical blank
+    fn handle_event(&mut self, event: Event, timestamp: u64) -> bool {
+        tracing::trace!(target: "psx_core::psx", %event, timestamp, "Handling scheduled event");
+
+        let mmu = &mut self.cpu.mmu;
+        match event {
+            Event::HBlankStart => {
+                mmu.set_video_blanking(true, mmu.gpu.timing.in_vblank);
+            }
+            Event::HBlankEnd => return self.start_scanline(timestamp),
+            Event::Timer(_) => {
+                mmu.sync_timers();
+                mmu.schedule_timers();
+            }
+            Event::Cdrom => {
+                mmu.sync_cdrom();
+                mmu.schedule_cdrom();
+                // A new sector may have raised the data request
+                mmu.perform_dma_transfers();
+            }
+            Event::Sio => {
+                mmu.sync_sio();
+                mmu.schedule_sio();
+            }
+            Event::Dma(channel_id) => mmu.resume_dma_channel(channel_id),
+            Event::Lightpen => {
+                mmu.irq.status.set_lightpen(true);
+                tracing::trace!(target: "psx_core::psx", scanline = mmu.gpu.timing.scanline, "Lightpen IRQ");
+            }
         }
 
-        self.cpu.mmu.perform_dma_transfers();
+        false
+    }
+
+    /// Move the beam to the next scanline and schedule its horizontal blank
+    fn start_scanline(&mut self, timestamp: u64) -> bool {
+        let mmu = &mut self.cpu.mmu;
+        let video_mode = mmu.gpu.gp.gp1_status.video_mode();
+
+        let scanline = (mmu.gpu.timing.scanline + 1) % VideoTiming::scanlines_per_frame(video_mode);
+        mmu.gpu.timing.scanline = scanline;
+
+        let was_in_vblank = mmu.gpu.timing.in_vblank;
+        let in_vblank = match scanline {
+            0 => false,
+            line if line == VideoTiming::vblank_start_scanline(video_mode) => true,
+            _ => was_in_vblank,
+        };
+
+        mmu.set_video_blanking(false, in_vblank);
 
-        if self.cycles >= NTSC_VBLANK_DURATION {
-            self.cpu
-                .mmu
-                .gpu
+        let vblank_started = in_vblank && !was_in_vblank;
+        if vblank_started {
+            mmu.irq.status.set_vblank(true);
+            mmu.gpu
                 .gp
                 .gp1_status
                 .set_drawing_even_odd_lines_in_interlace_mode(false);
-        }
 
-        let frame_complete = self.cycles >= NTSC_VBLANK_CYCLES;
-        if frame_complete {
-            self.cycles = 0;
+            mmu.sio.vblank(video_mode);
 
-            self.cpu.mmu.irq.status.set_vblank(true);
-            self.cpu
-                .mmu
-                .gpu
-                .gp
-                .gp1_status
-                .set_drawing_even_odd_lines_in_interlace_mode(true);
+            if let Some(cartridge) = &mut mmu.cartridge
+                && let Err(e) = cartridge.vblank()
+            {
+                tracing::error!(target: "psx_core::cart", error = %e, "Failed to flush cartridge image");
+            }
 
             tracing::trace!(target: "psx_core::psx", "VBLANK period reached, setting I_STAT bit");
+        } else if was_in_vblank && !in_vblank {
+            mmu.gpu.gp.gp1_status.set_drawing_even_odd_lines_in_interlace_mode(true);
         }
 
-        Ok((instr?, frame_complete))
+        mmu.scheduler
+            .schedule_at(Event::HBlankStart, timestamp + VideoTiming::hblank_start_cycles());
+        mmu.scheduler
+            .schedule_at(Event::HBlankEnd, timestamp + VideoTiming::scanline_cycles(video_mode));
+
+        // A light gun pulls the lightpen IRQ when the beam passes the point it aims at
+        if let Some((target_scanline, cycles)) = mmu.sio.lightpen_target()
+            && target_scanline == scanline
+        {
+            mmu.scheduler.schedule_at(Event::Lightpen, timestamp + cycles);
+        }
+
+        vblank_started
     }
 
     pub fn frame(&self) -> (Vec<(u8, u8, u8)>, usize, usize) {
@@ -143,3 +368,29 @@ impl Psx {
         (self.cpu.mmu.gpu.display_frame(), width, height)
     }
 }
+
+fn boot_exe(cpu: &mut Cpu, exe: &Exe) {
+    cpu.mmu.load(exe.map_address, &exe.data);
+
+    // Like the BIOS Exec() function, zero fill the BSS before starting
+    for offset in 0..exe.bss_size {
+        cpu.mmu.write_u8(exe.bss_address.wrapping_add(offset), 0);
+    }
+
+    cpu.write_register(28, exe.initial_gp);
+
+    if exe.initial_sp != 0 {
+        cpu.write_register(29, exe.sp());
+    }
+
+    if exe.initial_fp != 0 {
+        cpu.write_register(30, exe.fp());
+    }
+
+    cpu.pc = exe.entry_point;
+
+    tracing::debug!(
+        target: "psx_core::psx",
+        "EXE license string: {}", exe.license
+    );
+}
@@ -0,0 +1,264 @@
+use crate::psx::Psx;
+use crate::savestate::SavestateError;
+use std::collections::VecDeque;
+
+// About 30 seconds of NTSC frames with the default interval and everything but RAM and VRAM idle
+pub const REWIND_DEFAULT_CAPACITY: usize = 128 * 1024 * 1024;
+pub const REWIND_DEFAULT_INTERVAL: u64 = 2;
+
+/// Snapshot that only makes sense on top of the snapshot taken right after it
+struct Delta {
+    frame: u64,
+    length: usize, // Length of the snapshot, states grow and shrink with the FIFOs
+    runs: Vec<u8>, // (unchanged bytes, changed bytes) u32 pairs, each followed by the changed bytes XORed
+}
+
+/// Ring buffer of save states taken every `interval` frames, bounded to `capacity` bytes
+///
+/// Only the newest snapshot is kept whole, every older one is stored as the XOR against its successor with
+/// the unchanged runs left out. Dropping the oldest snapshot is free and rewinding undoes one delta at a time.
+pub struct Rewind {
+    capacity: usize,
+    interval: u64,
+    frame: u64, // Frames recorded so far, the frame the console is at
+    newest: Option<(u64, Vec<u8>)>,
+    deltas: VecDeque<Delta>, // Oldest first
+    deltas_size: usize,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize, interval: u64) -> Self {
+        Self {
+            capacity,
+            interval: interval.max(1),
+            frame: 0,
+            newest: None,
+            deltas: VecDeque::new(),
+            deltas_size: 0,
+        }
+    }
+
+    /// Call once for every completed frame, a snapshot is taken every `interval` frames
+    pub fn record(&mut self, psx: &Psx) {
+        self.frame += 1;
+        if !self.frame.is_multiple_of(self.interval) {
+            return;
+        }
+
+        let state = psx.save_state();
+        if let Some((frame, previous)) = &self.newest {
+            let delta = Delta {
+                frame: *frame,
+                length: previous.len(),
+                runs: encode(previous, &state),
+            };
+            self.deltas_size += delta.runs.len();
+            self.deltas.push_back(delta);
+        }
+        self.newest = Some((self.frame, state));
+
+        while self.memory_usage() > self.capacity
+            && let Some(delta) = self.deltas.pop_front()
+        {
+            self.deltas_size -= delta.runs.len();
+        }
+    }
+
+    /// Go back to the previous snapshot, holding a rewind key calls this once per displayed frame
+    pub fn step_back(&mut self, psx: &mut Psx) -> Result<bool, SavestateError> {
+        let target = match &self.newest {
+            Some((frame, _)) if *frame < self.frame => *frame,
+            Some(_) => match self.deltas.back() {
+                Some(delta) => delta.frame,
+                None => return Ok(false),
+            },
+            None => return Ok(false),
+        };
+
+        self.rewind_to(psx, target).map(|_| true)
+    }
+
+    /// Rewind exactly `frames` frames, as far as the buffer reaches. Returns the frames that were rewound
+    ///
+    /// The closest older snapshot is loaded and the console runs forward to the frame, with the controller
+    /// input of that snapshot.
+    pub fn rewind(&mut self, psx: &mut Psx, frames: u64) -> Result<u64, SavestateError> {
+        let target = self.frame.saturating_sub(frames).max(self.oldest_frame());
+        if target == self.frame {
+            return Ok(0);
+        }
+
+        let rewound = self.frame - target;
+        self.rewind_to(psx, target)?;
+        Ok(rewound)
+    }
+
+    fn rewind_to(&mut self, psx: &mut Psx, target: u64) -> Result<(), SavestateError> {
+        let target = target.max(self.oldest_frame());
+        let Some((frame, state)) = &mut self.newest else {
+            return Ok(());
+        };
+
+        while *frame > target
+            && let Some(delta) = self.deltas.pop_back()
+        {
+            decode(state, &delta);
+            *frame = delta.frame;
+            self.deltas_size -= delta.runs.len();
+        }
+
+        psx.load_state(state)?;
+        self.frame = *frame;
+
+        while self.frame < target {
+            psx.run_frame();
+            self.frame += 1;
+        }
+
+        tracing::debug!(target: "psx_core::rewind", frame = self.frame, "Rewound");
+
+        Ok(())
+    }
+
+    /// Oldest frame that can still be reached
+    pub fn oldest_frame(&self) -> u64 {
+        match (self.deltas.front(), &self.newest) {
+            (Some(delta), _) => delta.frame,
+            (None, Some((frame, _))) => *frame,
+            (None, None) => self.frame,
+        }
+    }
+
+    /// Frames that can be rewound right now
+    pub fn frames_available(&self) -> u64 {
+        self.frame - self.oldest_frame()
+    }
+
+    /// Bytes held by the snapshots
+    pub fn memory_usage(&self) -> usize {
+        self.newest.as_ref().map_or(0, |(_, state)| state.len()) + self.deltas_size
+    }
+
+    /// Forget every snapshot, e.g. after a save state was loaded or the console was reset
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+        self.deltas_size = 0;
+    }
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self::new(REWIND_DEFAULT_CAPACITY, REWIND_DEFAULT_INTERVAL)
+    }
+}
+
+/// XOR `older` against `newer` and keep only the runs that differ, bytes past the end of either count as 0
+fn encode(older: &[u8], newer: &[u8]) -> Vec<u8> {
+    let length = older.len().max(newer.len());
+    let byte = |state: &[u8], index: usize| state.get(index).copied().unwrap_or(0);
+
+    let mut runs = Vec::new();
+    let mut index = 0;
+    while index < length {
+        let unchanged_start = index;
+        while index < length && byte(older, index) == byte(newer, index) {
+            index += 1;
+        }
+
+        let changed_start = index;
+        while index < length && byte(older, index) != byte(newer, index) {
+            index += 1;
+        }
+
+        if changed_start == index {
+            break;
+        }
+
+        runs.extend_from_slice(&((changed_start - unchanged_start) as u32).to_le_bytes());
+        runs.extend_from_slice(&((index - changed_start) as u32).to_le_bytes());
+        runs.extend((changed_start..index).map(|i| byte(older, i) ^ byte(newer, i)));
+    }
+
+    runs
+}
+
+/// Turn `state` back into the snapshot `delta` was made against
+fn decode(state: &mut Vec<u8>, delta: &Delta) {
+    state.resize(state.len().max(delta.length), 0);
+
+    let mut runs = delta.runs.as_slice();
+    let mut index = 0;
+    while let [u0, u1, u2, u3, c0, c1, c2, c3, rest @ ..] = runs {
+        index += u32::from_le_bytes([*u0, *u1, *u2, *u3]) as usize;
+        let changed = u32::from_le_bytes([*c0, *c1, *c2, *c3]) as usize;
+
+        for (byte, xor) in state[index..index + changed].iter_mut().zip(&rest[..changed]) {
+            *byte ^= xor;
+        }
+
+        index += changed;
+        runs = &rest[changed..];
+    }
+
+    state.truncate(delta.length);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(older: &[u8], newer: &[u8]) -> Vec<u8> {
+        let delta = Delta {
+            frame: 0,
+            length: older.len(),
+            runs: encode(older, newer),
+        };
+
+        let mut state = newer.to_vec();
+        decode(&mut state, &delta);
+        state
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let older: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut newer = older.clone();
+        newer[0] ^= 1;
+        newer[500..510].fill(0xAA);
+        newer[999] = 0;
+
+        assert_eq!(round_trip(&older, &newer), older);
+        assert_eq!(round_trip(&newer, &older), newer);
+    }
+
+    #[test]
+    fn test_encode_skips_unchanged_runs() {
+        let older = vec![0x55; 4096];
+        assert!(encode(&older, &older).is_empty());
+        assert_eq!(round_trip(&older, &older), older);
+
+        let mut newer = older.clone();
+        newer[2000..2004].fill(0);
+        let runs = encode(&older, &newer);
+        assert_eq!(runs.len(), 8 + 4);
+        assert_eq!(runs[..8], [0xD0, 0x07, 0, 0, 4, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_encode_decode_different_lengths() {
+        let short: Vec<u8> = (1..=100).collect();
+        let mut long: Vec<u8> = (1..=100).rev().collect();
+        long.extend([0, 0, 7, 0]);
+
+        assert_eq!(round_trip(&short, &long), short);
+        assert_eq!(round_trip(&long, &short), long);
+        assert_eq!(round_trip(&[], &long), []);
+        assert_eq!(round_trip(&long, &[]), long);
+
+        // Trailing zeroes past the shorter state look unchanged, only the length tells them apart
+        let padded = [short.as_slice(), &[0; 16]].concat();
+        assert_eq!(round_trip(&padded, &short), padded);
+        assert_eq!(round_trip(&short, &padded), short);
+    }
+}
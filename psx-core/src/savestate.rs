@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+
+pub const SAVESTATE_MAGIC: &[u8; 4] = b"PSPS";
+
+// Bump whenever the layout of any device changes, older states are refused instead of misread
+pub const SAVESTATE_VERSION: u16 = 1;
+
+#[derive(Debug)]
+pub enum SavestateError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    Corrupted,
+    Truncated,
+    BiosMismatch,
+    RamSizeMismatch,
+    DeviceMismatch,
+    Invalid(&'static str),
+}
+
+impl std::fmt::Display for SavestateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SavestateError::InvalidMagic => write!(f, "Not a save state"),
+            SavestateError::UnsupportedVersion(version) => write!(
+                f,
+                "Save state version {} is not supported, expected version {}",
+                version, SAVESTATE_VERSION
+            ),
+            SavestateError::Corrupted => write!(f, "Save state checksum mismatch"),
+            SavestateError::Truncated => write!(f, "Save state ends unexpectedly"),
+            SavestateError::BiosMismatch => write!(f, "Save state was made with a different BIOS"),
+            SavestateError::RamSizeMismatch => write!(f, "Save state was made with a different RAM size"),
+            SavestateError::DeviceMismatch => write!(
+                f,
+                "Save state was made with different controllers, multitaps or memory cards"
+            ),
+            SavestateError::Invalid(what) => write!(f, "Invalid save state: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for SavestateError {}
+
+/// Device that can be snapshotted, loading restores it in place so host side connections survive
+pub trait Savestate {
+    fn save_state(&self, writer: &mut StateWriter);
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError>;
+}
+
+/// Plain value that is stored as is, little endian
+pub trait State: Sized {
+    fn write(&self, writer: &mut StateWriter);
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError>;
+}
+
+pub struct StateWriter {
+    buffer: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self { buffer: Vec::new() }
+    }
+
+    #[inline(always)]
+    pub fn write<T: State>(&mut self, value: &T) {
+        value.write(self);
+    }
+
+    /// Raw bytes without a length, the reader has to know how many to expect
+    #[inline(always)]
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+impl Default for StateWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    #[inline(always)]
+    pub fn read<T: State>(&mut self) -> Result<T, SavestateError> {
+        T::read(self)
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], SavestateError> {
+        if self.data.len() < length {
+            return Err(SavestateError::Truncated);
+        }
+
+        let (bytes, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    /// Fill `buffer` with as many raw bytes as it is long
+    pub fn read_into(&mut self, buffer: &mut [u8]) -> Result<(), SavestateError> {
+        buffer.copy_from_slice(self.read_bytes(buffer.len())?);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], SavestateError> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    /// Bytes that have not been read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// FNV-1a, guards against truncated or corrupted files before anything is overwritten
+pub(crate) fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+macro_rules! impl_state_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl State for $ty {
+                #[inline(always)]
+                fn write(&self, writer: &mut StateWriter) {
+                    writer.write_bytes(&self.to_le_bytes());
+                }
+
+                #[inline(always)]
+                fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+                    Ok(<$ty>::from_le_bytes(reader.read_array()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_state_for_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+// Counters are stored as 64-bit so states move between hosts of different pointer width
+impl State for usize {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write(&(*self as u64));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        usize::try_from(reader.read::<u64>()?).map_err(|_| SavestateError::Invalid("counter out of range"))
+    }
+}
+
+impl State for bool {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write(&(*self as u8));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<u8>()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SavestateError::Invalid("boolean out of range")),
+        }
+    }
+}
+
+impl State for f32 {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write(&self.to_bits());
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        Ok(f32::from_bits(reader.read()?))
+    }
+}
+
+impl<T: State> State for Option<T> {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write(&self.is_some());
+        if let Some(value) = self {
+            writer.write(value);
+        }
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<bool>()? {
+            true => Ok(Some(reader.read()?)),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<A: State, B: State> State for (A, B) {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write(&self.0);
+        writer.write(&self.1);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        Ok((reader.read()?, reader.read()?))
+    }
+}
+
+impl<T: State, const N: usize> State for [T; N] {
+    fn write(&self, writer: &mut StateWriter) {
+        for item in self {
+            writer.write(item);
+        }
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        let mut items = Vec::with_capacity(N);
+        for _ in 0..N {
+            items.push(reader.read()?);
+        }
+
+        Ok(items.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+}
+
+impl<T: State> State for Vec<T> {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write(&(self.len() as u32));
+        for item in self {
+            writer.write(item);
+        }
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        let length = reader.read::<u32>()? as usize;
+
+        // Every item takes at least a byte, a bogus length must not allocate gigabytes
+        if length > reader.remaining().len() {
+            return Err(SavestateError::Truncated);
+        }
+
+        let mut items = Vec::with_capacity(length);
+        for _ in 0..length {
+            items.push(reader.read()?);
+        }
+
+        Ok(items)
+    }
+}
+
+impl<T: State> State for VecDeque<T> {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write(&(self.len() as u32));
+        for item in self {
+            writer.write(item);
+        }
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        Ok(reader.read::<Vec<T>>()?.into())
+    }
+}
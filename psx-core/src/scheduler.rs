@@ -0,0 +1,286 @@
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
+
+/// Something a device wants to happen at a given point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    HBlankStart,
+    HBlankEnd,
+    Timer(u8),
+    Cdrom,
+    Sio,
+    Dma(u8),
+    Lightpen,
+}
+
+impl Event {
+    const SLOTS: usize = 15;
+
+    /// Every event kind owns exactly one slot, so scheduling it again replaces the pending one.
+    /// Slot order also breaks ties between events that are due at the same cycle.
+    #[inline(always)]
+    const fn slot(self) -> usize {
+        match self {
+            Event::HBlankStart => 0,
+            Event::HBlankEnd => 1,
+            Event::Timer(id) => 2 + id as usize,
+            Event::Cdrom => 5,
+            Event::Sio => 6,
+            Event::Dma(channel) => 7 + channel as usize,
+            Event::Lightpen => 14,
+        }
+    }
+
+    const fn from_slot(slot: usize) -> Self {
+        match slot {
+            0 => Event::HBlankStart,
+            1 => Event::HBlankEnd,
+            2..=4 => Event::Timer((slot - 2) as u8),
+            5 => Event::Cdrom,
+            6 => Event::Sio,
+            7..=13 => Event::Dma((slot - 7) as u8),
+            14 => Event::Lightpen,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::HBlankStart => write!(f, "HBlank Start"),
+            Event::HBlankEnd => write!(f, "HBlank End"),
+            Event::Timer(id) => write!(f, "Timer {}", id),
+            Event::Cdrom => write!(f, "CDROM"),
+            Event::Sio => write!(f, "SIO"),
+            Event::Dma(channel) => write!(f, "DMA{}", channel),
+            Event::Lightpen => write!(f, "Lightpen"),
+        }
+    }
+}
+
+const NOT_SCHEDULED: u64 = u64::MAX;
+
+/// Keeps track of the system clock and the next timestamp each device needs attention at.
+/// The CPU runs freely until `now` reaches the earliest deadline, at which point the due
+/// events are popped in timestamp order and dispatched by `Psx`.
+pub struct Scheduler {
+    now: u64,
+    deadlines: [u64; Event::SLOTS],
+    next_deadline: u64,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            now: 0,
+            deadlines: [NOT_SCHEDULED; Event::SLOTS],
+            next_deadline: NOT_SCHEDULED,
+        }
+    }
+
+    /// Number of CPU cycles elapsed since power on
+    #[inline(always)]
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    #[inline(always)]
+    pub fn advance(&mut self, cycles: usize) {
+        self.now += cycles as u64;
+    }
+
+    #[inline(always)]
+    pub fn is_due(&self) -> bool {
+        self.now >= self.next_deadline
+    }
+
+    /// Schedule `event` to fire `cycles` from now, replacing any pending instance of it
+    pub fn schedule(&mut self, event: Event, cycles: u64) {
+        self.schedule_at(event, self.now + cycles);
+    }
+
+    /// Schedule `event` at an absolute timestamp, replacing any pending instance of it
+    pub fn schedule_at(&mut self, event: Event, timestamp: u64) {
+        let slot = event.slot();
+        let previous = std::mem::replace(&mut self.deadlines[slot], timestamp);
+
+        if previous == self.next_deadline {
+            self.recalculate_next_deadline();
+        } else {
+            self.next_deadline = self.next_deadline.min(timestamp);
+        }
+
+        tracing::trace!(
+            target: "psx_core::scheduler",
+            %event, timestamp, now = self.now,
+            "Event scheduled"
+        );
+    }
+
+    pub fn cancel(&mut self, event: Event) {
+        let slot = event.slot();
+        if self.deadlines[slot] != NOT_SCHEDULED {
+            self.deadlines[slot] = NOT_SCHEDULED;
+            self.recalculate_next_deadline();
+        }
+    }
+
+    pub fn deadline(&self, event: Event) -> Option<u64> {
+        match self.deadlines[event.slot()] {
+            NOT_SCHEDULED => None,
+            timestamp => Some(timestamp),
+        }
+    }
+
+    /// Pop the earliest event that is due, together with the timestamp it was scheduled for
+    pub fn pop_due(&mut self) -> Option<(Event, u64)> {
+        if !self.is_due() {
+            return None;
+        }
+
+        let (slot, timestamp) = self
+            .deadlines
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by_key(|&(_, timestamp)| timestamp)?;
+
+        self.deadlines[slot] = NOT_SCHEDULED;
+        self.recalculate_next_deadline();
+
+        Some((Event::from_slot(slot), timestamp))
+    }
+
+    fn recalculate_next_deadline(&mut self) {
+        self.next_deadline = self.deadlines.iter().copied().min().unwrap_or(NOT_SCHEDULED);
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Savestate for Scheduler {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.now);
+        writer.write(&self.deadlines);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.now = reader.read()?;
+        self.deadlines = reader.read()?;
+        self.recalculate_next_deadline();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pop_all(scheduler: &mut Scheduler) -> Vec<(Event, u64)> {
+        std::iter::from_fn(|| scheduler.pop_due()).collect()
+    }
+
+    #[test]
+    fn test_slot_round_trip() {
+        for slot in 0..Event::SLOTS {
+            assert_eq!(Event::from_slot(slot).slot(), slot);
+        }
+    }
+
+    #[test]
+    fn test_pop_in_timestamp_then_slot_order() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(Event::Lightpen, 10);
+        scheduler.schedule(Event::Dma(2), 10);
+        scheduler.schedule(Event::Timer(1), 10);
+        scheduler.schedule(Event::HBlankEnd, 5);
+        scheduler.schedule(Event::Cdrom, 20);
+
+        assert_eq!(scheduler.pop_due(), None);
+
+        scheduler.advance(10);
+        assert_eq!(
+            pop_all(&mut scheduler),
+            [
+                (Event::HBlankEnd, 5),
+                (Event::Timer(1), 10),
+                (Event::Dma(2), 10),
+                (Event::Lightpen, 10),
+            ]
+        );
+        assert_eq!(scheduler.deadline(Event::Cdrom), Some(20));
+    }
+
+    #[test]
+    fn test_reschedule_and_cancel() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(Event::Sio, 10);
+        scheduler.schedule(Event::Cdrom, 30);
+
+        // Scheduling again replaces the pending event, even with a later deadline
+        scheduler.schedule(Event::Sio, 50);
+        assert_eq!(scheduler.deadline(Event::Sio), Some(50));
+        scheduler.advance(10);
+        assert!(!scheduler.is_due());
+
+        scheduler.schedule_at(Event::Sio, 5);
+        assert!(scheduler.is_due());
+        assert_eq!(scheduler.pop_due(), Some((Event::Sio, 5)));
+
+        scheduler.cancel(Event::Cdrom);
+        scheduler.cancel(Event::Cdrom);
+        assert_eq!(scheduler.deadline(Event::Cdrom), None);
+        scheduler.advance(100);
+        assert_eq!(scheduler.pop_due(), None);
+    }
+
+    #[test]
+    fn test_pop_due_across_advances() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(Event::HBlankStart, 100);
+        scheduler.schedule(Event::Timer(0), 250);
+
+        scheduler.advance(60);
+        assert_eq!(pop_all(&mut scheduler), []);
+
+        scheduler.advance(60);
+        assert_eq!(pop_all(&mut scheduler), [(Event::HBlankStart, 100)]);
+
+        // A device rescheduling from its handler lands after the current time
+        scheduler.schedule_at(Event::HBlankStart, 100 + 200);
+        scheduler.advance(200);
+        assert_eq!(scheduler.now(), 320);
+        assert_eq!(
+            pop_all(&mut scheduler),
+            [(Event::Timer(0), 250), (Event::HBlankStart, 300)]
+        );
+        assert!(!scheduler.is_due());
+    }
+
+    #[test]
+    fn test_save_load_pending_events() {
+        let mut scheduler = Scheduler::new();
+        scheduler.advance(1000);
+        scheduler.schedule(Event::Dma(6), 40);
+        scheduler.schedule(Event::Timer(2), 20);
+
+        let mut writer = StateWriter::new();
+        scheduler.save_state(&mut writer);
+        let state = writer.into_inner();
+
+        let mut loaded = Scheduler::new();
+        loaded.schedule(Event::Cdrom, 5);
+        loaded.load_state(&mut StateReader::new(&state)).unwrap();
+
+        assert_eq!(loaded.now(), 1000);
+        assert_eq!(loaded.deadline(Event::Cdrom), None);
+        assert!(!loaded.is_due());
+
+        loaded.advance(40);
+        assert_eq!(pop_all(&mut loaded), [(Event::Timer(2), 1020), (Event::Dma(6), 1040)]);
+    }
+}
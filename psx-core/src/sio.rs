@@ -1,11 +1,22 @@
+pub mod dualshock;
+pub mod guncon;
 pub mod joy;
+pub mod justifier;
+pub mod link;
 pub mod memcard;
+pub mod mouse;
+pub mod multitap;
+pub mod negcon;
 pub mod sio0;
 pub mod sio1;
+pub mod unirom;
 
+use crate::gpu::status::VideoMode;
 use crate::mmu::bus::{Bus8, Bus16, Bus32};
-use crate::sio::joy::ControllerState;
-use crate::sio::sio1::Sio1;
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
+use crate::sio::joy::{ControllerState, ControllerType, PointerState, RumbleCallback};
+use crate::sio::memcard::MemoryCard;
+use crate::sio::sio1::{SerialBackend, Sio1};
 use proc_bitfield::bitfield;
 use sio0::{SIO0_BAUD_ADDR_END, SIO0_TX_DATA_ADDR_START, Sio0};
 use sio1::{SIO1_BAUD_ADDR_END, SIO1_TX_DATA_ADDR_START};
@@ -48,7 +59,11 @@ bitfield! {
         pub rx_fifo_not_empty: bool @ 1,
         pub tx_ready_2: bool @ 2,
         pub rx_parity_error: bool @ 3,
-        pub ack_input_level: bool @ 7,
+        pub rx_fifo_overrun: bool @ 4,
+        pub rx_bad_stop_bit: bool @ 5,
+        pub rx_input_level: bool @ 6,
+        pub ack_input_level: bool @ 7, // DSR input level on SIO1
+        pub cts_input_level: bool @ 8,
         pub interrupt_request: bool @ 9,
         pub baud_timer: u32 @ 11..=31,
     }
@@ -70,6 +85,7 @@ bitfield! {
         pub char_length: u8 @ 2..=3,
         pub parity_enable: bool @ 4,
         pub parity_odd: bool @ 5,
+        pub stop_bit_length: u8 @ 6..=7,
         pub clock_polarity: bool @ 8,
     }
 }
@@ -77,6 +93,7 @@ bitfield! {
 pub struct Sio {
     sio0: Sio0,
     sio1: Sio1,
+    last_sync: u64,
 }
 
 impl Sio {
@@ -84,20 +101,94 @@ impl Sio {
         Sio {
             sio0: Sio0::new(),
             sio1: Sio1::new(),
+            last_sync: 0,
         }
     }
 
-    pub fn set_controller_state(&mut self, state: ControllerState) {
-        self.sio0.set_controller_state(state);
+    pub fn connect_multitap(&mut self, port: usize, connected: bool) {
+        self.sio0.connect_multitap(port, connected);
     }
 
-    pub fn tick(&mut self, cycles: usize) {
+    pub fn has_multitap(&self, port: usize) -> bool {
+        self.sio0.has_multitap(port)
+    }
+
+    pub fn set_controller_state(&mut self, port: usize, slot: usize, state: ControllerState) {
+        self.sio0.set_controller_state(port, slot, state);
+    }
+
+    pub fn connect_controller(&mut self, port: usize, slot: usize, controller_type: ControllerType) {
+        self.sio0.connect_controller(port, slot, controller_type);
+    }
+
+    pub fn set_pointer_state(&mut self, port: usize, slot: usize, state: PointerState) {
+        self.sio0.set_pointer_state(port, slot, state);
+    }
+
+    pub fn vblank(&mut self, video_mode: VideoMode) {
+        self.sio0.vblank(video_mode);
+    }
+
+    pub fn lightpen_target(&self) -> Option<(u16, u64)> {
+        self.sio0.lightpen_target()
+    }
+
+    pub fn set_rumble_callback(&mut self, port: usize, slot: usize, callback: RumbleCallback) {
+        self.sio0.set_rumble_callback(port, slot, callback);
+    }
+
+    pub fn insert_memory_card(&mut self, port: usize, slot: usize, card: MemoryCard) {
+        self.sio0.insert_memory_card(port, slot, card);
+    }
+
+    pub fn memory_card(&self, port: usize, slot: usize) -> Option<&MemoryCard> {
+        self.sio0.memory_card(port, slot)
+    }
+
+    /// Plug `backend` into the SIO1 serial port, replacing whatever was connected
+    pub fn connect_serial(&mut self, backend: Box<dyn SerialBackend>) {
+        self.sio1.connect(backend);
+    }
+
+    pub fn disconnect_serial(&mut self) {
+        self.sio1.disconnect();
+    }
+
+    /// What is plugged into the controller ports, save states only load into the same setup
+    pub(crate) fn device_layout(&self) -> Vec<u8> {
+        self.sio0.device_layout()
+    }
+
+    pub(crate) fn memory_card_checksums(&self) -> Vec<Option<u64>> {
+        self.sio0.memory_card_checksums()
+    }
+
+    pub(crate) fn has_only_direct_pads(&self) -> bool {
+        self.sio0.has_only_direct_pads()
+    }
+
+    /// Catch the serial ports up to the scheduler timestamp `now`
+    pub fn sync(&mut self, now: u64) {
+        let cycles = now.saturating_sub(self.last_sync) as usize;
+        self.last_sync = now;
         self.sio0.tick(cycles);
+        self.sio1.tick(cycles);
+    }
+
+    /// Cycles until either port needs to be synced again
+    pub fn cycles_until_event(&self) -> Option<u64> {
+        let sio0 = self.sio0.cycles_until_irq();
+        let sio1 = self.sio1.cycles_until_event();
+        sio0.into_iter().chain(sio1).min()
     }
 
     pub fn should_trigger_irq(&self) -> bool {
         self.sio0.should_trigger_irq()
     }
+
+    pub fn check_and_clear_sio1_irq(&mut self) -> bool {
+        self.sio1.check_and_clear_irq()
+    }
 }
 
 impl Bus8 for Sio {
@@ -149,3 +240,18 @@ impl Bus32 for Sio {
         self.write_u16(address, value as u16);
     }
 }
+
+impl Savestate for Sio {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.sio0.save_state(writer);
+        self.sio1.save_state(writer);
+        writer.write(&self.last_sync);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.sio0.load_state(reader)?;
+        self.sio1.load_state(reader)?;
+        self.last_sync = reader.read()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use super::joy::{CONTROLLER_ACK_DELAY, Controller, ControllerState, ControllerType, RumbleCallback};
+use super::sio0::SioDevice;
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
+
+const ID_DIGITAL: u8 = 0x41;
+const ID_ANALOG: u8 = 0x73;
+const ID_CONFIG: u8 = 0xF3;
+const ID_HIGH: u8 = 0x5A;
+
+// PSX-SPX: "4Dh: Select which of the 6 poll parameter bytes drive the motors, 00h = small, 01h = large, FFh = none"
+const RUMBLE_SMALL: u8 = 0x00;
+const RUMBLE_LARGE: u8 = 0x01;
+const RUMBLE_NONE: u8 = 0xFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DualShockTransferState {
+    Idle,
+    Selected,
+    SendingData(usize), // Index of the next byte after the command byte, 0 is the ID high byte
+}
+
+/// SCPH-1200 DualShock, digital or analog mode, config mode and two rumble motors
+pub struct DualShockDevice {
+    state: ControllerState,
+    transfer_state: DualShockTransferState,
+    command: u8,
+    id: u8,
+    params: [u8; 7], // TAP byte followed by the parameter bytes of the current command
+    payload: [u8; 6],
+
+    analog: bool,
+    analog_locked: bool, // Set by command 44h, disables the analog button
+    config_mode: bool,
+    rumble_mapping: [u8; 6],
+    motors: (u8, u8), // Small, large
+    rumble_callback: Option<RumbleCallback>,
+}
+
+impl DualShockDevice {
+    pub fn new() -> Self {
+        Self {
+            state: ControllerState::default(),
+            transfer_state: DualShockTransferState::Idle,
+            command: 0,
+            id: ID_DIGITAL,
+            params: [0; 7],
+            payload: [0; 6],
+            analog: false,
+            analog_locked: false,
+            config_mode: false,
+            rumble_mapping: [RUMBLE_NONE; 6],
+            motors: (0, 0),
+            rumble_callback: None,
+        }
+    }
+
+    fn payload_len(&self) -> usize {
+        // PSX-SPX: the low nibble of the ID is the number of halfwords that follow
+        (self.id & 0x0F) as usize * 2
+    }
+
+    /// Prepare the reply for `command`, the bytes that depend on parameters are filled in while they arrive
+    fn begin_command(&mut self, command: u8) -> bool {
+        self.command = command;
+        self.params = [0; 7];
+        self.payload = [0; 6];
+
+        self.id = match (self.config_mode, self.analog) {
+            (true, _) => ID_CONFIG,
+            (false, true) => ID_ANALOG,
+            (false, false) => ID_DIGITAL,
+        };
+
+        match (command, self.config_mode) {
+            (0x42, _) | (0x43, false) => self.fill_poll_payload(),
+            // PSX-SPX: "45h: 01h,02h,LED,02h,01h,00h"
+            (0x45, true) => self.payload = [0x01, 0x02, self.analog as u8, 0x02, 0x01, 0x00],
+            (0x4D, true) => self.payload = self.rumble_mapping,
+            (0x40..=0x4F, true) => {}
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn fill_poll_payload(&mut self) {
+        let (byte1, byte2) = if self.analog || self.config_mode {
+            self.state.to_analog_button_bytes()
+        } else {
+            self.state.to_button_bytes()
+        };
+
+        self.payload[0] = byte1;
+        self.payload[1] = byte2;
+        self.payload[2..6].copy_from_slice(&self.state.to_analog_bytes());
+    }
+
+    /// Reply bytes of the variable response commands depend on the first parameter
+    fn variable_payload(&self, index: usize) -> u8 {
+        let param = self.params[1];
+
+        match (self.command, param) {
+            // PSX-SPX: "46h: 00h,00h,01h,02h,00h,0Ah" or "00h,00h,01h,01h,01h,14h"
+            (0x46, 0x00) => [0x00, 0x00, 0x01, 0x02, 0x00, 0x0A][index],
+            (0x46, 0x01) => [0x00, 0x00, 0x01, 0x01, 0x01, 0x14][index],
+            // PSX-SPX: "47h: 00h,00h,02h,00h,01h,00h"
+            (0x47, 0x00) => [0x00, 0x00, 0x02, 0x00, 0x01, 0x00][index],
+            // PSX-SPX: "4Ch: 00h,00h,00h,04h,00h,00h" or "00h,00h,00h,07h,00h,00h"
+            (0x4C, 0x00) => [0x00, 0x00, 0x00, 0x04, 0x00, 0x00][index],
+            (0x4C, 0x01) => [0x00, 0x00, 0x00, 0x07, 0x00, 0x00][index],
+            _ => self.payload[index],
+        }
+    }
+
+    /// Apply the command once all parameters have been received
+    fn finish_command(&mut self) {
+        let params = self.params;
+
+        match (self.command, self.config_mode) {
+            (0x42, _) => self.update_motors(&params[1..]),
+            (0x43, _) => {
+                self.config_mode = params[1] == 0x01;
+                tracing::debug!(target: "psx_core::joy", config_mode = self.config_mode, "Config mode");
+            }
+            (0x44, true) => {
+                self.analog = params[1] == 0x01;
+                self.analog_locked = params[2] == 0x03;
+                tracing::debug!(target: "psx_core::joy", analog = self.analog, locked = self.analog_locked, "Set LED state");
+            }
+            (0x4D, true) => {
+                self.rumble_mapping.copy_from_slice(&params[1..]);
+                tracing::debug!(target: "psx_core::joy", mapping = ?self.rumble_mapping, "Rumble mapping");
+            }
+            _ => {}
+        }
+    }
+
+    fn update_motors(&mut self, params: &[u8]) {
+        let mut motors = (0, 0);
+
+        for (&mapping, &param) in self.rumble_mapping.iter().zip(params) {
+            match mapping {
+                // The small motor can only be switched on or off
+                RUMBLE_SMALL => motors.0 = if param & 0x01 != 0 { 0xFF } else { 0x00 },
+                RUMBLE_LARGE => motors.1 = param,
+                _ => {}
+            }
+        }
+
+        if motors != self.motors {
+            self.motors = motors;
+            tracing::trace!(target: "psx_core::joy", small = motors.0, large = motors.1, "Rumble");
+
+            if let Some(callback) = &mut self.rumble_callback {
+                callback(motors.0, motors.1);
+            }
+        }
+    }
+}
+
+impl Default for DualShockDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Controller for DualShockDevice {
+    fn controller_type(&self) -> ControllerType {
+        ControllerType::DualShock
+    }
+
+    fn set_state(&mut self, state: ControllerState) {
+        // The analog button toggles the mode on press, unless a game has locked it
+        if state.analog && !self.state.analog && !self.analog_locked {
+            self.analog = !self.analog;
+            tracing::debug!(target: "psx_core::joy", analog = self.analog, "Analog button");
+        }
+
+        self.state = state;
+    }
+
+    fn set_rumble_callback(&mut self, callback: RumbleCallback) {
+        self.rumble_callback = Some(callback);
+    }
+}
+
+impl SioDevice for DualShockDevice {
+    fn process_byte(&mut self, tx_byte: u8) -> u8 {
+        tracing::trace!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Processing byte");
+
+        match self.transfer_state {
+            DualShockTransferState::Idle => {
+                if tx_byte != 0x01 {
+                    tracing::warn!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Unexpected byte while DualShock is idle");
+                    return 0xFF;
+                }
+
+                self.transfer_state = DualShockTransferState::Selected;
+                tracing::debug!(target: "psx_core::joy", "DualShock selected");
+
+                0xFF
+            }
+            DualShockTransferState::Selected => {
+                if !self.begin_command(tx_byte) {
+                    tracing::error!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), config_mode = self.config_mode, "Unknown DualShock command");
+                    self.transfer_state = DualShockTransferState::Idle;
+                    return 0xFF;
+                }
+
+                self.transfer_state = DualShockTransferState::SendingData(0);
+                self.id
+            }
+            DualShockTransferState::SendingData(index) => {
+                self.params[index] = tx_byte;
+
+                let rx_byte = match index {
+                    0 => ID_HIGH,
+                    _ => self.variable_payload(index - 1),
+                };
+
+                if index == self.payload_len() {
+                    // Last byte - transfer complete
+                    self.finish_command();
+                    self.transfer_state = DualShockTransferState::Idle;
+                } else {
+                    self.transfer_state = DualShockTransferState::SendingData(index + 1);
+                }
+
+                rx_byte
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.transfer_state = DualShockTransferState::Idle;
+    }
+
+    fn is_selected(&self) -> bool {
+        self.transfer_state != DualShockTransferState::Idle
+    }
+
+    fn deselect(&mut self) {
+        if self.transfer_state != DualShockTransferState::Idle {
+            tracing::debug!(target: "psx_core::joy", "DualShock deselected");
+            self.transfer_state = DualShockTransferState::Idle;
+        }
+    }
+
+    fn device_id(&self) -> u8 {
+        0x01
+    }
+
+    fn ack_delay(&self) -> usize {
+        CONTROLLER_ACK_DELAY
+    }
+}
+
+impl State for DualShockTransferState {
+    fn write(&self, writer: &mut StateWriter) {
+        let (tag, index) = match *self {
+            DualShockTransferState::Idle => (0u8, 0),
+            DualShockTransferState::Selected => (1, 0),
+            DualShockTransferState::SendingData(index) => (2, index),
+        };
+        writer.write(&(tag, index));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<(u8, usize)>()? {
+            (0, _) => Ok(DualShockTransferState::Idle),
+            (1, _) => Ok(DualShockTransferState::Selected),
+            (2, index) => Ok(DualShockTransferState::SendingData(index)),
+            _ => Err(SavestateError::Invalid("DualShock transfer state out of range")),
+        }
+    }
+}
+
+/// The rumble callback belongs to the host and is kept
+impl Savestate for DualShockDevice {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.state);
+        writer.write(&self.transfer_state);
+        writer.write(&self.command);
+        writer.write(&self.id);
+        writer.write(&self.params);
+        writer.write(&self.payload);
+        writer.write(&self.analog);
+        writer.write(&self.analog_locked);
+        writer.write(&self.config_mode);
+        writer.write(&self.rumble_mapping);
+        writer.write(&self.motors);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.state = reader.read()?;
+        self.transfer_state = reader.read()?;
+        self.command = reader.read()?;
+        self.id = reader.read()?;
+        self.params = reader.read()?;
+        self.payload = reader.read()?;
+        self.analog = reader.read()?;
+        self.analog_locked = reader.read()?;
+        self.config_mode = reader.read()?;
+        self.rumble_mapping = reader.read()?;
+        self.motors = reader.read()?;
+        Ok(())
+    }
+}
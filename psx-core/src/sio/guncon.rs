@@ -0,0 +1,185 @@
+use super::joy::{CONTROLLER_ACK_DELAY, Controller, ControllerState, ControllerType, PointerState};
+use super::sio0::SioDevice;
+use crate::gpu::status::VideoMode;
+use crate::gpu::timing::VideoTiming;
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
+
+const GUNCON_ID_LOW: u8 = 0x63;
+const GUNCON_ID_HIGH: u8 = 0x5A;
+
+// PSX-SPX: "X=0001h, Y=000Ah" is reported when the gun points off screen (or at a dark area)
+const GUNCON_OFF_SCREEN: (u16, u16) = (0x0001, 0x000A);
+
+// PSX-SPX: the X coordinate is counted in 8MHz units since the horizontal sync
+const GUNCON_X_CLOCK: u64 = 8_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GunconTransferState {
+    Idle,
+    Selected,
+    SendingData(usize), // Index of the next byte after the command byte, 0 is the ID high byte
+}
+
+/// Namco GunCon, aims with the host cursor, left button is the trigger, right is A and middle is B
+pub struct GunconDevice {
+    pointer: PointerState,
+    coordinates: (u16, u16), // Latched at the last vertical blank
+    payload: [u8; 6],
+    transfer_state: GunconTransferState,
+}
+
+impl GunconDevice {
+    pub fn new() -> Self {
+        Self {
+            pointer: PointerState::default(),
+            coordinates: GUNCON_OFF_SCREEN,
+            payload: [0xFF; 6],
+            transfer_state: GunconTransferState::Idle,
+        }
+    }
+
+    /// PSX-SPX: "Trigger = bit13, A = bit3, B = bit14 (0 = pressed)", then X and Y
+    fn build_payload(&self) -> [u8; 6] {
+        let byte1 = !((self.pointer.right as u8) << 3);
+        let byte2 = !((self.pointer.left as u8) << 5 | (self.pointer.middle as u8) << 6);
+        let [x_low, x_high] = self.coordinates.0.to_le_bytes();
+        let [y_low, y_high] = self.coordinates.1.to_le_bytes();
+
+        [byte1, byte2, x_low, x_high, y_low, y_high]
+    }
+}
+
+impl Default for GunconDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Controller for GunconDevice {
+    fn controller_type(&self) -> ControllerType {
+        ControllerType::Guncon
+    }
+
+    fn set_state(&mut self, _state: ControllerState) {}
+
+    fn set_pointer_state(&mut self, state: PointerState) {
+        self.pointer = state;
+    }
+
+    fn vblank(&mut self, video_mode: VideoMode) {
+        self.coordinates = match self.pointer.position {
+            Some((x, y)) => {
+                let (video_cycle, scanline) = VideoTiming::sync_beam_position(video_mode, x, y);
+                let x = video_cycle * GUNCON_X_CLOCK / VideoTiming::video_clock(video_mode);
+                (x as u16, scanline)
+            }
+            None => GUNCON_OFF_SCREEN,
+        };
+    }
+}
+
+impl SioDevice for GunconDevice {
+    fn process_byte(&mut self, tx_byte: u8) -> u8 {
+        tracing::trace!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Processing byte");
+
+        match self.transfer_state {
+            GunconTransferState::Idle => {
+                if tx_byte != 0x01 {
+                    tracing::warn!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Unexpected byte while GunCon is idle");
+                    return 0xFF;
+                }
+
+                self.transfer_state = GunconTransferState::Selected;
+                tracing::debug!(target: "psx_core::joy", "GunCon selected");
+
+                0xFF
+            }
+            GunconTransferState::Selected => {
+                if tx_byte != 0x42 {
+                    tracing::error!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Unknown command while GunCon selected");
+                    self.transfer_state = GunconTransferState::Idle;
+                    return 0xFF;
+                }
+
+                self.payload = self.build_payload();
+                self.transfer_state = GunconTransferState::SendingData(0);
+                GUNCON_ID_LOW
+            }
+            GunconTransferState::SendingData(index) => {
+                let rx_byte = match index {
+                    0 => GUNCON_ID_HIGH,
+                    _ => self.payload[index - 1],
+                };
+
+                if index == self.payload.len() {
+                    // Last byte - transfer complete
+                    self.transfer_state = GunconTransferState::Idle;
+                } else {
+                    self.transfer_state = GunconTransferState::SendingData(index + 1);
+                }
+
+                rx_byte
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.transfer_state = GunconTransferState::Idle;
+    }
+
+    fn is_selected(&self) -> bool {
+        self.transfer_state != GunconTransferState::Idle
+    }
+
+    fn deselect(&mut self) {
+        if self.transfer_state != GunconTransferState::Idle {
+            tracing::debug!(target: "psx_core::joy", "GunCon deselected");
+            self.transfer_state = GunconTransferState::Idle;
+        }
+    }
+
+    fn device_id(&self) -> u8 {
+        0x01
+    }
+
+    fn ack_delay(&self) -> usize {
+        CONTROLLER_ACK_DELAY
+    }
+}
+
+impl State for GunconTransferState {
+    fn write(&self, writer: &mut StateWriter) {
+        let (tag, index) = match *self {
+            GunconTransferState::Idle => (0u8, 0),
+            GunconTransferState::Selected => (1, 0),
+            GunconTransferState::SendingData(index) => (2, index),
+        };
+        writer.write(&(tag, index));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<(u8, usize)>()? {
+            (0, _) => Ok(GunconTransferState::Idle),
+            (1, _) => Ok(GunconTransferState::Selected),
+            (2, index) => Ok(GunconTransferState::SendingData(index)),
+            _ => Err(SavestateError::Invalid("GunCon transfer state out of range")),
+        }
+    }
+}
+
+impl Savestate for GunconDevice {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.pointer);
+        writer.write(&self.coordinates);
+        writer.write(&self.payload);
+        writer.write(&self.transfer_state);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.pointer = reader.read()?;
+        self.coordinates = reader.read()?;
+        self.payload = reader.read()?;
+        self.transfer_state = reader.read()?;
+        Ok(())
+    }
+}
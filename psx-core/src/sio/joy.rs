@@ -1,6 +1,15 @@
+use super::dualshock::DualShockDevice;
+use super::guncon::GunconDevice;
+use super::justifier::JustifierDevice;
+use super::mouse::MouseDevice;
+use super::negcon::NegconDevice;
 use super::sio0::SioDevice;
+use crate::gpu::status::VideoMode;
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
 
-#[derive(Clone, Copy, Default)]
+pub const ANALOG_CENTER: u8 = 0x80;
+
+#[derive(Clone, Copy)]
 pub struct ControllerState {
     // D-Pad
     pub d_up: bool,
@@ -23,6 +32,45 @@ pub struct ControllerState {
     // System buttons
     pub start: bool,
     pub select: bool,
+
+    // Stick buttons and the mode button, analog controllers only
+    pub l3: bool,
+    pub r3: bool,
+    pub analog: bool,
+
+    // Analog sticks, 00h = left/up, 80h = center, FFh = right/down
+    pub left_x: u8,
+    pub left_y: u8,
+    pub right_x: u8,
+    pub right_y: u8,
+}
+
+impl Default for ControllerState {
+    fn default() -> Self {
+        Self {
+            d_up: false,
+            d_down: false,
+            d_left: false,
+            d_right: false,
+            cross: false,
+            circle: false,
+            square: false,
+            triangle: false,
+            l1: false,
+            l2: false,
+            r1: false,
+            r2: false,
+            start: false,
+            select: false,
+            l3: false,
+            r3: false,
+            analog: false,
+            left_x: ANALOG_CENTER,
+            left_y: ANALOG_CENTER,
+            right_x: ANALOG_CENTER,
+            right_y: ANALOG_CENTER,
+        }
+    }
 }
 
 impl ControllerState {
@@ -49,13 +97,35 @@ impl ControllerState {
 
         (byte1, byte2)
     }
+
+    /// Button bytes of an analog controller in analog mode, L3 and R3 replace the two unused bits
+    pub fn to_analog_button_bytes(&self) -> (u8, u8) {
+        let (byte1, byte2) = self.to_button_bytes();
+        (byte1 & !((self.l3 as u8) << 1 | (self.r3 as u8) << 2), byte2)
+    }
+
+    /// PSX-SPX: analog data is sent as RightX, RightY, LeftX, LeftY
+    pub fn to_analog_bytes(&self) -> [u8; 4] {
+        [self.right_x, self.right_y, self.left_x, self.left_y]
+    }
+}
+
+/// Host mouse input, used by pointer devices instead of the button state
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PointerState {
+    pub delta_x: i32, // Motion since the previous update
+    pub delta_y: i32,
+    pub position: Option<(f32, f32)>, // Cursor on the display from 0.0 to 1.0, None when it is off screen
+    pub left: bool,
+    pub right: bool,
+    pub middle: bool,
 }
 
 impl std::fmt::Debug for ControllerState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "[DPad: {}{}{}{} | Buttons: {}{}{}{} | Shoulders: {}{}{}{} | System: {}{}]",
+            "[DPad: {}{}{}{} | Buttons: {}{}{}{} | Shoulders: {}{}{}{} | System: {}{}{} | Sticks: {}{} {:02X},{:02X} {:02X},{:02X}]",
             if self.d_up { "U" } else { "" },
             if self.d_down { "D" } else { "" },
             if self.d_left { "L" } else { "" },
@@ -70,6 +140,72 @@ impl std::fmt::Debug for ControllerState {
             if self.r2 { "R2" } else { "" },
             if self.start { "Start" } else { "" },
             if self.select { "Select" } else { "" },
+            if self.analog { "Analog" } else { "" },
+            if self.l3 { "L3" } else { "" },
+            if self.r3 { "R3" } else { "" },
+            self.left_x,
+            self.left_y,
+            self.right_x,
+            self.right_y,
+        )
+    }
+}
+
+// Cycles between the end of a byte transfer and the /ACK pulse of the pad
+pub(crate) const CONTROLLER_ACK_DELAY: usize = 450;
+
+/// Receives the (small, large) motor values whenever a game changes them
+pub type RumbleCallback = Box<dyn FnMut(u8, u8) + Send>;
+
+/// Device that can be plugged into the controller side of a port
+pub trait Controller: SioDevice + Savestate + Send {
+    fn controller_type(&self) -> ControllerType;
+
+    fn set_state(&mut self, state: ControllerState);
+
+    /// Only pointer devices follow the host mouse
+    fn set_pointer_state(&mut self, _state: PointerState) {}
+
+    /// Called at the start of every vertical blank
+    fn vblank(&mut self, _video_mode: VideoMode) {}
+
+    /// Scanline and CPU cycles into it at which a light gun sees the beam and pulls the lightpen IRQ
+    fn lightpen_target(&self) -> Option<(u16, u64)> {
+        None
+    }
+
+    /// Only controllers with motors ever call the callback
+    fn set_rumble_callback(&mut self, _callback: RumbleCallback) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControllerType {
+    #[default]
+    Digital, // SCPH-1080
+    DualShock, // SCPH-1200
+    Mouse,     // SCPH-1090
+    Negcon,    // NPC-101
+    Guncon,    // NPC-103
+    Justifier, // SLUH-00017
+}
+
+impl ControllerType {
+    pub fn create(self) -> Box<dyn Controller> {
+        match self {
+            ControllerType::Digital => Box::new(ControllerDevice::new()),
+            ControllerType::DualShock => Box::new(DualShockDevice::new()),
+            ControllerType::Mouse => Box::new(MouseDevice::new()),
+            ControllerType::Negcon => Box::new(NegconDevice::new()),
+            ControllerType::Guncon => Box::new(GunconDevice::new()),
+            ControllerType::Justifier => Box::new(JustifierDevice::new()),
+        }
+    }
+
+    /// Mice and light guns follow a PointerState instead of the button state
+    pub fn uses_pointer(self) -> bool {
+        matches!(
+            self,
+            ControllerType::Mouse | ControllerType::Guncon | ControllerType::Justifier
         )
     }
 }
@@ -94,8 +230,14 @@ impl ControllerDevice {
             transfer_state: ControllerTransferState::Idle,
         }
     }
+}
+
+impl Controller for ControllerDevice {
+    fn controller_type(&self) -> ControllerType {
+        ControllerType::Digital
+    }
 
-    pub fn set_state(&mut self, state: ControllerState) {
+    fn set_state(&mut self, state: ControllerState) {
         self.state = state;
     }
 }
@@ -106,7 +248,10 @@ impl SioDevice for ControllerDevice {
 
         match self.transfer_state {
             ControllerTransferState::Idle => {
-                debug_assert!(tx_byte == 0x01, "Unexpected byte in Idle state");
+                if tx_byte != 0x01 {
+                    tracing::warn!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Unexpected byte while controller is idle");
+                    return 0xFF;
+                }
 
                 self.transfer_state = ControllerTransferState::Selected;
                 tracing::debug!(target: "psx_core::joy", "Controller selected");
@@ -174,4 +319,140 @@ impl SioDevice for ControllerDevice {
     fn device_id(&self) -> u8 {
         0x01
     }
+
+    fn ack_delay(&self) -> usize {
+        CONTROLLER_ACK_DELAY
+    }
+}
+
+impl State for ControllerState {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write(&[
+            self.d_up,
+            self.d_down,
+            self.d_left,
+            self.d_right,
+            self.cross,
+            self.circle,
+            self.square,
+            self.triangle,
+            self.l1,
+            self.l2,
+            self.r1,
+            self.r2,
+            self.start,
+            self.select,
+            self.l3,
+            self.r3,
+            self.analog,
+        ]);
+        writer.write(&[self.left_x, self.left_y, self.right_x, self.right_y]);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        let [
+            d_up,
+            d_down,
+            d_left,
+            d_right,
+            cross,
+            circle,
+            square,
+            triangle,
+            l1,
+            l2,
+            r1,
+            r2,
+            start,
+            select,
+            l3,
+            r3,
+            analog,
+        ] = reader.read()?;
+        let [left_x, left_y, right_x, right_y] = reader.read()?;
+
+        Ok(Self {
+            d_up,
+            d_down,
+            d_left,
+            d_right,
+            cross,
+            circle,
+            square,
+            triangle,
+            l1,
+            l2,
+            r1,
+            r2,
+            start,
+            select,
+            l3,
+            r3,
+            analog,
+            left_x,
+            left_y,
+            right_x,
+            right_y,
+        })
+    }
+}
+
+impl State for PointerState {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write(&self.delta_x);
+        writer.write(&self.delta_y);
+        writer.write(&self.position);
+        writer.write(&[self.left, self.right, self.middle]);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        let delta_x = reader.read()?;
+        let delta_y = reader.read()?;
+        let position = reader.read()?;
+        let [left, right, middle] = reader.read()?;
+
+        Ok(Self {
+            delta_x,
+            delta_y,
+            position,
+            left,
+            right,
+            middle,
+        })
+    }
+}
+
+impl State for ControllerTransferState {
+    fn write(&self, writer: &mut StateWriter) {
+        let (tag, index) = match *self {
+            ControllerTransferState::Idle => (0u8, 0),
+            ControllerTransferState::Selected => (1, 0),
+            ControllerTransferState::CommandReceived => (2, 0),
+            ControllerTransferState::SendingData(index) => (3, index),
+        };
+        writer.write(&(tag, index));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<(u8, u8)>()? {
+            (0, _) => Ok(ControllerTransferState::Idle),
+            (1, _) => Ok(ControllerTransferState::Selected),
+            (2, _) => Ok(ControllerTransferState::CommandReceived),
+            (3, index) => Ok(ControllerTransferState::SendingData(index)),
+            _ => Err(SavestateError::Invalid("controller transfer state out of range")),
+        }
+    }
+}
+
+impl Savestate for ControllerDevice {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.state);
+        writer.write(&self.transfer_state);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.state = reader.read()?;
+        self.transfer_state = reader.read()?;
+        Ok(())
+    }
 }
@@ -0,0 +1,180 @@
+use super::joy::{CONTROLLER_ACK_DELAY, Controller, ControllerState, ControllerType, PointerState};
+use super::sio0::SioDevice;
+use crate::gpu::status::VideoMode;
+use crate::gpu::timing::VideoTiming;
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
+
+const JUSTIFIER_ID_LOW: u8 = 0x31;
+const JUSTIFIER_ID_HIGH: u8 = 0x5A;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JustifierTransferState {
+    Idle,
+    Selected,
+    SendingData(usize), // Index of the next byte after the command byte, 0 is the ID high byte
+}
+
+/// Konami Justifier, aims with the host cursor, left button is the trigger, right is the side button and
+/// middle is Start
+///
+/// The gun only reports its buttons, games read the position from the timers when the lightpen IRQ fires
+pub struct JustifierDevice {
+    pointer: PointerState,
+    target: Option<(u16, u64)>, // Latched at the last vertical blank
+    payload: [u8; 2],
+    transfer_state: JustifierTransferState,
+}
+
+impl JustifierDevice {
+    pub fn new() -> Self {
+        Self {
+            pointer: PointerState::default(),
+            target: None,
+            payload: [0xFF; 2],
+            transfer_state: JustifierTransferState::Idle,
+        }
+    }
+
+    /// PSX-SPX: "Trigger = bit13, Special = bit14, Start = bit3 (0 = pressed)"
+    fn build_payload(&self) -> [u8; 2] {
+        let byte1 = !((self.pointer.middle as u8) << 3);
+        let byte2 = !((self.pointer.left as u8) << 5 | (self.pointer.right as u8) << 6);
+
+        [byte1, byte2]
+    }
+}
+
+impl Default for JustifierDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Controller for JustifierDevice {
+    fn controller_type(&self) -> ControllerType {
+        ControllerType::Justifier
+    }
+
+    fn set_state(&mut self, _state: ControllerState) {}
+
+    fn set_pointer_state(&mut self, state: PointerState) {
+        self.pointer = state;
+    }
+
+    fn vblank(&mut self, video_mode: VideoMode) {
+        self.target = self.pointer.position.map(|(x, y)| {
+            let (video_cycle, scanline) = VideoTiming::display_beam_position(video_mode, x, y);
+            (scanline, VideoTiming::video_to_cpu_cycles(video_cycle))
+        });
+    }
+
+    fn lightpen_target(&self) -> Option<(u16, u64)> {
+        self.target
+    }
+}
+
+impl SioDevice for JustifierDevice {
+    fn process_byte(&mut self, tx_byte: u8) -> u8 {
+        tracing::trace!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Processing byte");
+
+        match self.transfer_state {
+            JustifierTransferState::Idle => {
+                if tx_byte != 0x01 {
+                    tracing::warn!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Unexpected byte while Justifier is idle");
+                    return 0xFF;
+                }
+
+                self.transfer_state = JustifierTransferState::Selected;
+                tracing::debug!(target: "psx_core::joy", "Justifier selected");
+
+                0xFF
+            }
+            JustifierTransferState::Selected => {
+                if tx_byte != 0x42 {
+                    tracing::error!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Unknown command while Justifier selected");
+                    self.transfer_state = JustifierTransferState::Idle;
+                    return 0xFF;
+                }
+
+                self.payload = self.build_payload();
+                self.transfer_state = JustifierTransferState::SendingData(0);
+                JUSTIFIER_ID_LOW
+            }
+            JustifierTransferState::SendingData(index) => {
+                let rx_byte = match index {
+                    0 => JUSTIFIER_ID_HIGH,
+                    _ => self.payload[index - 1],
+                };
+
+                if index == self.payload.len() {
+                    // Last byte - transfer complete
+                    self.transfer_state = JustifierTransferState::Idle;
+                } else {
+                    self.transfer_state = JustifierTransferState::SendingData(index + 1);
+                }
+
+                rx_byte
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.transfer_state = JustifierTransferState::Idle;
+    }
+
+    fn is_selected(&self) -> bool {
+        self.transfer_state != JustifierTransferState::Idle
+    }
+
+    fn deselect(&mut self) {
+        if self.transfer_state != JustifierTransferState::Idle {
+            tracing::debug!(target: "psx_core::joy", "Justifier deselected");
+            self.transfer_state = JustifierTransferState::Idle;
+        }
+    }
+
+    fn device_id(&self) -> u8 {
+        0x01
+    }
+
+    fn ack_delay(&self) -> usize {
+        CONTROLLER_ACK_DELAY
+    }
+}
+
+impl State for JustifierTransferState {
+    fn write(&self, writer: &mut StateWriter) {
+        let (tag, index) = match *self {
+            JustifierTransferState::Idle => (0u8, 0),
+            JustifierTransferState::Selected => (1, 0),
+            JustifierTransferState::SendingData(index) => (2, index),
+        };
+        writer.write(&(tag, index));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<(u8, usize)>()? {
+            (0, _) => Ok(JustifierTransferState::Idle),
+            (1, _) => Ok(JustifierTransferState::Selected),
+            (2, index) => Ok(JustifierTransferState::SendingData(index)),
+            _ => Err(SavestateError::Invalid("Justifier transfer state out of range")),
+        }
+    }
+}
+
+impl Savestate for JustifierDevice {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.pointer);
+        writer.write(&self.target);
+        writer.write(&self.payload);
+        writer.write(&self.transfer_state);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.pointer = reader.read()?;
+        self.target = reader.read()?;
+        self.payload = reader.read()?;
+        self.transfer_state = reader.read()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+use super::sio1::SerialBackend;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+// Every frame on the wire is a kind byte followed by one payload byte
+const FRAME_DATA: u8 = 0;
+const FRAME_LINES: u8 = 1; // bit0 = DTR, bit1 = RTS
+
+/// Where the two ends of a link cable meet, `host:port`, `tcp:host:port` or `unix:/path/to/socket`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkAddress {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for LinkAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            #[cfg(unix)]
+            return Ok(LinkAddress::Unix(PathBuf::from(path)));
+            #[cfg(not(unix))]
+            return Err(format!("Unix sockets are not supported on this platform: {}", path));
+        }
+
+        let address = s.strip_prefix("tcp:").unwrap_or(s);
+        if address.is_empty() {
+            return Err("Empty link address".to_string());
+        }
+
+        Ok(LinkAddress::Tcp(address.to_string()))
+    }
+}
+
+/// Serial link cable (SCPH-1040) between two emulator instances over a local socket
+///
+/// The peer's DTR and RTS outputs show up as our DSR and CTS inputs, like the crossed wires in the cable
+pub struct LinkCable {
+    writer: Box<dyn Write + Send>,
+    frames: Receiver<[u8; 2]>,
+    rx_queue: VecDeque<u8>,
+    remote_dtr: bool,
+    remote_rts: bool,
+    connected: bool,
+}
+
+impl LinkCable {
+    /// Wait for the other instance to connect to `address`
+    pub fn listen(address: &LinkAddress) -> io::Result<Self> {
+        match address {
+            LinkAddress::Tcp(address) => {
+                let listener = TcpListener::bind(address)?;
+                tracing::info!(target: "psx_core::link", address, "Waiting for link cable peer");
+                let (stream, peer) = listener.accept()?;
+                tracing::info!(target: "psx_core::link", %peer, "Link cable connected");
+                stream.set_nodelay(true)?;
+                Self::from_streams(stream.try_clone()?, stream)
+            }
+            #[cfg(unix)]
+            LinkAddress::Unix(path) => {
+                let _ = std::fs::remove_file(path);
+                let listener = UnixListener::bind(path)?;
+                tracing::info!(target: "psx_core::link", path = %path.display(), "Waiting for link cable peer");
+                let (stream, _) = listener.accept()?;
+                tracing::info!(target: "psx_core::link", "Link cable connected");
+                Self::from_streams(stream.try_clone()?, stream)
+            }
+        }
+    }
+
+    /// Connect to another instance listening on `address`
+    pub fn connect(address: &LinkAddress) -> io::Result<Self> {
+        match address {
+            LinkAddress::Tcp(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_nodelay(true)?;
+                tracing::info!(target: "psx_core::link", address, "Link cable connected");
+                Self::from_streams(stream.try_clone()?, stream)
+            }
+            #[cfg(unix)]
+            LinkAddress::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                tracing::info!(target: "psx_core::link", path = %path.display(), "Link cable connected");
+                Self::from_streams(stream.try_clone()?, stream)
+            }
+        }
+    }
+
+    fn from_streams(mut reader: impl Read + Send + 'static, writer: impl Write + Send + 'static) -> io::Result<Self> {
+        let (sender, frames) = mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("link-cable".to_string())
+            .spawn(move || {
+                let mut frame = [0u8; 2];
+                while reader.read_exact(&mut frame).is_ok() {
+                    if sender.send(frame).is_err() {
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            writer: Box::new(writer),
+            frames,
+            rx_queue: VecDeque::new(),
+            remote_dtr: false,
+            remote_rts: false,
+            connected: true,
+        })
+    }
+
+    fn write_frame(&mut self, kind: u8, value: u8) {
+        if !self.connected {
+            return;
+        }
+
+        if let Err(error) = self.writer.write_all(&[kind, value]) {
+            tracing::warn!(target: "psx_core::link", %error, "Link cable disconnected");
+            self.connected = false;
+        }
+    }
+
+    /// Drain everything the reader thread has received so far
+    fn poll(&mut self) {
+        loop {
+            match self.frames.try_recv() {
+                Ok([FRAME_DATA, byte]) => self.rx_queue.push_back(byte),
+                Ok([FRAME_LINES, lines]) => {
+                    self.remote_dtr = lines & 0b01 != 0;
+                    self.remote_rts = lines & 0b10 != 0;
+                }
+                Ok([kind, _]) => {
+                    tracing::warn!(target: "psx_core::link", kind, "Unknown link cable frame");
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if self.connected {
+                        tracing::warn!(target: "psx_core::link", "Link cable peer went away");
+                    }
+
+                    // An unplugged cable drops every handshake line
+                    self.connected = false;
+                    self.remote_dtr = false;
+                    self.remote_rts = false;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl SerialBackend for LinkCable {
+    fn send(&mut self, byte: u8) {
+        self.write_frame(FRAME_DATA, byte);
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        self.poll();
+        self.rx_queue.pop_front()
+    }
+
+    fn set_output_lines(&mut self, dtr: bool, rts: bool) {
+        self.write_frame(FRAME_LINES, dtr as u8 | (rts as u8) << 1);
+    }
+
+    fn input_lines(&mut self) -> (bool, bool) {
+        self.poll();
+        (self.remote_dtr, self.remote_rts)
+    }
+}
@@ -1,21 +1,345 @@
 use super::sio0::SioDevice;
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
+use std::path::PathBuf;
+
+pub const MEMORY_CARD_SIZE: usize = 128 * 1024;
+pub const MEMORY_CARD_SECTOR_SIZE: usize = 128;
+pub const MEMORY_CARD_SECTOR_COUNT: u16 = (MEMORY_CARD_SIZE / MEMORY_CARD_SECTOR_SIZE) as u16;
+
+// PSX-SPX: "FLAG: bit2 = error, bit3 = fresh card (set on power-up or card insertion, cleared by Write)"
+const FLAG_ERROR: u8 = 1 << 2;
+const FLAG_NEW_CARD: u8 = 1 << 3;
+
+const CARD_ID1: u8 = 0x5A;
+const CARD_ID2: u8 = 0x5D;
+const COMMAND_ACK1: u8 = 0x5C;
+const COMMAND_ACK2: u8 = 0x5D;
+
+const END_GOOD: u8 = 0x47;
+const END_BAD_CHECKSUM: u8 = 0x4E;
+const END_BAD_SECTOR: u8 = 0xFF;
+
+// Cycles between the end of a byte transfer and the /ACK pulse of the card
+const MEMORY_CARD_ACK_DELAY: usize = 170;
+
+// Index of the first data byte within a Read/Write command, counted after the command byte
+const READ_DATA_START: usize = 8;
+const WRITE_DATA_START: usize = 4;
+
+// Vblanks without sector writes before the image is saved, a save spans many sectors
+const MEMORY_CARD_FLUSH_IDLE_FRAMES: u32 = 60;
+
+/// 128 KiB raw card image, optionally backed by a `.mcr` file on disk
+pub struct MemoryCard {
+    data: Box<[u8; MEMORY_CARD_SIZE]>,
+    path: Option<PathBuf>,
+    dirty: bool,
+    idle_frames: u32,
+}
+
+impl MemoryCard {
+    /// Freshly formatted card that only lives in memory
+    pub fn new() -> Self {
+        let mut data: Box<[u8; MEMORY_CARD_SIZE]> = vec![0; MEMORY_CARD_SIZE].try_into().unwrap();
+
+        // PSX-SPX: Header Frame (Block 0, Frame 0) starts with "MC"
+        data[0] = b'M';
+        data[1] = b'C';
+        Self::update_frame_checksum(&mut data[..MEMORY_CARD_SECTOR_SIZE]);
+
+        // Directory frames (Block 0, Frame 1..15), all blocks free
+        for frame in 1..16 {
+            let frame = &mut data[frame * MEMORY_CARD_SECTOR_SIZE..(frame + 1) * MEMORY_CARD_SECTOR_SIZE];
+            frame[0] = 0xA0; // Free (freshly formatted)
+            frame[8..10].copy_from_slice(&[0xFF, 0xFF]); // No next block
+            Self::update_frame_checksum(frame);
+        }
+
+        // Broken sector list (Block 0, Frame 16..35), no broken sectors
+        for frame in 16..36 {
+            let frame = &mut data[frame * MEMORY_CARD_SECTOR_SIZE..(frame + 1) * MEMORY_CARD_SECTOR_SIZE];
+            frame[0..4].copy_from_slice(&[0xFF; 4]);
+            frame[8..10].copy_from_slice(&[0xFF, 0xFF]);
+            Self::update_frame_checksum(frame);
+        }
+
+        // Write test frame (Block 0, Frame 63) is a copy of the header frame
+        data.copy_within(0..MEMORY_CARD_SECTOR_SIZE, 63 * MEMORY_CARD_SECTOR_SIZE);
+
+        Self {
+            data,
+            path: None,
+            dirty: false,
+            idle_frames: 0,
+        }
+    }
+
+    /// Open the `.mcr` image at `path`, a new formatted image is created if the file does not exist yet
+    pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+
+        if !path.exists() {
+            let mut card = Self::new();
+            card.path = Some(path);
+            card.dirty = true;
+            card.flush()?;
+            return Ok(card);
+        }
+
+        let mut card = Self::from_image(std::fs::read(&path)?)?;
+        card.path = Some(path);
+        Ok(card)
+    }
+
+    /// Card from a raw 128 KiB image that only lives in memory
+    pub fn from_image(image: Vec<u8>) -> std::io::Result<Self> {
+        let data = image.try_into().map_err(|image: Vec<u8>| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Memory card image must be {} bytes, got {} bytes",
+                    MEMORY_CARD_SIZE,
+                    image.len()
+                ),
+            )
+        })?;
+
+        Ok(Self {
+            data,
+            path: None,
+            dirty: false,
+            idle_frames: 0,
+        })
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        self.dirty = true;
+        self.data.as_mut_slice()
+    }
+
+    /// Write the image back to its file if it was modified since the last flush
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.dirty = false;
+        match &self.path {
+            Some(path) => std::fs::write(path, self.data.as_slice()),
+            None => Ok(()),
+        }
+    }
+
+    /// Called once per frame, flushes the image once the card has not been written for a while
+    pub fn vblank(&mut self) -> std::io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.idle_frames += 1;
+        if self.idle_frames < MEMORY_CARD_FLUSH_IDLE_FRAMES {
+            return Ok(());
+        }
+
+        self.flush()
+    }
+
+    fn sector(&self, sector: u16) -> &[u8] {
+        let offset = sector as usize * MEMORY_CARD_SECTOR_SIZE;
+        &self.data[offset..offset + MEMORY_CARD_SECTOR_SIZE]
+    }
+
+    fn write_sector(&mut self, sector: u16, data: &[u8; MEMORY_CARD_SECTOR_SIZE]) {
+        let offset = sector as usize * MEMORY_CARD_SECTOR_SIZE;
+        self.data[offset..offset + MEMORY_CARD_SECTOR_SIZE].copy_from_slice(data);
+        self.dirty = true;
+        self.idle_frames = 0;
+    }
+
+    /// PSX-SPX: "7Fh Checksum (all above bytes XORed with each other)"
+    pub fn frame_checksum(frame: &[u8]) -> u8 {
+        frame[..MEMORY_CARD_SECTOR_SIZE - 1]
+            .iter()
+            .fold(0, |checksum, byte| checksum ^ byte)
+    }
+
+    pub fn update_frame_checksum(frame: &mut [u8]) {
+        frame[MEMORY_CARD_SECTOR_SIZE - 1] = Self::frame_checksum(frame);
+    }
+}
+
+impl Default for MemoryCard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for MemoryCard {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::error!(target: "psx_core::mc", error = %e, "Failed to flush memory card image");
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum MemoryCardTransferState {
     Idle,
     Selected,
-    CommandReceived(u8),
-    SendingData(u8),
+    Read(usize),  // Index of the next byte after the command byte
+    Write(usize), // Index of the next byte after the command byte
+    GetId(usize), // Index of the next byte after the command byte
 }
 
 pub struct MemoryCardDevice {
+    card: MemoryCard,
+    flag: u8,
     transfer_state: MemoryCardTransferState,
+    sector: u16,
+    checksum: u8,
+    previous_byte: u8, // Write replies echo the previously received byte
+    write_buffer: [u8; MEMORY_CARD_SECTOR_SIZE],
 }
 
 impl MemoryCardDevice {
-    pub fn new() -> Self {
+    pub fn new(card: MemoryCard) -> Self {
         Self {
+            card,
+            flag: FLAG_NEW_CARD,
             transfer_state: MemoryCardTransferState::Idle,
+            sector: 0,
+            checksum: 0,
+            previous_byte: 0,
+            write_buffer: [0; MEMORY_CARD_SECTOR_SIZE],
+        }
+    }
+
+    pub fn card(&self) -> &MemoryCard {
+        &self.card
+    }
+
+    pub fn vblank(&mut self) -> std::io::Result<()> {
+        self.card.vblank()
+    }
+
+    fn process_read(&mut self, index: usize, tx_byte: u8) -> u8 {
+        self.transfer_state = MemoryCardTransferState::Read(index + 1);
+
+        match index {
+            0 => CARD_ID1,
+            1 => CARD_ID2,
+            2 => {
+                self.sector = (tx_byte as u16) << 8;
+                0x00
+            }
+            3 => {
+                self.sector |= tx_byte as u16;
+                self.checksum = (self.sector >> 8) as u8 ^ self.sector as u8;
+                (self.sector >> 8) as u8
+            }
+            4 => COMMAND_ACK1,
+            5 => COMMAND_ACK2,
+            6 | 7 if self.sector >= MEMORY_CARD_SECTOR_COUNT => {
+                // PSX-SPX: invalid sectors are confirmed as FFFFh and the transfer is aborted
+                if index == 7 {
+                    tracing::warn!(target: "psx_core::mc", sector = format!("{:04X}", self.sector), "Read from invalid sector");
+                    self.transfer_state = MemoryCardTransferState::Idle;
+                }
+                0xFF
+            }
+            6 => (self.sector >> 8) as u8,
+            7 => self.sector as u8,
+            READ_DATA_START..=135 => {
+                let byte = self.card.sector(self.sector)[index - READ_DATA_START];
+                self.checksum ^= byte;
+                byte
+            }
+            136 => self.checksum,
+            _ => {
+                tracing::debug!(target: "psx_core::mc", sector = format!("{:04X}", self.sector), "Sector read");
+                self.transfer_state = MemoryCardTransferState::Idle;
+                END_GOOD
+            }
+        }
+    }
+
+    fn process_write(&mut self, index: usize, tx_byte: u8) -> u8 {
+        self.transfer_state = MemoryCardTransferState::Write(index + 1);
+
+        let reply = match index {
+            0 => CARD_ID1,
+            1 => CARD_ID2,
+            2 => {
+                self.sector = (tx_byte as u16) << 8;
+                0x00
+            }
+            3 => {
+                self.sector |= tx_byte as u16;
+                self.checksum = (self.sector >> 8) as u8 ^ self.sector as u8;
+                self.previous_byte
+            }
+            WRITE_DATA_START..=131 => {
+                self.write_buffer[index - WRITE_DATA_START] = tx_byte;
+                self.checksum ^= tx_byte;
+                self.previous_byte
+            }
+            132 => {
+                // The received checksum is only compared once the end byte is sent
+                self.checksum ^= tx_byte;
+                self.previous_byte
+            }
+            133 => COMMAND_ACK1,
+            134 => COMMAND_ACK2,
+            _ => {
+                self.transfer_state = MemoryCardTransferState::Idle;
+                return self.finish_write();
+            }
+        };
+
+        self.previous_byte = tx_byte;
+        reply
+    }
+
+    fn finish_write(&mut self) -> u8 {
+        if self.sector >= MEMORY_CARD_SECTOR_COUNT {
+            tracing::warn!(target: "psx_core::mc", sector = format!("{:04X}", self.sector), "Write to invalid sector");
+            self.flag |= FLAG_ERROR;
+            return END_BAD_SECTOR;
+        }
+
+        // The sender checksum was XORed into ours, so a match leaves zero behind
+        if self.checksum != 0 {
+            tracing::warn!(target: "psx_core::mc", sector = format!("{:04X}", self.sector), "Write with bad checksum");
+            self.flag |= FLAG_ERROR;
+            return END_BAD_CHECKSUM;
+        }
+
+        self.card.write_sector(self.sector, &self.write_buffer);
+        self.flag &= !(FLAG_ERROR | FLAG_NEW_CARD);
+
+        tracing::debug!(target: "psx_core::mc", sector = format!("{:04X}", self.sector), "Sector written");
+
+        END_GOOD
+    }
+
+    fn process_get_id(&mut self, index: usize) -> u8 {
+        self.transfer_state = MemoryCardTransferState::GetId(index + 1);
+
+        match index {
+            0 => CARD_ID1,
+            1 => CARD_ID2,
+            2 => COMMAND_ACK1,
+            3 => COMMAND_ACK2,
+            4 => 0x04,
+            5 | 6 => 0x00,
+            _ => {
+                self.transfer_state = MemoryCardTransferState::Idle;
+                0x80
+            }
         }
     }
 }
@@ -24,7 +348,10 @@ impl SioDevice for MemoryCardDevice {
     fn process_byte(&mut self, tx_byte: u8) -> u8 {
         match self.transfer_state {
             MemoryCardTransferState::Idle => {
-                debug_assert!(tx_byte == 0x81, "Unexpected byte in Idle state");
+                if tx_byte != 0x81 {
+                    tracing::warn!(target: "psx_core::mc", tx = format!("{:02X}", tx_byte), "Unexpected byte while memory card is idle");
+                    return 0xFF;
+                }
 
                 self.transfer_state = MemoryCardTransferState::Selected;
                 tracing::debug!(target: "psx_core::mc", "Memory card selected");
@@ -32,23 +359,24 @@ impl SioDevice for MemoryCardDevice {
                 0xFF
             }
             MemoryCardTransferState::Selected => {
-                // Receive command byte
                 tracing::debug!(target: "psx_core::mc", cmd = format!("{:02X}", tx_byte), "Memory card command received");
-                self.transfer_state = MemoryCardTransferState::CommandReceived(tx_byte);
-                0xFF
-            }
-            MemoryCardTransferState::CommandReceived(cmd) => {
-                // Process command
-                tracing::error!(target: "psx_core::mc", cmd = format!("{:02X}", cmd), "Unknown memory card command");
-                self.transfer_state = MemoryCardTransferState::SendingData(0);
-                0xFF
-            }
-            MemoryCardTransferState::SendingData(index) => {
-                // Send data bytes
-                tracing::error!(target: "psx_core::mc", index, "Memory card data transfer not implemented");
-                self.transfer_state = MemoryCardTransferState::Idle;
-                0xFF
+
+                self.transfer_state = match tx_byte {
+                    0x52 => MemoryCardTransferState::Read(0),
+                    0x57 => MemoryCardTransferState::Write(0),
+                    0x53 => MemoryCardTransferState::GetId(0),
+                    _ => {
+                        tracing::error!(target: "psx_core::mc", cmd = format!("{:02X}", tx_byte), "Unknown memory card command");
+                        MemoryCardTransferState::Idle
+                    }
+                };
+                self.previous_byte = tx_byte;
+
+                self.flag
             }
+            MemoryCardTransferState::Read(index) => self.process_read(index, tx_byte),
+            MemoryCardTransferState::Write(index) => self.process_write(index, tx_byte),
+            MemoryCardTransferState::GetId(index) => self.process_get_id(index),
         }
     }
 
@@ -70,4 +398,225 @@ impl SioDevice for MemoryCardDevice {
     fn device_id(&self) -> u8 {
         0x81
     }
+
+    fn ack_delay(&self) -> usize {
+        MEMORY_CARD_ACK_DELAY
+    }
+}
+
+impl State for MemoryCardTransferState {
+    fn write(&self, writer: &mut StateWriter) {
+        let (tag, index) = match *self {
+            MemoryCardTransferState::Idle => (0u8, 0),
+            MemoryCardTransferState::Selected => (1, 0),
+            MemoryCardTransferState::Read(index) => (2, index),
+            MemoryCardTransferState::Write(index) => (3, index),
+            MemoryCardTransferState::GetId(index) => (4, index),
+        };
+        writer.write(&(tag, index));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<(u8, usize)>()? {
+            (0, _) => Ok(MemoryCardTransferState::Idle),
+            (1, _) => Ok(MemoryCardTransferState::Selected),
+            (2, index) => Ok(MemoryCardTransferState::Read(index)),
+            (3, index) => Ok(MemoryCardTransferState::Write(index)),
+            (4, index) => Ok(MemoryCardTransferState::GetId(index)),
+            _ => Err(SavestateError::Invalid("memory card transfer state out of range")),
+        }
+    }
+}
+
+/// Only the transfer in flight, the card contents are media and stay as they are
+impl Savestate for MemoryCardDevice {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.flag);
+        writer.write(&self.transfer_state);
+        writer.write(&self.sector);
+        writer.write(&self.checksum);
+        writer.write(&self.previous_byte);
+        writer.write_bytes(&self.write_buffer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.flag = reader.read()?;
+        self.transfer_state = reader.read()?;
+        self.sector = reader.read()?;
+        self.checksum = reader.read()?;
+        self.previous_byte = reader.read()?;
+        reader.read_into(&mut self.write_buffer)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTOR: u16 = 0x0123;
+
+    fn device() -> MemoryCardDevice {
+        let mut image = MemoryCard::new().data().to_vec();
+        let offset = SECTOR as usize * MEMORY_CARD_SECTOR_SIZE;
+        for (i, byte) in image[offset..offset + MEMORY_CARD_SECTOR_SIZE].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        MemoryCardDevice::new(MemoryCard::from_image(image).unwrap())
+    }
+
+    fn transfer(device: &mut MemoryCardDevice, bytes: &[u8]) -> Vec<u8> {
+        bytes.iter().map(|&byte| device.process_byte(byte)).collect()
+    }
+
+    fn read_command(sector: u16) -> Vec<u8> {
+        let mut command = vec![0x81, 0x52, 0x00, 0x00, (sector >> 8) as u8, sector as u8];
+        command.resize(command.len() + 4 + MEMORY_CARD_SECTOR_SIZE + 2, 0x00);
+        command
+    }
+
+    fn write_command(sector: u16, data: &[u8; MEMORY_CARD_SECTOR_SIZE], checksum: u8) -> Vec<u8> {
+        let mut command = vec![0x81, 0x57, 0x00, 0x00, (sector >> 8) as u8, sector as u8];
+        command.extend_from_slice(data);
+        command.extend_from_slice(&[checksum, 0x00, 0x00, 0x00]);
+        command
+    }
+
+    fn checksum(sector: u16, data: &[u8]) -> u8 {
+        data.iter()
+            .fold((sector >> 8) as u8 ^ sector as u8, |checksum, byte| checksum ^ byte)
+    }
+
+    #[test]
+    fn test_read_sector() {
+        let mut device = device();
+        let reply = transfer(&mut device, &read_command(SECTOR));
+
+        let data: Vec<u8> = (0..MEMORY_CARD_SECTOR_SIZE as u8).collect();
+        assert_eq!(reply[..4], [0xFF, FLAG_NEW_CARD, CARD_ID1, CARD_ID2]);
+        assert_eq!(reply[4..10], [0x00, 0x01, COMMAND_ACK1, COMMAND_ACK2, 0x01, 0x23]);
+        assert_eq!(reply[10..10 + MEMORY_CARD_SECTOR_SIZE], data);
+        assert_eq!(reply[10 + MEMORY_CARD_SECTOR_SIZE], checksum(SECTOR, &data));
+        assert_eq!(reply[11 + MEMORY_CARD_SECTOR_SIZE], END_GOOD);
+        assert!(!device.is_selected());
+    }
+
+    #[test]
+    fn test_read_invalid_sector() {
+        let mut device = device();
+        let reply = transfer(&mut device, &read_command(MEMORY_CARD_SECTOR_COUNT)[..10]);
+
+        assert_eq!(reply[8..10], [0xFF, 0xFF]);
+        assert!(!device.is_selected());
+    }
+
+    #[test]
+    fn test_write_sector() {
+        let mut device = device();
+        let data = [0xA5; MEMORY_CARD_SECTOR_SIZE];
+        let reply = transfer(&mut device, &write_command(SECTOR, &data, checksum(SECTOR, &data)));
+
+        assert_eq!(reply[..5], [0xFF, FLAG_NEW_CARD, CARD_ID1, CARD_ID2, 0x00]);
+        // Every byte from the sector number on echoes the one received before it
+        assert_eq!(reply[5..8], [0x01, 0x23, 0xA5]);
+        assert_eq!(reply[reply.len() - 3..], [COMMAND_ACK1, COMMAND_ACK2, END_GOOD]);
+        assert!(!device.is_selected());
+
+        assert_eq!(device.card().sector(SECTOR), data);
+        assert!(device.card().dirty);
+    }
+
+    #[test]
+    fn test_write_bad_checksum() {
+        let mut device = device();
+        let data = [0xA5; MEMORY_CARD_SECTOR_SIZE];
+        let reply = transfer(&mut device, &write_command(SECTOR, &data, !checksum(SECTOR, &data)));
+
+        assert_eq!(reply.last(), Some(&END_BAD_CHECKSUM));
+        assert_eq!(device.card().sector(SECTOR)[0], 0x00);
+        assert!(!device.card().dirty);
+
+        // The error is reported in FLAG of the next command, the card still counts as new
+        let reply = transfer(&mut device, &[0x81, 0x53]);
+        assert_eq!(reply[1], FLAG_NEW_CARD | FLAG_ERROR);
+    }
+
+    #[test]
+    fn test_write_invalid_sector() {
+        let mut device = device();
+        let data = [0xA5; MEMORY_CARD_SECTOR_SIZE];
+        let sector = MEMORY_CARD_SECTOR_COUNT;
+        let reply = transfer(&mut device, &write_command(sector, &data, checksum(sector, &data)));
+
+        assert_eq!(reply.last(), Some(&END_BAD_SECTOR));
+        assert!(!device.card().dirty);
+    }
+
+    #[test]
+    fn test_get_id() {
+        let mut device = device();
+        let reply = transfer(&mut device, &[0x81, 0x53, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert_eq!(
+            reply,
+            [
+                0xFF,
+                FLAG_NEW_CARD,
+                CARD_ID1,
+                CARD_ID2,
+                COMMAND_ACK1,
+                COMMAND_ACK2,
+                0x04,
+                0x00,
+                0x00,
+                0x80
+            ]
+        );
+        assert!(!device.is_selected());
+    }
+
+    #[test]
+    fn test_new_card_flag() {
+        let mut device = device();
+
+        // Reads leave the flag alone, the first good write clears it
+        transfer(&mut device, &read_command(SECTOR));
+        assert_eq!(transfer(&mut device, &[0x81, 0x53])[1], FLAG_NEW_CARD);
+        device.reset();
+
+        let data = [0xA5; MEMORY_CARD_SECTOR_SIZE];
+        transfer(&mut device, &write_command(SECTOR, &data, checksum(SECTOR, &data)));
+        assert_eq!(transfer(&mut device, &[0x81, 0x53])[1], 0x00);
+    }
+
+    #[test]
+    fn test_flush_waits_for_idle_frames() {
+        let path = std::env::temp_dir().join(format!("psx-memcard-{}.mcr", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let saved = |offset: usize| std::fs::read(&path).unwrap()[offset];
+        let offset = SECTOR as usize * MEMORY_CARD_SECTOR_SIZE;
+
+        // A missing image is created right away
+        let mut device = MemoryCardDevice::new(MemoryCard::open(&path).unwrap());
+        assert_eq!(saved(0), b'M');
+
+        let data = [0xA5; MEMORY_CARD_SECTOR_SIZE];
+        transfer(&mut device, &write_command(SECTOR, &data, checksum(SECTOR, &data)));
+
+        for _ in 1..MEMORY_CARD_FLUSH_IDLE_FRAMES {
+            device.vblank().unwrap();
+        }
+        assert_eq!(saved(offset), 0x00);
+
+        device.vblank().unwrap();
+        assert_eq!(saved(offset), 0xA5);
+
+        // Dropping the card saves what was not flushed yet
+        let data = [0x5A; MEMORY_CARD_SECTOR_SIZE];
+        transfer(&mut device, &write_command(SECTOR, &data, checksum(SECTOR, &data)));
+        drop(device);
+        assert_eq!(saved(offset), 0x5A);
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,172 @@
+use super::joy::{CONTROLLER_ACK_DELAY, Controller, ControllerState, ControllerType, PointerState};
+use super::sio0::SioDevice;
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
+
+const MOUSE_ID_LOW: u8 = 0x12;
+const MOUSE_ID_HIGH: u8 = 0x5A;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MouseTransferState {
+    Idle,
+    Selected,
+    SendingData(usize), // Index of the next byte after the command byte, 0 is the ID high byte
+}
+
+/// SCPH-1090 PlayStation Mouse, relative motion and two buttons
+pub struct MouseDevice {
+    pointer: PointerState,
+    motion: (i32, i32), // Motion that has not been reported yet
+    payload: [u8; 4],
+    transfer_state: MouseTransferState,
+}
+
+impl MouseDevice {
+    pub fn new() -> Self {
+        Self {
+            pointer: PointerState::default(),
+            motion: (0, 0),
+            payload: [0xFF; 4],
+            transfer_state: MouseTransferState::Idle,
+        }
+    }
+
+    /// PSX-SPX: "bit10 = right button, bit11 = left button (0 = pressed)", then signed X and Y deltas
+    fn take_payload(&mut self) -> [u8; 4] {
+        let delta_x = self.motion.0.clamp(i8::MIN as i32, i8::MAX as i32);
+        let delta_y = self.motion.1.clamp(i8::MIN as i32, i8::MAX as i32);
+
+        // Motion beyond the range of a single poll is reported by the next one
+        self.motion.0 -= delta_x;
+        self.motion.1 -= delta_y;
+
+        let buttons = !((self.pointer.right as u8) << 2 | (self.pointer.left as u8) << 3);
+
+        [0xFF, buttons, delta_x as i8 as u8, delta_y as i8 as u8]
+    }
+}
+
+impl Default for MouseDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Controller for MouseDevice {
+    fn controller_type(&self) -> ControllerType {
+        ControllerType::Mouse
+    }
+
+    fn set_state(&mut self, _state: ControllerState) {}
+
+    fn set_pointer_state(&mut self, state: PointerState) {
+        self.motion.0 += state.delta_x;
+        self.motion.1 += state.delta_y;
+        self.pointer = state;
+    }
+}
+
+impl SioDevice for MouseDevice {
+    fn process_byte(&mut self, tx_byte: u8) -> u8 {
+        tracing::trace!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Processing byte");
+
+        match self.transfer_state {
+            MouseTransferState::Idle => {
+                if tx_byte != 0x01 {
+                    tracing::warn!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Unexpected byte while mouse is idle");
+                    return 0xFF;
+                }
+
+                self.transfer_state = MouseTransferState::Selected;
+                tracing::debug!(target: "psx_core::joy", "Mouse selected");
+
+                0xFF
+            }
+            MouseTransferState::Selected => {
+                if tx_byte != 0x42 {
+                    tracing::error!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Unknown command while mouse selected");
+                    self.transfer_state = MouseTransferState::Idle;
+                    return 0xFF;
+                }
+
+                self.payload = self.take_payload();
+                self.transfer_state = MouseTransferState::SendingData(0);
+                MOUSE_ID_LOW
+            }
+            MouseTransferState::SendingData(index) => {
+                let rx_byte = match index {
+                    0 => MOUSE_ID_HIGH,
+                    _ => self.payload[index - 1],
+                };
+
+                if index == self.payload.len() {
+                    // Last byte - transfer complete
+                    self.transfer_state = MouseTransferState::Idle;
+                } else {
+                    self.transfer_state = MouseTransferState::SendingData(index + 1);
+                }
+
+                rx_byte
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.transfer_state = MouseTransferState::Idle;
+    }
+
+    fn is_selected(&self) -> bool {
+        self.transfer_state != MouseTransferState::Idle
+    }
+
+    fn deselect(&mut self) {
+        if self.transfer_state != MouseTransferState::Idle {
+            tracing::debug!(target: "psx_core::joy", "Mouse deselected");
+            self.transfer_state = MouseTransferState::Idle;
+        }
+    }
+
+    fn device_id(&self) -> u8 {
+        0x01
+    }
+
+    fn ack_delay(&self) -> usize {
+        CONTROLLER_ACK_DELAY
+    }
+}
+
+impl State for MouseTransferState {
+    fn write(&self, writer: &mut StateWriter) {
+        let (tag, index) = match *self {
+            MouseTransferState::Idle => (0u8, 0),
+            MouseTransferState::Selected => (1, 0),
+            MouseTransferState::SendingData(index) => (2, index),
+        };
+        writer.write(&(tag, index));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<(u8, usize)>()? {
+            (0, _) => Ok(MouseTransferState::Idle),
+            (1, _) => Ok(MouseTransferState::Selected),
+            (2, index) => Ok(MouseTransferState::SendingData(index)),
+            _ => Err(SavestateError::Invalid("mouse transfer state out of range")),
+        }
+    }
+}
+
+impl Savestate for MouseDevice {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.pointer);
+        writer.write(&self.motion);
+        writer.write(&self.payload);
+        writer.write(&self.transfer_state);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.pointer = reader.read()?;
+        self.motion = reader.read()?;
+        self.payload = reader.read()?;
+        self.transfer_state = reader.read()?;
+        Ok(())
+    }
+}
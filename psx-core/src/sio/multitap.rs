@@ -0,0 +1,245 @@
+use super::sio0::{DeviceSlot, SioDevice};
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
+
+pub const MULTITAP_SLOT_COUNT: usize = 4;
+
+// PSX-SPX: "Multitap ID is 80h,5Ah", every slot answers with 8 bytes in multitap mode (padded with FFh)
+const MULTITAP_ID_LOW: u8 = 0x80;
+const MULTITAP_ID_HIGH: u8 = 0x5A;
+const MULTITAP_SLOT_BYTES: usize = 8;
+
+// Address byte, command byte and TAP byte come before the slot data
+const MULTITAP_DATA_START: usize = 3;
+const MULTITAP_DATA_END: usize = MULTITAP_DATA_START + MULTITAP_SLOT_COUNT * MULTITAP_SLOT_BYTES;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MultitapTransferState {
+    Idle,
+    Controller(usize),   // Forwarding to the pad in slot A, byte index of the transfer
+    MultitapRead(usize), // Reading all four pads, byte index of the transfer
+    MemoryCard(usize),   // Forwarding to the card in this slot
+    Finished,            // Transfer is over or nothing answered, ignore everything until /SEL goes high
+}
+
+/// SCPH-1070 multitap, four pads and four memory cards behind a single port
+pub struct Multitap {
+    slots: [DeviceSlot; MULTITAP_SLOT_COUNT],
+    transfer_state: MultitapTransferState,
+    command: u8,
+    multitap_mode: bool, // Set by the TAP byte of a pad read, applies from the next transfer on
+}
+
+impl Multitap {
+    /// Multitap with `slot_a` plugged into slot A and a pad in every other slot
+    pub fn new(slot_a: DeviceSlot) -> Self {
+        Self {
+            slots: [slot_a, DeviceSlot::new(), DeviceSlot::new(), DeviceSlot::new()],
+            transfer_state: MultitapTransferState::Idle,
+            command: 0,
+            multitap_mode: false,
+        }
+    }
+
+    /// Unplug the multitap, returns what was plugged into slot A
+    pub fn into_slot_a(self) -> DeviceSlot {
+        let [slot_a, ..] = self.slots;
+        slot_a
+    }
+
+    pub fn slot(&self, slot: usize) -> Option<&DeviceSlot> {
+        self.slots.get(slot)
+    }
+
+    pub fn slot_mut(&mut self, slot: usize) -> Option<&mut DeviceSlot> {
+        self.slots.get_mut(slot)
+    }
+
+    /// Forward one byte of a multitap mode read to the pad of the slot it belongs to
+    fn read_slot(&mut self, index: usize, tx_byte: u8) -> u8 {
+        let slot = (index - MULTITAP_DATA_START) / MULTITAP_SLOT_BYTES;
+        let controller = &mut self.slots[slot].controller;
+
+        // The multitap addresses the pad itself, the host only sends the command and parameter bytes
+        if (index - MULTITAP_DATA_START).is_multiple_of(MULTITAP_SLOT_BYTES) {
+            controller.reset();
+            controller.process_byte(0x01);
+            return controller.process_byte(tx_byte);
+        }
+
+        if controller.is_selected() {
+            controller.process_byte(tx_byte)
+        } else {
+            0xFF
+        }
+    }
+}
+
+impl SioDevice for Multitap {
+    fn process_byte(&mut self, tx_byte: u8) -> u8 {
+        tracing::trace!(target: "psx_core::multitap", tx = format!("{:02X}", tx_byte), state = ?self.transfer_state, "Processing byte");
+
+        match self.transfer_state {
+            MultitapTransferState::Idle => match tx_byte {
+                0x01 if self.multitap_mode => {
+                    self.transfer_state = MultitapTransferState::MultitapRead(1);
+                    tracing::debug!(target: "psx_core::multitap", "Multitap read selected");
+                    0xFF
+                }
+                0x01 => {
+                    let controller = &mut self.slots[0].controller;
+                    controller.reset();
+                    self.transfer_state = MultitapTransferState::Controller(1);
+                    controller.process_byte(tx_byte)
+                }
+                // PSX-SPX: memory cards in slot A..D are addressed with 81h..84h
+                0x81..=0x84 => {
+                    let slot = (tx_byte - 0x81) as usize;
+                    let Some(memory_card) = self.slots[slot].memory_card.as_mut() else {
+                        tracing::trace!(target: "psx_core::multitap", slot, "Memory card selected - no card inserted");
+                        self.transfer_state = MultitapTransferState::Finished;
+                        return 0xFF;
+                    };
+
+                    memory_card.reset();
+                    self.transfer_state = MultitapTransferState::MemoryCard(slot);
+                    memory_card.process_byte(0x81)
+                }
+                _ => {
+                    tracing::trace!(target: "psx_core::multitap", tx = format!("{:02X}", tx_byte), "Unknown address");
+                    self.transfer_state = MultitapTransferState::Finished;
+                    0xFF
+                }
+            },
+            MultitapTransferState::Controller(index) => {
+                // The TAP byte of a read command switches multitap mode on or off
+                match index {
+                    1 => self.command = tx_byte,
+                    2 if self.command == 0x42 => self.multitap_mode = tx_byte & 0x01 != 0,
+                    _ => {}
+                }
+
+                let controller = &mut self.slots[0].controller;
+                let rx_byte = controller.process_byte(tx_byte);
+
+                self.transfer_state = if controller.is_selected() {
+                    MultitapTransferState::Controller(index + 1)
+                } else {
+                    MultitapTransferState::Finished
+                };
+
+                rx_byte
+            }
+            MultitapTransferState::MultitapRead(index) => {
+                let rx_byte = match index {
+                    1 => {
+                        self.command = tx_byte;
+                        MULTITAP_ID_LOW
+                    }
+                    2 => {
+                        if self.command == 0x42 {
+                            self.multitap_mode = tx_byte & 0x01 != 0;
+                        }
+                        MULTITAP_ID_HIGH
+                    }
+                    _ => self.read_slot(index, tx_byte),
+                };
+
+                self.transfer_state = if index + 1 < MULTITAP_DATA_END {
+                    MultitapTransferState::MultitapRead(index + 1)
+                } else {
+                    MultitapTransferState::Finished
+                };
+
+                rx_byte
+            }
+            MultitapTransferState::MemoryCard(slot) => {
+                let memory_card = self.slots[slot].memory_card.as_mut().unwrap();
+                let rx_byte = memory_card.process_byte(tx_byte);
+
+                if !memory_card.is_selected() {
+                    self.transfer_state = MultitapTransferState::Finished;
+                }
+
+                rx_byte
+            }
+            MultitapTransferState::Finished => 0xFF,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.transfer_state = MultitapTransferState::Idle;
+        for slot in &mut self.slots {
+            slot.reset();
+        }
+    }
+
+    fn is_selected(&self) -> bool {
+        !matches!(
+            self.transfer_state,
+            MultitapTransferState::Idle | MultitapTransferState::Finished
+        )
+    }
+
+    fn deselect(&mut self) {
+        if self.transfer_state != MultitapTransferState::Idle {
+            tracing::debug!(target: "psx_core::multitap", "Multitap deselected");
+            self.reset();
+        }
+    }
+
+    fn device_id(&self) -> u8 {
+        0x01
+    }
+
+    fn ack_delay(&self) -> usize {
+        match self.transfer_state {
+            MultitapTransferState::MemoryCard(slot) => self.slots[slot].memory_card.as_ref().unwrap().ack_delay(),
+            _ => self.slots[0].controller.ack_delay(),
+        }
+    }
+}
+
+impl State for MultitapTransferState {
+    fn write(&self, writer: &mut StateWriter) {
+        let (tag, index) = match *self {
+            MultitapTransferState::Idle => (0u8, 0),
+            MultitapTransferState::Controller(index) => (1, index),
+            MultitapTransferState::MultitapRead(index) => (2, index),
+            MultitapTransferState::MemoryCard(slot) => (3, slot),
+            MultitapTransferState::Finished => (4, 0),
+        };
+        writer.write(&(tag, index));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<(u8, usize)>()? {
+            (0, _) => Ok(MultitapTransferState::Idle),
+            (1, index) => Ok(MultitapTransferState::Controller(index)),
+            (2, index) => Ok(MultitapTransferState::MultitapRead(index)),
+            (3, slot) if slot < MULTITAP_SLOT_COUNT => Ok(MultitapTransferState::MemoryCard(slot)),
+            (4, _) => Ok(MultitapTransferState::Finished),
+            _ => Err(SavestateError::Invalid("multitap transfer state out of range")),
+        }
+    }
+}
+
+impl Savestate for Multitap {
+    fn save_state(&self, writer: &mut StateWriter) {
+        for slot in &self.slots {
+            slot.save_state(writer);
+        }
+        writer.write(&self.transfer_state);
+        writer.write(&self.command);
+        writer.write(&self.multitap_mode);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        for slot in &mut self.slots {
+            slot.load_state(reader)?;
+        }
+        self.transfer_state = reader.read()?;
+        self.command = reader.read()?;
+        self.multitap_mode = reader.read()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use super::joy::{CONTROLLER_ACK_DELAY, Controller, ControllerState, ControllerType};
+use super::sio0::SioDevice;
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
+
+const NEGCON_ID_LOW: u8 = 0x23;
+const NEGCON_ID_HIGH: u8 = 0x5A;
+
+// PSX-SPX: only Start, the D-Pad, R, B and A are digital, every other button bit reads as 1
+const NEGCON_BUTTONS_UNUSED_1: u8 = 0x07;
+const NEGCON_BUTTONS_UNUSED_2: u8 = 0xC7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NegconTransferState {
+    Idle,
+    Selected,
+    SendingData(usize), // Index of the next byte after the command byte, 0 is the ID high byte
+}
+
+/// Namco NeGcon, the twist of the left stick steers and Cross, Square and L1 are the analog I, II and L buttons
+///
+/// Circle is A, Triangle is B and R1 is R
+pub struct NegconDevice {
+    state: ControllerState,
+    payload: [u8; 6],
+    transfer_state: NegconTransferState,
+}
+
+impl NegconDevice {
+    pub fn new() -> Self {
+        Self {
+            state: ControllerState::default(),
+            payload: [0xFF; 6],
+            transfer_state: NegconTransferState::Idle,
+        }
+    }
+
+    /// PSX-SPX: buttons, then Twist (00h = left, 80h = center, FFh = right), I, II and L (00h = released)
+    fn build_payload(&self) -> [u8; 6] {
+        let (byte1, byte2) = self.state.to_button_bytes();
+        let analog = |pressed: bool| if pressed { 0xFF } else { 0x00 };
+
+        [
+            byte1 | NEGCON_BUTTONS_UNUSED_1,
+            byte2 | NEGCON_BUTTONS_UNUSED_2,
+            self.state.left_x,
+            analog(self.state.cross),
+            analog(self.state.square),
+            analog(self.state.l1),
+        ]
+    }
+}
+
+impl Default for NegconDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Controller for NegconDevice {
+    fn controller_type(&self) -> ControllerType {
+        ControllerType::Negcon
+    }
+
+    fn set_state(&mut self, state: ControllerState) {
+        self.state = state;
+    }
+}
+
+impl SioDevice for NegconDevice {
+    fn process_byte(&mut self, tx_byte: u8) -> u8 {
+        tracing::trace!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Processing byte");
+
+        match self.transfer_state {
+            NegconTransferState::Idle => {
+                if tx_byte != 0x01 {
+                    tracing::warn!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Unexpected byte while NeGcon is idle");
+                    return 0xFF;
+                }
+
+                self.transfer_state = NegconTransferState::Selected;
+                tracing::debug!(target: "psx_core::joy", "NeGcon selected");
+
+                0xFF
+            }
+            NegconTransferState::Selected => {
+                if tx_byte != 0x42 {
+                    tracing::error!(target: "psx_core::joy", tx = format!("{:02X}", tx_byte), "Unknown command while NeGcon selected");
+                    self.transfer_state = NegconTransferState::Idle;
+                    return 0xFF;
+                }
+
+                self.payload = self.build_payload();
+                self.transfer_state = NegconTransferState::SendingData(0);
+                NEGCON_ID_LOW
+            }
+            NegconTransferState::SendingData(index) => {
+                let rx_byte = match index {
+                    0 => NEGCON_ID_HIGH,
+                    _ => self.payload[index - 1],
+                };
+
+                if index == self.payload.len() {
+                    // Last byte - transfer complete
+                    self.transfer_state = NegconTransferState::Idle;
+                } else {
+                    self.transfer_state = NegconTransferState::SendingData(index + 1);
+                }
+
+                rx_byte
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.transfer_state = NegconTransferState::Idle;
+    }
+
+    fn is_selected(&self) -> bool {
+        self.transfer_state != NegconTransferState::Idle
+    }
+
+    fn deselect(&mut self) {
+        if self.transfer_state != NegconTransferState::Idle {
+            tracing::debug!(target: "psx_core::joy", "NeGcon deselected");
+            self.transfer_state = NegconTransferState::Idle;
+        }
+    }
+
+    fn device_id(&self) -> u8 {
+        0x01
+    }
+
+    fn ack_delay(&self) -> usize {
+        CONTROLLER_ACK_DELAY
+    }
+}
+
+impl State for NegconTransferState {
+    fn write(&self, writer: &mut StateWriter) {
+        let (tag, index) = match *self {
+            NegconTransferState::Idle => (0u8, 0),
+            NegconTransferState::Selected => (1, 0),
+            NegconTransferState::SendingData(index) => (2, index),
+        };
+        writer.write(&(tag, index));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<(u8, usize)>()? {
+            (0, _) => Ok(NegconTransferState::Idle),
+            (1, _) => Ok(NegconTransferState::Selected),
+            (2, index) => Ok(NegconTransferState::SendingData(index)),
+            _ => Err(SavestateError::Invalid("NeGcon transfer state out of range")),
+        }
+    }
+}
+
+impl Savestate for NegconDevice {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.state);
+        writer.write(&self.payload);
+        writer.write(&self.transfer_state);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.state = reader.read()?;
+        self.payload = reader.read()?;
+        self.transfer_state = reader.read()?;
+        Ok(())
+    }
+}
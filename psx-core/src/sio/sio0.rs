@@ -1,8 +1,11 @@
-use super::joy::{ControllerDevice, ControllerState};
-use super::memcard::MemoryCardDevice;
+use super::joy::{Controller, ControllerState, ControllerType, PointerState, RumbleCallback};
+use super::memcard::{MemoryCard, MemoryCardDevice};
+use super::multitap::{MULTITAP_SLOT_COUNT, Multitap};
+use crate::gpu::status::VideoMode;
 use crate::mmu::bus::{Bus8, Bus16, Bus32};
 use crate::sio::{SerialControl, SerialMode, SerialStatus};
 use std::collections::VecDeque;
+use crate::savestate::{Savestate, SavestateError, State, StateReader, StateWriter};
 
 pub trait SioDevice {
     fn process_byte(&mut self, tx_byte: u8) -> u8;
@@ -10,6 +13,12 @@ pub trait SioDevice {
     fn is_selected(&self) -> bool;
     fn deselect(&mut self);
     fn device_id(&self) -> u8;
+    fn ack_delay(&self) -> usize;
+
+    /// Devices pull /ACK low after every byte except the last one of a transfer
+    fn ack(&self) -> bool {
+        self.is_selected()
+    }
 }
 
 crate::define_addr!(SIO0_TX_DATA_ADDR, 0x1F80_1040, 0, 4, 0x10);
@@ -19,11 +28,66 @@ crate::define_addr!(SIO0_MODE_ADDR, 0x1F80_1048, 0, 2, 0x10);
 crate::define_addr!(SIO0_CTRL_ADDR, 0x1F80_104A, 0, 2, 0x10);
 crate::define_addr!(SIO0_BAUD_ADDR, 0x1F80_104E, 0, 2, 0x10);
 
+pub const SIO0_PORT_COUNT: usize = 2;
+
+/// A pad and a memory card slot, either of a port or of a multitap slot
+pub struct DeviceSlot {
+    pub controller: Box<dyn Controller>,
+    pub memory_card: Option<MemoryCardDevice>,
+}
+
+impl DeviceSlot {
+    pub fn new() -> Self {
+        Self {
+            controller: ControllerType::Digital.create(),
+            memory_card: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.controller.reset();
+        if let Some(memory_card) = &mut self.memory_card {
+            memory_card.reset();
+        }
+    }
+}
+
+impl Default for DeviceSlot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What is plugged into one of the two ports
+enum Port {
+    Direct(DeviceSlot),
+    Multitap(Box<Multitap>),
+}
+
+impl Port {
+    fn slot_mut(&mut self, slot: usize) -> Option<&mut DeviceSlot> {
+        match self {
+            Port::Direct(device_slot) if slot == 0 => Some(device_slot),
+            Port::Direct(_) => None,
+            Port::Multitap(multitap) => multitap.slot_mut(slot),
+        }
+    }
+
+    fn slot(&self, slot: usize) -> Option<&DeviceSlot> {
+        match self {
+            Port::Direct(device_slot) if slot == 0 => Some(device_slot),
+            Port::Direct(_) => None,
+            Port::Multitap(multitap) => multitap.slot(slot),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ActiveDevice {
     None,
-    Controller,
-    MemoryCard,
+    Controller(usize), // Port of the selected device
+    MemoryCard(usize),
+    Multitap(usize),
 }
 
 pub struct Sio0 {
@@ -35,10 +99,10 @@ pub struct Sio0 {
     cycles: usize,
     target_cycles: usize,       // IRQ delay in cycles (baud * 8)
     irq_trigger_counter: usize, // Number of pending IRQs (0 = idle)
+    ack_pending: bool,          // The device acknowledged the byte in flight
 
     // Devices
-    controller: ControllerDevice,
-    memory_card: MemoryCardDevice,
+    ports: [Port; SIO0_PORT_COUNT],
     active_device: ActiveDevice,
 }
 
@@ -53,8 +117,8 @@ impl Default for Sio0 {
             cycles: 0,
             target_cycles: 0,       // Will be set to baud*8 when TX is written
             irq_trigger_counter: 0, // 0 = idle
-            controller: ControllerDevice::new(),
-            memory_card: MemoryCardDevice::new(),
+            ack_pending: false,
+            ports: [Port::Direct(DeviceSlot::new()), Port::Direct(DeviceSlot::new())],
             active_device: ActiveDevice::None,
         }
     }
@@ -65,8 +129,95 @@ impl Sio0 {
         Self::default()
     }
 
-    pub fn set_controller_state(&mut self, state: ControllerState) {
-        self.controller.set_state(state);
+    /// Plug a multitap into `port` or unplug it, the devices of slot A stay connected to the port
+    pub fn connect_multitap(&mut self, port: usize, connected: bool) {
+        let previous = std::mem::replace(&mut self.ports[port], Port::Direct(DeviceSlot::new()));
+        self.ports[port] = match (previous, connected) {
+            (Port::Direct(slot_a), true) => Port::Multitap(Box::new(Multitap::new(slot_a))),
+            (Port::Multitap(multitap), false) => Port::Direct(multitap.into_slot_a()),
+            (previous, _) => previous,
+        };
+
+        self.reset_devices();
+        tracing::debug!(target: "psx_core::sio", port, connected, "Multitap");
+    }
+
+    pub fn has_multitap(&self, port: usize) -> bool {
+        matches!(self.ports[port], Port::Multitap(_))
+    }
+
+    /// Update the pad in `slot` of `port`, only multitaps have slots other than 0
+    pub fn set_controller_state(&mut self, port: usize, slot: usize, state: ControllerState) {
+        match self.ports[port].slot_mut(slot) {
+            Some(device_slot) => device_slot.controller.set_state(state),
+            None => tracing::warn!(target: "psx_core::sio", port, slot, "No controller slot"),
+        }
+    }
+
+    /// Replace the pad in `slot` of `port` with a new controller of type `controller_type`
+    pub fn connect_controller(&mut self, port: usize, slot: usize, controller_type: ControllerType) {
+        match self.ports[port].slot_mut(slot) {
+            Some(device_slot) => device_slot.controller = controller_type.create(),
+            None => tracing::warn!(target: "psx_core::sio", port, slot, "No controller slot"),
+        }
+
+        self.reset_devices();
+        tracing::debug!(target: "psx_core::sio", port, slot, ?controller_type, "Controller connected");
+    }
+
+    /// Feed host mouse input to the pointer device in `slot` of `port`
+    pub fn set_pointer_state(&mut self, port: usize, slot: usize, state: PointerState) {
+        match self.ports[port].slot_mut(slot) {
+            Some(device_slot) => device_slot.controller.set_pointer_state(state),
+            None => tracing::warn!(target: "psx_core::sio", port, slot, "No controller slot"),
+        }
+    }
+
+    /// Let every controller know a new frame has been scanned out, memory cards save their image once idle
+    pub fn vblank(&mut self, video_mode: VideoMode) {
+        for port in &mut self.ports {
+            for slot in 0..MULTITAP_SLOT_COUNT {
+                if let Some(device_slot) = port.slot_mut(slot) {
+                    device_slot.controller.vblank(video_mode);
+
+                    if let Some(memory_card) = &mut device_slot.memory_card
+                        && let Err(e) = memory_card.vblank()
+                    {
+                        tracing::error!(target: "psx_core::mc", error = %e, "Failed to flush memory card image");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Where the first light gun that sees the beam wants the lightpen IRQ
+    pub fn lightpen_target(&self) -> Option<(u16, u64)> {
+        self.ports
+            .iter()
+            .flat_map(|port| (0..MULTITAP_SLOT_COUNT).filter_map(move |slot| port.slot(slot)))
+            .find_map(|device_slot| device_slot.controller.lightpen_target())
+    }
+
+    pub fn set_rumble_callback(&mut self, port: usize, slot: usize, callback: RumbleCallback) {
+        match self.ports[port].slot_mut(slot) {
+            Some(device_slot) => device_slot.controller.set_rumble_callback(callback),
+            None => tracing::warn!(target: "psx_core::sio", port, slot, "No controller slot"),
+        }
+    }
+
+    pub fn insert_memory_card(&mut self, port: usize, slot: usize, card: MemoryCard) {
+        match self.ports[port].slot_mut(slot) {
+            Some(device_slot) => device_slot.memory_card = Some(MemoryCardDevice::new(card)),
+            None => tracing::warn!(target: "psx_core::sio", port, slot, "No memory card slot"),
+        }
+    }
+
+    pub fn memory_card(&self, port: usize, slot: usize) -> Option<&MemoryCard> {
+        self.ports[port]
+            .slot(slot)?
+            .memory_card
+            .as_ref()
+            .map(|device| device.card())
     }
 
     pub fn tick(&mut self, cycles: usize) {
@@ -84,11 +235,15 @@ impl Sio0 {
 
             self.irq_trigger_counter -= 1;
         }
+    }
 
-        // Check if devices were deselected (DTR went low)
-        if !self.control.dtr_output_level() {
-            self.reset_devices();
+    /// CPU cycles until the next pending transfer IRQ fires
+    pub fn cycles_until_irq(&self) -> Option<u64> {
+        if self.irq_trigger_counter == 0 {
+            return None;
         }
+
+        Some(self.target_cycles.saturating_sub(self.cycles) as u64)
     }
 
     pub fn should_trigger_irq(&self) -> bool {
@@ -96,47 +251,65 @@ impl Sio0 {
     }
 
     fn route_byte_to_device(&mut self, tx_byte: u8) -> u8 {
-        // If no device is active and DTR is asserted, check for device selection
+        // If no device is active and DTR is asserted, check for device selection on the selected port
         if self.active_device == ActiveDevice::None && self.control.dtr_output_level() {
-            match tx_byte {
-                0x01 => {
-                    if self.control.port_number() == 2 {
-                        // If Port 2 is selected, no controller is present
+            let port = self.control.port_number() as usize - 1;
+
+            match &self.ports[port] {
+                // The multitap answers to every address and forwards to its slots
+                Port::Multitap(_) => self.active_device = ActiveDevice::Multitap(port),
+                Port::Direct(_) if tx_byte == 0x01 => self.active_device = ActiveDevice::Controller(port),
+                Port::Direct(device_slot) if tx_byte == 0x81 => {
+                    if device_slot.memory_card.is_none() {
                         tracing::trace!(
                             target: "psx_core::sio",
-                            tx = format!("{:02X}", tx_byte),
-                            "Port 2 selected - no device"
+                            port,
+                            "Memory card selected - no card inserted"
                         );
                         return 0xFF;
                     }
 
-                    self.active_device = ActiveDevice::Controller;
+                    self.active_device = ActiveDevice::MemoryCard(port);
                 }
-                0x81 => {
-                    self.active_device = ActiveDevice::MemoryCard;
-                }
-                _ => {}
+                Port::Direct(_) => {}
             }
         }
 
         // Route to active device
-        match self.active_device {
-            ActiveDevice::Controller => self.controller.process_byte(tx_byte),
-            ActiveDevice::MemoryCard => {
-                // Memory card is stubbed - always return 0xFF (no device present)
-                tracing::trace!(target: "psx_core::sio", tx = format!("{:02X}", tx_byte), "Memory card stubbed");
-                0xFF
+        match self.active_device_mut() {
+            Some(device) => {
+                let rx_byte = device.process_byte(tx_byte);
+                self.ack_pending = device.ack();
+                rx_byte
             }
-            ActiveDevice::None => {
+            None => {
                 tracing::trace!(target: "psx_core::sio", tx = format!("{:02X}", tx_byte), "No device selected");
                 0xFF
             }
         }
     }
 
+    fn active_device_mut(&mut self) -> Option<&mut dyn SioDevice> {
+        match self.active_device {
+            ActiveDevice::Controller(port) => Some(self.ports[port].slot_mut(0)?.controller.as_mut()),
+            ActiveDevice::MemoryCard(port) => Some(self.ports[port].slot_mut(0)?.memory_card.as_mut()?),
+            ActiveDevice::Multitap(port) => match &mut self.ports[port] {
+                Port::Multitap(multitap) => Some(multitap.as_mut()),
+                Port::Direct(_) => None,
+            },
+            ActiveDevice::None => None,
+        }
+    }
+
+    /// Cycles between the end of the byte transfer and the /ACK pulse of the active device
+    fn ack_delay(&mut self) -> usize {
+        self.active_device_mut().map_or(0, |device| device.ack_delay())
+    }
+
     fn trigger_irq(&mut self) {
-        // Only send IRQs for controller, NOT for memory card (stubbed)
-        if self.active_device != ActiveDevice::MemoryCard {
+        // Only devices that acknowledged the byte raise an IRQ, missing /ACK is how the BIOS detects
+        // the end of a transfer or an empty slot
+        if std::mem::take(&mut self.ack_pending) {
             self.status.set_ack_input_level(true);
 
             // Trigger interrupt if enabled
@@ -154,9 +327,47 @@ impl Sio0 {
         }
     }
 
+    /// What is plugged in where, a state can only be loaded into the same setup
+    pub(crate) fn device_layout(&self) -> Vec<u8> {
+        let mut layout = Vec::new();
+        for port in &self.ports {
+            layout.push(matches!(port, Port::Multitap(_)) as u8);
+            for device_slot in (0..MULTITAP_SLOT_COUNT).filter_map(|slot| port.slot(slot)) {
+                layout.push(device_slot.controller.controller_type() as u8);
+                layout.push(device_slot.memory_card.is_some() as u8);
+            }
+        }
+
+        layout
+    }
+
+    /// Checksum of the card in every slot, in the order of `device_layout`
+    pub(crate) fn memory_card_checksums(&self) -> Vec<Option<u64>> {
+        self.ports
+            .iter()
+            .flat_map(|port| (0..MULTITAP_SLOT_COUNT).filter_map(|slot| port.slot(slot)))
+            .map(|device_slot| {
+                let device = device_slot.memory_card.as_ref()?;
+                Some(crate::savestate::checksum(device.card().data()))
+            })
+            .collect()
+    }
+
+    /// Whether both ports have a pad without a multitap, all a movie can record
+    pub(crate) fn has_only_direct_pads(&self) -> bool {
+        self.ports.iter().all(|port| match port {
+            Port::Direct(device_slot) => !device_slot.controller.controller_type().uses_pointer(),
+            Port::Multitap(_) => false,
+        })
+    }
+
     fn reset_devices(&mut self) {
-        self.controller.reset();
-        self.memory_card.reset();
+        for port in &mut self.ports {
+            match port {
+                Port::Direct(device_slot) => device_slot.reset(),
+                Port::Multitap(multitap) => multitap.reset(),
+            }
+        }
         self.active_device = ActiveDevice::None;
     }
 }
@@ -221,6 +432,7 @@ impl Bus16 for Sio0 {
                 let tx_byte = value as u8;
 
                 // Immediately process TX and get RX response
+                self.ack_pending = false;
                 let rx_byte = self.route_byte_to_device(tx_byte);
 
                 // Put response in RX FIFO immediately
@@ -231,9 +443,12 @@ impl Bus16 for Sio0 {
                 self.status.set_tx_ready_1(false);
                 self.status.set_tx_ready_2(false);
 
-                // Start IRQ countdown (baud * 8 cycles)
+                // Start IRQ countdown (baud * 8 cycles, plus the /ACK delay of the device)
                 self.cycles = 0;
                 self.target_cycles = self.baud as usize * 8;
+                if self.ack_pending {
+                    self.target_cycles += self.ack_delay();
+                }
                 self.irq_trigger_counter += 1;
 
                 tracing::trace!(
@@ -257,24 +472,26 @@ impl Bus16 for Sio0 {
                 // Handle reset bit
                 if value & 0x40 != 0 {
                     self.rx_fifo.clear();
-                    self.controller.reset();
-                    self.memory_card.reset();
-                    self.active_device = ActiveDevice::None;
+                    self.reset_devices();
                     self.cycles = 0;
                     self.irq_trigger_counter = 0; // Cancel any pending IRQs
+                    self.ack_pending = false;
                     self.status.set_rx_fifo_not_empty(false);
                     self.status.set_tx_ready_1(true);
                     self.status.set_tx_ready_2(true);
                     tracing::debug!(target: "psx_core::sio", "Reset");
                 }
 
+                let previous_port = self.control.port_number();
                 self.control.0 = value & !0x40; // Reset bit is write-only
 
-                // If port select changed to port 2, deselect any active device, but only if current device
-                // is *NOT* memory card
-                if matches!(self.active_device, ActiveDevice::None | ActiveDevice::Controller)
-                    && self.control.port_number() == 2
-                {
+                // /SEL of the previous port goes high when the other port is selected
+                if self.control.port_number() != previous_port {
+                    self.reset_devices();
+                }
+
+                // Devices are deselected as soon as DTR goes low
+                if !self.control.dtr_output_level() {
                     self.reset_devices();
                 }
 
@@ -316,3 +533,86 @@ impl Bus32 for Sio0 {
         unreachable!();
     }
 }
+
+impl Savestate for DeviceSlot {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.controller.save_state(writer);
+        if let Some(memory_card) = &self.memory_card {
+            memory_card.save_state(writer);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.controller.load_state(reader)?;
+        if let Some(memory_card) = &mut self.memory_card {
+            memory_card.load_state(reader)?;
+        }
+        Ok(())
+    }
+}
+
+impl State for ActiveDevice {
+    fn write(&self, writer: &mut StateWriter) {
+        let (tag, port) = match *self {
+            ActiveDevice::None => (0u8, 0),
+            ActiveDevice::Controller(port) => (1, port),
+            ActiveDevice::MemoryCard(port) => (2, port),
+            ActiveDevice::Multitap(port) => (3, port),
+        };
+        writer.write(&(tag, port));
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, SavestateError> {
+        match reader.read::<(u8, usize)>()? {
+            (0, _) => Ok(ActiveDevice::None),
+            (1, port) if port < SIO0_PORT_COUNT => Ok(ActiveDevice::Controller(port)),
+            (2, port) if port < SIO0_PORT_COUNT => Ok(ActiveDevice::MemoryCard(port)),
+            (3, port) if port < SIO0_PORT_COUNT => Ok(ActiveDevice::Multitap(port)),
+            _ => Err(SavestateError::Invalid("active SIO0 device out of range")),
+        }
+    }
+}
+
+/// Devices are loaded in place, the layout has to match, see `device_layout`
+impl Savestate for Sio0 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.control.0);
+        writer.write(&self.status.0);
+        writer.write(&self.mode.0);
+        writer.write(&self.baud);
+        writer.write(&self.rx_fifo);
+        writer.write(&self.cycles);
+        writer.write(&self.target_cycles);
+        writer.write(&self.irq_trigger_counter);
+        writer.write(&self.ack_pending);
+
+        for port in &self.ports {
+            match port {
+                Port::Direct(device_slot) => device_slot.save_state(writer),
+                Port::Multitap(multitap) => multitap.save_state(writer),
+            }
+        }
+        writer.write(&self.active_device);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.control.0 = reader.read()?;
+        self.status.0 = reader.read()?;
+        self.mode.0 = reader.read()?;
+        self.baud = reader.read()?;
+        self.rx_fifo = reader.read()?;
+        self.cycles = reader.read()?;
+        self.target_cycles = reader.read()?;
+        self.irq_trigger_counter = reader.read()?;
+        self.ack_pending = reader.read()?;
+
+        for port in &mut self.ports {
+            match port {
+                Port::Direct(device_slot) => device_slot.load_state(reader)?,
+                Port::Multitap(multitap) => multitap.load_state(reader)?,
+            }
+        }
+        self.active_device = reader.read()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,532 @@
+use super::link::LinkAddress;
+use crate::exe::{self, Exe};
+use crate::mmu::RamSize;
+use crate::psx::Psx;
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+// Protocol V2 acknowledges every chunk with a checksum
+const V2_CHUNK_SIZE: usize = 2048;
+
+/// Nothing larger than the RAM of a dev kit can be uploaded or dumped
+const MAX_TRANSFER_SIZE: usize = RamSize::DevKit.bytes() as usize;
+
+enum Request {
+    LoadExe(Vec<u8>),
+    WriteMemory {
+        address: u32,
+        data: Vec<u8>,
+    },
+    ReadMemory {
+        address: u32,
+        length: u32,
+        reply: Sender<Vec<u8>>,
+    },
+    Jump(u32),
+    Halt,
+    Continue,
+}
+
+/// Answers `nops` the way Unirom does over SIO1, from the host side
+///
+/// On hardware the protocol is handled by Unirom running on the console, here nothing has to run in the
+/// emulated machine. Supported are SEXE, SBIN, JUMP, DUMP (peek), DEBG, HALT and CONT, pokes go through SBIN.
+/// Peeks and pokes reach RAM and the scratchpad directly, peeks the BIOS too, so no device register is touched
+/// and anything else reads as zero. Tools that only open serial devices can be pointed at a pty bridged to the
+/// socket, e.g. with `socat pty,link=/tmp/ttyPSX,raw tcp:localhost:6699`
+pub struct UniromServer {
+    requests: Receiver<Request>,
+    halted: bool,
+}
+
+impl UniromServer {
+    /// Accept `nops` connections on `address` in the background, one at a time
+    pub fn listen(address: &LinkAddress) -> io::Result<Self> {
+        let (sender, requests) = mpsc::channel();
+
+        match address {
+            LinkAddress::Tcp(address) => {
+                let listener = TcpListener::bind(address)?;
+                tracing::info!(target: "psx_core::unirom", address, "Listening for nops");
+                spawn_server(
+                    move || {
+                        let (stream, _) = listener.accept()?;
+                        stream.set_nodelay(true)?;
+                        Ok(stream)
+                    },
+                    sender,
+                )?;
+            }
+            #[cfg(unix)]
+            LinkAddress::Unix(path) => {
+                let _ = std::fs::remove_file(path);
+                let listener = UnixListener::bind(path)?;
+                tracing::info!(target: "psx_core::unirom", path = %path.display(), "Listening for nops");
+                spawn_server(move || Ok(listener.accept()?.0), sender)?;
+            }
+        }
+
+        Ok(Self {
+            requests,
+            halted: false,
+        })
+    }
+
+    /// Whether the debugger halted the CPU, the frontend stops stepping until it continues
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Apply every command received since the last call, to be called between steps or frames
+    pub fn service(&mut self, psx: &mut Psx) {
+        while let Ok(request) = self.requests.try_recv() {
+            match request {
+                Request::LoadExe(buffer) => match Exe::parse(buffer) {
+                    Ok(exe) => {
+                        psx.load_exe(&exe);
+                        tracing::info!(
+                            target: "psx_core::unirom",
+                            entrypoint = format!("{:08X}", exe.entry_point),
+                            "Uploaded EXE"
+                        );
+                    }
+                    Err(e) => tracing::error!(target: "psx_core::unirom", error = %e, "Rejected uploaded EXE"),
+                },
+                Request::WriteMemory { address, data } => {
+                    let written = (address..)
+                        .zip(&data)
+                        .filter(|&(address, &byte)| psx.cpu.mmu.poke(address, byte))
+                        .count();
+
+                    if written < data.len() {
+                        tracing::warn!(
+                            target: "psx_core::unirom",
+                            skipped = data.len() - written,
+                            "Upload reached past RAM and the scratchpad, skipped those bytes"
+                        );
+                    }
+
+                    tracing::info!(
+                        target: "psx_core::unirom",
+                        address = format!("{:08X}", address),
+                        length = data.len(),
+                        "Uploaded binary"
+                    );
+                }
+                Request::ReadMemory { address, length, reply } => {
+                    let data = (0..length)
+                        .map(|offset| psx.cpu.mmu.peek(address.wrapping_add(offset)).unwrap_or(0))
+                        .collect();
+                    let _ = reply.send(data);
+                }
+                Request::Jump(address) => {
+                    psx.jump(address);
+                    tracing::info!(target: "psx_core::unirom", address = format!("{:08X}", address), "Jump");
+                }
+                Request::Halt => {
+                    self.halted = true;
+                    tracing::info!(target: "psx_core::unirom", pc = format!("{:08X}", psx.cpu.pc), "Halted");
+                }
+                Request::Continue => {
+                    self.halted = false;
+                    tracing::info!(target: "psx_core::unirom", "Continued");
+                }
+            }
+        }
+    }
+}
+
+fn spawn_server<S: Read + Write>(
+    mut accept: impl FnMut() -> io::Result<S> + Send + 'static, requests: Sender<Request>,
+) -> io::Result<()> {
+    std::thread::Builder::new().name("unirom".to_string()).spawn(move || {
+        loop {
+            let stream = match accept() {
+                Ok(stream) => stream,
+                Err(error) => {
+                    tracing::error!(target: "psx_core::unirom", %error, "Failed to accept connection");
+                    return;
+                }
+            };
+
+            tracing::debug!(target: "psx_core::unirom", "nops connected");
+
+            match Session::new(stream, &requests).run() {
+                // The emulator is gone, nobody is left to apply the commands
+                Err(SessionError::Closed) => return,
+                Err(SessionError::Io(error)) => {
+                    tracing::debug!(target: "psx_core::unirom", %error, "nops disconnected")
+                }
+                Err(SessionError::TooLarge(length)) => {
+                    tracing::warn!(target: "psx_core::unirom", length, "Transfer larger than RAM, dropping nops")
+                }
+                Ok(()) => {}
+            }
+        }
+    })?;
+
+    Ok(())
+}
+
+#[derive(Debug)]
+enum SessionError {
+    Io(io::Error),
+    Closed,
+    TooLarge(usize),
+}
+
+impl From<io::Error> for SessionError {
+    fn from(error: io::Error) -> Self {
+        SessionError::Io(error)
+    }
+}
+
+/// One `nops` connection, commands are 4 ASCII characters matched against the last 4 bytes received
+struct Session<'a, S> {
+    stream: S,
+    requests: &'a Sender<Request>,
+    protocol_v2: bool,
+}
+
+impl<'a, S: Read + Write> Session<'a, S> {
+    fn new(stream: S, requests: &'a Sender<Request>) -> Self {
+        Self {
+            stream,
+            requests,
+            protocol_v2: false,
+        }
+    }
+
+    fn run(&mut self) -> Result<(), SessionError> {
+        let mut window = [0u8; 4];
+
+        loop {
+            let mut byte = [0u8; 1];
+            if self.stream.read(&mut byte)? == 0 {
+                return Ok(());
+            }
+
+            window.rotate_left(1);
+            window[3] = byte[0];
+
+            if self.command(&window)? {
+                window = [0; 4];
+            }
+        }
+    }
+
+    /// Returns whether `window` held a command
+    fn command(&mut self, window: &[u8; 4]) -> Result<bool, SessionError> {
+        match window {
+            b"UPV2" => {
+                self.protocol_v2 = true;
+                self.stream.write_all(b"OKV2")?;
+            }
+            b"SEXE" => {
+                self.stream.write_all(b"OKAY")?;
+
+                let mut buffer = self.read_exact_vec(exe::HEADER_SIZE)?;
+                let checksum = self.read_u32()?;
+
+                let text_size = &buffer[exe::TEXT_SIZE_OFFSET..exe::TEXT_SIZE_OFFSET + 4];
+                let text_size = u32::from_le_bytes(text_size.try_into().unwrap()) as usize;
+                let text = self.read_payload(text_size, checksum)?;
+                buffer.extend_from_slice(&text);
+
+                self.send(Request::LoadExe(buffer))?;
+            }
+            b"SBIN" => {
+                self.stream.write_all(b"OKAY")?;
+
+                let address = self.read_u32()?;
+                let length = self.read_u32()? as usize;
+                let checksum = self.read_u32()?;
+                let data = self.read_payload(length, checksum)?;
+
+                self.send(Request::WriteMemory { address, data })?;
+            }
+            b"JUMP" => {
+                self.stream.write_all(b"OKAY")?;
+                let address = self.read_u32()?;
+                self.send(Request::Jump(address))?;
+            }
+            b"DUMP" => {
+                self.stream.write_all(b"OKAY")?;
+
+                let address = self.read_u32()?;
+                let length = self.read_u32()?;
+                if length as usize > MAX_TRANSFER_SIZE {
+                    return Err(SessionError::TooLarge(length as usize));
+                }
+
+                let (reply, data) = mpsc::channel();
+                self.send(Request::ReadMemory { address, length, reply })?;
+
+                let data = data.recv().map_err(|_| SessionError::Closed)?;
+                self.stream.write_all(&data)?;
+            }
+            b"DEBG" => {
+                // The debugger is always there, installing it is a no-op
+                self.stream.write_all(b"OKAY")?;
+            }
+            b"HALT" => {
+                self.send(Request::Halt)?;
+                self.stream.write_all(b"HLTD")?;
+            }
+            b"CONT" => {
+                self.send(Request::Continue)?;
+                self.stream.write_all(b"DONE")?;
+            }
+            _ => return Ok(false),
+        }
+
+        tracing::debug!(target: "psx_core::unirom", command = %String::from_utf8_lossy(window), "Command");
+        Ok(true)
+    }
+
+    fn send(&self, request: Request) -> Result<(), SessionError> {
+        self.requests.send(request).map_err(|_| SessionError::Closed)
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0u8; 4];
+        self.stream.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_exact_vec(&mut self, length: usize) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0u8; length];
+        self.stream.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Upload body, V1 sends it in one go while V2 has the host confirm a checksum after every chunk
+    fn read_payload(&mut self, length: usize, checksum: u32) -> Result<Vec<u8>, SessionError> {
+        // The length comes straight from the host, so it is checked before anything is allocated for it
+        if length > MAX_TRANSFER_SIZE {
+            return Err(SessionError::TooLarge(length));
+        }
+
+        let data = if self.protocol_v2 {
+            let mut data = Vec::with_capacity(length);
+
+            while data.len() < length {
+                let chunk = self.read_exact_vec(V2_CHUNK_SIZE.min(length - data.len()))?;
+
+                self.stream.write_all(b"CHEK")?;
+                if self.read_u32()? == sum(&chunk) {
+                    self.stream.write_all(b"MORE")?;
+                    data.extend_from_slice(&chunk);
+                } else {
+                    self.stream.write_all(b"ERR!")?;
+                }
+            }
+
+            data
+        } else {
+            self.read_exact_vec(length)?
+        };
+
+        if sum(&data) != checksum {
+            tracing::warn!(
+                target: "psx_core::unirom",
+                expected = format!("{:08X}", checksum),
+                actual = format!("{:08X}", sum(&data)),
+                "Upload checksum mismatch"
+            );
+        }
+
+        Ok(data)
+    }
+}
+
+fn sum(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |sum, &byte| sum.wrapping_add(byte as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// What nops sends, followed by what the session answers
+    struct Stream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Stream {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buffer)
+        }
+    }
+
+    impl Write for Stream {
+        fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+            self.output.write(buffer)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run a session over `input`, answering peeks with the address bytes, and collect its requests
+    fn session(input: &[u8]) -> (Result<(), SessionError>, Vec<u8>, Vec<Request>) {
+        let (sender, receiver) = mpsc::channel();
+        let mut stream = Stream {
+            input: Cursor::new(input.to_vec()),
+            output: Vec::new(),
+        };
+
+        let result = std::thread::scope(|scope| {
+            let emulator = scope.spawn(move || {
+                let mut requests = Vec::new();
+                for request in receiver {
+                    if let Request::ReadMemory { address, length, reply } = &request {
+                        let data = (0..*length).map(|offset| address.wrapping_add(offset) as u8).collect();
+                        reply.send(data).unwrap();
+                    }
+                    requests.push(request);
+                }
+                requests
+            });
+
+            let result = Session::new(&mut stream, &sender).run();
+            drop(sender);
+            (result, emulator.join().unwrap())
+        });
+
+        (result.0, stream.output, result.1)
+    }
+
+    fn exe(text: &[u8]) -> Vec<u8> {
+        let mut exe = vec![0u8; exe::HEADER_SIZE];
+        exe[..8].copy_from_slice(b"PS-X EXE");
+        exe[0x10..0x14].copy_from_slice(&0x8001_0000u32.to_le_bytes());
+        exe[0x18..0x1C].copy_from_slice(&0x8001_0000u32.to_le_bytes());
+        exe[exe::TEXT_SIZE_OFFSET..exe::TEXT_SIZE_OFFSET + 4].copy_from_slice(&(text.len() as u32).to_le_bytes());
+        exe.extend_from_slice(text);
+        exe
+    }
+
+    fn words(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|value| value.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_sexe() {
+        let text: Vec<u8> = (0..0x800).map(|i| i as u8).collect();
+        let exe = exe(&text);
+
+        // Noise before the command is skipped like Unirom does
+        let mut input = b"\r\nSEXE".to_vec();
+        input.extend_from_slice(&exe[..exe::HEADER_SIZE]);
+        input.extend_from_slice(&sum(&text).to_le_bytes());
+        input.extend_from_slice(&text);
+
+        let (result, output, requests) = session(&input);
+        assert!(result.is_ok());
+        assert_eq!(output, b"OKAY");
+        let [Request::LoadExe(buffer)] = &requests[..] else {
+            panic!("expected a single EXE upload");
+        };
+        assert!(*buffer == exe);
+
+        let parsed = Exe::parse(buffer.clone()).unwrap();
+        assert_eq!(parsed.entry_point, 0x8001_0000);
+        assert_eq!(parsed.data, text);
+    }
+
+    #[test]
+    fn test_sbin_v2_chunks() {
+        let data: Vec<u8> = (0..3000).map(|i| (i * 7) as u8).collect();
+        let (first, second) = data.split_at(V2_CHUNK_SIZE);
+
+        let mut input = b"UPV2SBIN".to_vec();
+        input.extend_from_slice(&words(&[0x8002_0000, data.len() as u32, sum(&data)]));
+        // The first chunk arrives corrupted and is sent again
+        input.extend_from_slice(first);
+        input.extend_from_slice(&sum(first).wrapping_add(1).to_le_bytes());
+        input.extend_from_slice(first);
+        input.extend_from_slice(&sum(first).to_le_bytes());
+        input.extend_from_slice(second);
+        input.extend_from_slice(&sum(second).to_le_bytes());
+
+        let (result, output, requests) = session(&input);
+        assert!(result.is_ok());
+        assert_eq!(output, b"OKV2OKAYCHEKERR!CHEKMORECHEKMORE");
+        assert!(matches!(
+            &requests[..],
+            [Request::WriteMemory { address: 0x8002_0000, data: written }] if *written == data
+        ));
+    }
+
+    #[test]
+    fn test_dump_jump_halt_continue() {
+        let mut input = b"DUMP".to_vec();
+        input.extend_from_slice(&words(&[0x8000_0010, 4]));
+        input.extend_from_slice(b"JUMP");
+        input.extend_from_slice(&words(&[0x8003_0000]));
+        input.extend_from_slice(b"DEBGHALTCONT");
+
+        let (result, output, requests) = session(&input);
+        assert!(result.is_ok());
+        assert_eq!(output, b"OKAY\x10\x11\x12\x13OKAYOKAYHLTDDONE");
+        assert!(matches!(
+            &requests[..],
+            [
+                Request::ReadMemory {
+                    address: 0x8000_0010,
+                    length: 4,
+                    ..
+                },
+                Request::Jump(0x8003_0000),
+                Request::Halt,
+                Request::Continue
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_oversized_transfers() {
+        let length = MAX_TRANSFER_SIZE as u32 + 1;
+
+        // Only the header arrives, a session that allocated for the body would wait for it
+        let mut input = b"SBIN".to_vec();
+        input.extend_from_slice(&words(&[0x8000_0000, u32::MAX, 0]));
+        let (result, _, requests) = session(&input);
+        assert!(matches!(result, Err(SessionError::TooLarge(length)) if length == u32::MAX as usize));
+        assert!(requests.is_empty());
+
+        let mut exe = exe(&[]);
+        exe[exe::TEXT_SIZE_OFFSET..exe::TEXT_SIZE_OFFSET + 4].copy_from_slice(&length.to_le_bytes());
+        let mut input = b"SEXE".to_vec();
+        input.extend_from_slice(&exe);
+        input.extend_from_slice(&0u32.to_le_bytes());
+        let (result, _, requests) = session(&input);
+        assert!(matches!(result, Err(SessionError::TooLarge(_))));
+        assert!(requests.is_empty());
+
+        let mut input = b"DUMP".to_vec();
+        input.extend_from_slice(&words(&[0x8000_0000, length]));
+        let (result, output, requests) = session(&input);
+        assert!(matches!(result, Err(SessionError::TooLarge(_))));
+        assert_eq!(output, b"OKAY");
+        assert!(requests.is_empty());
+    }
+
+    #[test]
+    fn test_truncated_upload() {
+        let mut input = b"SBIN".to_vec();
+        input.extend_from_slice(&words(&[0x8000_0000, 16, 0]));
+        input.extend_from_slice(&[0; 8]);
+
+        let (result, _, requests) = session(&input);
+        assert!(matches!(result, Err(SessionError::Io(_))));
+        assert!(requests.is_empty());
+    }
+}
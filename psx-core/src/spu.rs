@@ -1,26 +1,161 @@
 use crate::mmu::bus::Bus8;
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
 
+crate::define_addr!(SOUND_RAM_TRANSFER_ADDRESS_ADDR, 0x1F80_1DA6, 0, 0x02, 0x02);
 crate::define_addr!(SOUND_RAM_TRANSFER_FIFO_ADDR, 0x1F80_1DA8, 0, 0x02, 0x02);
+crate::define_addr!(CONTROL_REGISTER_ADDR, 0x1F80_1DAA, 0, 0x02, 0x02);
 crate::define_addr!(STATUS_REGISTER_ADDR, 0x1F80_1DAE, 0, 0x02, 0x02);
 
+pub const SOUND_RAM_SIZE: usize = 512 * 1024;
+
+// Registers live at 1F801C00h..1F801FFFh
+const SPU_REGISTERS_SIZE: usize = 0x400;
+
+// PSX-SPX: "SPUCNT 5-4 Sound RAM Transfer Mode (0=Stop, 1=ManualWrite, 2=DMAwrite, 3=DMAread)"
+const TRANSFER_MODE_DMA_WRITE: u16 = 2;
+const TRANSFER_MODE_DMA_READ: u16 = 3;
+
 pub struct Spu {
-    pub fake_ram: Vec<u8>,
+    pub registers: Vec<u8>,
+    pub sound_ram: Vec<u8>,
+    transfer_address: usize, // Current sound RAM address of the data transfer
 }
 
 impl Spu {
     pub fn new() -> Self {
         Spu {
-            fake_ram: vec![0; 512 * 1024],
+            registers: vec![0; SPU_REGISTERS_SIZE],
+            sound_ram: vec![0; SOUND_RAM_SIZE],
+            transfer_address: 0,
+        }
+    }
+
+    /// DMA4 may transfer data in the direction selected by SPUCNT
+    pub fn dma_request(&self) -> bool {
+        matches!(self.transfer_mode(), TRANSFER_MODE_DMA_WRITE | TRANSFER_MODE_DMA_READ)
+    }
+
+    pub fn dma_write(&mut self, word: u32) {
+        if self.transfer_mode() != TRANSFER_MODE_DMA_WRITE {
+            tracing::warn!(target: "psx_core::spu", mode = self.transfer_mode(), "DMA write while SPUCNT is not in DMA write mode");
         }
+
+        self.write_sound_ram(word as u16);
+        self.write_sound_ram((word >> 16) as u16);
+    }
+
+    pub fn dma_read(&mut self) -> u32 {
+        if self.transfer_mode() != TRANSFER_MODE_DMA_READ {
+            tracing::warn!(target: "psx_core::spu", mode = self.transfer_mode(), "DMA read while SPUCNT is not in DMA read mode");
+        }
+
+        let lo = self.read_sound_ram() as u32;
+        let hi = self.read_sound_ram() as u32;
+        lo | (hi << 16)
+    }
+
+    fn write_sound_ram(&mut self, value: u16) {
+        let [lo, hi] = value.to_le_bytes();
+        self.sound_ram[self.transfer_address] = lo;
+        self.sound_ram[self.transfer_address + 1] = hi;
+        self.transfer_address = (self.transfer_address + 2) % SOUND_RAM_SIZE;
+    }
+
+    fn read_sound_ram(&mut self) -> u16 {
+        let value = u16::from_le_bytes([
+            self.sound_ram[self.transfer_address],
+            self.sound_ram[self.transfer_address + 1],
+        ]);
+        self.transfer_address = (self.transfer_address + 2) % SOUND_RAM_SIZE;
+        value
+    }
+
+    #[inline(always)]
+    fn transfer_mode(&self) -> u16 {
+        (self.read_register(CONTROL_REGISTER_ADDR_START) >> 4) & 0b11
+    }
+
+    fn status(&self) -> u16 {
+        // 5-0   Current SPU Mode   (same as SPUCNT.Bit5-0, but, applied a bit delayed)
+        // 7     Data Transfer DMA Read/Write Request ;seems to be same as SPUCNT.Bit5
+        // 8     Data Transfer DMA Write Request  (0=No, 1=Yes)
+        // 9     Data Transfer DMA Read Request   (0=No, 1=Yes)
+        let control = self.read_register(CONTROL_REGISTER_ADDR_START);
+        let mode = self.transfer_mode();
+
+        let mut status = control & 0x3F;
+        status |= ((control >> 5) & 1) << 7;
+        status |= ((mode == TRANSFER_MODE_DMA_WRITE) as u16) << 8;
+        status |= ((mode == TRANSFER_MODE_DMA_READ) as u16) << 9;
+        status
+    }
+
+    #[inline(always)]
+    fn read_register(&self, address: u32) -> u16 {
+        let offset = address as usize - 0x1F80_1C00;
+        u16::from_le_bytes([self.registers[offset], self.registers[offset + 1]])
     }
 }
 
 impl Bus8 for Spu {
     fn read_u8(&mut self, address: u32) -> u8 {
-        self.fake_ram[address as usize - 0x1F80_1C00]
+        match address {
+            STATUS_REGISTER_ADDR_START..=STATUS_REGISTER_ADDR_END => {
+                (self.status() >> ((address & 1) * 8)) as u8
+            }
+            _ => self.registers[address as usize - 0x1F80_1C00],
+        }
     }
 
     fn write_u8(&mut self, address: u32, value: u8) {
-        self.fake_ram[address as usize - 0x1F80_1C00] = value;
+        self.registers[address as usize - 0x1F80_1C00] = value;
+
+        // Registers are 16-bit, act once the upper byte has been written
+        if address & 1 == 0 {
+            return;
+        }
+
+        let aligned_address = address & !1;
+        match aligned_address {
+            SOUND_RAM_TRANSFER_ADDRESS_ADDR_START => {
+                // PSX-SPX: "Used for manual write and DMA read/write SPU memory. Writing to this registers stores
+                // the written value in 1F801DA6h, and does additional store the value (multiplied by 8) in
+                // another internal "current address" register"
+                self.transfer_address = self.read_register(aligned_address) as usize * 8;
+            }
+            SOUND_RAM_TRANSFER_FIFO_ADDR_START => {
+                let value = self.read_register(aligned_address);
+                self.write_sound_ram(value);
+            }
+            CONTROL_REGISTER_ADDR_START => {
+                tracing::trace!(
+                    target: "psx_core::spu",
+                    value = format!("{:04X}", self.read_register(aligned_address)),
+                    transfer_mode = self.transfer_mode(),
+                    "SPUCNT write"
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Savestate for Spu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.registers);
+        writer.write_bytes(&self.sound_ram);
+        writer.write(&self.transfer_address);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        reader.read_into(&mut self.registers)?;
+        reader.read_into(&mut self.sound_ram)?;
+        self.transfer_address = reader.read()?;
+
+        if self.transfer_address >= SOUND_RAM_SIZE {
+            return Err(SavestateError::Invalid("SPU transfer address"));
+        }
+
+        Ok(())
     }
 }
@@ -1,4 +1,5 @@
 use crate::mmu::bus::{Bus8, Bus16, Bus32};
+use crate::savestate::{Savestate, SavestateError, StateReader, StateWriter};
 use proc_bitfield::bitfield;
 
 // PSX-SPX: "1F801100h+N*10h - Timer 0..2 Current Counter Value (R/W)"
@@ -33,12 +34,32 @@ bitfield! {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockSource {
+    SystemClock,
+    SystemClockDiv8,
+    DotClock,
+    HBlank,
+}
+
+impl std::fmt::Display for ClockSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClockSource::SystemClock => write!(f, "System Clock"),
+            ClockSource::SystemClockDiv8 => write!(f, "System Clock / 8"),
+            ClockSource::DotClock => write!(f, "Dot Clock"),
+            ClockSource::HBlank => write!(f, "HBlank"),
+        }
+    }
+}
+
 pub struct Timer<const TIMER_ID: u8> {
     pub counter: u16,
     pub mode: TimerMode,
     pub target: u16,
-    divider: u32,
-    sync_reached: bool,
+    prescaler: u64,     // Accumulated clock input that hasn't amounted to a full increment yet
+    in_blank: bool,     // Level of the blanking signal this timer synchronizes to
+    sync_reached: bool, // Sync mode 3 has seen its first blank and is free running
 }
 
 impl<const TIMER_ID: u8> Timer<TIMER_ID> {
@@ -47,40 +68,88 @@ impl<const TIMER_ID: u8> Timer<TIMER_ID> {
             counter: 0,
             mode: TimerMode(0),
             target: 0,
-            divider: 0,
+            prescaler: 0,
+            in_blank: false,
             sync_reached: false,
         }
     }
 
-    pub fn tick(&mut self, cycles: usize, in_hblank: bool, in_vblank: bool) -> bool {
-        let mut irq_triggered = false;
+    pub fn clock_source(&self) -> ClockSource {
+        // PSX-SPX:
+        //   Counter 0:  0 or 2 = System Clock,  1 or 3 = Dotclock
+        //   Counter 1:  0 or 2 = System Clock,  1 or 3 = Hblank
+        //   Counter 2:  0 or 1 = System Clock,  2 or 3 = System Clock/8
+        match (TIMER_ID, self.mode.clock_source()) {
+            (0, 1 | 3) => ClockSource::DotClock,
+            (1, 1 | 3) => ClockSource::HBlank,
+            (2, 2 | 3) => ClockSource::SystemClockDiv8,
+            _ => ClockSource::SystemClock,
+        }
+    }
 
-        // Determine which blanking signal to use based on timer
-        let in_blank = match TIMER_ID {
-            0 => in_hblank, // Timer 0 uses HBL
-            1 => in_vblank, // Timer 1 uses VBL
-            _ => false,     // Timer 2 doesn't use blanking
+    /// Run the timer for `cycles` system clock cycles. HBlank clocked timers are advanced by `count_hblank` instead.
+    pub fn tick(&mut self, cycles: usize, dot_clock_divider: u32) -> bool {
+        let Some((scale, period)) = self.clock_ratio(dot_clock_divider) else {
+            return false;
         };
 
-        let clock_divider = Self::get_clock_divider(&self.mode);
-        self.divider += cycles as u32;
+        self.prescaler += cycles as u64 * scale;
 
-        while self.divider >= clock_divider {
-            self.divider -= clock_divider;
+        let ticks = self.prescaler / period;
+        self.prescaler %= period;
 
-            if !self.should_increment(in_blank) {
-                continue;
-            }
+        if ticks == 0 || !self.is_counting() {
+            return false;
+        }
+
+        self.advance(ticks as u32)
+    }
+
+    /// One HBlank went by, which is a clock pulse for timer 1 if it uses the HBlank clock source
+    pub fn count_hblank(&mut self) -> bool {
+        if self.clock_source() != ClockSource::HBlank || !self.is_counting() {
+            return false;
+        }
+
+        self.advance(1)
+    }
 
-            let old_counter = self.counter;
-            self.counter = self.counter.wrapping_add(1);
+    /// Update the blanking signal this timer synchronizes to (HBlank for timer 0, VBlank for timer 1)
+    pub fn set_blank(&mut self, in_blank: bool) {
+        let blank_started = in_blank && !self.in_blank;
+        self.in_blank = in_blank;
+
+        if !blank_started || !self.mode.sync_enable() || TIMER_ID == 2 {
+            return;
+        }
+
+        match self.mode.sync_mode() {
+            // Reset counter to 0000h at blank
+            1 | 2 => self.counter = 0,
+            // Pause until blank occurs once, then switch to free run
+            3 => self.sync_reached = true,
+            _ => {}
+        }
+    }
+
+    /// Increment the counter `ticks` times, stopping at every target/overflow crossing on the way
+    fn advance(&mut self, mut ticks: u32) -> bool {
+        let mut irq_triggered = false;
+
+        while ticks > 0 {
+            let to_target = self.increments_until_target();
+            let to_overflow = self.increments_until_overflow();
+            let step = ticks.min(to_target).min(to_overflow);
+
+            self.counter = (self.counter as u32 + step) as u16;
+            ticks -= step;
 
             // Check for target
-            if self.counter == self.target {
+            if step == to_target {
                 self.mode.set_reached_target(true);
 
                 if self.mode.irq_at_target() {
-                    irq_triggered = self.trigger_irq();
+                    irq_triggered |= self.trigger_irq();
                 }
 
                 if self.mode.reset_counter() {
@@ -89,11 +158,11 @@ impl<const TIMER_ID: u8> Timer<TIMER_ID> {
             }
 
             // Check for overflow
-            if old_counter == 0xFFFF && self.counter == 0 {
+            if step == to_overflow {
                 self.mode.set_reached_overflow(true);
 
                 if self.mode.irq_at_overflow() {
-                    irq_triggered = self.trigger_irq();
+                    irq_triggered |= self.trigger_irq();
                 }
             }
         }
@@ -101,28 +170,64 @@ impl<const TIMER_ID: u8> Timer<TIMER_ID> {
         irq_triggered
     }
 
-    fn get_clock_divider(mode: &TimerMode) -> u32 {
-        match TIMER_ID {
-            0 => match mode.clock_source() {
-                0 | 2 => 1,
-                1 | 3 => 1, // TODO: dotclock
-                _ => 1,
-            },
-            1 => match mode.clock_source() {
-                0 | 2 => 1,
-                1 | 3 => 1, // TODO: hblank
-                _ => 1,
-            },
-            2 => match mode.clock_source() {
-                0 | 1 => 1,
-                2 | 3 => 8, // System clock / 8
-                _ => 1,
-            },
-            _ => 1,
+    /// System clock cycles until this timer raises its next IRQ, assuming the blanking signal stays as it is
+    pub fn cycles_until_irq(&self, dot_clock_divider: u32) -> Option<u64> {
+        // One-shot mode: the IRQ already fired and won't fire again until the mode is rewritten
+        if !self.mode.irq_repeat() && !self.mode.irq_request() {
+            return None;
+        }
+
+        if !self.is_counting() {
+            return None;
         }
+
+        // HBlank clocked timers only move on HBlank edges, which sync them anyway
+        let (scale, period) = self.clock_ratio(dot_clock_divider)?;
+
+        let to_target = self.increments_until_target();
+        let to_overflow = self.increments_until_overflow();
+
+        let increments = match (self.mode.irq_at_target(), self.mode.irq_at_overflow()) {
+            (true, true) => to_target.min(to_overflow),
+            (true, false) => to_target,
+            (false, true) if self.mode.reset_counter() && to_target < to_overflow => {
+                // The counter resets at the target before it can overflow, so wake up there and re-check
+                to_target
+            }
+            (false, true) => to_overflow,
+            (false, false) => return None,
+        };
+
+        Some((increments as u64 * period - self.prescaler).div_ceil(scale))
     }
 
-    fn should_increment(&mut self, in_blank: bool) -> bool {
+    #[inline(always)]
+    fn increments_until_target(&self) -> u32 {
+        match self.target.wrapping_sub(self.counter) {
+            0 => 0x1_0000,
+            distance => distance as u32,
+        }
+    }
+
+    #[inline(always)]
+    fn increments_until_overflow(&self) -> u32 {
+        0x1_0000 - self.counter as u32
+    }
+
+    /// Returns `(scale, period)`: each system clock cycle adds `scale` to the prescaler and every `period`
+    /// accumulated units increment the counter once
+    #[inline(always)]
+    fn clock_ratio(&self, dot_clock_divider: u32) -> Option<(u64, u64)> {
+        match self.clock_source() {
+            ClockSource::SystemClock => Some((1, 1)),
+            ClockSource::SystemClockDiv8 => Some((1, 8)),
+            // PSX-SPX: video clock = system clock * 11/7, one dot every `dot_clock_divider` video cycles
+            ClockSource::DotClock => Some((11, 7 * dot_clock_divider as u64)),
+            ClockSource::HBlank => None,
+        }
+    }
+
+    fn is_counting(&self) -> bool {
         if !self.mode.sync_enable() {
             return true;
         }
@@ -134,37 +239,11 @@ impl<const TIMER_ID: u8> Timer<TIMER_ID> {
 
         // Timer 0/1 sync modes
         match self.mode.sync_mode() {
-            0 => !in_blank, // Pause during blank
-            1 => {
-                // Reset at blank
-                if in_blank && !self.sync_reached {
-                    self.counter = 0;
-                    self.sync_reached = true;
-                } else if !in_blank {
-                    self.sync_reached = false;
-                }
-                true
-            }
-            2 => {
-                // Reset at blank and pause outside
-                if in_blank && !self.sync_reached {
-                    self.counter = 0;
-                    self.sync_reached = true;
-                } else if !in_blank {
-                    self.sync_reached = false;
-                }
-
-                in_blank
-            }
-            3 => {
-                // Pause until first blank, then free run
-                if in_blank {
-                    self.sync_reached = true;
-                }
-                
-                self.sync_reached
-            }
-            _ => true,
+            0 => !self.in_blank,    // Pause during blank
+            1 => true,              // Reset at blank
+            2 => self.in_blank,     // Reset at blank and pause outside
+            3 => self.sync_reached, // Pause until first blank, then free run
+            _ => unreachable!(),
         }
     }
 
@@ -199,7 +278,7 @@ impl<const TIMER_ID: u8> Timer<TIMER_ID> {
     pub fn write_mode(&mut self, value: u32) {
         self.mode = TimerMode(value);
         self.counter = 0;
-        self.divider = 0;
+        self.prescaler = 0;
         self.sync_reached = false;
 
         // 10    Interrupt Request       (0=Yes, 1=No) (Set after Writing)    (W=1) (R)
@@ -211,6 +290,9 @@ pub struct Timers {
     pub timer0: Timer<0>,
     pub timer1: Timer<1>,
     pub timer2: Timer<2>,
+    pub dot_clock_divider: u32,
+    in_hblank: bool,
+    last_sync: u64,
 }
 
 impl Timers {
@@ -219,15 +301,42 @@ impl Timers {
             timer0: Timer::new(),
             timer1: Timer::new(),
             timer2: Timer::new(),
+            dot_clock_divider: 10, // 256 dots per line after reset
+            in_hblank: false,
+            last_sync: 0,
         }
     }
 
-    pub fn tick(&mut self, cycles: usize, in_hblank: bool, in_vblank: bool) -> (bool, bool, bool) {
-        let tmr0_irq = self.timer0.tick(cycles, in_hblank, in_vblank);
-        let tmr1_irq = self.timer1.tick(cycles, in_hblank, in_vblank);
-        let tmr2_irq = self.timer2.tick(cycles, in_hblank, in_vblank);
+    /// Catch all timers up to the scheduler timestamp `now`
+    pub fn sync(&mut self, now: u64) -> (bool, bool, bool) {
+        let cycles = now.saturating_sub(self.last_sync) as usize;
+        self.last_sync = now;
+
+        let tmr0_irq = self.timer0.tick(cycles, self.dot_clock_divider);
+        let tmr1_irq = self.timer1.tick(cycles, self.dot_clock_divider);
+        let tmr2_irq = self.timer2.tick(cycles, self.dot_clock_divider);
         (tmr0_irq, tmr1_irq, tmr2_irq)
     }
+
+    /// Apply the blanking edges of the video output, must be called after `sync` up to the edge timestamp
+    pub fn set_blanking(&mut self, in_hblank: bool, in_vblank: bool) -> (bool, bool, bool) {
+        let hblank_started = in_hblank && !self.in_hblank;
+        self.in_hblank = in_hblank;
+
+        self.timer0.set_blank(in_hblank);
+        self.timer1.set_blank(in_vblank);
+
+        let tmr1_irq = hblank_started && self.timer1.count_hblank();
+        (false, tmr1_irq, false)
+    }
+
+    pub fn cycles_until_irq(&self) -> [Option<u64>; 3] {
+        [
+            self.timer0.cycles_until_irq(self.dot_clock_divider),
+            self.timer1.cycles_until_irq(self.dot_clock_divider),
+            self.timer2.cycles_until_irq(self.dot_clock_divider),
+        ]
+    }
 }
 
 impl Bus8 for Timers {
@@ -342,3 +451,299 @@ impl Bus32 for Timers {
         }
     }
 }
+
+impl<const TIMER_ID: u8> Savestate for Timer<TIMER_ID> {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write(&self.counter);
+        writer.write(&self.mode.0);
+        writer.write(&self.target);
+        writer.write(&self.prescaler);
+        writer.write(&self.in_blank);
+        writer.write(&self.sync_reached);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.counter = reader.read()?;
+        self.mode.0 = reader.read()?;
+        self.target = reader.read()?;
+        self.prescaler = reader.read()?;
+        self.in_blank = reader.read()?;
+        self.sync_reached = reader.read()?;
+        Ok(())
+    }
+}
+
+impl Savestate for Timers {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.timer0.save_state(writer);
+        self.timer1.save_state(writer);
+        self.timer2.save_state(writer);
+        writer.write(&self.dot_clock_divider);
+        writer.write(&self.in_hblank);
+        writer.write(&self.last_sync);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SavestateError> {
+        self.timer0.load_state(reader)?;
+        self.timer1.load_state(reader)?;
+        self.timer2.load_state(reader)?;
+        self.dot_clock_divider = reader.read()?;
+        self.in_hblank = reader.read()?;
+        self.last_sync = reader.read()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYNC_ENABLE: u32 = 1 << 0;
+    const RESET_AT_TARGET: u32 = 1 << 3;
+    const IRQ_AT_TARGET: u32 = 1 << 4;
+    const IRQ_AT_OVERFLOW: u32 = 1 << 5;
+    const IRQ_REPEAT: u32 = 1 << 6;
+    const IRQ_TOGGLE: u32 = 1 << 7;
+    const DOT_CLOCK_DIVIDER: u32 = 10;
+
+    fn sync_mode(mode: u32) -> u32 {
+        SYNC_ENABLE | (mode << 1)
+    }
+
+    fn clock_source(source: u32) -> u32 {
+        source << 8
+    }
+
+    #[test]
+    fn test_sync_pause_during_blank() {
+        let mut timer = Timer::<0>::new();
+        timer.write_mode(sync_mode(0));
+
+        timer.tick(10, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 10);
+
+        timer.set_blank(true);
+        timer.tick(10, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 10);
+
+        timer.set_blank(false);
+        timer.tick(5, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 15);
+    }
+
+    #[test]
+    fn test_sync_reset_at_blank() {
+        let mut timer = Timer::<1>::new();
+        timer.write_mode(sync_mode(1));
+
+        timer.tick(100, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 100);
+
+        // Only the start of the blank resets, the counter keeps running through it
+        timer.set_blank(true);
+        assert_eq!(timer.counter, 0);
+        timer.tick(5, DOT_CLOCK_DIVIDER);
+        timer.set_blank(true);
+        assert_eq!(timer.counter, 5);
+
+        timer.set_blank(false);
+        timer.tick(5, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 10);
+    }
+
+    #[test]
+    fn test_sync_reset_and_pause_outside_blank() {
+        let mut timer = Timer::<0>::new();
+        timer.write_mode(sync_mode(2));
+
+        timer.tick(10, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 0);
+
+        timer.set_blank(true);
+        timer.tick(7, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 7);
+
+        timer.set_blank(false);
+        timer.tick(7, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 7);
+
+        timer.set_blank(true);
+        assert_eq!(timer.counter, 0);
+    }
+
+    #[test]
+    fn test_sync_pause_until_first_blank() {
+        let mut timer = Timer::<1>::new();
+        timer.write_mode(sync_mode(3));
+
+        timer.tick(10, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 0);
+
+        timer.set_blank(true);
+        timer.tick(4, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 4);
+
+        // Free running from here on, later blanks change nothing
+        timer.set_blank(false);
+        timer.tick(4, DOT_CLOCK_DIVIDER);
+        timer.set_blank(true);
+        timer.tick(4, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 12);
+
+        // Rewriting the mode waits for a blank again
+        timer.write_mode(sync_mode(3));
+        timer.set_blank(false);
+        timer.tick(4, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 0);
+    }
+
+    #[test]
+    fn test_timer2_sync_modes() {
+        let mut timer = Timer::<2>::new();
+
+        for (mode, counting) in [(0, false), (1, true), (2, true), (3, false)] {
+            timer.write_mode(sync_mode(mode));
+            timer.tick(10, DOT_CLOCK_DIVIDER);
+            assert_eq!(timer.counter, if counting { 10 } else { 0 }, "sync mode {}", mode);
+        }
+    }
+
+    #[test]
+    fn test_dot_clock_source() {
+        let mut timer = Timer::<0>::new();
+        timer.write_mode(clock_source(1));
+        assert_eq!(timer.clock_source(), ClockSource::DotClock);
+
+        // 70 system clock cycles are 110 video cycles, 11 dots at a divider of 10
+        timer.tick(70, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 11);
+
+        // Fractions of a dot carry over to the next tick
+        timer.tick(6, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 11);
+        timer.tick(1, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 12);
+
+        timer.write_mode(clock_source(2));
+        assert_eq!(timer.clock_source(), ClockSource::SystemClock);
+    }
+
+    #[test]
+    fn test_hblank_source() {
+        let mut timers = Timers::new();
+        timers.timer1.write_mode(clock_source(1));
+        assert_eq!(timers.timer1.clock_source(), ClockSource::HBlank);
+
+        timers.sync(1000);
+        assert_eq!(timers.timer1.counter, 0);
+
+        // Counts the start of every HBlank
+        timers.set_blanking(true, false);
+        timers.set_blanking(true, false);
+        assert_eq!(timers.timer1.counter, 1);
+        timers.set_blanking(false, false);
+        timers.set_blanking(true, false);
+        assert_eq!(timers.timer1.counter, 2);
+
+        // Timer 0 has no HBlank source
+        let mut timer = Timer::<0>::new();
+        timer.write_mode(clock_source(3));
+        assert!(!timer.count_hblank());
+        assert_eq!(timer.counter, 0);
+    }
+
+    #[test]
+    fn test_system_clock_div8_source() {
+        let mut timer = Timer::<2>::new();
+        timer.write_mode(clock_source(2));
+        assert_eq!(timer.clock_source(), ClockSource::SystemClockDiv8);
+
+        timer.tick(15, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 1);
+        timer.tick(1, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.counter, 2);
+
+        timer.write_mode(clock_source(1));
+        assert_eq!(timer.clock_source(), ClockSource::SystemClock);
+    }
+
+    #[test]
+    fn test_target_irq_repeat_pulse() {
+        let mut timer = Timer::<2>::new();
+        timer.target = 10;
+        timer.write_mode(RESET_AT_TARGET | IRQ_AT_TARGET | IRQ_REPEAT);
+        assert!(timer.mode.irq_request());
+
+        assert!(!timer.tick(9, DOT_CLOCK_DIVIDER));
+        assert!(timer.tick(1, DOT_CLOCK_DIVIDER));
+        assert_eq!(timer.counter, 0);
+        assert!(!timer.mode.irq_request());
+
+        assert!(timer.tick(10, DOT_CLOCK_DIVIDER));
+
+        // Reached target is cleared by reading the mode
+        assert!(TimerMode(timer.read_mode()).reached_target());
+        assert!(!TimerMode(timer.read_mode()).reached_target());
+    }
+
+    #[test]
+    fn test_target_irq_one_shot() {
+        let mut timer = Timer::<2>::new();
+        timer.target = 10;
+        timer.write_mode(RESET_AT_TARGET | IRQ_AT_TARGET);
+
+        assert!(timer.tick(10, DOT_CLOCK_DIVIDER));
+        assert!(!timer.tick(10, DOT_CLOCK_DIVIDER));
+        assert_eq!(timer.cycles_until_irq(DOT_CLOCK_DIVIDER), None);
+
+        // Writing the mode arms it again
+        timer.write_mode(RESET_AT_TARGET | IRQ_AT_TARGET);
+        assert!(timer.tick(10, DOT_CLOCK_DIVIDER));
+    }
+
+    #[test]
+    fn test_target_irq_toggle() {
+        let mut timer = Timer::<2>::new();
+        timer.target = 10;
+        timer.write_mode(RESET_AT_TARGET | IRQ_AT_TARGET | IRQ_REPEAT | IRQ_TOGGLE);
+
+        // Bit 10 flips at every target, only the 1 -> 0 edge is an IRQ
+        assert!(timer.tick(10, DOT_CLOCK_DIVIDER));
+        assert!(!timer.mode.irq_request());
+        assert!(!timer.tick(10, DOT_CLOCK_DIVIDER));
+        assert!(timer.mode.irq_request());
+        assert!(timer.tick(10, DOT_CLOCK_DIVIDER));
+        assert!(!timer.mode.irq_request());
+    }
+
+    #[test]
+    fn test_overflow_irq() {
+        let mut timer = Timer::<0>::new();
+        timer.target = 0x8000;
+        timer.write_mode(IRQ_AT_OVERFLOW | IRQ_REPEAT);
+        timer.counter = 0xFFFE;
+
+        assert!(!timer.tick(1, DOT_CLOCK_DIVIDER));
+        assert!(timer.tick(1, DOT_CLOCK_DIVIDER));
+        assert_eq!(timer.counter, 0);
+
+        let mode = TimerMode(timer.read_mode());
+        assert!(mode.reached_overflow());
+        assert!(!mode.reached_target());
+    }
+
+    #[test]
+    fn test_cycles_until_irq() {
+        let mut timer = Timer::<2>::new();
+        timer.target = 100;
+        timer.write_mode(RESET_AT_TARGET | IRQ_AT_TARGET | IRQ_REPEAT);
+        assert_eq!(timer.cycles_until_irq(DOT_CLOCK_DIVIDER), Some(100));
+
+        timer.tick(40, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.cycles_until_irq(DOT_CLOCK_DIVIDER), Some(60));
+
+        timer.write_mode(RESET_AT_TARGET | IRQ_AT_TARGET | IRQ_REPEAT | clock_source(2));
+        timer.tick(3, DOT_CLOCK_DIVIDER);
+        assert_eq!(timer.cycles_until_irq(DOT_CLOCK_DIVIDER), Some(797));
+    }
+}
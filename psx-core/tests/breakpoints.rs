@@ -0,0 +1,237 @@
+//! COP0 execution and data breakpoints, from BPC/BDA matching through DCIC to returning from the handler
+
+use psx_core::cpu::Cpu;
+use psx_core::cpu::cop::cop0::{COP0_EXCEPTION_CODE_BREAK, Cop0, HardwareBreakpoint};
+use psx_core::cpu::cop::registers::DebugControlRegister;
+use std::sync::{Mutex, PoisonError};
+
+const PROGRAM: u32 = 0x8001_0000;
+const DATA: u32 = 0x8002_0000;
+const DEBUG_VECTOR: u32 = 0x8000_0040;
+
+// DCIC bits 23, 30 and 31 gate every breakpoint, bits 24..27 pick which ones trap
+const MASTER: u32 = (1 << 23) | (1 << 30) | (1 << 31);
+const EXECUTE: u32 = 1 << 24;
+const DATA_ACCESS: u32 = 1 << 25;
+const DATA_READ: u32 = 1 << 26;
+const DATA_WRITE: u32 = 1 << 27;
+
+fn write_word(cpu: &mut Cpu, address: u32, word: u32) {
+    let offset = (address & 0x1FFF_FFFF) as usize;
+    cpu.mmu.memory[offset..offset + 4].copy_from_slice(&word.to_le_bytes());
+}
+
+fn read_word(cpu: &Cpu, address: u32) -> u32 {
+    let offset = (address & 0x1FFF_FFFF) as usize;
+    u32::from_le_bytes(cpu.mmu.memory[offset..offset + 4].try_into().unwrap())
+}
+
+// Every CPU maps the whole 4 GiB address space, so the tests take turns on a single one
+static CPU: Mutex<Option<Cpu>> = Mutex::new(None);
+
+/// Run `test` on a CPU at the start of a short program, with a debug handler that turns breakpoints off and
+/// returns to EPC
+fn with_cpu(test: impl FnOnce(&mut Cpu)) {
+    let mut cpu = CPU.lock().unwrap_or_else(PoisonError::into_inner);
+    let cpu = cpu.get_or_insert_with(Cpu::new);
+
+    let ram_size = cpu.mmu.ram_size().bytes() as usize;
+    cpu.mmu.memory[..ram_size].fill(0);
+    cpu.registers = [0; 32];
+    cpu.load_delay = None;
+    cpu.delay_slot = None;
+    cpu.cop0 = Cop0::new();
+    cpu.hardware_breakpoint = None;
+
+    let program = [
+        0x2408_0001, // addiu $t0, $zero, 1
+        0x3C09_8002, // lui   $t1, 0x8002
+        0x8D2A_0010, // lw    $t2, 0x10($t1)
+        0xAD28_0020, // sw    $t0, 0x20($t1)
+        0x2508_0001, // addiu $t0, $t0, 1
+        0x0800_4005, // j     0x80010014
+        0x0000_0000, // nop
+    ];
+    for (index, &word) in program.iter().enumerate() {
+        write_word(cpu, PROGRAM + index as u32 * 4, word);
+    }
+
+    let handler = [
+        0x0000_0000, // nop
+        0x4080_3800, // mtc0  $zero, DCIC
+        0x401A_7000, // mfc0  $k0, EPC
+        0x0000_0000, // nop
+        0x0340_0008, // jr    $k0
+        0x4200_0010, // rfe
+    ];
+    for (index, &word) in handler.iter().enumerate() {
+        write_word(cpu, DEBUG_VECTOR + index as u32 * 4, word);
+    }
+
+    write_word(cpu, DATA + 0x10, 0x1234_5678);
+    cpu.pc = PROGRAM;
+    test(cpu);
+}
+
+/// Tick until a breakpoint traps, None if none did within `ticks`
+fn run_until_break(cpu: &mut Cpu, ticks: usize) -> Option<HardwareBreakpoint> {
+    for _ in 0..ticks {
+        cpu.tick().unwrap();
+        if let Some(breakpoint) = cpu.hardware_breakpoint.take() {
+            return Some(breakpoint);
+        }
+    }
+    None
+}
+
+/// The program has run through once and is spinning in its final loop
+fn assert_finished(cpu: &mut Cpu) {
+    assert_eq!(run_until_break(cpu, 30), None);
+    assert_eq!(cpu.registers[8], 2);
+    assert_eq!(cpu.registers[10], 0x1234_5678);
+    assert_eq!(read_word(cpu, DATA + 0x20), 1);
+    assert!(cpu.pc == PROGRAM + 0x14 || cpu.pc == PROGRAM + 0x18);
+}
+
+#[test]
+fn test_execution_breakpoint() {
+    with_cpu(|cpu| {
+        cpu.cop0.bpc = PROGRAM + 0x10;
+        cpu.cop0.bdcm = 0xFFFF_FFFF;
+        cpu.cop0.dcic = DebugControlRegister(MASTER | EXECUTE);
+
+        assert_eq!(
+            run_until_break(cpu, 10),
+            Some(HardwareBreakpoint::Execute(PROGRAM + 0x10))
+        );
+
+        // The instruction at BPC has not run yet, the handler was entered right away
+        assert_eq!(cpu.registers[8], 1);
+        assert_eq!(cpu.pc, DEBUG_VECTOR + 4);
+        assert_eq!(cpu.cop0.epc, PROGRAM + 0x10);
+        assert_eq!(cpu.cop0.cause.exception_code(), COP0_EXCEPTION_CODE_BREAK);
+        assert!(cpu.cop0.dcic.any_break());
+        assert!(cpu.cop0.dcic.code_break());
+        assert!(!cpu.cop0.dcic.data_break());
+
+        assert_finished(cpu);
+        assert_eq!(cpu.cop0.dcic.0, 0);
+    });
+}
+
+#[test]
+fn test_execution_breakpoint_mask() {
+    with_cpu(|cpu| {
+        cpu.cop0.bpc = PROGRAM + 0x1C;
+        cpu.cop0.bdcm = 0xFFFF_FFFF;
+        cpu.cop0.dcic = DebugControlRegister(MASTER | EXECUTE);
+
+        // Never reached
+        assert_eq!(run_until_break(cpu, 30), None);
+    });
+
+    // BPCM leaves the low 4 bits out of the comparison
+    with_cpu(|cpu| {
+        cpu.cop0.bpc = PROGRAM + 0x1C;
+        cpu.cop0.bdcm = 0xFFFF_FFF0;
+        cpu.cop0.dcic = DebugControlRegister(MASTER | EXECUTE);
+
+        assert_eq!(
+            run_until_break(cpu, 10),
+            Some(HardwareBreakpoint::Execute(PROGRAM + 0x10))
+        );
+        assert_finished(cpu);
+    });
+}
+
+#[test]
+fn test_data_read_breakpoint() {
+    with_cpu(|cpu| {
+        cpu.cop0.bda = DATA + 0x10;
+        cpu.cop0.bdam = 0xFFFF_FFFF;
+        cpu.cop0.dcic = DebugControlRegister(MASTER | DATA_ACCESS | DATA_READ);
+
+        assert_eq!(run_until_break(cpu, 10), Some(HardwareBreakpoint::Read(DATA + 0x10)));
+
+        // The load finished before the trap, its value is on the way through the load delay
+        assert_eq!(cpu.load_delay, Some((10, 0x1234_5678)));
+        assert_eq!(cpu.cop0.epc, PROGRAM + 0x08);
+        assert_eq!(cpu.cop0.cause.exception_code(), COP0_EXCEPTION_CODE_BREAK);
+        assert!(cpu.cop0.dcic.any_break());
+        assert!(cpu.cop0.dcic.data_break());
+        assert!(cpu.cop0.dcic.data_read_break());
+        assert!(!cpu.cop0.dcic.data_write_break());
+        assert!(!cpu.cop0.dcic.code_break());
+
+        assert_finished(cpu);
+    });
+}
+
+#[test]
+fn test_data_write_breakpoint() {
+    with_cpu(|cpu| {
+        cpu.cop0.bda = DATA + 0x20;
+        cpu.cop0.bdam = 0xFFFF_FFFF;
+        cpu.cop0.dcic = DebugControlRegister(MASTER | DATA_ACCESS | DATA_WRITE);
+
+        assert_eq!(run_until_break(cpu, 10), Some(HardwareBreakpoint::Write(DATA + 0x20)));
+
+        assert_eq!(read_word(cpu, DATA + 0x20), 1);
+        assert_eq!(cpu.cop0.epc, PROGRAM + 0x0C);
+        assert!(cpu.cop0.dcic.data_write_break());
+        assert!(!cpu.cop0.dcic.data_read_break());
+
+        assert_finished(cpu);
+    });
+}
+
+#[test]
+fn test_data_breakpoint_mask() {
+    // BDAM leaves the low byte out, so the read of DATA + 10h matches before the write of DATA + 20h
+    with_cpu(|cpu| {
+        cpu.cop0.bda = DATA + 0x80;
+        cpu.cop0.bdam = 0xFFFF_FF00;
+        cpu.cop0.dcic = DebugControlRegister(MASTER | DATA_ACCESS | DATA_READ | DATA_WRITE);
+
+        assert_eq!(run_until_break(cpu, 10), Some(HardwareBreakpoint::Read(DATA + 0x10)));
+        assert_finished(cpu);
+    });
+
+    // A read-only breakpoint ignores the write to its address
+    with_cpu(|cpu| {
+        cpu.cop0.bda = DATA + 0x20;
+        cpu.cop0.bdam = 0xFFFF_FFFF;
+        cpu.cop0.dcic = DebugControlRegister(MASTER | DATA_ACCESS | DATA_READ);
+
+        assert_finished(cpu);
+        assert_eq!(cpu.cop0.dcic.0, MASTER | DATA_ACCESS | DATA_READ);
+    });
+}
+
+#[test]
+fn test_dcic_enable_bits() {
+    let all = MASTER | EXECUTE | DATA_ACCESS | DATA_READ | DATA_WRITE;
+    let unused = 0x8004_0000;
+
+    // Each master bit on its own turns everything off, then the execution and data enables on their own
+    let cases = [
+        (all & !(1 << 23), PROGRAM + 0x10, DATA + 0x10),
+        (all & !(1 << 30), PROGRAM + 0x10, DATA + 0x10),
+        (all & !(1 << 31), PROGRAM + 0x10, DATA + 0x10),
+        (all & !EXECUTE, PROGRAM + 0x10, unused),
+        (all & !DATA_ACCESS, unused, DATA + 0x10),
+    ];
+
+    for (dcic, bpc, bda) in cases {
+        with_cpu(|cpu| {
+            cpu.cop0.bpc = bpc;
+            cpu.cop0.bdcm = 0xFFFF_FFFF;
+            cpu.cop0.bda = bda;
+            cpu.cop0.bdam = 0xFFFF_FFFF;
+            cpu.cop0.dcic = DebugControlRegister(dcic);
+
+            assert_eq!(run_until_break(cpu, 30), None, "DCIC {:08X} trapped", dcic);
+            assert_eq!(cpu.cop0.dcic.0, dcic, "DCIC {:08X} recorded a break", dcic);
+        });
+    }
+}
@@ -0,0 +1,227 @@
+//! Boots the test EXEs listed in `conformance/tests.txt` and compares what they print or draw
+//!
+//! The programs and a BIOS are not part of the repository. They are looked up in `test-roms/` at
+//! the workspace root, or wherever `PSX_TEST_ROMS` points, with the BIOS at `bios/SCPH1001.BIN`
+//! in there unless `PSX_TEST_BIOS` says otherwise. Tests whose EXE is missing are skipped. With
+//! `PSX_REQUIRE_TEST_ROMS=1` a missing directory or BIOS fails instead. Once both are there, a run
+//! that compared nothing because every test was skipped or missing fails as well.
+//!
+//! Run with `PSX_BLESS=1` to write the current output as the new expectation of every test that is
+//! not a known failure, including the skipped ones.
+
+use psx_core::cpu::internal::{tty_buffer, tty_line_buffer};
+use psx_core::psx::Psx;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = include_str!("conformance/tests.txt");
+
+enum Expect {
+    Tty,
+    Frame,
+}
+
+#[derive(PartialEq, Eq)]
+enum Status {
+    Ok,
+    Fail,
+    Skip,
+}
+
+struct TestExe {
+    name: String,
+    exe: PathBuf,
+    frames: usize,
+    expect: Expect,
+    status: Status,
+}
+
+impl TestExe {
+    fn expected_path(&self) -> PathBuf {
+        let extension = match self.expect {
+            Expect::Tty => "tty",
+            Expect::Frame => "frame",
+        };
+
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/conformance/expected")
+            .join(format!("{}.{}", self.name, extension))
+    }
+}
+
+fn parse_manifest(manifest: &str) -> Vec<TestExe> {
+    manifest
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name, exe, frames, expect, status] = fields[..] else {
+                panic!("Malformed manifest line: {}", line);
+            };
+
+            TestExe {
+                name: name.to_string(),
+                exe: PathBuf::from(exe),
+                frames: frames.parse().expect("Frame count is not a number"),
+                expect: match expect {
+                    "tty" => Expect::Tty,
+                    "frame" => Expect::Frame,
+                    _ => panic!("Unknown expectation '{}' for {}", expect, name),
+                },
+                status: match status {
+                    "ok" => Status::Ok,
+                    "fail" => Status::Fail,
+                    "skip" => Status::Skip,
+                    _ => panic!("Unknown status '{}' for {}", status, name),
+                },
+            }
+        })
+        .collect()
+}
+
+fn rom_dir() -> PathBuf {
+    std::env::var_os("PSX_TEST_ROMS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../test-roms"))
+}
+
+/// FNV-1a of the displayed frame, together with its resolution
+fn frame_hash(psx: &Psx) -> String {
+    let (frame, width, height) = psx.frame();
+    let hash = frame
+        .iter()
+        .flat_map(|&(r, g, b)| [r, g, b])
+        .fold(0xCBF2_9CE4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+        });
+
+    format!("{}x{} {:016X}\n", width, height, hash)
+}
+
+/// Run one EXE and return what it produced, or why it could not finish
+fn run(bios: &[u8], test: &TestExe, exe: Vec<u8>) -> Result<String, String> {
+    // The TTY is shared by every console in the process, this is why the tests run one after another
+    tty_buffer().lock().unwrap().clear();
+    tty_line_buffer().lock().unwrap().clear();
+
+    let mut psx = Psx::new(bios);
+    psx.sideload_exe(exe).map_err(|e| format!("invalid EXE: {}", e))?;
+
+    for frame in 0..test.frames {
+        if !psx.run_frame() {
+            return Err(format!("emulation error in frame {}, PC: {:08X}", frame, psx.cpu.pc));
+        }
+    }
+
+    Ok(match test.expect {
+        Expect::Tty => tty_buffer().lock().unwrap().clone(),
+        Expect::Frame => frame_hash(&psx),
+    })
+}
+
+#[test]
+fn test_exes() {
+    let required = std::env::var_os("PSX_REQUIRE_TEST_ROMS").is_some();
+
+    let rom_dir = rom_dir();
+    if !rom_dir.is_dir() {
+        assert!(!required, "{:?} does not exist", rom_dir);
+        eprintln!("Skipping test EXEs, {:?} does not exist", rom_dir);
+        return;
+    }
+
+    let bios_path = std::env::var_os("PSX_TEST_BIOS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| rom_dir.join("bios/SCPH1001.BIN"));
+    let Ok(bios) = fs::read(&bios_path) else {
+        assert!(!required, "No BIOS at {:?}", bios_path);
+        eprintln!("Skipping test EXEs, no BIOS at {:?}", bios_path);
+        return;
+    };
+
+    let bless = std::env::var_os("PSX_BLESS").is_some();
+    let mut failures = Vec::new();
+    let mut compared = 0;
+
+    for test in parse_manifest(MANIFEST) {
+        if test.status == Status::Skip && !bless {
+            eprintln!("{}: skipped, not checked against hardware yet", test.name);
+            continue;
+        }
+
+        let Ok(exe) = fs::read(rom_dir.join(&test.exe)) else {
+            eprintln!("{}: skipped, {:?} is missing", test.name, test.exe);
+            continue;
+        };
+
+        let actual = match run(&bios, &test, exe) {
+            Ok(actual) => actual,
+            Err(e) => {
+                failures.push(format!("{}: {}", test.name, e));
+                continue;
+            }
+        };
+
+        let expected_path = test.expected_path();
+        if bless && test.status != Status::Fail {
+            fs::write(&expected_path, &actual).expect("Failed to write expectation");
+            eprintln!("{}: blessed", test.name);
+            continue;
+        }
+
+        compared += 1;
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if test.status == Status::Fail => {
+                if expected == actual {
+                    failures.push(format!("{}: known failure passes now, mark it ok", test.name));
+                } else {
+                    eprintln!("{}: known failure", test.name);
+                }
+            }
+            Ok(expected) if expected == actual => eprintln!("{}: ok", test.name),
+            Ok(expected) => failures.push(format!(
+                "{}: output differs from {:?}\n--- expected\n{}\n--- actual\n{}",
+                test.name, expected_path, expected, actual
+            )),
+            Err(_) => failures.push(format!(
+                "{}: no expectation at {:?}, run with PSX_BLESS=1 once the output is known to be right",
+                test.name, expected_path
+            )),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} test EXEs failed:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+
+    // Passing without looking at a single output would look just like passing everything
+    assert!(
+        bless || compared > 0,
+        "No test EXE was compared to an expectation, every test is skipped or missing"
+    );
+}
+
+#[test]
+fn test_manifest_is_valid() {
+    let tests = parse_manifest(MANIFEST);
+    assert!(!tests.is_empty());
+
+    for (index, test) in tests.iter().enumerate() {
+        assert!(test.frames > 0, "{} runs no frames", test.name);
+        assert!(
+            test.status == Status::Skip || test.expected_path().is_file(),
+            "{} is not skipped but has no expectation at {:?}",
+            test.name,
+            test.expected_path()
+        );
+        assert!(
+            tests[..index].iter().all(|other| other.name != test.name),
+            "{} is listed twice",
+            test.name
+        );
+    }
+}
@@ -0,0 +1,749 @@
+#!/usr/bin/env python3
+"""Generates the single-instruction CPU vectors next to this script.
+
+Expectations come from the small R3000A model below, written from the MIPS manual and
+PSX-SPX rather than from the emulator, so the two can be checked against each other.
+Run it from anywhere, it rewrites every .json file in this directory.
+"""
+
+import json
+import os
+
+BASE = 0x8001_0000  # Programs start here, away from the BIOS call hooks at A0h/B0h/C0h
+DATA = 0x8002_0000  # Scratch data used by loads and stores
+MASK = 0xFFFF_FFFF
+
+SR_BEV = 1 << 22
+SR_ISC = 1 << 16
+
+EXC_ADEL = 4
+EXC_ADES = 5
+EXC_SYSCALL = 8
+EXC_BREAK = 9
+EXC_OV = 12
+
+
+def s32(value):
+    value &= MASK
+    return value - (1 << 32) if value & 0x8000_0000 else value
+
+
+def sext16(value):
+    value &= 0xFFFF
+    return value - 0x1_0000 if value & 0x8000 else value
+
+
+# Encoders
+
+
+def r_type(funct, rs=0, rt=0, rd=0, sa=0):
+    return (rs << 21) | (rt << 16) | (rd << 11) | (sa << 6) | funct
+
+
+def i_type(op, rs, rt, imm):
+    return (op << 26) | (rs << 21) | (rt << 16) | (imm & 0xFFFF)
+
+
+def j_type(op, target):
+    return (op << 26) | ((target >> 2) & 0x03FF_FFFF)
+
+
+def cop(n, fmt, rt, rd):
+    return ((0x10 | n) << 26) | (fmt << 21) | (rt << 16) | (rd << 11)
+
+
+def gte(cmd, sf=0, lm=0):
+    return 0x4A00_0000 | (sf << 19) | (lm << 10) | cmd
+
+
+NOP = 0
+RFE = 0x4200_0010
+
+
+class Exception_(Exception):
+    def __init__(self, code):
+        self.code = code
+
+
+class Cpu:
+    def __init__(self, state):
+        self.pc = state["pc"]
+        self.regs = list(state["regs"])
+        self.hi = state["hi"]
+        self.lo = state["lo"]
+        self.load_delay = tuple(state["load_delay"]) if state["load_delay"] else None
+        self.load_pending = False
+        self.sr = state["cop0"]["sr"]
+        self.cause = state["cop0"]["cause"]
+        self.epc = state["cop0"]["epc"]
+        self.cop2_data = {int(i): value for i, value in state.get("cop2_data", {}).items()}
+        self.cop2_control = {int(i): value for i, value in state.get("cop2_control", {}).items()}
+        self.ram = {address & 0x1F_FFFC: word for address, word in state["ram"]}
+        self.branch_target = None
+
+    # Memory, RAM only, byte granular on top of the word dictionary
+
+    def read8(self, address):
+        word = self.ram.get(address & 0x1F_FFFC, 0)
+        return (word >> (8 * (address & 3))) & 0xFF
+
+    def write8(self, address, value):
+        if self.sr & SR_ISC:
+            return
+        key = address & 0x1F_FFFC
+        shift = 8 * (address & 3)
+        word = self.ram.get(key, 0) & ~(0xFF << shift)
+        self.ram[key] = word | ((value & 0xFF) << shift)
+
+    def read16(self, address):
+        return self.read8(address) | (self.read8(address + 1) << 8)
+
+    def read32(self, address):
+        return self.read16(address) | (self.read16(address + 2) << 16)
+
+    def write16(self, address, value):
+        self.write8(address, value)
+        self.write8(address + 1, value >> 8)
+
+    def write32(self, address, value):
+        self.write16(address, value)
+        self.write16(address + 2, value >> 16)
+
+    # Registers and the load delay slot
+
+    def set(self, index, value):
+        if index == 0:
+            return
+        if self.load_delay and self.load_delay[0] == index:
+            self.load_delay = None
+            self.load_pending = False
+        self.regs[index] = value & MASK
+
+    def load(self, index, value):
+        if index == 0:
+            return
+        if not self.load_pending and self.load_delay and self.load_delay[0] != index:
+            self.regs[self.load_delay[0]] = self.load_delay[1]
+        self.load_delay = (index, value & MASK)
+        self.load_pending = True
+
+    def finish_load(self):
+        if not self.load_pending and self.load_delay:
+            self.regs[self.load_delay[0]] = self.load_delay[1]
+            self.load_delay = None
+        else:
+            self.load_pending = False
+
+    def raise_exception(self, code, in_delay_slot):
+        self.cause = (self.cause & ~(0x1F << 2) & ~(3 << 30)) | (code << 2)
+        if in_delay_slot:
+            self.cause |= 1 << 31
+        self.epc = (self.pc - 4) & MASK if in_delay_slot else self.pc
+        self.sr = (self.sr & ~0x3F) | ((self.sr << 2) & 0x3F)
+        self.pc = 0xBFC0_0180 if self.sr & SR_BEV else 0x8000_0080
+
+    # Execution
+
+    def tick(self):
+        in_delay_slot = self.branch_target is not None
+        target = self.branch_target
+        self.branch_target = None
+
+        word = self.read32(self.pc)
+        try:
+            self.execute(word, in_delay_slot)
+            raised = False
+        except Exception_ as e:
+            self.raise_exception(e.code, in_delay_slot)
+            raised = True
+
+        self.finish_load()
+
+        if raised:
+            return
+        if in_delay_slot:
+            self.pc = target
+        else:
+            self.pc = (self.pc + 4) & MASK
+
+    def branch(self, taken, target):
+        self.branch_target = (target if taken else self.pc + 8) & MASK
+
+    def execute(self, word, in_delay_slot):
+        op = word >> 26
+        rs = (word >> 21) & 0x1F
+        rt = (word >> 16) & 0x1F
+        rd = (word >> 11) & 0x1F
+        sa = (word >> 6) & 0x1F
+        funct = word & 0x3F
+        imm = word & 0xFFFF
+        simm = sext16(imm)
+        x = self.regs[rs]
+        y = self.regs[rt]
+        address = (x + simm) & MASK
+        relative = (self.pc + 4 + (simm << 2)) & MASK
+
+        if op == 0x00:
+            if funct == 0x00:
+                self.set(rd, y << sa)
+            elif funct == 0x02:
+                self.set(rd, y >> sa)
+            elif funct == 0x03:
+                self.set(rd, s32(y) >> sa)
+            elif funct == 0x04:
+                self.set(rd, y << (x & 0x1F))
+            elif funct == 0x06:
+                self.set(rd, y >> (x & 0x1F))
+            elif funct == 0x07:
+                self.set(rd, s32(y) >> (x & 0x1F))
+            elif funct == 0x08:
+                self.branch(True, x)
+            elif funct == 0x09:
+                self.set(rd, self.pc + 8)
+                self.branch(True, x)
+            elif funct == 0x0C:
+                raise Exception_(EXC_SYSCALL)
+            elif funct == 0x0D:
+                raise Exception_(EXC_BREAK)
+            elif funct == 0x10:
+                self.set(rd, self.hi)
+            elif funct == 0x11:
+                self.hi = x
+            elif funct == 0x12:
+                self.set(rd, self.lo)
+            elif funct == 0x13:
+                self.lo = x
+            elif funct == 0x18:
+                result = (s32(x) * s32(y)) & 0xFFFF_FFFF_FFFF_FFFF
+                self.hi, self.lo = result >> 32, result & MASK
+            elif funct == 0x19:
+                result = x * y
+                self.hi, self.lo = result >> 32, result & MASK
+            elif funct == 0x1A:
+                if y == 0:
+                    self.hi, self.lo = x, 0xFFFF_FFFF if s32(x) >= 0 else 1
+                elif x == 0x8000_0000 and y == MASK:
+                    self.hi, self.lo = 0, 0x8000_0000
+                else:
+                    quotient = abs(s32(x)) // abs(s32(y))
+                    if (s32(x) < 0) != (s32(y) < 0):
+                        quotient = -quotient
+                    self.lo = quotient & MASK
+                    self.hi = (s32(x) - quotient * s32(y)) & MASK
+            elif funct == 0x1B:
+                if y == 0:
+                    self.hi, self.lo = x, MASK
+                else:
+                    self.hi, self.lo = x % y, x // y
+            elif funct == 0x20:
+                result = s32(x) + s32(y)
+                if not -(1 << 31) <= result < (1 << 31):
+                    raise Exception_(EXC_OV)
+                self.set(rd, result)
+            elif funct == 0x21:
+                self.set(rd, x + y)
+            elif funct == 0x22:
+                result = s32(x) - s32(y)
+                if not -(1 << 31) <= result < (1 << 31):
+                    raise Exception_(EXC_OV)
+                self.set(rd, result)
+            elif funct == 0x23:
+                self.set(rd, x - y)
+            elif funct == 0x24:
+                self.set(rd, x & y)
+            elif funct == 0x25:
+                self.set(rd, x | y)
+            elif funct == 0x26:
+                self.set(rd, x ^ y)
+            elif funct == 0x27:
+                self.set(rd, ~(x | y))
+            elif funct == 0x2A:
+                self.set(rd, int(s32(x) < s32(y)))
+            elif funct == 0x2B:
+                self.set(rd, int(x < y))
+            else:
+                raise ValueError(f"unknown funct {funct:02X}")
+        elif op == 0x01:
+            link = rt & 0x1E == 0x10
+            taken = s32(x) < 0 if rt & 1 == 0 else s32(x) >= 0
+            if link:
+                self.set(31, self.pc + 8)
+            self.branch(taken, relative)
+        elif op in (0x02, 0x03):
+            if op == 0x03:
+                self.set(31, self.pc + 8)
+            self.branch(True, ((self.pc + 4) & 0xF000_0000) | ((word & 0x03FF_FFFF) << 2))
+        elif op == 0x04:
+            self.branch(x == y, relative)
+        elif op == 0x05:
+            self.branch(x != y, relative)
+        elif op == 0x06:
+            self.branch(s32(x) <= 0, relative)
+        elif op == 0x07:
+            self.branch(s32(x) > 0, relative)
+        elif op == 0x08:
+            result = s32(x) + simm
+            if not -(1 << 31) <= result < (1 << 31):
+                raise Exception_(EXC_OV)
+            self.set(rt, result)
+        elif op == 0x09:
+            self.set(rt, x + simm)
+        elif op == 0x0A:
+            self.set(rt, int(s32(x) < simm))
+        elif op == 0x0B:
+            self.set(rt, int(x < (simm & MASK)))
+        elif op == 0x0C:
+            self.set(rt, x & imm)
+        elif op == 0x0D:
+            self.set(rt, x | imm)
+        elif op == 0x0E:
+            self.set(rt, x ^ imm)
+        elif op == 0x0F:
+            self.set(rt, imm << 16)
+        elif op in (0x10, 0x12):
+            self.execute_cop(op & 3, word, rs, rt, rd)
+        elif op == 0x20:
+            value = self.read8(address)
+            self.load(rt, value - 0x100 if value & 0x80 else value)
+        elif op == 0x24:
+            self.load(rt, self.read8(address))
+        elif op in (0x21, 0x25):
+            if address & 1:
+                raise Exception_(EXC_ADEL)
+            value = self.read16(address)
+            self.load(rt, sext16(value) if op == 0x21 else value)
+        elif op == 0x23:
+            if address & 3:
+                raise Exception_(EXC_ADEL)
+            self.load(rt, self.read32(address))
+        elif op in (0x22, 0x26):
+            # LWL/LWR merge with the value that is still in the load delay slot
+            current = self.load_delay[1] if self.load_delay and self.load_delay[0] == rt else self.regs[rt]
+            word_value = self.read32(address & ~3)
+            offset = address & 3
+            if op == 0x22:
+                shift = 8 * (3 - offset)
+                merged = (current & (MASK >> (32 - shift) if shift else 0)) | ((word_value << shift) & MASK)
+            else:
+                shift = 8 * offset
+                keep = (MASK << (32 - shift)) & MASK if shift else 0
+                merged = (current & keep) | (word_value >> shift)
+            self.load(rt, merged)
+        elif op == 0x28:
+            self.write8(address, y)
+        elif op == 0x29:
+            if address & 1:
+                raise Exception_(EXC_ADES)
+            self.write16(address, y)
+        elif op == 0x2B:
+            if address & 3:
+                raise Exception_(EXC_ADES)
+            self.write32(address, y)
+        elif op in (0x2A, 0x2E):
+            offset = address & 3
+            if op == 0x2A:
+                for index in range(offset + 1):
+                    self.write8(address - index, y >> (8 * (3 - index)))
+            else:
+                for index in range(4 - offset):
+                    self.write8(address + index, y >> (8 * index))
+        elif op == 0x32:
+            if address & 3:
+                raise Exception_(EXC_ADEL)
+            self.cop2_data[rt] = self.read32(address)
+        elif op == 0x3A:
+            if address & 3:
+                raise Exception_(EXC_ADES)
+            self.write32(address, self.cop2_data.get(rt, 0))
+        else:
+            raise ValueError(f"unknown op {op:02X}")
+
+    def execute_cop(self, n, word, rs, rt, rd):
+        if n == 2 and rs & 0x10:
+            self.execute_gte(word)
+        elif n == 0 and rs == 0x10:
+            self.sr = (self.sr & ~0xF) | ((self.sr >> 2) & 0xF)
+        elif rs in (0x00, 0x02):
+            if n == 0:
+                value = {12: self.sr, 13: self.cause, 14: self.epc}[rd]
+            elif rs == 0x00:
+                value = self.cop2_data.get(rd, 0)
+            else:
+                value = self.cop2_control.get(rd, 0)
+            self.load(rt, value)
+        elif rs in (0x04, 0x06):
+            value = self.regs[rt]
+            if n == 0:
+                if rd == 12:
+                    self.sr = value
+                elif rd == 13:
+                    self.cause = value
+                elif rd == 14:
+                    self.epc = value
+            elif rs == 0x04:
+                self.cop2_data[rd] = value
+            else:
+                self.cop2_control[rd] = value
+        else:
+            raise ValueError(f"unknown cop{n} format {rs:02X}")
+
+    def execute_gte(self, word):
+        """Only the commands simple enough to state by hand, the GTE has its own suite"""
+        cmd = word & 0x3F
+        sf = 12 if word & (1 << 19) else 0
+        data = self.cop2_data
+        if cmd == 0x06:
+            sx = [sext16(data.get(12 + i, 0)) for i in range(3)]
+            sy = [sext16(data.get(12 + i, 0) >> 16) for i in range(3)]
+            data[24] = (sx[0] * sy[1] + sx[1] * sy[2] + sx[2] * sy[0]
+                        - sx[0] * sy[2] - sx[1] * sy[0] - sx[2] * sy[1]) & MASK
+            self.cop2_control[31] = 0
+        elif cmd == 0x28:
+            for i in range(3):
+                ir = sext16(data.get(9 + i, 0))
+                mac = (ir * ir) >> sf
+                data[25 + i] = mac & MASK
+                data[9 + i] = min(mac, 0x7FFF)
+            self.cop2_control[31] = 0
+        elif cmd in (0x2D, 0x2E):
+            zsf = sext16(self.cop2_control.get(29 if cmd == 0x2D else 30, 0))
+            first = 17 if cmd == 0x2D else 16
+            mac0 = zsf * sum(data.get(i, 0) & 0xFFFF for i in range(first, 20))
+            data[24] = mac0 & MASK
+            data[7] = max(0, min(mac0 >> 12, 0xFFFF))
+            self.cop2_control[31] = 0
+        # Other commands only have their CPU side checked here
+
+    def state(self, checked_cop2):
+        return make_state(
+            pc=self.pc,
+            regs=self.regs,
+            hi=self.hi,
+            lo=self.lo,
+            load_delay=list(self.load_delay) if self.load_delay else None,
+            sr=self.sr,
+            cause=self.cause,
+            epc=self.epc,
+            cop2_data={i: self.cop2_data.get(i, 0) for i in checked_cop2[0]},
+            cop2_control={i: self.cop2_control.get(i, 0) for i in checked_cop2[1]},
+            ram=[[0x8000_0000 | address, word] for address, word in sorted(self.ram.items()) if word],
+        )
+
+
+def make_state(pc, regs, hi=0, lo=0, load_delay=None, sr=0, cause=0, epc=0, cop2_data=None, cop2_control=None, ram=()):
+    state = {
+        "pc": pc,
+        "regs": [value & MASK for value in regs],
+        "hi": hi & MASK,
+        "lo": lo & MASK,
+        "load_delay": load_delay,
+        "cop0": {"sr": sr, "cause": cause, "epc": epc},
+        "ram": [list(entry) for entry in ram],
+    }
+    if cop2_data:
+        state["cop2_data"] = {str(i): value & MASK for i, value in sorted(cop2_data.items())}
+    if cop2_control:
+        state["cop2_control"] = {str(i): value & MASK for i, value in sorted(cop2_control.items())}
+    return state
+
+
+def vector(name, program, regs=None, ticks=None, hi=0, lo=0, load_delay=None, sr=0, cause=0, epc=0, data=(),
+           cop2_data=None, cop2_control=None):
+    """`program` is placed at BASE, `data` are (address, word) pairs, `regs` maps index to value"""
+    all_regs = [0] * 32
+    for index, value in (regs or {}).items():
+        all_regs[index] = value & MASK
+
+    ram = [[BASE + 4 * i, word] for i, word in enumerate(program) if word]
+    ram += [[address, word] for address, word in data]
+
+    initial = make_state(BASE, all_regs, hi, lo, load_delay, sr, cause, epc, cop2_data, cop2_control, ram)
+    cpu = Cpu(initial)
+    for _ in range(ticks or len(program)):
+        cpu.tick()
+    assert cpu.branch_target is None, f"{name} stops in the middle of a branch"
+
+    checked = (sorted(set(cop2_data or {}) | set(cpu.cop2_data)), sorted(set(cop2_control or {}) | set(cpu.cop2_control)))
+    return {"name": name, "ticks": ticks or len(program), "initial": initial, "final": cpu.state(checked)}
+
+
+# Corpus
+
+
+def alu():
+    v = []
+    for name, funct, x, y in [
+        ("add", 0x20, 5, 7),
+        ("add negative", 0x20, -5, 3),
+        ("addu wraps", 0x21, 0xFFFF_FFFF, 2),
+        ("sub", 0x22, 10, 3),
+        ("subu wraps", 0x23, 0, 1),
+        ("and", 0x24, 0xF0F0_F0F0, 0xFF00_FF00),
+        ("or", 0x25, 0xF0F0_0000, 0x0000_0F0F),
+        ("xor", 0x26, 0xFFFF_0000, 0xF0F0_F0F0),
+        ("nor", 0x27, 0x1234_0000, 0x0000_5678),
+        ("slt less", 0x2A, -1, 1),
+        ("slt greater", 0x2A, 1, -1),
+        ("sltu less", 0x2B, 1, 0xFFFF_FFFF),
+        ("sltu greater", 0x2B, 0xFFFF_FFFF, 1),
+    ]:
+        v.append(vector(name, [r_type(funct, rs=1, rt=2, rd=3)], {1: x, 2: y}))
+
+    v.append(vector("add overflow traps", [r_type(0x20, rs=1, rt=2, rd=3)], {1: 0x7FFF_FFFF, 2: 1, 3: 0xDEAD}, sr=1))
+    v.append(vector("add negative overflow traps", [r_type(0x20, rs=1, rt=2, rd=3)], {1: 0x8000_0000, 2: -1}))
+    v.append(vector("sub overflow traps", [r_type(0x22, rs=1, rt=2, rd=3)], {1: 0x8000_0000, 2: 1, 3: 7}))
+    v.append(vector("addu never traps", [r_type(0x21, rs=1, rt=2, rd=3)], {1: 0x7FFF_FFFF, 2: 1}))
+    v.append(vector("writes to $zero are dropped", [r_type(0x21, rs=1, rt=2, rd=0)], {1: 1, 2: 2}))
+    v.append(vector("rd = rs", [r_type(0x21, rs=1, rt=2, rd=1)], {1: 40, 2: 2}))
+
+    for name, op, x, imm in [
+        ("addi", 0x08, 100, -1),
+        ("addiu", 0x09, 0xFFFF_FFFF, 1),
+        ("addiu sign extends", 0x09, 0x1_0000, 0x8000),
+        ("slti", 0x0A, -2, -1),
+        ("slti false", 0x0A, 5, -1),
+        ("sltiu compares sign extended", 0x0B, 0x7FFF_FFFF, 0xFFFF),
+        ("sltiu false", 0x0B, 0xFFFF_FFFF, 0x8000),
+        ("andi zero extends", 0x0C, 0xFFFF_FFFF, 0x8001),
+        ("ori zero extends", 0x0D, 0x1234_0000, 0x8765),
+        ("xori zero extends", 0x0E, 0xFFFF_FFFF, 0xFFFF),
+        ("lui", 0x0F, 0x5555_5555, 0xBEEF),
+    ]:
+        v.append(vector(name, [i_type(op, 1, 2, imm)], {1: x, 2: 0xCAFE}))
+
+    v.append(vector("addi overflow traps", [i_type(0x08, 1, 2, 1)], {1: 0x7FFF_FFFF, 2: 3}, sr=0x0000_0401))
+    v.append(vector("addi overflow traps through the ROM vector", [i_type(0x08, 1, 2, -1)], {1: 0x8000_0000}, sr=SR_BEV))
+    return v
+
+
+def shift():
+    v = []
+    for name, funct, value, amount in [
+        ("sll", 0x00, 0x8000_0001, 4),
+        ("srl", 0x02, 0x8000_0010, 4),
+        ("sra", 0x03, 0x8000_0010, 4),
+        ("sra positive", 0x03, 0x4000_0010, 31),
+    ]:
+        v.append(vector(name, [r_type(funct, rt=2, rd=3, sa=amount)], {2: value}))
+
+    for name, funct, value, amount in [
+        ("sllv", 0x04, 0x0000_0003, 30),
+        ("sllv uses the low 5 bits", 0x04, 0x0000_0003, 0x21),
+        ("srlv", 0x06, 0xF000_0000, 28),
+        ("srlv uses the low 5 bits", 0x06, 0xF000_0000, 0xFFFF_FFE4),
+        ("srav", 0x07, 0x8000_0000, 31),
+        ("srav uses the low 5 bits", 0x07, 0x8000_0000, 0x40),
+    ]:
+        v.append(vector(name, [r_type(funct, rs=1, rt=2, rd=3)], {1: amount, 2: value}))
+    return v
+
+
+def muldiv():
+    v = []
+    for name, funct, x, y in [
+        ("mult", 0x18, 1234, 5678),
+        ("mult negative", 0x18, -3, 7),
+        ("mult min", 0x18, 0x8000_0000, 0x8000_0000),
+        ("multu", 0x19, 0xFFFF_FFFF, 0xFFFF_FFFF),
+        ("multu large", 0x19, 0x8000_0000, 2),
+        ("div", 0x1A, 100, 7),
+        ("div negative dividend", 0x1A, -100, 7),
+        ("div negative divisor", 0x1A, 100, -7),
+        ("div by zero, positive", 0x1A, 5, 0),
+        ("div by zero, negative", 0x1A, -5, 0),
+        ("div overflow", 0x1A, 0x8000_0000, -1),
+        ("divu", 0x1B, 0xFFFF_FFFF, 16),
+        ("divu by zero", 0x1B, 0x1234_5678, 0),
+    ]:
+        v.append(vector(name, [r_type(funct, rs=1, rt=2)], {1: x, 2: y}, hi=0xAAAA, lo=0xBBBB))
+
+    v.append(vector("mfhi", [r_type(0x10, rd=3)], {3: 1}, hi=0x1234_5678))
+    v.append(vector("mflo", [r_type(0x12, rd=3)], {3: 1}, lo=0x8765_4321))
+    v.append(vector("mthi", [r_type(0x11, rs=1)], {1: 0xDEAD_BEEF}))
+    v.append(vector("mtlo", [r_type(0x13, rs=1)], {1: 0xCAFE_BABE}))
+    return v
+
+
+def load_store():
+    v = []
+    data = [(DATA, 0x8281_7F80), (DATA + 4, 0x1234_5678)]
+
+    # A load lands after the instruction in its delay slot, which still sees the old value
+    for name, op, offset in [
+        ("lb", 0x20, 0),
+        ("lb sign extends", 0x20, 3),
+        ("lbu", 0x24, 3),
+        ("lh", 0x21, 0),
+        ("lh sign extends", 0x21, 2),
+        ("lhu", 0x25, 2),
+        ("lw", 0x23, 4),
+    ]:
+        program = [i_type(op, 1, 2, offset), r_type(0x21, rs=2, rd=3)]
+        v.append(vector(name, program, {1: DATA, 2: 0x1111}, data=data))
+
+    v.append(vector("lw leaves the load in flight", [i_type(0x23, 1, 2, 4)], {1: DATA, 2: 0x1111}, data=data))
+    v.append(vector("lw negative offset", [i_type(0x23, 1, 2, -4), NOP], {1: DATA + 8}, data=data))
+    v.append(vector("lw through KSEG1", [i_type(0x23, 1, 2, 4), NOP], {1: DATA | 0x2000_0000}, data=data))
+    v.append(vector("load to $zero is dropped", [i_type(0x23, 1, 0, 4), NOP], {1: DATA}, data=data))
+    v.append(vector("write in the delay slot wins over the load", [i_type(0x23, 1, 2, 4), i_type(0x09, 0, 2, 5), NOP],
+                    {1: DATA}, data=data))
+    v.append(vector("back to back loads to different registers", [i_type(0x23, 1, 2, 0), i_type(0x23, 1, 3, 4), NOP],
+                    {1: DATA}, data=data))
+    v.append(vector("back to back loads to the same register", [i_type(0x23, 1, 2, 0), i_type(0x23, 1, 2, 4), NOP],
+                    {1: DATA}, data=data))
+    v.append(vector("pending load from the initial state lands", [NOP], {2: 1}, load_delay=[2, 0xABCD]))
+
+    v.append(vector("lh unaligned traps", [i_type(0x21, 1, 2, 1)], {1: DATA, 2: 9}, data=data))
+    v.append(vector("lhu unaligned traps", [i_type(0x25, 1, 2, 3)], {1: DATA}, data=data))
+    v.append(vector("lw unaligned traps", [i_type(0x23, 1, 2, 2)], {1: DATA, 2: 9}, data=data))
+
+    for name, op, offset, value in [
+        ("sb", 0x28, 1, 0xAABB_CCDD),
+        ("sh", 0x29, 2, 0xAABB_CCDD),
+        ("sw", 0x2B, 4, 0xAABB_CCDD),
+        ("sw negative offset", 0x2B, -4, 0x0102_0304),
+    ]:
+        base = DATA + 8 if offset < 0 else DATA
+        v.append(vector(name, [i_type(op, 1, 2, offset)], {1: base, 2: value}, data=data))
+
+    v.append(vector("sh unaligned traps", [i_type(0x29, 1, 2, 1)], {1: DATA, 2: 0xFFFF}, data=data))
+    v.append(vector("sw unaligned traps", [i_type(0x2B, 1, 2, 3)], {1: DATA, 2: 0xFFFF}, data=data))
+    v.append(vector("sw with the cache isolated is dropped", [i_type(0x2B, 1, 2, 0)], {1: DATA, 2: 0xFFFF},
+                    sr=SR_ISC, data=data))
+    return v
+
+
+def unaligned():
+    v = []
+    data = [(DATA, 0x4433_2211), (DATA + 4, 0x8877_6655)]
+    for offset in range(4):
+        v.append(vector(f"lwl offset {offset}", [i_type(0x22, 1, 2, offset), NOP], {1: DATA, 2: 0xAABB_CCDD},
+                        data=data))
+        v.append(vector(f"lwr offset {offset}", [i_type(0x26, 1, 2, offset), NOP], {1: DATA, 2: 0xAABB_CCDD},
+                        data=data))
+        v.append(vector(f"swl offset {offset}", [i_type(0x2A, 1, 2, offset)], {1: DATA, 2: 0xAABB_CCDD}, data=data))
+        v.append(vector(f"swr offset {offset}", [i_type(0x2E, 1, 2, offset)], {1: DATA, 2: 0xAABB_CCDD}, data=data))
+
+    v.append(vector("lwr then lwl loads an unaligned word", [i_type(0x26, 1, 2, 1), i_type(0x22, 1, 2, 4), NOP],
+                    {1: DATA}, data=data))
+    v.append(vector("lwl merges with the load in flight", [i_type(0x23, 1, 2, 4), i_type(0x22, 1, 2, 1), NOP],
+                    {1: DATA, 2: 0xAABB_CCDD}, data=data))
+    v.append(vector("swr then swl stores an unaligned word", [i_type(0x2E, 1, 2, 1), i_type(0x2A, 1, 2, 4)],
+                    {1: DATA, 2: 0xAABB_CCDD}, data=data))
+    return v
+
+
+def branch():
+    v = []
+    after = BASE + 0x40
+    # The delay slot always runs, it bumps $3 so taken and not taken branches can be told apart
+    slot = i_type(0x09, 3, 3, 1)
+    for name, word, regs in [
+        ("beq taken", i_type(0x04, 1, 2, 0x0F), {1: 5, 2: 5}),
+        ("beq not taken", i_type(0x04, 1, 2, 0x0F), {1: 5, 2: 6}),
+        ("bne taken", i_type(0x05, 1, 2, 0x0F), {1: 5, 2: 6}),
+        ("bne not taken", i_type(0x05, 1, 2, 0x0F), {1: 5, 2: 5}),
+        ("blez taken on zero", i_type(0x06, 1, 0, 0x0F), {1: 0}),
+        ("blez not taken", i_type(0x06, 1, 0, 0x0F), {1: 1}),
+        ("bgtz taken", i_type(0x07, 1, 0, 0x0F), {1: 1}),
+        ("bgtz not taken on negative", i_type(0x07, 1, 0, 0x0F), {1: -1}),
+        ("bltz taken", i_type(0x01, 1, 0x00, 0x0F), {1: -1}),
+        ("bltz not taken on zero", i_type(0x01, 1, 0x00, 0x0F), {1: 0}),
+        ("bgez taken on zero", i_type(0x01, 1, 0x01, 0x0F), {1: 0}),
+        ("bgez not taken", i_type(0x01, 1, 0x01, 0x0F), {1: -1}),
+        ("bltzal taken", i_type(0x01, 1, 0x10, 0x0F), {1: -1}),
+        ("bltzal links when not taken", i_type(0x01, 1, 0x10, 0x0F), {1: 1}),
+        ("bgezal taken", i_type(0x01, 1, 0x11, 0x0F), {1: 1}),
+        ("bgezal links when not taken", i_type(0x01, 1, 0x11, 0x0F), {1: -1}),
+        ("beq backwards", i_type(0x04, 0, 0, -4), {}),
+    ]:
+        v.append(vector(name, [word, slot], regs))
+
+    v.append(vector("j", [j_type(0x02, after), slot], {}))
+    v.append(vector("jal", [j_type(0x03, after), slot], {31: 1}))
+    v.append(vector("jr", [r_type(0x08, rs=4), slot], {4: after}))
+    v.append(vector("jalr", [r_type(0x09, rs=4, rd=5), slot], {4: after}))
+    v.append(vector("jalr reads rs before linking into it", [r_type(0x09, rs=4, rd=4), slot], {4: after}))
+    v.append(vector("delay slot sees the link register", [j_type(0x03, after), r_type(0x21, rs=31, rd=3)], {}))
+    v.append(vector("load in the delay slot lands at the target", [j_type(0x02, after), i_type(0x23, 1, 2, 0), NOP],
+                    {1: DATA}, ticks=3, data=[(DATA, 0x600D_F00D)]))
+    return v
+
+
+def exception():
+    v = []
+    v.append(vector("syscall", [0x0000_000C], {4: 1}, sr=0x0000_0005))
+    v.append(vector("break", [0x0001_000D], {}, sr=0x0000_0003))
+    v.append(vector("syscall keeps the other cause bits", [0x0000_000C], {}, cause=0x0000_0300))
+    v.append(vector("syscall through the ROM vector", [0x0000_000C], {}, sr=SR_BEV | 1))
+    v.append(vector("syscall in a delay slot points EPC at the branch", [j_type(0x02, BASE + 0x40), 0x0000_000C],
+                    {}))
+    v.append(vector("overflow in a delay slot", [i_type(0x04, 0, 0, 0x0F), r_type(0x20, rs=1, rt=1, rd=2)],
+                    {1: 0x4000_0000, 2: 3}))
+    v.append(vector("rfe", [RFE], {}, sr=0x0000_003C))
+    v.append(vector("rfe keeps the old bits", [RFE], {}, sr=0x4000_0031))
+    return v
+
+
+def coprocessor():
+    v = []
+    v.append(vector("mfc0 sr", [cop(0, 0x00, 2, 12), NOP], {}, sr=0x1040_0401))
+    v.append(vector("mfc0 epc is delayed", [cop(0, 0x00, 2, 14), r_type(0x21, rs=2, rd=3)], {2: 7}, epc=0x8000_1234))
+    v.append(vector("mtc0 sr", [cop(0, 0x04, 2, 12)], {2: 0x4000_0000}))
+    v.append(vector("mtc0 cause", [cop(0, 0x04, 2, 13)], {2: 0x0000_0300}))
+    v.append(vector("mfc2", [cop(2, 0x00, 2, 9), NOP], {}, cop2_data={9: 0x1234}))
+    v.append(vector("mtc2", [cop(2, 0x04, 2, 8)], {2: 0x0800}, cop2_data={8: 0}))
+    v.append(vector("cfc2", [cop(2, 0x02, 2, 29), NOP], {}, cop2_control={29: 0x0155}))
+    v.append(vector("ctc2", [cop(2, 0x06, 2, 30)], {2: 0x0100}, cop2_control={30: 0}))
+    v.append(vector("lwc2", [i_type(0x32, 1, 9, 4)], {1: DATA}, data=[(DATA + 4, 0x0000_0ABC)], cop2_data={9: 0}))
+    v.append(vector("lwc2 unaligned traps", [i_type(0x32, 1, 9, 2)], {1: DATA}, cop2_data={9: 0x77}))
+    v.append(vector("swc2", [i_type(0x3A, 1, 24, 0)], {1: DATA}, cop2_data={24: 0x0BAD_CAFE}))
+    v.append(vector("swc2 unaligned traps", [i_type(0x3A, 1, 24, 1)], {1: DATA}, cop2_data={24: 0x0BAD_CAFE}))
+    return v
+
+
+def gte_commands():
+    v = []
+    cop2 = 0x4000_0000  # SR bit 30 enables COP2
+    v.append(vector("gte nclip", [gte(0x06)], {}, sr=cop2,
+                    cop2_data={12: 0, 13: 0x0000_000A, 14: 0x000A_0000, 24: 0}, cop2_control={31: 0}))
+    v.append(vector("gte sqr", [gte(0x28, sf=1)], {}, sr=cop2,
+                    cop2_data={9: 0x1000, 10: 0x2000, 11: 0xFFFF_F000, 25: 0, 26: 0, 27: 0}, cop2_control={31: 0}))
+    v.append(vector("gte avsz3", [gte(0x2D)], {}, sr=cop2,
+                    cop2_data={7: 0, 17: 100, 18: 200, 19: 300, 24: 0}, cop2_control={29: 0x155, 31: 0}))
+    v.append(vector("gte avsz4", [gte(0x2E)], {}, sr=cop2,
+                    cop2_data={7: 0, 16: 100, 17: 200, 18: 300, 19: 400, 24: 0}, cop2_control={30: 0x100, 31: 0}))
+
+    # Only the CPU side of the remaining commands, a GTE command is one instruction that moves on
+    for name, cmd in [
+        ("rtps", 0x01), ("op", 0x0C), ("dpcs", 0x10), ("intpl", 0x11), ("mvmva", 0x12), ("ncds", 0x13),
+        ("cdp", 0x14), ("ncdt", 0x16), ("nccs", 0x1B), ("cc", 0x1C), ("ncs", 0x1E), ("nct", 0x20),
+        ("dcpl", 0x29), ("dpct", 0x2A), ("rtpt", 0x30), ("gpf", 0x3D), ("gpl", 0x3E), ("ncct", 0x3F),
+    ]:
+        v.append(vector(f"gte {name}", [gte(cmd, sf=1, lm=1)], {2: 0x1234}, sr=cop2))
+    return v
+
+
+CORPUS = {
+    "alu": alu,
+    "shift": shift,
+    "muldiv": muldiv,
+    "load_store": load_store,
+    "unaligned": unaligned,
+    "branch": branch,
+    "exception": exception,
+    "coprocessor": coprocessor,
+    "gte": gte_commands,
+}
+
+
+def main():
+    directory = os.path.dirname(os.path.abspath(__file__))
+    for name, generate in CORPUS.items():
+        # One vector per line keeps diffs of a regenerated corpus readable
+        with open(os.path.join(directory, f"{name}.json"), "w") as f:
+            f.write("[\n" + ",\n".join(json.dumps(v) for v in generate()) + "\n]\n")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,356 @@
+//! Single-instruction CPU vectors, each sets up a `Cpu`, ticks it a few times and checks the whole state
+//!
+//! The corpus in `cpu/` is written by `cpu/generate.py`. Vectors from elsewhere, like logs of real
+//! hardware, run as well when `PSX_CPU_VECTORS` points at a JSON file or a directory of them.
+//!
+//! A vector looks like this, RAM is zero unless listed and every word that is not listed in `final`
+//! has to still be zero:
+//!
+//! ```json
+//! {
+//!   "name": "addu", "ticks": 1,
+//!   "initial": { "pc": 2147549184, "regs": [0, 5, 7, ...], "hi": 0, "lo": 0, "load_delay": null,
+//!                "cop0": { "sr": 0, "cause": 0, "epc": 0 }, "ram": [[2147549184, 4333601]] },
+//!   "final": { ... }
+//! }
+//! ```
+//!
+//! `load_delay` is a `[register, value]` pair that lands after the next instruction. `cop2_data` and
+//! `cop2_control` map register indices to values, only the listed registers are set and checked.
+
+use psx_core::cpu::Cpu;
+use psx_core::cpu::cop::cop0::Cop0;
+use psx_core::cpu::cop::cop2::Cop2;
+use psx_core::cpu::cop::registers::{CauseRegister, StatusRegister};
+use psx_core::cpu::decoder::{Instruction, Opcode};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::mem::{Discriminant, discriminant};
+use std::path::{Path, PathBuf};
+
+const CORPUS: &[&str] = &[
+    "alu",
+    "shift",
+    "muldiv",
+    "load_store",
+    "unaligned",
+    "branch",
+    "exception",
+    "coprocessor",
+    "gte",
+];
+
+#[derive(Deserialize)]
+struct Vector {
+    name: String,
+    ticks: usize,
+    initial: State,
+    #[serde(rename = "final")]
+    expected: State,
+}
+
+#[derive(Deserialize)]
+struct State {
+    pc: u32,
+    regs: [u32; 32],
+    hi: u32,
+    lo: u32,
+    #[serde(default)]
+    load_delay: Option<(u8, u32)>,
+    #[serde(default)]
+    cop0: Cop0State,
+    #[serde(default)]
+    cop2_data: BTreeMap<u8, u32>,
+    #[serde(default)]
+    cop2_control: BTreeMap<u8, u32>,
+    #[serde(default)]
+    ram: Vec<(u32, u32)>,
+}
+
+#[derive(Deserialize, Default)]
+struct Cop0State {
+    sr: u32,
+    cause: u32,
+    epc: u32,
+}
+
+fn corpus_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cpu")
+        .join(format!("{}.json", name))
+}
+
+fn read_vectors(path: &Path) -> Vec<Vector> {
+    let json = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("Failed to read {:?}: {}", path, e));
+    serde_json::from_str(&json).unwrap_or_else(|e| panic!("Failed to parse {:?}: {}", path, e))
+}
+
+fn external_vectors() -> Vec<PathBuf> {
+    let Some(path) = std::env::var_os("PSX_CPU_VECTORS").map(PathBuf::from) else {
+        return Vec::new();
+    };
+
+    if path.is_file() {
+        return vec![path];
+    }
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&path)
+        .unwrap_or_else(|e| panic!("Failed to read {:?}: {}", path, e))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn ram_offset(cpu: &Cpu, address: u32) -> usize {
+    (address & 0x1FFF_FFFF & (cpu.mmu.ram_size().bytes() - 1)) as usize
+}
+
+fn load(cpu: &mut Cpu, state: &State) {
+    let ram_size = cpu.mmu.ram_size().bytes() as usize;
+    cpu.mmu.memory[..ram_size].fill(0);
+    for &(address, word) in &state.ram {
+        let offset = ram_offset(cpu, address);
+        cpu.mmu.memory[offset..offset + 4].copy_from_slice(&word.to_le_bytes());
+    }
+
+    cpu.pc = state.pc;
+    cpu.registers = state.regs;
+    cpu.hi = state.hi;
+    cpu.lo = state.lo;
+    cpu.load_delay = state.load_delay.map(|(register, value)| (register as usize, value));
+    cpu.load_delay_pending = false;
+    cpu.delay_slot = None;
+
+    cpu.cop0 = Cop0::new();
+    cpu.cop0.sr = StatusRegister(state.cop0.sr);
+    cpu.cop0.cause = CauseRegister(state.cop0.cause);
+    cpu.cop0.epc = state.cop0.epc;
+
+    cpu.cop2 = Cop2::new();
+    for (&register, &value) in &state.cop2_data {
+        cpu.cop2.write_data_register(register, value);
+    }
+    for (&register, &value) in &state.cop2_control {
+        cpu.cop2.write_control_register(register, value);
+    }
+}
+
+/// Every difference between the CPU and `expected`, empty if they match
+fn compare(cpu: &mut Cpu, expected: &State) -> Vec<String> {
+    let mut differences = Vec::new();
+    let mut check = |what: &str, actual: u32, expected: u32| {
+        if actual != expected {
+            differences.push(format!("{}: {:08X}, expected {:08X}", what, actual, expected));
+        }
+    };
+
+    check("pc", cpu.pc, expected.pc);
+    for (index, (&actual, &expected)) in cpu.registers.iter().zip(&expected.regs).enumerate() {
+        check(&format!("r{}", index), actual, expected);
+    }
+    check("hi", cpu.hi, expected.hi);
+    check("lo", cpu.lo, expected.lo);
+    check("sr", cpu.cop0.sr.0, expected.cop0.sr);
+    check("cause", cpu.cop0.cause.0, expected.cop0.cause);
+    check("epc", cpu.cop0.epc, expected.cop0.epc);
+
+    for (&register, &value) in &expected.cop2_data {
+        check(
+            &format!("cop2 data {}", register),
+            cpu.cop2.read_data_register(register),
+            value,
+        );
+    }
+    for (&register, &value) in &expected.cop2_control {
+        check(
+            &format!("cop2 control {}", register),
+            cpu.cop2.read_control_register(register),
+            value,
+        );
+    }
+
+    for &(address, word) in &expected.ram {
+        let offset = ram_offset(cpu, address);
+        let actual = u32::from_le_bytes(cpu.mmu.memory[offset..offset + 4].try_into().unwrap());
+        check(&format!("ram {:08X}", address), actual, word);
+
+        // Cleared so the scan below only finds writes nobody expected
+        cpu.mmu.memory[offset..offset + 4].fill(0);
+    }
+
+    let actual_load_delay = cpu.load_delay.map(|(register, value)| (register as u8, value));
+    if actual_load_delay != expected.load_delay {
+        differences.push(format!(
+            "load delay: {:X?}, expected {:X?}",
+            actual_load_delay, expected.load_delay
+        ));
+    }
+
+    let ram_size = cpu.mmu.ram_size().bytes() as usize;
+    if let Some(offset) = cpu.mmu.memory[..ram_size].iter().position(|&byte| byte != 0) {
+        differences.push(format!("ram {:08X}: unexpected write", offset & !3));
+    }
+
+    differences
+}
+
+/// Run every vector in `path`, returns the failures
+fn run_file(cpu: &mut Cpu, path: &Path) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    for vector in read_vectors(path) {
+        load(cpu, &vector.initial);
+        for _ in 0..vector.ticks {
+            let _ = cpu.tick();
+        }
+
+        let differences = compare(cpu, &vector.expected);
+        if !differences.is_empty() {
+            failures.push(format!(
+                "{:?} {}:\n    {}",
+                path.file_name().unwrap(),
+                vector.name,
+                differences.join("\n    ")
+            ));
+        }
+    }
+
+    failures
+}
+
+#[test]
+fn test_cpu_vectors() {
+    // The bus backs the whole address space, so one CPU is shared by every vector
+    let mut cpu = Cpu::new();
+
+    let paths = CORPUS.iter().map(|name| corpus_path(name)).chain(external_vectors());
+    let failures: Vec<String> = paths.flat_map(|path| run_file(&mut cpu, &path)).collect();
+
+    assert!(
+        failures.is_empty(),
+        "{} vectors failed:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_corpus_covers_every_opcode() {
+    let covered: Vec<Discriminant<Opcode>> = CORPUS
+        .iter()
+        .flat_map(|name| read_vectors(&corpus_path(name)))
+        .flat_map(|vector| {
+            let pc = vector.initial.pc;
+            vector
+                .initial
+                .ram
+                .into_iter()
+                .filter(move |&(address, _)| address == pc)
+                .map(|(_, word)| discriminant(&Instruction::decode(word).opcode))
+        })
+        .collect();
+
+    let every_opcode = [
+        Opcode::Add,
+        Opcode::AddUnsigned,
+        Opcode::AddImmediate,
+        Opcode::AddImmediateUnsigned,
+        Opcode::Sub,
+        Opcode::SubUnsigned,
+        Opcode::Multiply,
+        Opcode::MultiplyUnsigned,
+        Opcode::Divide,
+        Opcode::DivideUnsigned,
+        Opcode::And,
+        Opcode::AndImmediate,
+        Opcode::Or,
+        Opcode::OrImmediate,
+        Opcode::Xor,
+        Opcode::XorImmediate,
+        Opcode::Nor,
+        Opcode::SetLessThan,
+        Opcode::SetLessThanImmediate,
+        Opcode::SetLessThanUnsigned,
+        Opcode::SetLessThanImmediateUnsigned,
+        Opcode::ShiftLeftLogical,
+        Opcode::ShiftRightLogical,
+        Opcode::ShiftRightArithmetic,
+        Opcode::ShiftLeftLogicalVariable,
+        Opcode::ShiftRightLogicalVariable,
+        Opcode::ShiftRightArithmeticVariable,
+        Opcode::LoadByte,
+        Opcode::LoadByteUnsigned,
+        Opcode::LoadHalfword,
+        Opcode::LoadHalfwordUnsigned,
+        Opcode::LoadWord,
+        Opcode::LoadWordLeft,
+        Opcode::LoadWordRight,
+        Opcode::LoadUpperImmediate,
+        Opcode::StoreByte,
+        Opcode::StoreHalfword,
+        Opcode::StoreWord,
+        Opcode::StoreWordLeft,
+        Opcode::StoreWordRight,
+        Opcode::BranchEqual,
+        Opcode::BranchNotEqual,
+        Opcode::BranchGreaterThanZero,
+        Opcode::BranchLessEqualZero,
+        Opcode::BranchGreaterEqualZero,
+        Opcode::BranchLessThanZero,
+        Opcode::BranchLessThanZeroAndLink,
+        Opcode::BranchGreaterEqualZeroAndLink,
+        Opcode::Jump,
+        Opcode::JumpAndLink,
+        Opcode::JumpRegister,
+        Opcode::JumpAndLinkRegister,
+        Opcode::SystemCall,
+        Opcode::Break,
+        Opcode::MoveFromHi,
+        Opcode::MoveToHi,
+        Opcode::MoveFromLo,
+        Opcode::MoveToLo,
+        Opcode::MoveControlFromCoprocessor(2),
+        Opcode::MoveControlToCoprocessor(2),
+        Opcode::MoveFromCoprocessor(0),
+        Opcode::MoveToCoprocessor(0),
+        Opcode::LoadWordToCoprocessor(2),
+        Opcode::StoreWordFromCoprocessor(2),
+        Opcode::ReturnFromException,
+        Opcode::GteRtps,
+        Opcode::GteNclip,
+        Opcode::GteOp,
+        Opcode::GteDpcs,
+        Opcode::GteIntpl,
+        Opcode::GteMvmva,
+        Opcode::GteNcds,
+        Opcode::GteCdp,
+        Opcode::GteNcdt,
+        Opcode::GteNccs,
+        Opcode::GteCc,
+        Opcode::GteNcs,
+        Opcode::GteNct,
+        Opcode::GteSqr,
+        Opcode::GteDcpl,
+        Opcode::GteDpct,
+        Opcode::GteAvsz3,
+        Opcode::GteAvsz4,
+        Opcode::GteRtpt,
+        Opcode::GteGpf,
+        Opcode::GteGpl,
+        Opcode::GteNcct,
+    ];
+
+    let missing: Vec<String> = every_opcode
+        .iter()
+        .filter(|opcode| !covered.contains(&discriminant(*opcode)))
+        .map(|opcode| {
+            let mut instruction = Instruction::nop();
+            instruction.opcode = *opcode;
+            instruction.to_string()
+        })
+        .collect();
+
+    assert!(missing.is_empty(), "No vector starts with: {}", missing.join(", "));
+}
@@ -0,0 +1,98 @@
+//! PS-X EXE header parsing and validation.
+
+use psx_core::exe::{Exe, ExeError};
+
+const HEADER_SIZE: usize = 0x800;
+
+/// Build an EXE with a text section of `text_size` bytes, followed by `padding` bytes that are not part of it
+fn build(text_size: u32, padding: usize) -> Vec<u8> {
+    let mut exe = vec![0; HEADER_SIZE];
+    set_field(&mut exe, 0x10, 0x8001_0000); // pc
+    set_field(&mut exe, 0x14, 0x8003_0000); // gp
+    set_field(&mut exe, 0x18, 0x8001_0000); // t_addr
+    set_field(&mut exe, 0x1C, text_size); // t_size
+    set_field(&mut exe, 0x28, 0x8002_0000); // b_addr
+    set_field(&mut exe, 0x2C, 0x0000_0100); // b_size
+    set_field(&mut exe, 0x30, 0x801F_FF00); // sp/fp base
+    set_field(&mut exe, 0x34, 0x0000_00F0); // sp/fp offset
+    exe[0x00..0x08].copy_from_slice(b"PS-X EXE");
+    exe[0x4C..0x55].copy_from_slice(b"Homebrew!");
+
+    exe.extend(std::iter::repeat_n(0xAA, text_size as usize));
+    exe.extend(std::iter::repeat_n(0x55, padding));
+    exe
+}
+
+fn set_field(exe: &mut [u8], offset: usize, value: u32) {
+    exe[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[test]
+fn test_parse() {
+    let exe = Exe::parse(build(0x1000, 0x123)).expect("valid EXE");
+
+    assert_eq!(exe.entry_point, 0x8001_0000);
+    assert_eq!(exe.initial_gp, 0x8003_0000);
+    assert_eq!(exe.map_address, 0x8001_0000);
+    assert_eq!(exe.bss_address, 0x8002_0000);
+    assert_eq!(exe.bss_size, 0x100);
+    assert_eq!(exe.sp(), 0x801F_FFF0);
+    assert_eq!(exe.fp(), 0x801F_FFF0);
+    assert_eq!(exe.license, "Homebrew!");
+    assert_eq!(exe.header.len(), HEADER_SIZE);
+
+    // The padding after t_size bytes is not loaded
+    assert_eq!(exe.data.len(), 0x1000);
+    assert!(exe.data.iter().all(|&b| b == 0xAA));
+}
+
+#[test]
+fn test_too_short() {
+    assert_eq!(Exe::parse(Vec::new()).err(), Some(ExeError::TooShort(0)));
+
+    let mut exe = build(0, 0);
+    exe.truncate(HEADER_SIZE - 1);
+    assert_eq!(Exe::parse(exe).err(), Some(ExeError::TooShort(HEADER_SIZE - 1)));
+}
+
+#[test]
+fn test_invalid_magic() {
+    let mut exe = build(0x800, 0);
+    exe[0x00..0x08].copy_from_slice(b"PS-X EXF");
+    assert_eq!(Exe::parse(exe).err(), Some(ExeError::InvalidMagic));
+
+    assert_eq!(Exe::parse(vec![0; 0x1000]).err(), Some(ExeError::InvalidMagic));
+}
+
+#[test]
+fn test_invalid_sizes() {
+    let mut exe = build(0x800, 0);
+    set_field(&mut exe, 0x1C, 0x1000);
+    assert_eq!(
+        Exe::parse(exe).err(),
+        Some(ExeError::Truncated {
+            expected: 0x1000,
+            actual: 0x800
+        })
+    );
+
+    let mut exe = build(0x800, 0);
+    set_field(&mut exe, 0x1C, 0xFFFF_F800);
+    assert_eq!(
+        Exe::parse(exe).err(),
+        Some(ExeError::TooLarge {
+            section: "text",
+            size: 0xFFFF_F800
+        })
+    );
+
+    let mut exe = build(0x800, 0);
+    set_field(&mut exe, 0x2C, 0x0100_0000);
+    assert_eq!(
+        Exe::parse(exe).err(),
+        Some(ExeError::TooLarge {
+            section: "BSS",
+            size: 0x0100_0000
+        })
+    );
+}
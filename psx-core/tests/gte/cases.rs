@@ -0,0 +1,696 @@
+//! Hand-computed cases, every register that is not listed in `base` or `input` starts at zero
+
+use crate::reference::*;
+
+pub struct Case {
+    pub name: &'static str,
+    pub command: u32,
+    /// Written before `input`
+    pub base: &'static [(usize, u32)],
+    pub input: &'static [(usize, u32)],
+    pub output: &'static [(usize, u32)],
+}
+
+const IDENTITY: [(usize, u32); 5] = [
+    (RT11RT12, 0x1000),
+    (RT13RT21, 0),
+    (RT22RT23, 0x1000),
+    (RT31RT32, 0),
+    (RT33, 0x1000),
+];
+
+const LIGHT_IDENTITY: [(usize, u32); 5] = [
+    (L11L12, 0x1000),
+    (L13L21, 0),
+    (L22L23, 0x1000),
+    (L31L32, 0),
+    (L33, 0x1000),
+];
+
+const COLOR_IDENTITY: [(usize, u32); 5] = [
+    (LR1LR2, 0x1000),
+    (LR3LG1, 0),
+    (LG2LG3, 0x1000),
+    (LB1LB2, 0),
+    (LB3, 0x1000),
+];
+
+/// Lit normal of (100h, 200h, 300h) through identity matrices ends up at (810h, 820h, 830h)
+const LIGHTING: [(usize, u32); 16] = [
+    LIGHT_IDENTITY[0],
+    LIGHT_IDENTITY[1],
+    LIGHT_IDENTITY[2],
+    LIGHT_IDENTITY[3],
+    LIGHT_IDENTITY[4],
+    COLOR_IDENTITY[0],
+    COLOR_IDENTITY[1],
+    COLOR_IDENTITY[2],
+    COLOR_IDENTITY[3],
+    COLOR_IDENTITY[4],
+    (RBK, 0x710),
+    (GBK, 0x620),
+    (BBK, 0x530),
+    (VXY0, 0x0200_0100),
+    (VZ0, 0x300),
+    (VXY1, 0x0120_0110),
+];
+
+pub const COMMAND_CASES: &[Case] = &[
+    Case {
+        name: "rtps identity",
+        command: RTPS | SF,
+        base: &IDENTITY,
+        input: &[(VXY0, 0x0020_0010), (VZ0, 0x200), (H, 0x200), (DQA, 0x100)],
+        output: &[
+            (MAC1, 0x10),
+            (MAC2, 0x20),
+            (MAC3, 0x200),
+            (IR1, 0x10),
+            (IR2, 0x20),
+            (IR3, 0x200),
+            (SZ3, 0x200),
+            (SXY2, 0x0020_0010),
+            (MAC0, 0x0100_0000),
+            (IR0, 0x1000),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "rtps translation and offset",
+        command: RTPS | SF,
+        base: &IDENTITY,
+        input: &[
+            (VXY0, 0xFFF0_0010),
+            (VZ0, 0x100),
+            (TRX, 0x10),
+            (TRY, 0x20),
+            (TRZ, 0x100),
+            (H, 0x100),
+            (OFX, 0x10_0000),
+            (OFY, 0xFFF0_0000),
+            (DQA, 0x80),
+            (DQB, 0x100_0000),
+        ],
+        output: &[
+            (MAC1, 0x20),
+            (MAC2, 0x10),
+            (MAC3, 0x200),
+            (SZ3, 0x200),
+            (SXY2, 0xFFF8_0020),
+            (MAC0, 0x140_0000),
+            (IR0, 0x1000),
+            (FLAG, flag::IR0),
+        ],
+    },
+    Case {
+        name: "rtpt",
+        command: RTPT | SF,
+        base: &IDENTITY,
+        input: &[
+            (VXY0, 0x0020_0010),
+            (VZ0, 0x200),
+            (VXY1, 0x0020_0018),
+            (VZ1, 0x200),
+            (VXY2, 0x0030_0028),
+            (VZ2, 0x400),
+            (SZ3, 0x55),
+            (SXY2, 0x1234_5678),
+            (H, 0x200),
+            (DQA, 0x100),
+        ],
+        output: &[
+            (SXY0, 0x0020_0010),
+            (SXY1, 0x0020_0018),
+            (SXY2, 0x0018_0014),
+            (SZ0, 0x55),
+            (SZ1, 0x200),
+            (SZ2, 0x200),
+            (SZ3, 0x400),
+            (MAC1, 0x28),
+            (MAC2, 0x30),
+            (MAC3, 0x400),
+            (MAC0, 0x80_0000),
+            (IR0, 0x800),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "nclip",
+        command: NCLIP,
+        base: &[],
+        input: &[(SXY1, 0x10), (SXY2, 0x0010_0000)],
+        output: &[(MAC0, 0x100), (FLAG, 0)],
+    },
+    Case {
+        name: "op",
+        command: OP | SF,
+        base: &[],
+        input: &[
+            (RT11RT12, 0x1000),
+            (RT22RT23, 0x7000_2000),
+            (RT33, 0x1000),
+            (IR1, 0x100),
+            (IR2, 0x200),
+            (IR3, 0x200),
+        ],
+        output: &[
+            (MAC1, 0x200),
+            (MAC2, 0xFFFF_FF00),
+            (MAC3, 0),
+            (IR1, 0x200),
+            (IR2, 0xFFFF_FF00),
+            (IR3, 0),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "sqr",
+        command: SQR | SF,
+        base: &[],
+        input: &[(IR1, 0x1000), (IR2, 0xFFFF_F800), (IR3, 0x2000)],
+        output: &[(MAC1, 0x1000), (MAC2, 0x400), (MAC3, 0x4000), (IR2, 0x400), (FLAG, 0)],
+    },
+    Case {
+        name: "avsz3",
+        command: AVSZ3,
+        base: &[],
+        input: &[(SZ0, 0x1000), (SZ1, 0x2AA), (SZ2, 0x2AA), (SZ3, 0x2AA), (ZSF3, 0x100)],
+        output: &[(MAC0, 0x7_FE00), (OTZ, 0x7F), (FLAG, 0)],
+    },
+    Case {
+        name: "avsz4",
+        command: AVSZ4,
+        base: &[],
+        input: &[(SZ0, 0x100), (SZ1, 0x200), (SZ2, 0x300), (SZ3, 0x400), (ZSF4, 0x80)],
+        output: &[(MAC0, 0x5_0000), (OTZ, 0x50), (FLAG, 0)],
+    },
+    Case {
+        name: "mvmva rt v0 tr",
+        command: mvmva(0, 0, 0) | SF,
+        base: &IDENTITY,
+        input: &[(VXY0, 0x0200_0100), (VZ0, 0x300), (TRX, 1), (TRY, 2), (TRZ, 3)],
+        output: &[(MAC1, 0x101), (MAC2, 0x202), (MAC3, 0x303), (IR3, 0x303), (FLAG, 0)],
+    },
+    Case {
+        name: "mvmva llm ir bk",
+        command: mvmva(1, 3, 1) | SF,
+        base: &LIGHT_IDENTITY,
+        input: &[(IR1, 0x10), (IR2, 0x20), (IR3, 0x30), (RBK, 1), (GBK, 2), (BBK, 3)],
+        output: &[(MAC1, 0x11), (MAC2, 0x22), (MAC3, 0x33), (FLAG, 0)],
+    },
+    Case {
+        name: "mvmva lcm v1 without translation",
+        command: mvmva(2, 1, 3),
+        base: &COLOR_IDENTITY,
+        input: &[(VXY1, 0x0002_0001), (VZ1, 3), (TRX, 5)],
+        output: &[(MAC1, 0x1000), (MAC2, 0x2000), (MAC3, 0x3000), (FLAG, 0)],
+    },
+    Case {
+        // The far color and the first column only leave the IR1 flag behind
+        name: "mvmva far color bug",
+        command: mvmva(0, 0, 2) | SF,
+        base: &IDENTITY,
+        input: &[(VXY0, 0x0200_0100), (VZ0, 0x300), (RFC, 0x8000)],
+        output: &[
+            (MAC1, 0),
+            (MAC2, 0x200),
+            (MAC3, 0x300),
+            (IR1, 0),
+            (FLAG, flag::ERROR | flag::IR1),
+        ],
+    },
+    Case {
+        // Matrix 3 is made of -R*16, R*16, IR0, RT13 and RT22
+        name: "mvmva garbage matrix",
+        command: mvmva(3, 0, 3) | SF,
+        base: &[],
+        input: &[
+            (RT13RT21, 0x1000),
+            (RT22RT23, 0x800),
+            (RGBC, 0x40),
+            (VXY0, 0x0200_0100),
+            (VZ0, 0x300),
+        ],
+        output: &[(MAC1, 0x40), (MAC2, 0x600), (MAC3, 0x300), (FLAG, 0)],
+    },
+    Case {
+        name: "dpcs",
+        command: DPCS | SF,
+        base: &[],
+        input: &[(RGBC, 0x3000_0080), (RFC, 0xFE0), (GFC, 0x800), (IR0, 0x800)],
+        output: &[
+            (MAC1, 0xBF0),
+            (MAC2, 0x400),
+            (MAC3, 0),
+            (IR1, 0xBF0),
+            (RGB2, 0x3000_40BF),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        // Takes RGB0 each time, which the pushes move along
+        name: "dpct",
+        command: DPCT | SF,
+        base: &[],
+        input: &[
+            (RGBC, 0x3000_0000),
+            (RGB0, 0x80),
+            (RGB1, 0x40),
+            (RGB2, 0),
+            (RFC, 0xFE0),
+            (GFC, 0x800),
+            (IR0, 0x800),
+        ],
+        output: &[
+            (RGB0, 0x3000_40BF),
+            (RGB1, 0x3000_409F),
+            (RGB2, 0x3000_407F),
+            (MAC1, 0x7F0),
+            (MAC2, 0x400),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "intpl",
+        command: INTPL | SF,
+        base: &[],
+        input: &[
+            (RGBC, 0x3000_0000),
+            (IR1, 0x800),
+            (IR3, 0x100),
+            (RFC, 0x1000),
+            (GFC, 0x1000),
+            (IR0, 0x800),
+        ],
+        output: &[
+            (MAC1, 0xC00),
+            (MAC2, 0x800),
+            (MAC3, 0x80),
+            (RGB2, 0x3008_80C0),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "dcpl",
+        command: DCPL | SF,
+        base: &[],
+        input: &[
+            (RGBC, 0x3020_4080),
+            (IR1, 0x1000),
+            (IR2, 0x1000),
+            (IR3, 0x1000),
+            (RFC, 0x1000),
+            (IR0, 0x800),
+        ],
+        output: &[
+            (MAC1, 0xC00),
+            (MAC2, 0x200),
+            (MAC3, 0x100),
+            (RGB2, 0x3010_20C0),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "ncs",
+        command: NCS | SF,
+        base: &LIGHTING,
+        input: &[(RGBC, 0x3000_0000)],
+        output: &[
+            (MAC1, 0x810),
+            (MAC2, 0x820),
+            (MAC3, 0x830),
+            (IR1, 0x810),
+            (RGB2, 0x3083_8281),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "nct",
+        command: NCT | SF,
+        base: &LIGHTING,
+        input: &[(VZ1, 0x130), (RGBC, 0x3000_0000)],
+        output: &[
+            (RGB0, 0x3083_8281),
+            (RGB1, 0x3066_7482),
+            (RGB2, 0x3053_6271),
+            (MAC1, 0x710),
+            (MAC2, 0x620),
+            (MAC3, 0x530),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "nccs",
+        command: NCCS | SF,
+        base: &LIGHTING,
+        input: &[(RGBC, 0x3080_8080)],
+        output: &[
+            (MAC1, 0x408),
+            (MAC2, 0x410),
+            (MAC3, 0x418),
+            (RGB2, 0x3041_4140),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "ncct",
+        command: NCCT | SF,
+        base: &LIGHTING,
+        input: &[(VZ1, 0x130), (RGBC, 0x3080_8080)],
+        output: &[
+            (RGB0, 0x3041_4140),
+            (RGB1, 0x3033_3A41),
+            (RGB2, 0x3029_3138),
+            (MAC1, 0x388),
+            (MAC2, 0x310),
+            (MAC3, 0x298),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "cc",
+        command: CC | SF,
+        base: &COLOR_IDENTITY,
+        input: &[(IR1, 0x810), (IR2, 0x820), (IR3, 0x830), (RGBC, 0x3080_8080)],
+        output: &[
+            (MAC1, 0x408),
+            (MAC2, 0x410),
+            (MAC3, 0x418),
+            (RGB2, 0x3041_4140),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "ncds",
+        command: NCDS | SF,
+        base: &LIGHTING,
+        input: &[(RGBC, 0x3080_8080), (IR0, 0x800)],
+        output: &[
+            (MAC1, 0x204),
+            (MAC2, 0x208),
+            (MAC3, 0x20C),
+            (RGB2, 0x3020_2020),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "ncdt",
+        command: NCDT | SF,
+        base: &LIGHTING,
+        input: &[(VZ1, 0x130), (RGBC, 0x3080_8080), (IR0, 0x800)],
+        output: &[
+            (RGB0, 0x3020_2020),
+            (RGB1, 0x3019_1D20),
+            (RGB2, 0x3014_181C),
+            (MAC1, 0x1C4),
+            (MAC2, 0x188),
+            (MAC3, 0x14C),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "cdp",
+        command: CDP | SF,
+        base: &COLOR_IDENTITY,
+        input: &[
+            (IR1, 0x810),
+            (IR2, 0x820),
+            (IR3, 0x830),
+            (RGBC, 0x3080_8080),
+            (IR0, 0x800),
+        ],
+        output: &[
+            (MAC1, 0x204),
+            (MAC2, 0x208),
+            (MAC3, 0x20C),
+            (RGB2, 0x3020_2020),
+            (FLAG, 0),
+        ],
+    },
+    Case {
+        name: "gpf",
+        command: GPF | SF,
+        base: &[],
+        input: &[
+            (RGBC, 0x2C00_0000),
+            (IR0, 0x1000),
+            (IR1, 0x800),
+            (IR2, 0x1000),
+            (IR3, 0xFFFF_FFF0),
+        ],
+        output: &[
+            (MAC1, 0x800),
+            (MAC2, 0x1000),
+            (MAC3, 0xFFFF_FFF0),
+            (RGB2, 0x2C00_FF80),
+            (FLAG, flag::COLOR_G | flag::COLOR_B),
+        ],
+    },
+    Case {
+        name: "gpl",
+        command: GPL | SF,
+        base: &[],
+        input: &[
+            (RGBC, 0x2C00_0000),
+            (MAC1, 0x100),
+            (MAC2, 0x200),
+            (MAC3, 0x300),
+            (IR0, 0x800),
+            (IR1, 0x20),
+            (IR2, 0x40),
+            (IR3, 0x60),
+        ],
+        output: &[
+            (MAC1, 0x110),
+            (MAC2, 0x220),
+            (MAC3, 0x330),
+            (RGB2, 0x2C33_2211),
+            (FLAG, 0),
+        ],
+    },
+];
+
+pub const FLAG_CASES: &[Case] = &[
+    Case {
+        name: "divide overflow",
+        command: RTPS | SF,
+        base: &IDENTITY,
+        input: &[(VXY0, 0x0020_0010), (VZ0, 0x100), (H, 0x200), (DQA, 0x100)],
+        output: &[
+            (SXY2, 0x003F_001F),
+            (MAC0, 0x01FF_FF00),
+            (IR0, 0x1000),
+            (FLAG, flag::ERROR | flag::DIVIDE | flag::IR0),
+        ],
+    },
+    Case {
+        name: "divide by zero",
+        command: RTPS | SF,
+        base: &IDENTITY,
+        input: &[(H, 1), (DQA, 1)],
+        output: &[
+            (SZ3, 0),
+            (MAC0, 0x1_FFFF),
+            (IR0, 0x1F),
+            (FLAG, flag::ERROR | flag::DIVIDE),
+        ],
+    },
+    Case {
+        // Without sf, MAC3 saturates into IR3 but the flag only looks at MAC3 shifted by 12
+        name: "ir3 saturated without flag",
+        command: RTPS,
+        base: &IDENTITY,
+        input: &[(VZ0, 0x10)],
+        output: &[(MAC3, 0x1_0000), (IR3, 0x7FFF), (SZ3, 0x10), (FLAG, 0)],
+    },
+    Case {
+        name: "ir3 flag from z",
+        command: RTPS | SF,
+        base: &IDENTITY,
+        input: &[(TRZ, 0x3FFF_0001)],
+        output: &[
+            (MAC3, 0x3FFF_0001),
+            (IR3, 0x7FFF),
+            (SZ3, 0xFFFF),
+            (FLAG, flag::ERROR | flag::IR3 | flag::SZ3_OTZ),
+        ],
+    },
+    Case {
+        name: "sz3 negative",
+        command: RTPS | SF,
+        base: &IDENTITY,
+        input: &[(TRZ, 0xFFFF_FF00), (H, 0x100)],
+        output: &[
+            (MAC3, 0xFFFF_FF00),
+            (IR3, 0xFFFF_FF00),
+            (SZ3, 0),
+            (FLAG, flag::ERROR | flag::DIVIDE | flag::SZ3_OTZ),
+        ],
+    },
+    Case {
+        name: "lm saturates at zero",
+        command: mvmva(0, 0, 3) | SF | LM,
+        base: &IDENTITY,
+        input: &[(VXY0, 0x0100_FF00)],
+        output: &[
+            (MAC1, 0xFFFF_FF00),
+            (IR1, 0),
+            (IR2, 0x100),
+            (FLAG, flag::ERROR | flag::IR1),
+        ],
+    },
+    Case {
+        name: "sx2 and sy2",
+        command: RTPS | SF,
+        base: &IDENTITY,
+        input: &[(VXY0, 0xF000_1000), (VZ0, 0x100), (H, 0x100)],
+        output: &[(SXY2, 0xFC00_03FF), (FLAG, flag::ERROR | flag::SX2 | flag::SY2)],
+    },
+    Case {
+        name: "mac0 positive overflow",
+        command: RTPS | SF,
+        base: &IDENTITY,
+        input: &[(VXY0, 0x10), (VZ0, 0x100), (H, 0x100), (OFX, 0x7FFF_FFFF)],
+        output: &[
+            (SXY2, 0x3FF),
+            (MAC0, 0),
+            (FLAG, flag::ERROR | flag::MAC0_POSITIVE | flag::SX2),
+        ],
+    },
+    Case {
+        name: "mac0 negative overflow",
+        command: RTPS | SF,
+        base: &IDENTITY,
+        input: &[(VXY0, 0xFFF0_0000), (VZ0, 0x100), (H, 0x100), (OFY, 0x8000_0000)],
+        output: &[
+            (SXY2, 0xFC00_0000),
+            (FLAG, flag::ERROR | flag::MAC0_NEGATIVE | flag::SY2),
+        ],
+    },
+    Case {
+        name: "ir0 saturates at zero",
+        command: RTPS | SF,
+        base: &IDENTITY,
+        input: &[(VZ0, 0x100), (DQB, 0xFFFF_F000)],
+        output: &[(MAC0, 0xFFFF_F000), (IR0, 0), (FLAG, flag::IR0)],
+    },
+    Case {
+        name: "nclip positive overflow",
+        command: NCLIP,
+        base: &[],
+        input: &[(SXY0, 0x7FFF), (SXY1, 0x7FFF_0000), (SXY2, 0x8000_8000)],
+        output: &[(MAC0, 0xBFFE_0001), (FLAG, flag::ERROR | flag::MAC0_POSITIVE)],
+    },
+    Case {
+        name: "nclip negative overflow",
+        command: NCLIP,
+        base: &[],
+        input: &[(SXY0, 0x7FFF_0000), (SXY1, 0x7FFF), (SXY2, 0x8000_8000)],
+        output: &[(MAC0, 0x4001_FFFF), (FLAG, flag::ERROR | flag::MAC0_NEGATIVE)],
+    },
+    Case {
+        name: "avsz3 saturation",
+        command: AVSZ3,
+        base: &[],
+        input: &[(SZ1, 0xFFFF), (SZ2, 0xFFFF), (SZ3, 0xFFFF), (ZSF3, 0x7FFF)],
+        output: &[
+            (MAC0, 0x7FFB_8003),
+            (OTZ, 0xFFFF),
+            (FLAG, flag::ERROR | flag::MAC0_POSITIVE | flag::SZ3_OTZ),
+        ],
+    },
+    Case {
+        name: "avsz4 negative",
+        command: AVSZ4,
+        base: &[],
+        input: &[(SZ0, 1), (SZ1, 1), (SZ2, 1), (SZ3, 1), (ZSF4, 0xF000)],
+        output: &[(MAC0, 0xFFFF_C000), (OTZ, 0), (FLAG, flag::ERROR | flag::SZ3_OTZ)],
+    },
+    Case {
+        name: "sqr ir3 without sf",
+        command: SQR,
+        base: &[],
+        input: &[(IR1, 3), (IR2, 0xFFFF_FFFC), (IR3, 0x100)],
+        output: &[
+            (MAC1, 9),
+            (MAC2, 16),
+            (MAC3, 0x1_0000),
+            (IR3, 0x7FFF),
+            (FLAG, flag::IR3),
+        ],
+    },
+    Case {
+        name: "mac1-3 positive overflow",
+        command: mvmva(0, 0, 0) | SF,
+        base: &[],
+        input: &[
+            (RT11RT12, 0x7FFF_7FFF),
+            (RT13RT21, 0x7FFF_7FFF),
+            (RT22RT23, 0x7FFF_7FFF),
+            (RT31RT32, 0x7FFF_7FFF),
+            (RT33, 0x7FFF),
+            (VXY0, 0x7FFF_7FFF),
+            (VZ0, 0x7FFF),
+            (TRX, 0x7FFF_FFFF),
+            (TRY, 0x7FFF_FFFF),
+            (TRZ, 0x7FFF_FFFF),
+        ],
+        output: &[
+            (MAC1, 0x800B_FFCF),
+            (MAC2, 0x800B_FFCF),
+            (MAC3, 0x800B_FFCF),
+            (IR1, 0xFFFF_8000),
+            (IR2, 0xFFFF_8000),
+            (IR3, 0xFFFF_8000),
+            (FLAG, 0xF1C0_0000),
+        ],
+    },
+    Case {
+        name: "mac1-3 negative overflow",
+        command: mvmva(0, 0, 0) | SF,
+        base: &[],
+        input: &[
+            (RT11RT12, 0x7FFF_7FFF),
+            (RT13RT21, 0x7FFF_7FFF),
+            (RT22RT23, 0x7FFF_7FFF),
+            (RT31RT32, 0x7FFF_7FFF),
+            (RT33, 0x7FFF),
+            (VXY0, 0x8000_8000),
+            (VZ0, 0x8000),
+            (TRX, 0x8000_0000),
+            (TRY, 0x8000_0000),
+            (TRZ, 0x8000_0000),
+        ],
+        output: &[
+            (MAC1, 0x7FF4_0018),
+            (MAC2, 0x7FF4_0018),
+            (MAC3, 0x7FF4_0018),
+            (IR1, 0x7FFF),
+            (IR2, 0x7FFF),
+            (IR3, 0x7FFF),
+            (FLAG, 0x8FC0_0000),
+        ],
+    },
+    Case {
+        // The first two terms overflow upwards, the third brings the sum back down through the wrap
+        name: "mac1 intermediate overflow",
+        command: mvmva(0, 0, 0),
+        base: &[],
+        input: &[(RT11RT12, 0x8000_7FFF), (VXY0, 0x7FFF_7FFF), (TRX, 0x7FFF_FFFF)],
+        output: &[
+            (MAC1, 0xFFFF_7001),
+            (IR1, 0xFFFF_8000),
+            (
+                FLAG,
+                flag::ERROR | flag::MAC1_POSITIVE | flag::MAC1_NEGATIVE | flag::IR1,
+            ),
+        ],
+    },
+    Case {
+        name: "color saturation",
+        command: GPF | SF,
+        base: &[],
+        input: &[(IR0, 0x1000), (IR1, 0x7FFF), (IR2, 0x8000), (IR3, 0xFF0)],
+        output: &[(RGB2, 0x00FF_00FF), (FLAG, flag::COLOR_R | flag::COLOR_G)],
+    },
+];
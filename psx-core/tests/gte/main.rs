@@ -0,0 +1,331 @@
+//! GTE conformance, hand-computed cases for every command and FLAG bit plus a randomized differential
+//! against the slow model in `reference.rs`
+//!
+//! The random run is reproducible, `PSX_GTE_SEED` picks another seed and `PSX_GTE_ROUNDS` makes it
+//! longer. A failure prints the seed, the command and the registers it started from.
+
+mod cases;
+mod reference;
+
+use cases::{COMMAND_CASES, Case, FLAG_CASES};
+use psx_core::cpu::Cpu;
+use psx_core::cpu::cop::cop2::Cop2;
+use psx_core::cpu::decoder::Instruction;
+use reference::*;
+use std::sync::Mutex;
+
+const DEFAULT_SEED: u64 = 0x5053_5850_5350_5350;
+const DEFAULT_ROUNDS: usize = 10_000;
+
+type Registers = &'static [(usize, u32)];
+
+/// The bus backs the whole address space, so the tests take turns on one CPU
+static CPU: Mutex<Option<Cpu>> = Mutex::new(None);
+
+fn with_cpu<T>(f: impl FnOnce(&mut Cpu) -> T) -> T {
+    let mut cpu = CPU.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(cpu.get_or_insert_with(Cpu::new))
+}
+
+fn read(cop2: &Cop2, register: usize) -> u32 {
+    if register < 32 {
+        cop2.read_data_register(register as u8)
+    } else {
+        cop2.read_control_register(register as u8 - 32)
+    }
+}
+
+fn write(cop2: &mut Cop2, register: usize, value: u32) {
+    if register < 32 {
+        cop2.write_data_register(register as u8, value);
+    } else {
+        cop2.write_control_register(register as u8 - 32, value);
+    }
+}
+
+/// Both sides with every register written as zero, so LZCR has been computed
+fn reset() -> (Cop2, Gte) {
+    let mut cop2 = Cop2::new();
+    let mut gte = Gte::default();
+    for register in 0..64 {
+        write(&mut cop2, register, 0);
+        gte.write(register, 0);
+    }
+    (cop2, gte)
+}
+
+fn execute(cpu: &mut Cpu, cop2: &mut Cop2, command: u32) {
+    let instruction = Instruction::decode(0x4A00_0000 | command);
+    cpu.cop2 = *cop2;
+    (instruction.handler)(&instruction, cpu);
+    *cop2 = cpu.cop2;
+}
+
+fn differences(cop2: &Cop2, gte: &Gte) -> Vec<String> {
+    (0..64)
+        .filter(|&register| read(cop2, register) != gte.read(register))
+        .map(|register| {
+            format!(
+                "{}: {:08X}, reference {:08X}",
+                REGISTER_NAMES[register],
+                read(cop2, register),
+                gte.read(register)
+            )
+        })
+        .collect()
+}
+
+fn run_cases(cases: &[Case]) {
+    let mut failures = Vec::new();
+
+    with_cpu(|cpu| {
+        for case in cases {
+            let (mut cop2, mut gte) = reset();
+            for &(register, value) in case.base.iter().chain(case.input) {
+                write(&mut cop2, register, value);
+                gte.write(register, value);
+            }
+
+            execute(cpu, &mut cop2, case.command);
+            gte.execute(case.command);
+
+            for &(register, expected) in case.output {
+                for (side, actual) in [("emulator", read(&cop2, register)), ("reference", gte.read(register))] {
+                    if actual != expected {
+                        failures.push(format!(
+                            "{}: {} {} is {:08X}, expected {:08X}",
+                            case.name, side, REGISTER_NAMES[register], actual, expected
+                        ));
+                    }
+                }
+            }
+
+            for difference in differences(&cop2, &gte) {
+                failures.push(format!("{}: {}", case.name, difference));
+            }
+        }
+    });
+
+    assert!(
+        failures.is_empty(),
+        "{} mismatches:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_commands() {
+    run_cases(COMMAND_CASES);
+}
+
+#[test]
+fn test_flags() {
+    run_cases(FLAG_CASES);
+}
+
+#[test]
+fn test_registers() {
+    // Each write is followed by the reads it should produce
+    let steps: &[(usize, u32, Registers)] = &[
+        (VZ0, 0x1234_8000, &[(VZ0, 0xFFFF_8000)]),
+        (VXY0, 0x8000_7FFF, &[(VXY0, 0x8000_7FFF)]),
+        (IR1, 0x0000_8000, &[(IR1, 0xFFFF_8000)]),
+        (
+            IRGB,
+            0x7FFF,
+            &[(IR1, 0xF80), (IR2, 0xF80), (IR3, 0xF80), (IRGB, 0x7FFF), (ORGB, 0x7FFF)],
+        ),
+        (IR1, 0x8000, &[(ORGB, 0x7FE0)]),
+        (IR2, 0x40, &[(ORGB, 0x7C00)]),
+        (IR3, 0x100, &[(ORGB, 0x0800), (IRGB, 0x0800)]),
+        (ORGB, 0, &[(ORGB, 0x0800)]),
+        (SXY0, 1, &[]),
+        (SXY1, 2, &[]),
+        (SXY2, 3, &[(SXYP, 3)]),
+        (SXYP, 4, &[(SXY0, 2), (SXY1, 3), (SXY2, 4), (SXYP, 4)]),
+        (LZCS, 0xFFFF, &[(LZCR, 16)]),
+        (LZCS, 0xFF00_0000, &[(LZCR, 8)]),
+        (LZCS, 0, &[(LZCR, 32)]),
+        (LZCS, 0xFFFF_FFFF, &[(LZCR, 32), (LZCS, 0xFFFF_FFFF)]),
+        (LZCR, 5, &[(LZCR, 32)]),
+        (OTZ, 0xFFFF_FFFF, &[(OTZ, 0xFFFF)]),
+        (SZ2, 0x1_2345, &[(SZ2, 0x2345)]),
+        (H, 0x8000, &[(H, 0xFFFF_8000)]),
+        (FLAG, 0xFFFF_FFFF, &[(FLAG, 0xFFFF_F000)]),
+        (FLAG, 0x4_0000, &[(FLAG, 0x8004_0000)]),
+        (FLAG, 0x40_0000, &[(FLAG, 0x40_0000)]),
+        (FLAG, 0, &[(FLAG, 0)]),
+        (RT33, 0x8000, &[(RT33, 0xFFFF_8000)]),
+        (DQA, 0xFFFF, &[(DQA, 0xFFFF_FFFF)]),
+        (ZSF4, 0x1_7FFF, &[(ZSF4, 0x7FFF)]),
+        (MAC2, 0x8765_4321, &[(MAC2, 0x8765_4321)]),
+        (RES1, 0xDEAD_BEEF, &[(RES1, 0xDEAD_BEEF)]),
+        (RGBC, 0x1122_3344, &[(RGBC, 0x1122_3344)]),
+    ];
+
+    let (mut cop2, mut gte) = reset();
+    let mut failures = Vec::new();
+    for (step, &(register, value, reads)) in steps.iter().enumerate() {
+        write(&mut cop2, register, value);
+        gte.write(register, value);
+
+        for &(register, expected) in reads {
+            for (side, actual) in [("emulator", read(&cop2, register)), ("reference", gte.read(register))] {
+                if actual != expected {
+                    failures.push(format!(
+                        "step {}: {} {} is {:08X}, expected {:08X}",
+                        step, side, REGISTER_NAMES[register], actual, expected
+                    ));
+                }
+            }
+        }
+
+        for difference in differences(&cop2, &gte) {
+            failures.push(format!("step {}: {}", step, difference));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} mismatches:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_division() {
+    // With RT zero and sf set SZ3 is TRZ, and with DQA 1 the quotient ends up in MAC0
+    let mut failures = Vec::new();
+
+    with_cpu(|cpu| {
+        for h in [
+            0x0001, 0x00FF, 0x0100, 0x0155, 0x1000, 0x7FFF, 0x8000, 0xABCD, 0xFE3F, 0xFFFF,
+        ] {
+            let (mut cop2, mut gte) = reset();
+            for (register, value) in [(H, h), (DQA, 1)] {
+                write(&mut cop2, register, value);
+                gte.write(register, value);
+            }
+
+            for sz3 in 1..=0xFFFF {
+                write(&mut cop2, TRZ, sz3);
+                gte.write(TRZ, sz3);
+
+                execute(cpu, &mut cop2, RTPS | SF);
+                gte.execute(RTPS | SF);
+
+                let (quotient, expected) = (read(&cop2, MAC0), gte.read(MAC0));
+                let (flags, expected_flags) = (read(&cop2, FLAG), gte.read(FLAG));
+                if (quotient, flags) != (expected, expected_flags) && failures.len() < 32 {
+                    failures.push(format!(
+                        "H {:04X} / SZ3 {:04X}: {:05X} with FLAG {:08X}, reference {:05X} with FLAG {:08X}",
+                        h, sz3, quotient, flags, expected, expected_flags
+                    ));
+                }
+            }
+        }
+
+        // Rounds up to 20000h, which is clamped without setting the divide overflow flag
+        let (mut cop2, _) = reset();
+        for (register, value) in [(H, 0xFE3F), (DQA, 1), (TRZ, 0x7F20)] {
+            write(&mut cop2, register, value);
+        }
+        execute(cpu, &mut cop2, RTPS | SF);
+        if (read(&cop2, MAC0), read(&cop2, FLAG)) != (0x1_FFFF, 0) {
+            failures.push(format!(
+                "H FE3F / SZ3 7F20: {:05X} with FLAG {:08X}, expected 1FFFF with FLAG 0",
+                read(&cop2, MAC0),
+                read(&cop2, FLAG)
+            ));
+        }
+    });
+
+    assert!(failures.is_empty(), "division mismatches:\n{}", failures.join("\n"));
+}
+
+/// xorshift64, good enough to shake out saturation edges
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 32) as u32
+    }
+
+    /// Mostly the corners where saturation and overflow live
+    fn value(&mut self) -> u32 {
+        let half = |rng: &mut Self| match rng.next() % 4 {
+            0 => [0x0000, 0x7FFF, 0x8000, 0xFFFF, 0x1000, 0xF000][rng.next() as usize % 6],
+            1 => rng.next() & 0xFFFF,
+            _ => (rng.next() % 0x2001).wrapping_sub(0x1000) & 0xFFFF,
+        };
+
+        match self.next() % 4 {
+            0 => self.next(),
+            1 => [0, 1, 0x7FFF_FFFF, 0x8000_0000, 0xFFFF_FFFF, 0x0000_1000][self.next() as usize % 6],
+            2 => half(self) | (half(self) << 16),
+            _ => (half(self) as u16 as i16 as i32) as u32,
+        }
+    }
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+#[test]
+fn test_random_against_reference() {
+    let seed = std::env::var("PSX_GTE_SEED")
+        .ok()
+        .and_then(|value| u64::from_str_radix(value.trim_start_matches("0x"), 16).ok())
+        .unwrap_or(DEFAULT_SEED);
+    let rounds = env_or("PSX_GTE_ROUNDS", DEFAULT_ROUNDS);
+    let mut rng = Rng(seed | 1);
+
+    with_cpu(|cpu| {
+        for round in 0..rounds {
+            let (mut cop2, mut gte) = reset();
+
+            // IRGB and SXYP first so the registers they spill into still get their own values
+            let order = [IRGB, SXYP]
+                .into_iter()
+                .chain((0..64).filter(|&r| !matches!(r, IRGB | SXYP | ORGB | LZCR)));
+            for register in order {
+                let value = rng.value();
+                write(&mut cop2, register, value);
+                gte.write(register, value);
+            }
+
+            for _ in 0..4 {
+                let before: Vec<u32> = (0..64).map(|register| gte.read(register)).collect();
+                let command = (rng.next() & 0x01FF_FFC0) | COMMANDS[rng.next() as usize % COMMANDS.len()];
+
+                execute(cpu, &mut cop2, command);
+                gte.execute(command);
+
+                let differences = differences(&cop2, &gte);
+                assert!(
+                    differences.is_empty(),
+                    "seed {:X} round {}: command {:07X} differs:\n    {}\nstarting from:\n    {}",
+                    seed,
+                    round,
+                    command,
+                    differences.join("\n    "),
+                    before
+                        .iter()
+                        .enumerate()
+                        .map(|(register, value)| format!("{} = {:08X}", REGISTER_NAMES[register], value))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+    });
+}
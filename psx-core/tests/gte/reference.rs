@@ -0,0 +1,665 @@
+//! Slow GTE model written straight from the hardware documentation
+//!
+//! Nothing is shared with `interpreter::gte`. Registers live in plain typed fields and every
+//! command is spelled out step by step, so the two implementations can be checked against each
+//! other.
+
+// Indexing rows and components by number keeps the formulas close to the documentation
+#![allow(clippy::needless_range_loop)]
+
+pub const VXY0: usize = 0;
+pub const VZ0: usize = 1;
+pub const VXY1: usize = 2;
+pub const VZ1: usize = 3;
+pub const VXY2: usize = 4;
+pub const VZ2: usize = 5;
+pub const RGBC: usize = 6;
+pub const OTZ: usize = 7;
+pub const IR0: usize = 8;
+pub const IR1: usize = 9;
+pub const IR2: usize = 10;
+pub const IR3: usize = 11;
+pub const SXY0: usize = 12;
+pub const SXY1: usize = 13;
+pub const SXY2: usize = 14;
+pub const SXYP: usize = 15;
+pub const SZ0: usize = 16;
+pub const SZ1: usize = 17;
+pub const SZ2: usize = 18;
+pub const SZ3: usize = 19;
+pub const RGB0: usize = 20;
+pub const RGB1: usize = 21;
+pub const RGB2: usize = 22;
+pub const RES1: usize = 23;
+pub const MAC0: usize = 24;
+pub const MAC1: usize = 25;
+pub const MAC2: usize = 26;
+pub const MAC3: usize = 27;
+pub const IRGB: usize = 28;
+pub const ORGB: usize = 29;
+pub const LZCS: usize = 30;
+pub const LZCR: usize = 31;
+pub const RT11RT12: usize = 32;
+pub const RT13RT21: usize = 33;
+pub const RT22RT23: usize = 34;
+pub const RT31RT32: usize = 35;
+pub const RT33: usize = 36;
+pub const TRX: usize = 37;
+pub const TRY: usize = 38;
+pub const TRZ: usize = 39;
+pub const L11L12: usize = 40;
+pub const L13L21: usize = 41;
+pub const L22L23: usize = 42;
+pub const L31L32: usize = 43;
+pub const L33: usize = 44;
+pub const RBK: usize = 45;
+pub const GBK: usize = 46;
+pub const BBK: usize = 47;
+pub const LR1LR2: usize = 48;
+pub const LR3LG1: usize = 49;
+pub const LG2LG3: usize = 50;
+pub const LB1LB2: usize = 51;
+pub const LB3: usize = 52;
+pub const RFC: usize = 53;
+pub const GFC: usize = 54;
+pub const BFC: usize = 55;
+pub const OFX: usize = 56;
+pub const OFY: usize = 57;
+pub const H: usize = 58;
+pub const DQA: usize = 59;
+pub const DQB: usize = 60;
+pub const ZSF3: usize = 61;
+pub const ZSF4: usize = 62;
+pub const FLAG: usize = 63;
+
+pub const REGISTER_NAMES: [&str; 64] = [
+    "VXY0", "VZ0", "VXY1", "VZ1", "VXY2", "VZ2", "RGBC", "OTZ", "IR0", "IR1", "IR2", "IR3", "SXY0", "SXY1", "SXY2",
+    "SXYP", "SZ0", "SZ1", "SZ2", "SZ3", "RGB0", "RGB1", "RGB2", "RES1", "MAC0", "MAC1", "MAC2", "MAC3", "IRGB", "ORGB",
+    "LZCS", "LZCR", "RT11RT12", "RT13RT21", "RT22RT23", "RT31RT32", "RT33", "TRX", "TRY", "TRZ", "L11L12", "L13L21",
+    "L22L23", "L31L32", "L33", "RBK", "GBK", "BBK", "LR1LR2", "LR3LG1", "LG2LG3", "LB1LB2", "LB3", "RFC", "GFC", "BFC",
+    "OFX", "OFY", "H", "DQA", "DQB", "ZSF3", "ZSF4", "FLAG",
+];
+
+/// FLAG bits, named after what sets them
+pub mod flag {
+    pub const IR0: u32 = 1 << 12;
+    pub const SY2: u32 = 1 << 13;
+    pub const SX2: u32 = 1 << 14;
+    pub const MAC0_NEGATIVE: u32 = 1 << 15;
+    pub const MAC0_POSITIVE: u32 = 1 << 16;
+    pub const DIVIDE: u32 = 1 << 17;
+    pub const SZ3_OTZ: u32 = 1 << 18;
+    pub const COLOR_B: u32 = 1 << 19;
+    pub const COLOR_G: u32 = 1 << 20;
+    pub const COLOR_R: u32 = 1 << 21;
+    pub const IR3: u32 = 1 << 22;
+    pub const IR2: u32 = 1 << 23;
+    pub const IR1: u32 = 1 << 24;
+    pub const MAC3_NEGATIVE: u32 = 1 << 25;
+    pub const MAC2_NEGATIVE: u32 = 1 << 26;
+    pub const MAC1_NEGATIVE: u32 = 1 << 27;
+    pub const MAC3_POSITIVE: u32 = 1 << 28;
+    pub const MAC2_POSITIVE: u32 = 1 << 29;
+    pub const MAC1_POSITIVE: u32 = 1 << 30;
+    pub const ERROR: u32 = 1 << 31;
+
+    /// Bits that also raise ERROR, IR3, IR0 and the color bits do not
+    pub const ERROR_CAUSES: u32 = MAC1_POSITIVE
+        | MAC2_POSITIVE
+        | MAC3_POSITIVE
+        | MAC1_NEGATIVE
+        | MAC2_NEGATIVE
+        | MAC3_NEGATIVE
+        | IR1
+        | IR2
+        | SZ3_OTZ
+        | DIVIDE
+        | MAC0_POSITIVE
+        | MAC0_NEGATIVE
+        | SX2
+        | SY2;
+
+    pub const MAC_POSITIVE: [u32; 4] = [MAC0_POSITIVE, MAC1_POSITIVE, MAC2_POSITIVE, MAC3_POSITIVE];
+    pub const MAC_NEGATIVE: [u32; 4] = [MAC0_NEGATIVE, MAC1_NEGATIVE, MAC2_NEGATIVE, MAC3_NEGATIVE];
+    pub const IR: [u32; 4] = [IR0, IR1, IR2, IR3];
+    pub const COLOR: [u32; 3] = [COLOR_R, COLOR_G, COLOR_B];
+}
+
+pub const RTPS: u32 = 0x01;
+pub const NCLIP: u32 = 0x06;
+pub const OP: u32 = 0x0C;
+pub const DPCS: u32 = 0x10;
+pub const INTPL: u32 = 0x11;
+pub const MVMVA: u32 = 0x12;
+pub const NCDS: u32 = 0x13;
+pub const CDP: u32 = 0x14;
+pub const NCDT: u32 = 0x16;
+pub const NCCS: u32 = 0x1B;
+pub const CC: u32 = 0x1C;
+pub const NCS: u32 = 0x1E;
+pub const NCT: u32 = 0x20;
+pub const SQR: u32 = 0x28;
+pub const DCPL: u32 = 0x29;
+pub const DPCT: u32 = 0x2A;
+pub const AVSZ3: u32 = 0x2D;
+pub const AVSZ4: u32 = 0x2E;
+pub const RTPT: u32 = 0x30;
+pub const GPF: u32 = 0x3D;
+pub const GPL: u32 = 0x3E;
+pub const NCCT: u32 = 0x3F;
+
+pub const COMMANDS: [u32; 22] = [
+    RTPS, NCLIP, OP, DPCS, INTPL, MVMVA, NCDS, CDP, NCDT, NCCS, CC, NCS, NCT, SQR, DCPL, DPCT, AVSZ3, AVSZ4, RTPT, GPF,
+    GPL, NCCT,
+];
+
+/// Shift results right by 12 bits
+pub const SF: u32 = 1 << 19;
+/// Saturate IR1-3 at 0 instead of -8000h
+pub const LM: u32 = 1 << 10;
+
+pub const fn mvmva(matrix: u32, vector: u32, translation: u32) -> u32 {
+    MVMVA | (matrix << 17) | (vector << 15) | (translation << 13)
+}
+
+type Matrix = [[i16; 3]; 3];
+
+#[derive(Clone, Default)]
+pub struct Gte {
+    v: [[i16; 3]; 3],
+    rgbc: [u8; 4],
+    otz: u16,
+    ir: [i16; 4],
+    sxy: [[i16; 2]; 3],
+    sz: [u16; 4],
+    rgb: [[u8; 4]; 3],
+    res1: u32,
+    mac: [i32; 4],
+    lzcs: u32,
+    rt: Matrix,
+    tr: [i32; 3],
+    llm: Matrix,
+    bk: [i32; 3],
+    lcm: Matrix,
+    fc: [i32; 3],
+    ofx: i32,
+    ofy: i32,
+    h: u16,
+    dqa: i16,
+    dqb: i32,
+    zsf3: i16,
+    zsf4: i16,
+    flag: u32,
+}
+
+/// UNR table entry as the hardware generates it, 257 entries
+fn unr_table(index: usize) -> u64 {
+    ((0x40000 / (index as i64 + 0x100) + 1) / 2 - 0x101).max(0) as u64
+}
+
+fn sign_extend_44(value: i64) -> i64 {
+    (value << 20) >> 20
+}
+
+fn pair(low: i16, high: i16) -> u32 {
+    (low as u16 as u32) | ((high as u16 as u32) << 16)
+}
+
+/// The 9 entries of a matrix packed two per register, the last one alone
+fn read_matrix(matrix: &Matrix, word: usize) -> u32 {
+    let entry = |index: usize| matrix[index / 3][index % 3];
+    if word == 4 {
+        entry(8) as i32 as u32
+    } else {
+        pair(entry(word * 2), entry(word * 2 + 1))
+    }
+}
+
+fn write_matrix(matrix: &mut Matrix, word: usize, value: u32) {
+    let first = word * 2;
+    matrix[first / 3][first % 3] = value as i16;
+    if word < 4 {
+        let second = first + 1;
+        matrix[second / 3][second % 3] = (value >> 16) as i16;
+    }
+}
+
+impl Gte {
+    pub fn read(&self, register: usize) -> u32 {
+        match register {
+            VXY0 | VXY1 | VXY2 => pair(self.v[register / 2][0], self.v[register / 2][1]),
+            VZ0 | VZ1 | VZ2 => self.v[register / 2][2] as i32 as u32,
+            RGBC => u32::from_le_bytes(self.rgbc),
+            OTZ => self.otz as u32,
+            IR0..=IR3 => self.ir[register - IR0] as i32 as u32,
+            SXY0..=SXY2 => pair(self.sxy[register - SXY0][0], self.sxy[register - SXY0][1]),
+            SXYP => pair(self.sxy[2][0], self.sxy[2][1]),
+            SZ0..=SZ3 => self.sz[register - SZ0] as u32,
+            RGB0..=RGB2 => u32::from_le_bytes(self.rgb[register - RGB0]),
+            RES1 => self.res1,
+            MAC0..=MAC3 => self.mac[register - MAC0] as u32,
+            IRGB | ORGB => {
+                let component = |ir: i16| (ir >> 7).clamp(0, 0x1F) as u32;
+                component(self.ir[1]) | (component(self.ir[2]) << 5) | (component(self.ir[3]) << 10)
+            }
+            LZCS => self.lzcs,
+            LZCR => {
+                if (self.lzcs as i32) < 0 {
+                    self.lzcs.leading_ones()
+                } else {
+                    self.lzcs.leading_zeros()
+                }
+            }
+            RT11RT12..=RT33 => read_matrix(&self.rt, register - RT11RT12),
+            TRX..=TRZ => self.tr[register - TRX] as u32,
+            L11L12..=L33 => read_matrix(&self.llm, register - L11L12),
+            RBK..=BBK => self.bk[register - RBK] as u32,
+            LR1LR2..=LB3 => read_matrix(&self.lcm, register - LR1LR2),
+            RFC..=BFC => self.fc[register - RFC] as u32,
+            OFX => self.ofx as u32,
+            OFY => self.ofy as u32,
+            // Unsigned, but reads back sign-extended
+            H => self.h as i16 as i32 as u32,
+            DQA => self.dqa as i32 as u32,
+            DQB => self.dqb as u32,
+            ZSF3 => self.zsf3 as i32 as u32,
+            ZSF4 => self.zsf4 as i32 as u32,
+            FLAG => {
+                if self.flag & flag::ERROR_CAUSES != 0 {
+                    self.flag | flag::ERROR
+                } else {
+                    self.flag
+                }
+            }
+            _ => panic!("No GTE register {}", register),
+        }
+    }
+
+    pub fn write(&mut self, register: usize, value: u32) {
+        let low = value as i16;
+        let high = (value >> 16) as i16;
+
+        match register {
+            VXY0 | VXY1 | VXY2 => {
+                self.v[register / 2][0] = low;
+                self.v[register / 2][1] = high;
+            }
+            VZ0 | VZ1 | VZ2 => self.v[register / 2][2] = low,
+            RGBC => self.rgbc = value.to_le_bytes(),
+            OTZ => self.otz = value as u16,
+            IR0..=IR3 => self.ir[register - IR0] = low,
+            SXY0..=SXY2 => self.sxy[register - SXY0] = [low, high],
+            SXYP => {
+                self.sxy[0] = self.sxy[1];
+                self.sxy[1] = self.sxy[2];
+                self.sxy[2] = [low, high];
+            }
+            SZ0..=SZ3 => self.sz[register - SZ0] = value as u16,
+            RGB0..=RGB2 => self.rgb[register - RGB0] = value.to_le_bytes(),
+            RES1 => self.res1 = value,
+            MAC0..=MAC3 => self.mac[register - MAC0] = value as i32,
+            IRGB => {
+                for component in 0..3 {
+                    self.ir[component + 1] = ((value >> (component * 5)) & 0x1F) as i16 * 0x80;
+                }
+            }
+            ORGB | LZCR => {}
+            LZCS => self.lzcs = value,
+            RT11RT12..=RT33 => write_matrix(&mut self.rt, register - RT11RT12, value),
+            TRX..=TRZ => self.tr[register - TRX] = value as i32,
+            L11L12..=L33 => write_matrix(&mut self.llm, register - L11L12, value),
+            RBK..=BBK => self.bk[register - RBK] = value as i32,
+            LR1LR2..=LB3 => write_matrix(&mut self.lcm, register - LR1LR2, value),
+            RFC..=BFC => self.fc[register - RFC] = value as i32,
+            OFX => self.ofx = value as i32,
+            OFY => self.ofy = value as i32,
+            H => self.h = value as u16,
+            DQA => self.dqa = low,
+            DQB => self.dqb = value as i32,
+            ZSF3 => self.zsf3 = low,
+            ZSF4 => self.zsf4 = low,
+            FLAG => self.flag = value & 0x7FFF_F000,
+            _ => panic!("No GTE register {}", register),
+        }
+    }
+
+    /// Run the command in the low 25 bits of a COP2 instruction
+    pub fn execute(&mut self, command: u32) {
+        let sf = if command & SF != 0 { 12 } else { 0 };
+        let lm = command & LM != 0;
+
+        self.flag = 0;
+
+        match command & 0x3F {
+            RTPS => self.rtp(0, sf, lm, true),
+            RTPT => {
+                self.rtp(0, sf, lm, false);
+                self.rtp(1, sf, lm, false);
+                self.rtp(2, sf, lm, true);
+            }
+            NCLIP => self.nclip(),
+            OP => self.op(sf, lm),
+            DPCS => self.dpcs(self.rgbc, sf, lm),
+            DPCT => {
+                for _ in 0..3 {
+                    self.dpcs(self.rgb[0], sf, lm);
+                }
+            }
+            INTPL => {
+                let mac = [1, 2, 3].map(|i| (self.ir[i] as i64) << 12);
+                self.interpolate(mac, sf, lm);
+                self.push_color();
+            }
+            DCPL => {
+                let mac = [0, 1, 2].map(|i| (self.rgbc[i] as i64 * self.ir[i + 1] as i64) << 4);
+                self.interpolate(mac, sf, lm);
+                self.push_color();
+            }
+            MVMVA => self.mvmva(command, sf, lm),
+            NCS => self.nc(0, sf, lm, Finish::Plain),
+            NCT => (0..3).for_each(|vector| self.nc(vector, sf, lm, Finish::Plain)),
+            NCCS => self.nc(0, sf, lm, Finish::Color),
+            NCCT => (0..3).for_each(|vector| self.nc(vector, sf, lm, Finish::Color)),
+            NCDS => self.nc(0, sf, lm, Finish::Depth),
+            NCDT => (0..3).for_each(|vector| self.nc(vector, sf, lm, Finish::Depth)),
+            CC => self.cc(sf, lm, Finish::Color),
+            CDP => self.cc(sf, lm, Finish::Depth),
+            SQR => {
+                let ir = self.ir;
+                for i in 1..4 {
+                    self.set_mac_ir(i, ir[i] as i64 * ir[i] as i64, sf, lm);
+                }
+            }
+            AVSZ3 => {
+                let sz = self.sz;
+                self.average_z(self.zsf3, &sz[1..]);
+            }
+            AVSZ4 => {
+                let sz = self.sz;
+                self.average_z(self.zsf4, &sz);
+            }
+            GPF => {
+                let ir = self.ir;
+                for i in 1..4 {
+                    self.set_mac_ir(i, ir[i] as i64 * ir[0] as i64, sf, lm);
+                }
+                self.push_color();
+            }
+            GPL => {
+                let ir = self.ir;
+                for i in 1..4 {
+                    let mac = (self.mac[i] as i64) << sf;
+                    self.set_mac_ir(i, ir[i] as i64 * ir[0] as i64 + mac, sf, lm);
+                }
+                self.push_color();
+            }
+            _ => panic!("The reference does not know GTE command {:02X}", command & 0x3F),
+        }
+    }
+
+    fn check_mac(&mut self, index: usize, value: i64) {
+        let bits = if index == 0 { 32 } else { 44 };
+        if value < -(1 << (bits - 1)) {
+            self.flag |= flag::MAC_NEGATIVE[index];
+        } else if value >= 1 << (bits - 1) {
+            self.flag |= flag::MAC_POSITIVE[index];
+        }
+    }
+
+    /// Add up terms the way MAC1-3 do, 44 bits wide with the overflow checked after every addition
+    fn accumulate(&mut self, index: usize, terms: &[i64]) -> i64 {
+        let mut sum = 0;
+        for term in terms {
+            sum += term;
+            self.check_mac(index, sum);
+            sum = sign_extend_44(sum);
+        }
+        sum
+    }
+
+    fn saturate_ir(&mut self, index: usize, value: i32, lm: bool) -> i16 {
+        let min = if lm { 0 } else { -0x8000 };
+        if value < min || value > 0x7FFF {
+            self.flag |= flag::IR[index];
+        }
+        value.clamp(min, 0x7FFF) as i16
+    }
+
+    /// MAC1-3 get `value >> sf` cut to 32 bits, IR1-3 the saturated MAC
+    fn set_mac_ir(&mut self, index: usize, value: i64, sf: u32, lm: bool) {
+        self.check_mac(index, value);
+        self.mac[index] = (value >> sf) as i32;
+        self.ir[index] = self.saturate_ir(index, self.mac[index], lm);
+    }
+
+    fn transform(&mut self, matrix: Matrix, vector: [i16; 3], translation: [i32; 3], sf: u32, lm: bool) {
+        for row in 0..3 {
+            let sum = self.accumulate(
+                row + 1,
+                &[
+                    (translation[row] as i64) << 12,
+                    matrix[row][0] as i64 * vector[0] as i64,
+                    matrix[row][1] as i64 * vector[1] as i64,
+                    matrix[row][2] as i64 * vector[2] as i64,
+                ],
+            );
+            self.set_mac_ir(row + 1, sum, sf, lm);
+        }
+    }
+
+    /// MAC + (FC - MAC) * IR0
+    fn interpolate(&mut self, mac: [i64; 3], sf: u32, lm: bool) {
+        for i in 0..3 {
+            self.set_mac_ir(i + 1, ((self.fc[i] as i64) << 12) - mac[i], sf, false);
+        }
+        for i in 0..3 {
+            let value = self.ir[i + 1] as i64 * self.ir[0] as i64 + mac[i];
+            self.set_mac_ir(i + 1, value, sf, lm);
+        }
+    }
+
+    fn push_color(&mut self) {
+        let mut color = [0, 0, 0, self.rgbc[3]];
+        for i in 0..3 {
+            let value = self.mac[i + 1] >> 4;
+            if !(0..=0xFF).contains(&value) {
+                self.flag |= flag::COLOR[i];
+            }
+            color[i] = value.clamp(0, 0xFF) as u8;
+        }
+
+        self.rgb[0] = self.rgb[1];
+        self.rgb[1] = self.rgb[2];
+        self.rgb[2] = color;
+    }
+
+    fn divide(&mut self, h: u16, sz3: u16) -> u64 {
+        if h as u32 >= sz3 as u32 * 2 {
+            self.flag |= flag::DIVIDE;
+            return 0x1FFFF;
+        }
+
+        let shift = sz3.leading_zeros();
+        let n = (h as u64) << shift;
+        let d = (sz3 as u64) << shift;
+        let u = unr_table(((d - 0x7FC0) >> 7) as usize) + 0x101;
+        let d = (0x2000080 - d * u) >> 8;
+        let d = (0x80 + d * u) >> 8;
+
+        // Quotients that round up to 20000h are clamped, without the flag
+        ((n * d + 0x8000) >> 16).min(0x1FFFF)
+    }
+
+    fn rtp(&mut self, vector: usize, sf: u32, lm: bool, last: bool) {
+        let v = self.v[vector];
+        let mut sums = [0; 3];
+        for row in 0..3 {
+            sums[row] = self.accumulate(
+                row + 1,
+                &[
+                    (self.tr[row] as i64) << 12,
+                    self.rt[row][0] as i64 * v[0] as i64,
+                    self.rt[row][1] as i64 * v[1] as i64,
+                    self.rt[row][2] as i64 * v[2] as i64,
+                ],
+            );
+            self.mac[row + 1] = (sums[row] >> sf) as i32;
+        }
+
+        self.ir[1] = self.saturate_ir(1, self.mac[1], lm);
+        self.ir[2] = self.saturate_ir(2, self.mac[2], lm);
+
+        // IR3 is saturated like the others, but its flag only looks at the sum shifted by 12
+        let z = (sums[2] >> 12) as i32;
+        if !(-0x8000..=0x7FFF).contains(&z) {
+            self.flag |= flag::IR3;
+        }
+        self.ir[3] = self.mac[3].clamp(if lm { 0 } else { -0x8000 }, 0x7FFF) as i16;
+
+        if !(0..=0xFFFF).contains(&z) {
+            self.flag |= flag::SZ3_OTZ;
+        }
+        self.sz = [self.sz[1], self.sz[2], self.sz[3], z.clamp(0, 0xFFFF) as u16];
+
+        let quotient = self.divide(self.h, self.sz[3]) as i64;
+
+        let sx = quotient * self.ir[1] as i64 + self.ofx as i64;
+        let sy = quotient * self.ir[2] as i64 + self.ofy as i64;
+        self.check_mac(0, sx);
+        self.check_mac(0, sy);
+
+        let (sx, sy) = ((sx >> 16) as i32, (sy >> 16) as i32);
+        if !(-0x400..=0x3FF).contains(&sx) {
+            self.flag |= flag::SX2;
+        }
+        if !(-0x400..=0x3FF).contains(&sy) {
+            self.flag |= flag::SY2;
+        }
+        self.sxy[0] = self.sxy[1];
+        self.sxy[1] = self.sxy[2];
+        self.sxy[2] = [sx.clamp(-0x400, 0x3FF) as i16, sy.clamp(-0x400, 0x3FF) as i16];
+
+        if last {
+            let depth = quotient * self.dqa as i64 + self.dqb as i64;
+            self.check_mac(0, depth);
+            self.mac[0] = depth as i32;
+
+            let ir0 = depth >> 12;
+            if !(0..=0x1000).contains(&ir0) {
+                self.flag |= flag::IR0;
+            }
+            self.ir[0] = ir0.clamp(0, 0x1000) as i16;
+        }
+    }
+
+    fn nclip(&mut self) {
+        let [[x0, y0], [x1, y1], [x2, y2]] = self.sxy.map(|xy| xy.map(|c| c as i64));
+        let value = x0 * y1 + x1 * y2 + x2 * y0 - x0 * y2 - x1 * y0 - x2 * y1;
+        self.check_mac(0, value);
+        self.mac[0] = value as i32;
+    }
+
+    fn op(&mut self, sf: u32, lm: bool) {
+        let [d1, d2, d3] = [self.rt[0][0], self.rt[1][1], self.rt[2][2]].map(|d| d as i64);
+        let [_, ir1, ir2, ir3] = self.ir.map(|ir| ir as i64);
+        self.set_mac_ir(1, ir3 * d2 - ir2 * d3, sf, lm);
+        self.set_mac_ir(2, ir1 * d3 - ir3 * d1, sf, lm);
+        self.set_mac_ir(3, ir2 * d1 - ir1 * d2, sf, lm);
+    }
+
+    fn dpcs(&mut self, color: [u8; 4], sf: u32, lm: bool) {
+        let mac = [0, 1, 2].map(|i| (color[i] as i64) << 16);
+        self.interpolate(mac, sf, lm);
+        self.push_color();
+    }
+
+    fn mvmva(&mut self, command: u32, sf: u32, lm: bool) {
+        let matrix = match (command >> 17) & 3 {
+            0 => self.rt,
+            1 => self.llm,
+            2 => self.lcm,
+            _ => {
+                let r = (self.rgbc[0] as i16) << 4;
+                [[-r, r, self.ir[0]], [self.rt[0][2]; 3], [self.rt[1][1]; 3]]
+            }
+        };
+
+        let vector = match (command >> 15) & 3 {
+            3 => [self.ir[1], self.ir[2], self.ir[3]],
+            index => self.v[index as usize],
+        };
+
+        match (command >> 13) & 3 {
+            0 => self.transform(matrix, vector, self.tr, sf, lm),
+            1 => self.transform(matrix, vector, self.bk, sf, lm),
+            2 => {
+                // Broken on hardware: the far color and first column only leave flags behind
+                for row in 0..3 {
+                    let first = self.accumulate(
+                        row + 1,
+                        &[(self.fc[row] as i64) << 12, matrix[row][0] as i64 * vector[0] as i64],
+                    );
+                    self.saturate_ir(row + 1, (first >> sf) as i32, false);
+
+                    let rest = self.accumulate(
+                        row + 1,
+                        &[
+                            matrix[row][1] as i64 * vector[1] as i64,
+                            matrix[row][2] as i64 * vector[2] as i64,
+                        ],
+                    );
+                    self.set_mac_ir(row + 1, rest, sf, lm);
+                }
+            }
+            _ => self.transform(matrix, vector, [0; 3], sf, lm),
+        }
+    }
+
+    /// Normal color: light the normal, then color the light
+    fn nc(&mut self, vector: usize, sf: u32, lm: bool, finish: Finish) {
+        self.transform(self.llm, self.v[vector], [0; 3], sf, lm);
+        self.cc(sf, lm, finish);
+    }
+
+    /// Color the light in IR1-3
+    fn cc(&mut self, sf: u32, lm: bool, finish: Finish) {
+        self.transform(self.lcm, [self.ir[1], self.ir[2], self.ir[3]], self.bk, sf, lm);
+
+        let color = [0, 1, 2].map(|i| (self.rgbc[i] as i64 * self.ir[i + 1] as i64) << 4);
+        match finish {
+            Finish::Plain => {}
+            Finish::Color => {
+                for i in 0..3 {
+                    self.set_mac_ir(i + 1, color[i], sf, lm);
+                }
+            }
+            Finish::Depth => self.interpolate(color, sf, lm),
+        }
+
+        self.push_color();
+    }
+
+    fn average_z(&mut self, scale: i16, sz: &[u16]) {
+        let sum: i64 = sz.iter().map(|&z| z as i64).sum();
+        let value = scale as i64 * sum;
+        self.check_mac(0, value);
+        self.mac[0] = value as i32;
+
+        let otz = value >> 12;
+        if !(0..=0xFFFF).contains(&otz) {
+            self.flag |= flag::SZ3_OTZ;
+        }
+        self.otz = otz.clamp(0, 0xFFFF) as u16;
+    }
+}
+
+/// What the normal/color commands do with the lit color
+#[derive(Clone, Copy)]
+enum Finish {
+    /// NCS, NCT: push it as is
+    Plain,
+    /// NCCS, NCCT, CC: multiply with RGBC
+    Color,
+    /// NCDS, NCDT, CDP: multiply with RGBC and interpolate towards the far color
+    Depth,
+}
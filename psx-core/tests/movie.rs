@@ -0,0 +1,150 @@
+//! Movies have to replay into exactly the console they were recorded on, and notice when they don't.
+
+use psx_core::movie::{Movie, MovieError, MovieInput, MoviePlayer, MovieRecorder, MovieStart};
+use psx_core::psx::Psx;
+use psx_core::sio::joy::ControllerState;
+use psx_core::sio::memcard::MemoryCard;
+use std::sync::{Mutex, PoisonError};
+
+const BIOS_SIZE: usize = 0x8_0000;
+const CHECKSUM_INTERVAL: u32 = 5;
+
+// Every console maps the whole 4 GiB address space, so the tests take turns
+static CONSOLE: Mutex<()> = Mutex::new(());
+
+/// BIOS that keeps writing a counter across the first 4 KiB of RAM while the video timing runs on
+fn bios() -> Vec<u8> {
+    let program: [u32; 7] = [
+        0x3C08_8000, // lui   $t0, 0x8000
+        0x2529_0001, // addiu $t1, $t1, 1
+        0x312A_0FFC, // andi  $t2, $t1, 0xFFC
+        0x0148_5021, // addu  $t2, $t2, $t0
+        0xAD49_0000, // sw    $t1, 0($t2)
+        0x0BF0_0001, // j     0xBFC00004
+        0x0000_0000, // nop
+    ];
+
+    let mut bios: Vec<u8> = program.iter().flat_map(|word| word.to_le_bytes()).collect();
+    bios.resize(BIOS_SIZE, 0);
+    bios
+}
+
+fn input(frame: usize) -> MovieInput {
+    let pad = ControllerState {
+        cross: frame.is_multiple_of(2),
+        d_left: frame.is_multiple_of(3),
+        ..Default::default()
+    };
+    [pad, ControllerState::default()]
+}
+
+fn record(psx: &mut Psx, start: MovieStart, frames: usize) -> Movie {
+    let mut recorder = MovieRecorder::with_checksum_interval(psx, start, CHECKSUM_INTERVAL).unwrap();
+    for frame in 0..frames {
+        for (port, state) in input(frame).iter().enumerate() {
+            psx.set_controller_state(port, *state);
+        }
+        assert!(psx.run_frame(), "emulation error at {:08X}", psx.cpu.pc);
+        recorder.record_frame(psx, input(frame));
+    }
+    recorder.finish()
+}
+
+/// RAM and VRAM, what the movie checksums cover
+fn memory(psx: &Psx) -> (Vec<u8>, Vec<u8>) {
+    let mmu = &psx.cpu.mmu;
+    (
+        mmu.memory[..mmu.ram_size().bytes() as usize].to_vec(),
+        mmu.gpu.gp.vram.clone(),
+    )
+}
+
+#[test]
+fn test_power_on_playback() {
+    let _console = CONSOLE.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let (movie, expected) = {
+        let mut psx = Psx::new(&bios());
+        let movie = record(&mut psx, MovieStart::PowerOn, 30);
+        (movie.to_bytes(), memory(&psx))
+    };
+
+    let mut psx = Psx::new(&bios());
+    let mut player = MoviePlayer::new(Movie::parse(&movie).unwrap(), &mut psx).unwrap();
+    assert_eq!(player.run(&mut psx).unwrap(), 30);
+    assert!(player.is_finished());
+    assert!(memory(&psx) == expected, "playback ended on different RAM or VRAM");
+}
+
+#[test]
+fn test_savestate_playback_and_desync() {
+    let _console = CONSOLE.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut psx = Psx::new(&bios());
+    for _ in 0..5 {
+        psx.run_frame();
+    }
+
+    let state = psx.save_state();
+    let movie = record(&mut psx, MovieStart::Savestate(state), 20).to_bytes();
+    let expected = memory(&psx);
+
+    // Playback loads the embedded state, wherever the console went in the meantime
+    for _ in 0..7 {
+        psx.run_frame();
+    }
+    let mut player = MoviePlayer::new(Movie::parse(&movie).unwrap(), &mut psx).unwrap();
+    assert_eq!(player.run(&mut psx).unwrap(), 20);
+    assert!(memory(&psx) == expected, "playback ended on different RAM or VRAM");
+
+    // RAM changes behind the movie's back after frame 7, the checksum after frame 10 catches it
+    let mut player = MoviePlayer::new(Movie::parse(&movie).unwrap(), &mut psx).unwrap();
+    for _ in 0..7 {
+        assert!(player.start_frame(&mut psx));
+        psx.run_frame();
+        player.end_frame(&psx).unwrap();
+    }
+    psx.cpu.mmu.memory[0x1F_0000] ^= 0xFF;
+
+    assert!(matches!(player.run(&mut psx), Err(MovieError::Desync { frame: 10 })));
+}
+
+#[test]
+fn test_memory_card_contents() {
+    let _console = CONSOLE.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut psx = Psx::new(&bios());
+    psx.insert_memory_card(0, MemoryCard::new());
+
+    let state = psx.save_state();
+    let movie = record(&mut psx, MovieStart::Savestate(state), 5).to_bytes();
+
+    // Save states leave the cards out, so a card with other saves must be refused
+    let mut card = MemoryCard::new();
+    card.data_mut()[0x2000] = 0x51;
+    psx.insert_memory_card(0, card);
+    let result = MoviePlayer::new(Movie::parse(&movie).unwrap(), &mut psx);
+    assert!(matches!(result, Err(MovieError::MemoryCardMismatch)));
+
+    // A card in the other slot changes the device layout
+    psx.insert_memory_card(0, MemoryCard::new());
+    psx.insert_memory_card(1, MemoryCard::new());
+    let result = MoviePlayer::new(Movie::parse(&movie).unwrap(), &mut psx);
+    assert!(matches!(result, Err(MovieError::DeviceMismatch)));
+}
+
+#[test]
+fn test_same_memory_card_plays() {
+    let _console = CONSOLE.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut psx = Psx::new(&bios());
+    let mut card = MemoryCard::new();
+    card.data_mut()[0x2000] = 0x51;
+    psx.insert_memory_card(1, card);
+
+    let state = psx.save_state();
+    let movie = record(&mut psx, MovieStart::Savestate(state), 10).to_bytes();
+
+    let mut card = MemoryCard::new();
+    card.data_mut()[0x2000] = 0x51;
+    psx.insert_memory_card(1, card);
+    let mut player = MoviePlayer::new(Movie::parse(&movie).unwrap(), &mut psx).unwrap();
+    assert_eq!(player.run(&mut psx).unwrap(), 10);
+}
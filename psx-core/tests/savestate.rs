@@ -0,0 +1,93 @@
+//! Save states and the rewind buffer built on them have to resume exactly where the console was.
+
+use psx_core::psx::Psx;
+use psx_core::rewind::Rewind;
+use psx_core::savestate::SavestateError;
+use std::sync::{Mutex, PoisonError};
+
+const BIOS_SIZE: usize = 0x8_0000;
+
+// Every console maps the whole 4 GiB address space, so the tests take turns
+static CONSOLE: Mutex<()> = Mutex::new(());
+
+/// BIOS that keeps writing a counter across the first 4 KiB of RAM while the video timing runs on
+fn bios() -> Vec<u8> {
+    let program: [u32; 7] = [
+        0x3C08_8000, // lui   $t0, 0x8000
+        0x2529_0001, // addiu $t1, $t1, 1
+        0x312A_0FFC, // andi  $t2, $t1, 0xFFC
+        0x0148_5021, // addu  $t2, $t2, $t0
+        0xAD49_0000, // sw    $t1, 0($t2)
+        0x0BF0_0001, // j     0xBFC00004
+        0x0000_0000, // nop
+    ];
+
+    let mut bios: Vec<u8> = program.iter().flat_map(|word| word.to_le_bytes()).collect();
+    bios.resize(BIOS_SIZE, 0);
+    bios
+}
+
+fn run_frames(psx: &mut Psx, frames: usize) {
+    for _ in 0..frames {
+        assert!(psx.run_frame(), "emulation error at {:08X}", psx.cpu.pc);
+    }
+}
+
+#[test]
+fn test_save_load_run_round_trip() {
+    let _console = CONSOLE.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut psx = Psx::new(&bios());
+    run_frames(&mut psx, 10);
+
+    let state = psx.save_state();
+    run_frames(&mut psx, 30);
+    let expected = psx.save_state();
+
+    psx.load_state(&state).expect("state made by the same console loads");
+    assert!(psx.save_state() == state, "loading a state does not restore it exactly");
+
+    run_frames(&mut psx, 30);
+    assert!(psx.save_state() == expected, "console diverged after loading a state");
+
+    // A rejected state leaves the console as it was
+    let mut corrupted = state.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0xFF;
+    assert!(matches!(psx.load_state(&corrupted), Err(SavestateError::Corrupted)));
+    assert!(psx.save_state() == expected, "rejected state changed the console");
+}
+
+#[test]
+fn test_rewind_restores_earlier_frames() {
+    let _console = CONSOLE.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut psx = Psx::new(&bios());
+
+    // Snapshots every other frame, so odd frames are reached by running forward from the one before
+    let mut rewind = Rewind::new(64 * 1024 * 1024, 2);
+    let mut states = vec![psx.save_state()];
+    for _ in 0..20 {
+        run_frames(&mut psx, 1);
+        rewind.record(&psx);
+        states.push(psx.save_state());
+    }
+    assert_eq!(rewind.frames_available(), 18);
+
+    assert_eq!(rewind.rewind(&mut psx, 7).unwrap(), 7);
+    assert!(psx.save_state() == states[13], "rewinding did not restore frame 13");
+
+    assert!(rewind.step_back(&mut psx).unwrap());
+    assert!(psx.save_state() == states[12], "stepping back did not restore frame 12");
+
+    assert!(rewind.step_back(&mut psx).unwrap());
+    assert!(psx.save_state() == states[10], "stepping back did not restore frame 10");
+
+    // Recording again continues from the rewound frame
+    run_frames(&mut psx, 1);
+    rewind.record(&psx);
+    assert!(psx.save_state() == states[11], "console diverged after rewinding");
+
+    // Only as far back as the oldest snapshot
+    assert_eq!(rewind.rewind(&mut psx, 100).unwrap(), 9);
+    assert!(psx.save_state() == states[2], "rewinding did not stop at frame 2");
+    assert!(!rewind.step_back(&mut psx).unwrap());
+}
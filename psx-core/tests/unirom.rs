@@ -0,0 +1,124 @@
+//! nops talking to a running console over a socket, the way it talks to Unirom on hardware.
+#![cfg(unix)]
+
+use psx_core::psx::Psx;
+use psx_core::sio::link::LinkAddress;
+use psx_core::sio::unirom::UniromServer;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::thread::{self, JoinHandle};
+
+const BIOS_SIZE: usize = 0x8_0000;
+const EXE_HEADER_SIZE: usize = 0x800;
+
+/// BIOS that spins in place, so only the uploads change memory
+fn bios() -> Vec<u8> {
+    let program: [u32; 2] = [
+        0x0BF0_0000, // j     0xBFC00000
+        0x0000_0000, // nop
+    ];
+
+    let mut bios: Vec<u8> = program.iter().flat_map(|word| word.to_le_bytes()).collect();
+    bios.resize(BIOS_SIZE, 0);
+    bios
+}
+
+fn sum(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |sum, &byte| sum.wrapping_add(byte as u32))
+}
+
+fn expect(stream: &mut UnixStream, answer: &[u8; 4]) {
+    let mut buffer = [0u8; 4];
+    stream.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer, answer);
+}
+
+fn command(stream: &mut UnixStream, command: &[u8; 4], arguments: &[u32]) {
+    stream.write_all(command).unwrap();
+    expect(stream, b"OKAY");
+    for argument in arguments {
+        stream.write_all(&argument.to_le_bytes()).unwrap();
+    }
+}
+
+fn dump(stream: &mut UnixStream, address: u32, length: u32) -> Vec<u8> {
+    command(stream, b"DUMP", &[address, length]);
+    let mut data = vec![0u8; length as usize];
+    stream.read_exact(&mut data).unwrap();
+    data
+}
+
+/// Keep the console serviced and running while the client thread talks to it
+fn serve(psx: &mut Psx, server: &mut UniromServer, client: JoinHandle<UnixStream>) -> UnixStream {
+    while !client.is_finished() {
+        server.service(psx);
+        if !server.is_halted() {
+            psx.run_frame();
+        }
+    }
+    server.service(psx);
+    client.join().unwrap()
+}
+
+#[test]
+fn test_nops_session() {
+    let path = std::env::temp_dir().join(format!("psx-unirom-{}.sock", std::process::id()));
+    let mut server = UniromServer::listen(&LinkAddress::Unix(path.clone())).unwrap();
+    let mut psx = Psx::new(&bios());
+
+    let data: Vec<u8> = (0..0x100).map(|i| i as u8).collect();
+    let text: Vec<u8> = [0x0000_0000u32, 0x0800_C000, 0x0000_0000] // nop, j 0x80030000, nop
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    let mut exe = vec![0u8; EXE_HEADER_SIZE];
+    exe[..8].copy_from_slice(b"PS-X EXE");
+    exe[0x10..0x14].copy_from_slice(&0x8003_0000u32.to_le_bytes());
+    exe[0x18..0x1C].copy_from_slice(&0x8003_0000u32.to_le_bytes());
+    exe[0x1C..0x20].copy_from_slice(&(text.len() as u32).to_le_bytes());
+
+    let client = {
+        let (data, text) = (data.clone(), text.clone());
+        thread::spawn(move || {
+            let mut stream = UnixStream::connect(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            command(&mut stream, b"SBIN", &[0x8002_0000, data.len() as u32, sum(&data)]);
+            stream.write_all(&data).unwrap();
+            assert_eq!(dump(&mut stream, 0xA002_0000, 0x100), data);
+
+            // Registers read as zero and ignore writes, the interrupt controller never sees the access
+            command(&mut stream, b"SBIN", &[0x1F80_1074, 4, 4 * 0xFF]);
+            stream.write_all(&[0xFF; 4]).unwrap();
+            assert_eq!(dump(&mut stream, 0x1F80_1070, 8), [0; 8]);
+
+            command(&mut stream, b"SEXE", &[]);
+            stream.write_all(&exe).unwrap();
+            stream.write_all(&sum(&text).to_le_bytes()).unwrap();
+            stream.write_all(&text).unwrap();
+
+            stream.write_all(b"HALT").unwrap();
+            expect(&mut stream, b"HLTD");
+            stream
+        })
+    };
+
+    let mut stream = serve(&mut psx, &mut server, client);
+    assert!(server.is_halted());
+    assert_eq!(psx.cpu.mmu.memory[0x2_0000..0x2_0100], data[..]);
+    assert_eq!(psx.cpu.mmu.memory[0x3_0000..0x3_000C], text[..]);
+    assert_eq!(psx.cpu.mmu.irq.mask.0, 0);
+    // The console may have run the uploaded loop for a frame before the halt arrived
+    assert!((0x8003_0000..=0x8003_000C).contains(&psx.cpu.pc));
+
+    let client = thread::spawn(move || {
+        stream.write_all(b"CONT").unwrap();
+        expect(&mut stream, b"DONE");
+        stream
+    });
+
+    serve(&mut psx, &mut server, client);
+    assert!(!server.is_halted());
+    psx.run_frame();
+    assert!((0x8003_0000..=0x8003_000C).contains(&psx.cpu.pc));
+}
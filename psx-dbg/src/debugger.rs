@@ -6,14 +6,27 @@ use crate::states::mmu::MmuState;
 use crate::states::trace::TraceState;
 use crate::states::tty::TtyState;
 use crossbeam_channel::{Receiver, Sender};
+use psx_core::cartridge::Cartridge;
 use psx_core::cpu::decoder::Instruction;
 use psx_core::cpu::internal;
 use psx_core::gpu::{VRAM_HEIGHT, VRAM_WIDTH};
+use psx_core::mmu::RamSize;
+use psx_core::movie::{Movie, MovieInput, MoviePlayer, MovieRecorder, MovieStart};
 use psx_core::psx::Psx;
+use psx_core::sio::joy::ControllerState;
+use psx_core::sio::link::LinkAddress;
+use psx_core::sio::memcard::MemoryCard;
+use psx_core::sio::unirom::UniromServer;
 use std::collections::{HashSet, VecDeque};
+use std::path::Path;
 
 const GPU_UPDATE_INTERVAL: u32 = 100_000;
 
+enum MovieSession {
+    Recording(MovieRecorder),
+    Playing(MoviePlayer),
+}
+
 pub struct Debugger {
     pub psx: Psx,
     channel_send: Sender<DebuggerEvent>,
@@ -24,7 +37,16 @@ pub struct Debugger {
     breakpoints: HashSet<u32>,
     sideload_exe: Option<Vec<u8>>,
     bin_file: Option<Vec<u8>>,
+    memory_card_files: [Option<String>; 2],
+    expansion_rom_file: Option<String>,
+    pcdrv_root: Option<String>,
+    unirom: Option<UniromServer>,
     bios: Vec<u8>,
+    ram_size: RamSize,
+    state_name: String,                // Save state slots are named after the game
+    controller_state: ControllerState, // Latest input from the frontend, applied when the next frame starts
+    frame_input: MovieInput,
+    movie: Option<MovieSession>,
     cycle_counter: u32,
     frame_count: usize,
     fps_timer: std::time::Instant,
@@ -46,7 +68,16 @@ impl Debugger {
             breakpoints: HashSet::new(),
             sideload_exe: None,
             bin_file: None,
+            memory_card_files: [None, None],
+            expansion_rom_file: None,
+            pcdrv_root: None,
+            unirom: None,
             bios,
+            ram_size: RamSize::Retail,
+            state_name: file_stem(&bios_path),
+            controller_state: ControllerState::default(),
+            frame_input: [ControllerState::default(); 2],
+            movie: None,
             cycle_counter: 0,
             frame_count: 0,
             fps_timer: std::time::Instant::now(),
@@ -54,12 +85,31 @@ impl Debugger {
         }
     }
 
+    /// Rebuilds the console, has to come before anything else is loaded into it
+    pub fn with_ram_size(mut self, ram_size: RamSize) -> Self {
+        self.ram_size = ram_size;
+        self.psx = Psx::with_ram_size(&self.bios, ram_size);
+        self
+    }
+
     pub fn with_sideloaded_exe(mut self, path: Option<String>) -> Self {
         if let Some(exe_path) = path {
             let exe_buffer = std::fs::read(&exe_path)
                 .unwrap_or_else(|e| panic!("Failed to read sideloaded executable file '{}': {}", exe_path, e));
-            self.sideload_exe = Some(exe_buffer.clone());
-            self.psx.sideload_exe(exe_buffer);
+
+            // The console boots the BIOS instead, the UI shows why
+            if let Err(e) = self.psx.sideload_exe(exe_buffer.clone()) {
+                self.channel_send
+                    .send(DebuggerEvent::ExeFailed(format!(
+                        "Invalid sideloaded executable file '{}': {}",
+                        exe_path, e
+                    )))
+                    .expect("Failed to send EXE failed event");
+                return self;
+            }
+
+            self.sideload_exe = Some(exe_buffer);
+            self.state_name = file_stem(&exe_path);
         }
 
         self
@@ -71,14 +121,73 @@ impl Debugger {
                 .unwrap_or_else(|e| panic!("Failed to read CD-ROM image file '{}': {}", cdrom_path, e));
             self.bin_file = Some(bin.clone());
             self.psx.load_cdrom(bin);
+            self.state_name = file_stem(&cdrom_path);
+        }
+
+        self
+    }
+
+    pub fn with_memory_cards(mut self, paths: [Option<String>; 2]) -> Self {
+        self.memory_card_files = paths;
+        self.insert_memory_cards();
+        self
+    }
+
+    pub fn with_expansion_rom(mut self, path: Option<String>) -> Self {
+        self.expansion_rom_file = path;
+        self.insert_cartridge();
+        self
+    }
+
+    fn insert_cartridge(&mut self) {
+        if let Some(path) = &self.expansion_rom_file {
+            let cartridge = Cartridge::open(path)
+                .unwrap_or_else(|e| panic!("Failed to open expansion ROM image '{}': {}", path, e));
+            self.psx.insert_cartridge(cartridge);
         }
+    }
 
+    pub fn with_pcdrv(mut self, root: Option<String>) -> Self {
+        self.pcdrv_root = root;
+        self.enable_pcdrv();
         self
     }
 
+    fn enable_pcdrv(&mut self) {
+        if let Some(root) = &self.pcdrv_root {
+            self.psx
+                .enable_pcdrv(root)
+                .unwrap_or_else(|e| panic!("Failed to open PCdrv directory '{}': {}", root, e));
+        }
+    }
+
+    pub fn with_unirom(mut self, address: Option<String>) -> Self {
+        if let Some(address) = address {
+            let link_address = address
+                .parse::<LinkAddress>()
+                .unwrap_or_else(|e| panic!("Invalid Unirom address '{}': {}", address, e));
+            let server = UniromServer::listen(&link_address)
+                .unwrap_or_else(|e| panic!("Failed to listen for nops on '{}': {}", address, e));
+            self.unirom = Some(server);
+        }
+
+        self
+    }
+
+    fn insert_memory_cards(&mut self) {
+        for (slot, path) in self.memory_card_files.iter().enumerate() {
+            if let Some(path) = path {
+                let card = MemoryCard::open(path)
+                    .unwrap_or_else(|e| panic!("Failed to open memory card image '{}': {}", path, e));
+                self.psx.insert_memory_card(slot, card);
+            }
+        }
+    }
+
     pub fn run(&mut self) {
         loop {
             self.process_events();
+            self.service_unirom();
 
             if self.is_running {
                 // Check for breakpoints
@@ -100,8 +209,18 @@ impl Debugger {
                             self.trace.pop_front();
                         }
 
+                        // The game armed a COP0 breakpoint and it trapped
+                        if let Some(breakpoint) = self.psx.cpu.hardware_breakpoint.take() {
+                            self.is_running = false;
+                            self.channel_send
+                                .send(DebuggerEvent::HardwareBreakpointHit(breakpoint))
+                                .expect("Failed to send hardware breakpoint hit event");
+                        }
+
                         // Update FPS tracking
                         if frame_complete {
+                            self.complete_frame();
+
                             self.frame_count += 1;
                             let elapsed = self.fps_timer.elapsed().as_secs_f64();
                             if elapsed >= 1.0 {
@@ -147,6 +266,27 @@ impl Debugger {
         }
     }
 
+    fn service_unirom(&mut self) {
+        let Some(unirom) = &mut self.unirom else {
+            return;
+        };
+
+        let was_halted = unirom.is_halted();
+        unirom.service(&mut self.psx);
+
+        // nops halting or continuing the CPU works like the pause and run buttons
+        if unirom.is_halted() != was_halted {
+            self.is_running = !unirom.is_halted();
+            self.channel_send
+                .send(if self.is_running {
+                    DebuggerEvent::Unpaused
+                } else {
+                    DebuggerEvent::Paused
+                })
+                .expect("Failed to send pause state event");
+        }
+    }
+
     fn process_events(&mut self) {
         while let Ok(event) = self.channel_recv.try_recv() {
             match event {
@@ -157,7 +297,15 @@ impl Debugger {
                             .send(DebuggerEvent::Paused)
                             .expect("Failed to send paused event");
                     } else {
-                        let _ = self.psx.step();
+                        if let Ok((_, true)) = self.psx.step() {
+                            self.complete_frame();
+                        }
+
+                        if let Some(breakpoint) = self.psx.cpu.hardware_breakpoint.take() {
+                            self.channel_send
+                                .send(DebuggerEvent::HardwareBreakpointHit(breakpoint))
+                                .expect("Failed to send hardware breakpoint hit event");
+                        }
                     }
                 }
                 DebuggerEvent::Run => {
@@ -202,6 +350,7 @@ impl Debugger {
                     self.channel_send
                         .send(DebuggerEvent::TtyUpdated(TtyState {
                             buffer: internal::tty_buffer().lock().unwrap().clone(),
+                            post: self.psx.cpu.mmu.expansion2.post(),
                         }))
                         .unwrap();
                 }
@@ -230,62 +379,248 @@ impl Debugger {
                         .unwrap();
                 }
                 DebuggerEvent::Reset => {
-                    self.psx = Psx::new(&self.bios);
-
-                    if let Some(exe_buffer) = &self.sideload_exe {
-                        self.psx.sideload_exe(exe_buffer.clone());
+                    self.stop_movie();
+                    self.reset();
+                }
+                DebuggerEvent::UpdateController(controller_state) => {
+                    // Held back until the frame is over, so a movie can tell which frame saw it
+                    self.controller_state = controller_state;
+                }
+                DebuggerEvent::SetIgnoreErrors(ignore) => {
+                    self.ignore_errors = ignore;
+                }
+                DebuggerEvent::SaveState(slot) => {
+                    let path = self.state_path(slot);
+                    let event = match std::fs::write(&path, self.psx.save_state()) {
+                        Ok(()) => DebuggerEvent::StateSaved(slot),
+                        Err(e) => DebuggerEvent::StateFailed(format!("Failed to write '{}': {}", path, e)),
+                    };
+                    self.channel_send.send(event).unwrap();
+                }
+                DebuggerEvent::LoadState(slot) => {
+                    if self.movie.is_some() {
+                        self.channel_send
+                            .send(DebuggerEvent::StateFailed(
+                                "Stop the movie before loading a save state".to_string(),
+                            ))
+                            .unwrap();
+                        continue;
                     }
 
-                    if let Some(bin) = &self.bin_file {
-                        self.psx.load_cdrom(bin.clone());
+                    let path = self.state_path(slot);
+                    let result = std::fs::read(&path)
+                        .map_err(|e| format!("Failed to read '{}': {}", path, e))
+                        .and_then(|state| self.psx.load_state(&state).map_err(|e| e.to_string()));
+
+                    match result {
+                        Ok(()) => {
+                            self.trace.clear();
+                            self.send_full_update();
+                            self.channel_send.send(DebuggerEvent::StateLoaded(slot)).unwrap();
+                        }
+                        Err(e) => self.channel_send.send(DebuggerEvent::StateFailed(e)).unwrap(),
                     }
+                }
+                DebuggerEvent::RecordMovie { power_on } => {
+                    self.stop_movie();
 
-                    self.is_running = false;
-                    self.trace.clear();
-                    self.frame_count = 0;
-                    self.fps_timer = std::time::Instant::now();
-                    self.current_fps = 0.0;
+                    let recorder = if power_on {
+                        self.reset();
+                        MovieRecorder::from_power_on(&self.psx)
+                    } else {
+                        MovieRecorder::from_savestate(&self.psx)
+                    };
 
-                    psx_core::cpu::internal::tty_buffer().lock().unwrap().clear();
-                    psx_core::cpu::internal::tty_buffer().lock().unwrap().clear();
+                    let recorder = match recorder {
+                        Ok(recorder) => recorder,
+                        Err(e) => {
+                            self.channel_send.send(DebuggerEvent::MovieFailed(e.to_string())).unwrap();
+                            continue;
+                        }
+                    };
+                    self.movie = Some(MovieSession::Recording(recorder));
+                    self.start_frame();
 
                     self.channel_send
-                        .send(DebuggerEvent::CpuUpdated(CpuState {
-                            pc: self.psx.cpu.pc,
-                            registers: self.psx.cpu.registers.clone(),
-                            cop0: self.psx.cpu.cop0,
-                            cop2: self.psx.cpu.cop2,
-                            hi: self.psx.cpu.hi,
-                            lo: self.psx.cpu.lo,
-                        }))
-                        .unwrap();
-                    self.channel_send
-                        .send(DebuggerEvent::MmuUpdated(MmuState {
-                            data: self.psx.cpu.mmu.memory.clone(),
-                        }))
-                        .unwrap();
-                    self.channel_send
-                        .send(DebuggerEvent::TraceUpdated(TraceState {
-                            instructions: self.trace.clone(),
-                        }))
-                        .unwrap();
-                    self.channel_send
-                        .send(DebuggerEvent::TtyUpdated(TtyState {
-                            buffer: internal::tty_buffer().lock().unwrap().clone(),
-                        }))
+                        .send(DebuggerEvent::MovieUpdated(format!(
+                            "Recording movie to '{}'",
+                            self.movie_path()
+                        )))
                         .unwrap();
-                    self.channel_send
-                        .send(DebuggerEvent::Paused)
-                        .expect("Failed to send paused event");
                 }
-                DebuggerEvent::UpdateController(controller_state) => {
-                    self.psx.set_controller_state(controller_state);
+                DebuggerEvent::PlayMovie => {
+                    self.stop_movie();
+                    self.play_movie();
                 }
-                DebuggerEvent::SetIgnoreErrors(ignore) => {
-                    self.ignore_errors = ignore;
+                DebuggerEvent::StopMovie => {
+                    self.stop_movie();
                 }
                 _ => {}
             }
         }
     }
+
+    /// Power cycle the console with the same media, paused
+    fn reset(&mut self) {
+        self.psx = Psx::with_ram_size(&self.bios, self.ram_size);
+
+        if let Some(exe_buffer) = &self.sideload_exe {
+            self.psx
+                .sideload_exe(exe_buffer.clone())
+                .expect("Sideloaded EXE was validated when the debugger started");
+        }
+
+        if let Some(bin) = &self.bin_file {
+            self.psx.load_cdrom(bin.clone());
+        }
+
+        self.insert_memory_cards();
+        self.insert_cartridge();
+        self.enable_pcdrv();
+
+        self.is_running = false;
+        self.trace.clear();
+        self.frame_count = 0;
+        self.fps_timer = std::time::Instant::now();
+        self.current_fps = 0.0;
+
+        psx_core::cpu::internal::tty_buffer().lock().unwrap().clear();
+        psx_core::cpu::internal::tty_buffer().lock().unwrap().clear();
+
+        self.send_full_update();
+        self.channel_send
+            .send(DebuggerEvent::Paused)
+            .expect("Failed to send paused event");
+    }
+
+    fn state_path(&self, slot: usize) -> String {
+        format!("{}.ss{}", self.state_name, slot)
+    }
+
+    fn movie_path(&self) -> String {
+        format!("{}.pmv", self.state_name)
+    }
+
+    /// Apply the input of the frame that is about to start, from the movie being played or the frontend
+    fn start_frame(&mut self) {
+        if let Some(MovieSession::Playing(player)) = &mut self.movie
+            && !player.start_frame(&mut self.psx)
+        {
+            let frames = player.frame();
+            self.movie = None;
+            self.channel_send
+                .send(DebuggerEvent::MovieUpdated(format!(
+                    "Movie finished after {} frames",
+                    frames
+                )))
+                .unwrap();
+        }
+
+        if !matches!(self.movie, Some(MovieSession::Playing(_))) {
+            self.frame_input = [self.controller_state, ControllerState::default()];
+            for (port, state) in self.frame_input.iter().enumerate() {
+                self.psx.set_controller_state(port, *state);
+            }
+        }
+    }
+
+    fn complete_frame(&mut self) {
+        match &mut self.movie {
+            Some(MovieSession::Recording(recorder)) => recorder.record_frame(&self.psx, self.frame_input),
+            Some(MovieSession::Playing(player)) => {
+                if let Err(e) = player.end_frame(&self.psx) {
+                    self.movie = None;
+                    self.is_running = false;
+                    self.channel_send
+                        .send(DebuggerEvent::MovieFailed(e.to_string()))
+                        .unwrap();
+                    self.channel_send.send(DebuggerEvent::Paused).unwrap();
+                }
+            }
+            None => {}
+        }
+
+        self.start_frame();
+    }
+
+    fn play_movie(&mut self) {
+        let path = self.movie_path();
+        let result = std::fs::read(&path)
+            .map_err(|e| format!("Failed to read '{}': {}", path, e))
+            .and_then(|data| Movie::parse(&data).map_err(|e| e.to_string()))
+            .and_then(|movie| {
+                // A save state movie is loaded by the player, a power-on one needs a fresh console
+                if let MovieStart::PowerOn = movie.start() {
+                    self.reset();
+                }
+
+                MoviePlayer::new(movie, &mut self.psx).map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(player) => {
+                let frames = player.movie().len();
+                self.movie = Some(MovieSession::Playing(player));
+                self.start_frame();
+                self.trace.clear();
+                self.send_full_update();
+                self.channel_send
+                    .send(DebuggerEvent::MovieUpdated(format!(
+                        "Playing {} frames from '{}'",
+                        frames, path
+                    )))
+                    .unwrap();
+            }
+            Err(e) => self.channel_send.send(DebuggerEvent::MovieFailed(e)).unwrap(),
+        }
+    }
+
+    /// End playback, or write the recording next to the save states
+    fn stop_movie(&mut self) {
+        if let Some(MovieSession::Recording(recorder)) = self.movie.take() {
+            let path = self.movie_path();
+            let movie = recorder.finish();
+            let event = match std::fs::write(&path, movie.to_bytes()) {
+                Ok(()) => DebuggerEvent::MovieUpdated(format!("Saved {} frames to '{}'", movie.len(), path)),
+                Err(e) => DebuggerEvent::MovieFailed(format!("Failed to write '{}': {}", path, e)),
+            };
+            self.channel_send.send(event).unwrap();
+        }
+    }
+
+    /// Refresh every view after the console changed under the frontend
+    fn send_full_update(&self) {
+        self.channel_send
+            .send(DebuggerEvent::CpuUpdated(CpuState {
+                pc: self.psx.cpu.pc,
+                registers: self.psx.cpu.registers.clone(),
+                cop0: self.psx.cpu.cop0,
+                cop2: self.psx.cpu.cop2,
+                hi: self.psx.cpu.hi,
+                lo: self.psx.cpu.lo,
+            }))
+            .unwrap();
+        self.channel_send
+            .send(DebuggerEvent::MmuUpdated(MmuState {
+                data: self.psx.cpu.mmu.memory.clone(),
+            }))
+            .unwrap();
+        self.channel_send
+            .send(DebuggerEvent::TraceUpdated(TraceState {
+                instructions: self.trace.clone(),
+            }))
+            .unwrap();
+        self.channel_send
+            .send(DebuggerEvent::TtyUpdated(TtyState {
+                buffer: internal::tty_buffer().lock().unwrap().clone(),
+                post: self.psx.cpu.mmu.expansion2.post(),
+            }))
+            .unwrap();
+    }
+}
+
+fn file_stem(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map_or_else(|| "pspsps".to_string(), |stem| stem.to_string_lossy().into_owned())
 }
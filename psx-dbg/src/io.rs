@@ -4,6 +4,7 @@ use crate::states::gpu::GpuState;
 use crate::states::mmu::MmuState;
 use crate::states::trace::TraceState;
 use crate::states::tty::TtyState;
+use psx_core::cpu::cop::cop0::HardwareBreakpoint;
 use psx_core::sio::joy::ControllerState;
 
 pub enum DebuggerEvent {
@@ -22,11 +23,23 @@ pub enum DebuggerEvent {
     UpdateTty,
     UpdateController(ControllerState),
     SetIgnoreErrors(bool),
+    SaveState(usize),
+    LoadState(usize),
+    RecordMovie { power_on: bool },
+    PlayMovie,
+    StopMovie,
     BreakpointHit(u32),
+    HardwareBreakpointHit(HardwareBreakpoint),
     BreakpointsUpdated(BreakpointsState),
     TraceUpdated(TraceState),
     TtyUpdated(TtyState),
     CpuUpdated(CpuState),
     MmuUpdated(MmuState),
     GpuUpdated(GpuState),
+    StateSaved(usize),
+    StateLoaded(usize),
+    StateFailed(String),
+    MovieUpdated(String),
+    MovieFailed(String),
+    ExeFailed(String),
 }
@@ -8,6 +8,7 @@ use clap::Parser;
 use eframe::egui;
 use egui_dock::{DockArea, DockState};
 use egui_toast::{Toast, ToastKind, Toasts};
+use psx_core::mmu::RamSize;
 use psx_core::sio::joy::ControllerState;
 use std::collections::HashMap;
 use std::time::Duration;
@@ -22,6 +23,9 @@ use widgets::{
 use crate::debugger::Debugger;
 use crate::io::DebuggerEvent;
 
+// Save state slots offered in the State menu, stored next to the game as <name>.ss<slot>
+const STATE_SLOTS: usize = 4;
+
 #[derive(Parser, Debug)]
 #[command(about = "pspsps - a cute psx debugger", long_about = None)]
 struct Args {
@@ -34,6 +38,24 @@ struct Args {
     #[arg(long, help = "Path to EXE to sideload")]
     sideload: Option<String>,
 
+    #[arg(long, help = "Path to memory card image (.mcr) for slot 1, created if missing")]
+    memcard1: Option<String>,
+
+    #[arg(long, help = "Path to memory card image (.mcr) for slot 2, created if missing")]
+    memcard2: Option<String>,
+
+    #[arg(long, help = "Path to expansion ROM image (Action Replay, Xplorer, Caetla), flash writes are saved back")]
+    expansion_rom: Option<String>,
+
+    #[arg(long, help = "Host directory served to homebrew through PCdrv")]
+    pcdrv: Option<String>,
+
+    #[arg(long, help = "Accept nops uploads on host:port or unix:/path, speaking the Unirom serial protocol")]
+    unirom: Option<String>,
+
+    #[arg(long, help = "Install 8 MiB of main RAM like the dev kits instead of the retail 2 MiB")]
+    devkit_ram: bool,
+
     #[arg(long, value_delimiter = ',', help = "List of tracing targets")]
     log_targets: Option<Vec<String>>,
 
@@ -95,7 +117,7 @@ impl Default for PsxDebugger {
 }
 
 impl PsxDebugger {
-    fn new(bios_path: String, sideload_file: Option<String>, cdrom_file: Option<String>) -> Self {
+    fn new(args: Args) -> Self {
         let mut dock_state = DockState::new(vec![TabKind::Cpu, TabKind::Trace]);
         let [left_node, right_node] =
             dock_state
@@ -131,10 +153,20 @@ impl PsxDebugger {
         let (request_channel_send, request_channel_recv) = crossbeam_channel::unbounded();
         let (response_channel_send, response_channel_recv) = crossbeam_channel::unbounded();
 
+        let ram_size = if args.devkit_ram {
+            RamSize::DevKit
+        } else {
+            RamSize::Retail
+        };
         let thread = std::thread::spawn(move || {
-            let mut debugger = Debugger::new(bios_path, response_channel_send, request_channel_recv)
-                .with_sideloaded_exe(sideload_file)
-                .with_cdrom_image(cdrom_file);
+            let mut debugger = Debugger::new(args.bios, response_channel_send, request_channel_recv)
+                .with_ram_size(ram_size)
+                .with_sideloaded_exe(args.sideload)
+                .with_cdrom_image(args.cdrom)
+                .with_memory_cards([args.memcard1, args.memcard2])
+                .with_expansion_rom(args.expansion_rom)
+                .with_pcdrv(args.pcdrv)
+                .with_unirom(args.unirom);
             debugger.run();
         });
 
@@ -199,6 +231,16 @@ impl eframe::App for PsxDebugger {
                         style: Default::default(),
                     });
                 }
+                DebuggerEvent::HardwareBreakpointHit(breakpoint) => {
+                    self.state.is_running = false;
+
+                    self.toasts.add(Toast {
+                        text: format!("COP0 {} hit", breakpoint).into(),
+                        kind: ToastKind::Info,
+                        options: egui_toast::ToastOptions::default().duration(Some(Duration::from_secs(3))),
+                        style: Default::default(),
+                    });
+                }
                 DebuggerEvent::TraceUpdated(state) => {
                     self.state.trace = state;
                 }
@@ -241,6 +283,40 @@ impl eframe::App for PsxDebugger {
                         style: Default::default(),
                     });
                 }
+                DebuggerEvent::StateSaved(slot) => {
+                    self.toasts.add(Toast {
+                        text: format!("Saved state to slot {}", slot).into(),
+                        kind: ToastKind::Success,
+                        options: egui_toast::ToastOptions::default().duration(Some(Duration::from_secs(3))),
+                        style: Default::default(),
+                    });
+                }
+                DebuggerEvent::StateLoaded(slot) => {
+                    self.toasts.add(Toast {
+                        text: format!("Loaded state from slot {}", slot).into(),
+                        kind: ToastKind::Success,
+                        options: egui_toast::ToastOptions::default().duration(Some(Duration::from_secs(3))),
+                        style: Default::default(),
+                    });
+                }
+                DebuggerEvent::MovieUpdated(message) => {
+                    self.toasts.add(Toast {
+                        text: message.into(),
+                        kind: ToastKind::Info,
+                        options: egui_toast::ToastOptions::default().duration(Some(Duration::from_secs(3))),
+                        style: Default::default(),
+                    });
+                }
+                DebuggerEvent::StateFailed(error)
+                | DebuggerEvent::MovieFailed(error)
+                | DebuggerEvent::ExeFailed(error) => {
+                    self.toasts.add(Toast {
+                        text: error.into(),
+                        kind: ToastKind::Error,
+                        options: egui_toast::ToastOptions::default().duration(Some(Duration::from_secs(5))),
+                        style: Default::default(),
+                    });
+                }
                 _ => {}
             }
         }
@@ -260,6 +336,47 @@ impl eframe::App for PsxDebugger {
             .send(DebuggerEvent::UpdateTrace)
             .expect("Failed to send update Trace event");
 
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
+                ui.menu_button("State", |ui| {
+                    for slot in 1..=STATE_SLOTS {
+                        if ui.button(format!("Save slot {}", slot)).clicked() {
+                            let _ = self.channel_send.send(DebuggerEvent::SaveState(slot));
+                        }
+                    }
+
+                    ui.separator();
+
+                    for slot in 1..=STATE_SLOTS {
+                        if ui.button(format!("Load slot {}", slot)).clicked() {
+                            let _ = self.channel_send.send(DebuggerEvent::LoadState(slot));
+                        }
+                    }
+                });
+
+                // Movies are stored next to the save states as <name>.pmv
+                ui.menu_button("Movie", |ui| {
+                    if ui.button("Record from power-on").clicked() {
+                        let _ = self.channel_send.send(DebuggerEvent::RecordMovie { power_on: true });
+                    }
+
+                    if ui.button("Record from here").clicked() {
+                        let _ = self.channel_send.send(DebuggerEvent::RecordMovie { power_on: false });
+                    }
+
+                    if ui.button("Play").clicked() {
+                        let _ = self.channel_send.send(DebuggerEvent::PlayMovie);
+                    }
+
+                    ui.separator();
+
+                    if ui.button("Stop").clicked() {
+                        let _ = self.channel_send.send(DebuggerEvent::StopMovie);
+                    }
+                });
+            });
+        });
+
         let mut tab_viewer = TabViewer {
             channel_send: &self.channel_send,
             widgets: &mut self.widgets,
@@ -339,6 +456,10 @@ fn main() -> eframe::Result {
         targets = targets.with_target("psx_core::joy", tracing_level);
         targets = targets.with_target("psx_core::mc", tracing_level);
         targets = targets.with_target("psx_core::cdrom", tracing_level);
+        targets = targets.with_target("psx_core::unirom", tracing_level);
+        targets = targets.with_target("psx_core::pcdrv", tracing_level);
+        targets = targets.with_target("psx_core::exp2", tracing_level);
+        targets = targets.with_target("psx_core::cart", tracing_level);
     }
 
     if args.json {
@@ -367,6 +488,8 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "pspsps - a cute psx debugger",
         options,
-        Box::new(|_cc| Ok(Box::new(PsxDebugger::new(args.bios, args.sideload, args.cdrom)))),
+        Box::new(|_cc| {
+            Ok(Box::new(PsxDebugger::new(args)))
+        }),
     )
 }
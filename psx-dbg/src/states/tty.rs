@@ -1,11 +1,13 @@
 pub struct TtyState {
     pub buffer: String,
+    pub post: u8, // Last value written to the expansion 2 POST display
 }
 
 impl Default for TtyState {
     fn default() -> Self {
         Self {
             buffer: String::new(),
+            post: 0,
         }
     }
 }
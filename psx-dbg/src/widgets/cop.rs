@@ -93,6 +93,28 @@ impl Widget for CopWidget {
                             context.state.cpu.cop0.cause.interrupt_pending()
                         ));
                     });
+
+                CollapsingHeader::new("Debug Registers")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        let cop0 = &context.state.cpu.cop0;
+                        let cop0_prev = &context.state.previous_cpu.cop0;
+
+                        for (name, value, value_prev) in [
+                            ("DCIC", cop0.dcic.0, cop0_prev.dcic.0),
+                            ("BPC ", cop0.bpc, cop0_prev.bpc),
+                            ("BPCM", cop0.bdcm, cop0_prev.bdcm),
+                            ("BDA ", cop0.bda, cop0_prev.bda),
+                            ("BDAM", cop0.bdam, cop0_prev.bdam),
+                        ] {
+                            let text = format!("{}: {:08X}", name, value);
+                            if value != value_prev {
+                                ui.colored_label(COLOR_DIRTY, RichText::new(text).monospace());
+                            } else {
+                                ui.monospace(text);
+                            }
+                        }
+                    });
             });
 
         ui.separator();
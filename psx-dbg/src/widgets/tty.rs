@@ -15,6 +15,9 @@ impl Widget for TtyWidget {
     }
 
     fn ui(&mut self, ui: &mut Ui, context: &mut SharedContext) {
+        ui.monospace(format!("POST: {:02X}", context.state.tty.post));
+        ui.separator();
+
         ScrollArea::vertical()
             .auto_shrink([false, false])
             .stick_to_bottom(true)
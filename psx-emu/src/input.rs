@@ -1,4 +1,4 @@
-use psx_core::sio::joy::ControllerState;
+use psx_core::sio::joy::{ANALOG_CENTER, ControllerState, PointerState};
 use std::collections::HashSet;
 use winit::keyboard::{Key, KeyCode, NamedKey};
 
@@ -10,12 +10,65 @@ enum InputKey {
 
 pub struct InputState {
     pressed_keys: HashSet<InputKey>,
+    mouse_motion: (f64, f64),            // Accumulated since the last pointer state was taken
+    cursor_position: Option<(f32, f32)>, // Relative to the window, from 0.0 to 1.0
+    mouse_left: bool,
+    mouse_right: bool,
+    mouse_middle: bool,
 }
 
 impl InputState {
     pub fn new() -> Self {
         Self {
             pressed_keys: HashSet::new(),
+            mouse_motion: (0.0, 0.0),
+            cursor_position: None,
+            mouse_left: false,
+            mouse_right: false,
+            mouse_middle: false,
+        }
+    }
+
+    pub fn handle_mouse_motion(&mut self, delta: (f64, f64)) {
+        self.mouse_motion.0 += delta.0;
+        self.mouse_motion.1 += delta.1;
+    }
+
+    /// The frame is stretched over the whole window, so the cursor position maps straight to the display
+    pub fn handle_cursor_moved(
+        &mut self, position: winit::dpi::PhysicalPosition<f64>, window_size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        self.cursor_position = Some((
+            (position.x / window_size.width as f64) as f32,
+            (position.y / window_size.height as f64) as f32,
+        ));
+    }
+
+    pub fn handle_cursor_left(&mut self) {
+        self.cursor_position = None;
+    }
+
+    pub fn handle_mouse_button(&mut self, button: winit::event::MouseButton, state: winit::event::ElementState) {
+        let pressed = state == winit::event::ElementState::Pressed;
+        match button {
+            winit::event::MouseButton::Left => self.mouse_left = pressed,
+            winit::event::MouseButton::Right => self.mouse_right = pressed,
+            winit::event::MouseButton::Middle => self.mouse_middle = pressed,
+            _ => {}
+        }
+    }
+
+    /// Mouse buttons and the motion since the previous call
+    pub fn take_pointer_state(&mut self) -> PointerState {
+        let (delta_x, delta_y) = std::mem::take(&mut self.mouse_motion);
+
+        PointerState {
+            delta_x: delta_x as i32,
+            delta_y: delta_y as i32,
+            position: self.cursor_position,
+            left: self.mouse_left,
+            right: self.mouse_right,
+            middle: self.mouse_middle,
         }
     }
 
@@ -34,6 +87,7 @@ impl InputState {
             Key::Named(NamedKey::ArrowRight) => InputKey::Physical(KeyCode::ArrowRight),
             Key::Named(NamedKey::Enter) => InputKey::Physical(KeyCode::Enter),
             Key::Named(NamedKey::Space) => InputKey::Physical(KeyCode::Space),
+            Key::Named(NamedKey::Tab) => InputKey::Physical(KeyCode::Tab),
             _ => return,
         };
 
@@ -47,6 +101,23 @@ impl InputState {
         }
     }
 
+    /// Full deflection of a stick axis from a pair of keys
+    fn axis(&self, negative: char, positive: char) -> u8 {
+        match (
+            self.pressed_keys.contains(&InputKey::Character(negative)),
+            self.pressed_keys.contains(&InputKey::Character(positive)),
+        ) {
+            (true, false) => 0x00,
+            (false, true) => 0xFF,
+            _ => ANALOG_CENTER,
+        }
+    }
+
+    /// Tab is held to rewind
+    pub fn is_rewinding(&self) -> bool {
+        self.pressed_keys.contains(&InputKey::Physical(KeyCode::Tab))
+    }
+
     pub fn get_controller_state(&self) -> ControllerState {
         ControllerState {
             // D-Pad: Arrow keys
@@ -70,6 +141,15 @@ impl InputState {
             // System buttons
             start: self.pressed_keys.contains(&InputKey::Physical(KeyCode::Enter)),
             select: self.pressed_keys.contains(&InputKey::Physical(KeyCode::Backspace)),
+
+            // Analog controllers: IJKL = left stick, TFGH = right stick
+            l3: self.pressed_keys.contains(&InputKey::Character('c')),
+            r3: self.pressed_keys.contains(&InputKey::Character('v')),
+            analog: self.pressed_keys.contains(&InputKey::Character('m')),
+            left_x: self.axis('j', 'l'),
+            left_y: self.axis('i', 'k'),
+            right_x: self.axis('f', 'h'),
+            right_y: self.axis('t', 'g'),
         }
     }
 }
@@ -1,14 +1,23 @@
 mod input;
 mod renderer;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use psx_core::cartridge::Cartridge;
+use psx_core::mmu::RamSize;
+use psx_core::movie::{Movie, MoviePlayer, MovieRecorder};
 use psx_core::psx::Psx;
+use psx_core::rewind::{REWIND_DEFAULT_CAPACITY, REWIND_DEFAULT_INTERVAL, Rewind};
+use psx_core::sio::joy::{ControllerState, ControllerType};
+use psx_core::sio::link::{LinkAddress, LinkCable};
+use psx_core::sio::memcard::MemoryCard;
+use psx_core::sio::unirom::UniromServer;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
 use winit::application::ApplicationHandler;
-use winit::event::WindowEvent;
+use winit::event::{DeviceEvent, DeviceId, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::{Window, WindowId};
 
 #[derive(Parser, Debug)]
@@ -23,12 +32,108 @@ struct Args {
 
     #[arg(short, long)]
     sideload: Option<PathBuf>,
+
+    /// Memory card image (.mcr) for slot 1, created if missing
+    #[arg(long)]
+    memcard1: Option<PathBuf>,
+
+    /// Memory card image (.mcr) for slot 2, created if missing
+    #[arg(long)]
+    memcard2: Option<PathBuf>,
+
+    /// Controller plugged into port 1
+    #[arg(long, value_enum, default_value_t = Controller::Digital)]
+    controller1: Controller,
+
+    /// Controller plugged into port 2
+    #[arg(long, value_enum, default_value_t = Controller::Digital)]
+    controller2: Controller,
+
+    /// Plug a multitap into controller port 1 or 2
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2))]
+    multitap: Option<u8>,
+
+    /// Wait for another instance to plug into the serial port (host:port or unix:/path)
+    #[arg(long, conflicts_with = "link_connect")]
+    link_listen: Option<LinkAddress>,
+
+    /// Plug the serial port into another instance started with --link-listen
+    #[arg(long)]
+    link_connect: Option<LinkAddress>,
+
+    /// Cartridge ROM image for expansion region 1 (Action Replay, Xplorer, Caetla), flash writes are saved back
+    #[arg(long)]
+    expansion_rom: Option<PathBuf>,
+
+    /// Host directory served to homebrew through PCdrv
+    #[arg(long)]
+    pcdrv: Option<PathBuf>,
+
+    /// Accept nops uploads speaking the Unirom serial protocol (host:port or unix:/path)
+    #[arg(long)]
+    unirom: Option<LinkAddress>,
+
+    /// Install 8 MiB of main RAM like the dev kits instead of the retail 2 MiB
+    #[arg(long)]
+    devkit_ram: bool,
+
+    /// Memory for rewinding with Tab in MiB, 0 disables rewinding
+    #[arg(long, default_value_t = REWIND_DEFAULT_CAPACITY / (1024 * 1024))]
+    rewind_buffer: usize,
+
+    /// Frames between two rewind snapshots
+    #[arg(long, default_value_t = REWIND_DEFAULT_INTERVAL)]
+    rewind_interval: u64,
+
+    /// Record the controller input into a movie, written when the window is closed
+    #[arg(long, conflicts_with = "play_movie")]
+    record_movie: Option<PathBuf>,
+
+    /// Start the recorded movie from a save state instead of power-on
+    #[arg(long, requires = "record_movie")]
+    movie_from_state: Option<PathBuf>,
+
+    /// Play a movie back instead of reading the keyboard, the same media has to be given
+    #[arg(long)]
+    play_movie: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Controller {
+    Digital,
+    Dualshock,
+    Mouse,
+    Negcon,
+    Guncon,
+    Justifier,
+}
+
+impl From<Controller> for ControllerType {
+    fn from(controller: Controller) -> Self {
+        match controller {
+            Controller::Digital => ControllerType::Digital,
+            Controller::Dualshock => ControllerType::DualShock,
+            Controller::Mouse => ControllerType::Mouse,
+            Controller::Negcon => ControllerType::Negcon,
+            Controller::Guncon => ControllerType::Guncon,
+            Controller::Justifier => ControllerType::Justifier,
+        }
+    }
+}
+
+enum MovieSession {
+    Recording { recorder: MovieRecorder, path: PathBuf },
+    Playing(MoviePlayer),
 }
 
 struct App {
     window: Option<Arc<Window>>,
     renderer: Option<renderer::Renderer>,
     psx: Option<Psx>,
+    unirom: Option<UniromServer>,
+    state_name: String, // Save state files are named after the game
+    rewind: Option<Rewind>,
+    movie: Option<MovieSession>,
     input_state: input::InputState,
     frame_count: usize,
     fps_timer: std::time::Instant,
@@ -60,6 +165,7 @@ impl ApplicationHandler for App {
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
         match event {
             WindowEvent::CloseRequested => {
+                self.save_movie();
                 event_loop.exit();
             }
             WindowEvent::KeyboardInput { event, .. } => {
@@ -75,25 +181,88 @@ impl ApplicationHandler for App {
                         self.save_screenshot(width, height, &frame);
                     }
                 }
+
+                // F1-F4 save to slot 1-4, F5-F8 load them again
+                if event.state == winit::event::ElementState::Pressed && !event.repeat {
+                    match event.physical_key {
+                        PhysicalKey::Code(KeyCode::F1) => self.save_state(1),
+                        PhysicalKey::Code(KeyCode::F2) => self.save_state(2),
+                        PhysicalKey::Code(KeyCode::F3) => self.save_state(3),
+                        PhysicalKey::Code(KeyCode::F4) => self.save_state(4),
+                        PhysicalKey::Code(KeyCode::F5) => self.load_state(1),
+                        PhysicalKey::Code(KeyCode::F6) => self.load_state(2),
+                        PhysicalKey::Code(KeyCode::F7) => self.load_state(3),
+                        PhysicalKey::Code(KeyCode::F8) => self.load_state(4),
+                        _ => {}
+                    }
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(window) = &self.window {
+                    self.input_state.handle_cursor_moved(position, window.inner_size());
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.input_state.handle_cursor_left();
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.input_state.handle_mouse_button(button, state);
             }
             WindowEvent::RedrawRequested => {
                 if let Some(psx) = &mut self.psx {
-                    // Update controller state
+                    // Update controller state, a movie being played back takes over both ports
                     let controller_state = self.input_state.get_controller_state();
-                    psx.set_controller_state(controller_state);
-
-                    // Run emulation until frame completes
-                    loop {
-                        match psx.step() {
-                            Ok((_, frame_complete)) => {
-                                if frame_complete {
-                                    break;
-                                }
+                    if let Some(MovieSession::Playing(player)) = &mut self.movie
+                        && !player.start_frame(psx)
+                    {
+                        println!("Movie finished after {} frames", player.frame());
+                        self.movie = None;
+                    }
+
+                    if !matches!(self.movie, Some(MovieSession::Playing(_))) {
+                        psx.set_controller_state(0, controller_state);
+                    }
+
+                    // Mice and light guns on either port follow the host mouse
+                    let pointer_state = self.input_state.take_pointer_state();
+                    for port in 0..2 {
+                        psx.set_pointer_state(port, pointer_state);
+                    }
+
+                    // Apply uploads from nops, a halted CPU keeps showing the last frame
+                    if let Some(unirom) = &mut self.unirom {
+                        unirom.service(psx);
+                    }
+
+                    let halted = self.unirom.as_ref().is_some_and(UniromServer::is_halted);
+
+                    // Run emulation until frame completes, or go back in time while Tab is held
+                    if self.input_state.is_rewinding()
+                        && let Some(rewind) = &mut self.rewind
+                    {
+                        if let Err(e) = rewind.step_back(psx) {
+                            eprintln!("Failed to rewind: {}", e);
+                        }
+                    } else if !halted {
+                        if !psx.run_frame() {
+                            eprintln!("Error during emulation step");
+                        }
+
+                        if let Some(rewind) = &mut self.rewind {
+                            rewind.record(psx);
+                        }
+
+                        match &mut self.movie {
+                            Some(MovieSession::Recording { recorder, .. }) => {
+                                recorder.record_frame(psx, [controller_state, ControllerState::default()]);
                             }
-                            Err(_) => {
-                                eprintln!("Error during emulation step");
-                                break;
+                            Some(MovieSession::Playing(player)) => {
+                                if let Err(e) = player.end_frame(psx) {
+                                    eprintln!("{}, handing control back to the keyboard", e);
+                                    self.movie = None;
+                                }
                             }
+                            None => {}
                         }
                     }
 
@@ -131,6 +300,12 @@ impl ApplicationHandler for App {
             _ => {}
         }
     }
+
+    fn device_event(&mut self, _: &ActiveEventLoop, _: DeviceId, event: DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.input_state.handle_mouse_motion(delta);
+        }
+    }
 }
 
 impl App {
@@ -139,7 +314,12 @@ impl App {
         let bios = fs::read(&args.bios).expect("Failed to read BIOS file");
 
         // Create PSX instance
-        let mut psx = Psx::new(&bios);
+        let ram_size = if args.devkit_ram {
+            RamSize::DevKit
+        } else {
+            RamSize::Retail
+        };
+        let mut psx = Psx::with_ram_size(&bios, ram_size);
 
         // Load CD-ROM if provided
         if let Some(cdrom_path) = &args.cdrom {
@@ -151,14 +331,108 @@ impl App {
         // Load sideload EXE if provided
         if let Some(sideload_path) = &args.sideload {
             let exe_data = fs::read(sideload_path).expect("Failed to read sideload EXE file");
-            psx.sideload_exe(exe_data);
+            if let Err(e) = psx.sideload_exe(exe_data) {
+                eprintln!("Invalid sideload EXE {:?}: {}", sideload_path, e);
+                std::process::exit(1);
+            }
             println!("Loaded sideload EXE: {:?}", sideload_path);
         }
 
+        if let Some(path) = &args.expansion_rom {
+            let cartridge = Cartridge::open(path).expect("Failed to open expansion ROM image");
+            psx.insert_cartridge(cartridge);
+            println!("Inserted cartridge: {:?}", path);
+        }
+
+        if let Some(root) = &args.pcdrv {
+            psx.enable_pcdrv(root).expect("Failed to open PCdrv directory");
+            println!("Serving PCdrv from {:?}", root);
+        }
+
+        for (port, controller) in [args.controller1, args.controller2].into_iter().enumerate() {
+            psx.connect_controller(port, controller.into());
+            psx.set_rumble_callback(port, move |small, large| {
+                println!("Rumble on port {}: small {:02X}, large {:02X}", port + 1, small, large);
+            });
+        }
+
+        if let Some(port) = args.multitap {
+            psx.connect_multitap(port as usize - 1);
+            println!("Connected multitap to port {}", port);
+        }
+
+        if let Some(address) = &args.link_listen {
+            let cable = LinkCable::listen(address).expect("Failed to accept link cable connection");
+            psx.connect_serial(Box::new(cable));
+            println!("Link cable connected on {:?}", address);
+        } else if let Some(address) = &args.link_connect {
+            let cable = LinkCable::connect(address).expect("Failed to connect link cable");
+            psx.connect_serial(Box::new(cable));
+            println!("Link cable connected to {:?}", address);
+        }
+
+        let unirom = args.unirom.as_ref().map(|address| {
+            let server = UniromServer::listen(address).expect("Failed to listen for nops");
+            println!("Listening for nops on {:?}", address);
+            server
+        });
+
+        // Insert memory cards if provided
+        for (port, path) in [&args.memcard1, &args.memcard2].into_iter().enumerate() {
+            if let Some(path) = path {
+                let card = MemoryCard::open(path).expect("Failed to open memory card image");
+                psx.insert_memory_card(port, card);
+                println!("Inserted memory card {}: {:?}", port + 1, path);
+            }
+        }
+
+        let state_name = args
+            .cdrom
+            .as_ref()
+            .or(args.sideload.as_ref())
+            .unwrap_or(&args.bios)
+            .file_stem()
+            .map_or_else(|| "pspsps".to_string(), |stem| stem.to_string_lossy().into_owned());
+
+        let movie = if let Some(path) = &args.play_movie {
+            let data = fs::read(path).expect("Failed to read movie file");
+            let player = Movie::parse(&data)
+                .and_then(|movie| MoviePlayer::new(movie, &mut psx))
+                .unwrap_or_else(|e| panic!("Failed to play movie {:?}: {}", path, e));
+            println!("Playing movie of {} frames: {:?}", player.movie().len(), path);
+            Some(MovieSession::Playing(player))
+        } else if let Some(path) = &args.record_movie {
+            let recorder = match &args.movie_from_state {
+                Some(state_path) => {
+                    let state = fs::read(state_path).expect("Failed to read save state file");
+                    psx.load_state(&state)
+                        .unwrap_or_else(|e| panic!("Failed to load save state {:?}: {}", state_path, e));
+                    MovieRecorder::from_savestate(&psx)
+                }
+                None => MovieRecorder::from_power_on(&psx),
+            }
+            .unwrap_or_else(|e| panic!("Failed to record movie {:?}: {}", path, e));
+            println!("Recording movie: {:?}", path);
+            Some(MovieSession::Recording {
+                recorder,
+                path: path.clone(),
+            })
+        } else {
+            None
+        };
+
+        // Rewinding would change the past of a movie, so does loading a save state
+        let rewind = (args.rewind_buffer > 0 && movie.is_none())
+            .then(|| Rewind::new(args.rewind_buffer * 1024 * 1024, args.rewind_interval));
+
         Self {
             window: None,
             renderer: None,
             psx: Some(psx),
+            unirom,
+            state_name,
+            rewind,
+            movie,
             input_state: input::InputState::new(),
             frame_count: 0,
             fps_timer: std::time::Instant::now(),
@@ -166,6 +440,58 @@ impl App {
         }
     }
 
+    fn state_path(&self, slot: usize) -> PathBuf {
+        PathBuf::from(format!("{}.ss{}", self.state_name, slot))
+    }
+
+    fn save_state(&self, slot: usize) {
+        let Some(psx) = &self.psx else {
+            return;
+        };
+
+        let path = self.state_path(slot);
+        match fs::write(&path, psx.save_state()) {
+            Ok(()) => println!("Saved state to slot {}: {:?}", slot, path),
+            Err(e) => eprintln!("Failed to save state to {:?}: {}", path, e),
+        }
+    }
+
+    fn load_state(&mut self, slot: usize) {
+        if self.movie.is_some() {
+            eprintln!("Loading save states is disabled while a movie is recorded or played");
+            return;
+        }
+
+        let path = self.state_path(slot);
+        let Some(psx) = &mut self.psx else {
+            return;
+        };
+
+        match fs::read(&path) {
+            Ok(state) => match psx.load_state(&state) {
+                Ok(()) => {
+                    // Snapshots from before the load would rewind into a different timeline
+                    if let Some(rewind) = &mut self.rewind {
+                        rewind.clear();
+                    }
+                    println!("Loaded state from slot {}: {:?}", slot, path);
+                }
+                Err(e) => eprintln!("Failed to load state from {:?}: {}", path, e),
+            },
+            Err(e) => eprintln!("Failed to read state from {:?}: {}", path, e),
+        }
+    }
+
+    fn save_movie(&mut self) {
+        if let Some(MovieSession::Recording { recorder, path }) = self.movie.take() {
+            let movie = recorder.finish();
+            match fs::write(&path, movie.to_bytes()) {
+                Ok(()) => println!("Saved movie of {} frames to {:?}", movie.len(), path),
+                Err(e) => eprintln!("Failed to save movie to {:?}: {}", path, e),
+            }
+        }
+    }
+
     fn save_screenshot(&self, width: usize, height: usize, frame: &[(u8, u8, u8)]) {
         let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
         let filename = format!("screenshot_{}.png", timestamp);
@@ -0,0 +1,320 @@
+//! Runs the console without a window for test suites and CI: fixed frame counts, timeouts, hang detection, movie
+//! playback, screenshots, VRAM dumps and the TTY log. There is no audio output, the SPU only moves sound RAM over DMA
+//! and doesn't mix voices into samples yet.
+
+mod script;
+
+use clap::{ArgGroup, Parser};
+use psx_core::mmu::RamSize;
+use psx_core::movie::{Movie, MovieError, MoviePlayer};
+use psx_core::psx::Psx;
+use psx_core::sio::memcard::MemoryCard;
+use script::InputScript;
+use std::fs;
+use std::hash::{DefaultHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+const EXIT_EMULATION_ERROR: u8 = 1;
+const EXIT_HANG: u8 = 2;
+const EXIT_MOVIE: u8 = 3;
+const EXIT_INVALID_EXE: u8 = 4;
+
+// Reading the clock on every instruction would slow down the run noticeably
+const TIMEOUT_CHECK_STEPS: usize = 0x1000;
+
+#[derive(Parser, Debug)]
+#[command(name = "psx-headless")]
+#[command(about = "runs the psx without a window", long_about = None)]
+#[command(group(ArgGroup::new("limit").required(true).multiple(true).args(["frames", "timeout", "movie"])))]
+struct Args {
+    #[arg(short, long)]
+    bios: PathBuf,
+
+    #[arg(short, long)]
+    cdrom: Option<PathBuf>,
+
+    #[arg(short, long)]
+    sideload: Option<PathBuf>,
+
+    /// Memory card image (.mcr) for slot 1, changes are not written back
+    #[arg(long)]
+    memcard1: Option<PathBuf>,
+
+    /// Memory card image (.mcr) for slot 2, changes are not written back
+    #[arg(long)]
+    memcard2: Option<PathBuf>,
+
+    /// Install 8 MiB of main RAM like the dev kits instead of the retail 2 MiB
+    #[arg(long)]
+    devkit_ram: bool,
+
+    /// Number of frames to run
+    #[arg(short, long)]
+    frames: Option<usize>,
+
+    /// Seconds of wall time to run for, running out before --frames is reached counts as a hang
+    #[arg(short, long)]
+    timeout: Option<f64>,
+
+    /// Play back a movie and fail if it desyncs, it ends the run unless --frames stops it earlier
+    #[arg(long)]
+    movie: Option<PathBuf>,
+
+    /// Controller input script, one `<frame> <buttons...>` line per change like `300 cross 2:start`
+    #[arg(short, long, conflicts_with = "movie")]
+    input: Option<PathBuf>,
+
+    /// Keep going when an instruction fails instead of exiting
+    #[arg(long)]
+    ignore_errors: bool,
+
+    /// Count the run as hung once RAM and VRAM stayed the same for this many frames
+    #[arg(long)]
+    hang_frames: Option<usize>,
+
+    /// Write the last frame as PNG
+    #[arg(long)]
+    screenshot: Option<PathBuf>,
+
+    /// Write every Nth frame as <frame>.png into --screenshot-dir
+    #[arg(long, requires = "screenshot_dir")]
+    screenshot_every: Option<usize>,
+
+    #[arg(long, requires = "screenshot_every")]
+    screenshot_dir: Option<PathBuf>,
+
+    /// Write the raw 1024x512 16bpp VRAM when the run is over
+    #[arg(long)]
+    vram_dump: Option<PathBuf>,
+
+    /// Write everything printed to the TTY when the run is over
+    #[arg(long)]
+    tty_log: Option<PathBuf>,
+}
+
+enum Input {
+    None,
+    Script(InputScript),
+    Movie(MoviePlayer),
+}
+
+enum Outcome {
+    Finished,
+    EmulationError,
+    Hang(String),
+    Desync(MovieError),
+}
+
+/// How running a frame ended
+enum FrameEnd {
+    VBlank,
+    EmulationError,
+    Timeout,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let bios = fs::read(&args.bios).expect("Failed to read BIOS file");
+    let ram_size = if args.devkit_ram {
+        RamSize::DevKit
+    } else {
+        RamSize::Retail
+    };
+    let mut psx = Psx::with_ram_size(&bios, ram_size);
+
+    if let Some(path) = &args.cdrom {
+        psx.load_cdrom(fs::read(path).expect("Failed to read CD-ROM file"));
+    }
+
+    if let Some(path) = &args.sideload {
+        let exe = fs::read(path).expect("Failed to read sideload EXE file");
+        if let Err(e) = psx.sideload_exe(exe) {
+            eprintln!("{:?}: {}", path, e);
+            return ExitCode::from(EXIT_INVALID_EXE);
+        }
+    }
+
+    // Runs must not leave saves behind, so the cards only live in memory
+    for (port, path) in [&args.memcard1, &args.memcard2].into_iter().enumerate() {
+        if let Some(path) = path {
+            let image = fs::read(path).expect("Failed to read memory card image");
+            let card = MemoryCard::from_image(image).expect("Failed to parse memory card image");
+            psx.insert_memory_card(port, card);
+        }
+    }
+
+    let mut input = if let Some(path) = &args.movie {
+        let data = fs::read(path).expect("Failed to read movie file");
+        match Movie::parse(&data).and_then(|movie| MoviePlayer::new(movie, &mut psx)) {
+            Ok(player) => Input::Movie(player),
+            Err(e) => {
+                eprintln!("{:?}: {}", path, e);
+                return ExitCode::from(EXIT_MOVIE);
+            }
+        }
+    } else if let Some(path) = &args.input {
+        let text = fs::read_to_string(path).expect("Failed to read input script");
+        let script = InputScript::parse(&text).unwrap_or_else(|e| panic!("Invalid input script {:?}: {}", path, e));
+        Input::Script(script)
+    } else {
+        Input::None
+    };
+
+    if let Some(dir) = &args.screenshot_dir {
+        fs::create_dir_all(dir).expect("Failed to create screenshot directory");
+    }
+
+    let timeout = args.timeout.map(Duration::from_secs_f64);
+    let start_time = Instant::now();
+    let deadline = timeout.map(|timeout| start_time + timeout);
+    let mut frame = 0;
+    let mut last_memory_hash = None;
+    let mut unchanged_frames = 0;
+
+    // Running out of time is only a hang when a frame count was asked for
+    let timed_out = |frame: usize| match (args.frames, timeout) {
+        (Some(frames), Some(timeout)) => {
+            Outcome::Hang(format!("Reached only {} of {} frames in {:?}", frame, frames, timeout))
+        }
+        _ => Outcome::Finished,
+    };
+
+    let outcome = loop {
+        if args.frames.is_some_and(|frames| frame >= frames) {
+            break Outcome::Finished;
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break timed_out(frame);
+        }
+
+        match &mut input {
+            Input::None => {}
+            Input::Script(script) => {
+                for (port, state) in script.input(frame).iter().enumerate() {
+                    psx.set_controller_state(port, *state);
+                }
+            }
+            Input::Movie(player) => {
+                if !player.start_frame(&mut psx) {
+                    break Outcome::Finished;
+                }
+            }
+        }
+
+        match run_frame(&mut psx, args.ignore_errors, deadline) {
+            FrameEnd::VBlank => frame += 1,
+            FrameEnd::EmulationError => break Outcome::EmulationError,
+            FrameEnd::Timeout => break timed_out(frame),
+        }
+
+        if let Input::Movie(player) = &mut input
+            && let Err(e) = player.end_frame(&psx)
+        {
+            break Outcome::Desync(e);
+        }
+
+        if let (Some(every), Some(dir)) = (args.screenshot_every, &args.screenshot_dir)
+            && frame.is_multiple_of(every.max(1))
+        {
+            save_screenshot(&psx, &dir.join(format!("{}.png", frame)));
+        }
+
+        if let Some(hang_frames) = args.hang_frames {
+            let memory_hash = memory_hash(&psx);
+            if last_memory_hash == Some(memory_hash) {
+                unchanged_frames += 1;
+            } else {
+                last_memory_hash = Some(memory_hash);
+                unchanged_frames = 0;
+            }
+
+            if unchanged_frames >= hang_frames {
+                break Outcome::Hang(format!(
+                    "RAM and VRAM did not change for {} frames, PC: {:08X}",
+                    unchanged_frames, psx.cpu.pc
+                ));
+            }
+        }
+    };
+
+    // Outputs are written on failure too, they are what tells why a run failed
+    if let Some(path) = &args.screenshot {
+        save_screenshot(&psx, path);
+    }
+
+    if let Some(path) = &args.vram_dump
+        && let Err(e) = fs::write(path, &psx.cpu.mmu.gpu.gp.vram)
+    {
+        eprintln!("Failed to write VRAM dump {:?}: {}", path, e);
+    }
+
+    if let Some(path) = &args.tty_log {
+        let tty = psx_core::cpu::internal::tty_buffer().lock().unwrap().clone();
+        if let Err(e) = fs::write(path, tty) {
+            eprintln!("Failed to write TTY log {:?}: {}", path, e);
+        }
+    }
+
+    let elapsed = start_time.elapsed();
+    match outcome {
+        Outcome::Finished => {
+            println!("Ran {} frames in {:.2?}", frame, elapsed);
+            ExitCode::SUCCESS
+        }
+        Outcome::EmulationError => {
+            eprintln!("Emulation error during frame {}, PC: {:08X}", frame, psx.cpu.pc);
+            eprintln!("Registers: {:08X?}", psx.cpu.registers);
+            ExitCode::from(EXIT_EMULATION_ERROR)
+        }
+        Outcome::Hang(reason) => {
+            eprintln!("Hang after {} frames: {}", frame, reason);
+            ExitCode::from(EXIT_HANG)
+        }
+        Outcome::Desync(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(EXIT_MOVIE)
+        }
+    }
+}
+
+/// Run until the next vertical blank, stopping early on the first failing instruction unless errors are ignored, or
+/// once `deadline` has passed so a frame that never ends can't hang the run
+fn run_frame(psx: &mut Psx, ignore_errors: bool, deadline: Option<Instant>) -> FrameEnd {
+    let mut steps = 0usize;
+
+    loop {
+        match psx.step() {
+            Ok((_, true)) => return FrameEnd::VBlank,
+            Ok(_) => {}
+            Err(()) if ignore_errors => {}
+            Err(()) => return FrameEnd::EmulationError,
+        }
+
+        steps += 1;
+        if steps.is_multiple_of(TIMEOUT_CHECK_STEPS) && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return FrameEnd::Timeout;
+        }
+    }
+}
+
+/// Hash of RAM and VRAM, the same hash frame after frame means the game stopped doing anything
+fn memory_hash(psx: &Psx) -> u64 {
+    let mmu = &psx.cpu.mmu;
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&mmu.memory[..mmu.ram_size().bytes() as usize]);
+    hasher.write(&mmu.gpu.gp.vram);
+    hasher.finish()
+}
+
+fn save_screenshot(psx: &Psx, path: &Path) {
+    let (frame, width, height) = psx.frame();
+    let rgb_data: Vec<u8> = frame.iter().flat_map(|&(r, g, b)| [r, g, b]).collect();
+
+    if let Err(e) = image::save_buffer(path, &rgb_data, width as u32, height as u32, image::ColorType::Rgb8) {
+        eprintln!("Failed to save screenshot {:?}: {}", path, e);
+    }
+}
@@ -0,0 +1,125 @@
+use psx_core::movie::{MOVIE_PORT_COUNT, MovieInput};
+use psx_core::sio::joy::ControllerState;
+
+/// Controller input by frame, read from a text file
+///
+/// Every line holds the frame it takes effect on followed by what is held from then on, lines
+/// starting with `#` are comments:
+///
+/// ```text
+/// 0    start
+/// 10
+/// 300  cross d_right 2:circle lx=ff
+/// ```
+///
+/// Buttons are named after the fields of `ControllerState`, a `2:` prefix addresses port 2.
+pub struct InputScript {
+    changes: Vec<(usize, MovieInput)>,
+    next: usize,
+    input: MovieInput,
+}
+
+impl InputScript {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut changes: Vec<(usize, MovieInput)> = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let line_number = index + 1;
+            let mut tokens = line.split_whitespace();
+            let frame = tokens
+                .next()
+                .and_then(|token| token.parse::<usize>().ok())
+                .ok_or_else(|| format!("line {}: expected a frame number", line_number))?;
+
+            if let Some((previous, _)) = changes.last()
+                && *previous >= frame
+            {
+                return Err(format!(
+                    "line {}: frame {} is not after frame {}",
+                    line_number, frame, previous
+                ));
+            }
+
+            let mut input = [ControllerState::default(); MOVIE_PORT_COUNT];
+            for token in tokens {
+                let (port, name) = match token.split_once(':') {
+                    Some(("1", name)) => (0, name),
+                    Some(("2", name)) => (1, name),
+                    Some(_) => return Err(format!("line {}: unknown port in '{}'", line_number, token)),
+                    None => (0, token),
+                };
+
+                if !apply(&mut input[port], name) {
+                    return Err(format!("line {}: unknown input '{}'", line_number, token));
+                }
+            }
+
+            changes.push((frame, input));
+        }
+
+        Ok(Self {
+            changes,
+            next: 0,
+            input: [ControllerState::default(); MOVIE_PORT_COUNT],
+        })
+    }
+
+    /// Input held during `frame`, frames have to be asked for in order
+    pub fn input(&mut self, frame: usize) -> MovieInput {
+        while let Some((start, input)) = self.changes.get(self.next)
+            && *start <= frame
+        {
+            self.input = *input;
+            self.next += 1;
+        }
+
+        self.input
+    }
+}
+
+/// Press the button `name` or set an analog axis like `lx=80`, false if there is no such input
+fn apply(state: &mut ControllerState, name: &str) -> bool {
+    if let Some((axis, value)) = name.split_once('=') {
+        let Ok(value) = u8::from_str_radix(value, 16) else {
+            return false;
+        };
+
+        let axis = match axis {
+            "lx" => &mut state.left_x,
+            "ly" => &mut state.left_y,
+            "rx" => &mut state.right_x,
+            "ry" => &mut state.right_y,
+            _ => return false,
+        };
+        *axis = value;
+        return true;
+    }
+
+    let button = match name {
+        "d_up" => &mut state.d_up,
+        "d_down" => &mut state.d_down,
+        "d_left" => &mut state.d_left,
+        "d_right" => &mut state.d_right,
+        "cross" => &mut state.cross,
+        "circle" => &mut state.circle,
+        "square" => &mut state.square,
+        "triangle" => &mut state.triangle,
+        "l1" => &mut state.l1,
+        "l2" => &mut state.l2,
+        "r1" => &mut state.r1,
+        "r2" => &mut state.r2,
+        "start" => &mut state.start,
+        "select" => &mut state.select,
+        "l3" => &mut state.l3,
+        "r3" => &mut state.r3,
+        "analog" => &mut state.analog,
+        _ => return false,
+    };
+    *button = true;
+    true
+}
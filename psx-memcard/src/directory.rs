@@ -0,0 +1,148 @@
+use crate::SAVE_BLOCKS;
+
+const NO_NEXT_BLOCK: u16 = 0xFFFF;
+const FILENAME_LEN: usize = 20;
+
+/// PSX-SPX: "00h-03h Block Allocation State"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockState {
+    InUseFirst,
+    InUseMiddle,
+    InUseLast,
+    Free,
+    DeletedFirst,
+    DeletedMiddle,
+    DeletedLast,
+    Unknown(u32),
+}
+
+impl BlockState {
+    /// Free and deleted blocks can be overwritten by new saves
+    pub fn is_free(&self) -> bool {
+        matches!(
+            self,
+            BlockState::Free | BlockState::DeletedFirst | BlockState::DeletedMiddle | BlockState::DeletedLast
+        )
+    }
+
+    pub fn is_in_use(&self) -> bool {
+        matches!(
+            self,
+            BlockState::InUseFirst | BlockState::InUseMiddle | BlockState::InUseLast
+        )
+    }
+}
+
+impl From<u32> for BlockState {
+    fn from(value: u32) -> Self {
+        match value {
+            0x51 => BlockState::InUseFirst,
+            0x52 => BlockState::InUseMiddle,
+            0x53 => BlockState::InUseLast,
+            0xA0 => BlockState::Free,
+            0xA1 => BlockState::DeletedFirst,
+            0xA2 => BlockState::DeletedMiddle,
+            0xA3 => BlockState::DeletedLast,
+            _ => BlockState::Unknown(value),
+        }
+    }
+}
+
+impl From<BlockState> for u32 {
+    fn from(state: BlockState) -> Self {
+        match state {
+            BlockState::InUseFirst => 0x51,
+            BlockState::InUseMiddle => 0x52,
+            BlockState::InUseLast => 0x53,
+            BlockState::Free => 0xA0,
+            BlockState::DeletedFirst => 0xA1,
+            BlockState::DeletedMiddle => 0xA2,
+            BlockState::DeletedLast => 0xA3,
+            BlockState::Unknown(value) => value,
+        }
+    }
+}
+
+impl std::fmt::Display for BlockState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockState::InUseFirst => write!(f, "First"),
+            BlockState::InUseMiddle => write!(f, "Middle"),
+            BlockState::InUseLast => write!(f, "Last"),
+            BlockState::Free => write!(f, "Free"),
+            BlockState::DeletedFirst => write!(f, "Deleted (First)"),
+            BlockState::DeletedMiddle => write!(f, "Deleted (Middle)"),
+            BlockState::DeletedLast => write!(f, "Deleted (Last)"),
+            BlockState::Unknown(value) => write!(f, "Unknown ({:08X})", value),
+        }
+    }
+}
+
+/// Directory frame describing one save block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryEntry {
+    pub block: usize,
+    pub state: BlockState,
+    pub size: u32,           // Size of the whole save in bytes, only set in the first block
+    pub next: Option<usize>, // Next block of the save
+    pub filename: String,    // e.g. "BASLUS-00123SAVE0", only set in the first block
+}
+
+impl DirectoryEntry {
+    /// Entry of an unused block
+    pub fn free(block: usize) -> Self {
+        Self {
+            block,
+            state: BlockState::Free,
+            size: 0,
+            next: None,
+            filename: String::new(),
+        }
+    }
+
+    pub fn parse(block: usize, frame: &[u8]) -> Self {
+        let state = u32::from_le_bytes(frame[0x00..0x04].try_into().unwrap()).into();
+        let size = u32::from_le_bytes(frame[0x04..0x08].try_into().unwrap());
+
+        // PSX-SPX: "08h-09h Pointer to the NEXT block number (minus 1) used by the file (or FFFFh if last block)"
+        let next = match u16::from_le_bytes([frame[0x08], frame[0x09]]) {
+            NO_NEXT_BLOCK => None,
+            pointer => Some(pointer as usize + 1),
+        };
+
+        let filename = &frame[0x0A..0x0A + FILENAME_LEN];
+        let filename = filename.split(|&byte| byte == 0).next().unwrap_or_default();
+        let filename = String::from_utf8_lossy(filename).into_owned();
+
+        Self {
+            block,
+            state,
+            size,
+            next,
+            filename,
+        }
+    }
+
+    /// Store the entry in a directory frame, the checksum is left to the caller
+    pub fn write(&self, frame: &mut [u8]) {
+        frame.fill(0);
+
+        frame[0x00..0x04].copy_from_slice(&u32::from(self.state).to_le_bytes());
+        frame[0x04..0x08].copy_from_slice(&self.size.to_le_bytes());
+
+        let next = match self.next {
+            Some(block) => (block - 1) as u16,
+            None => NO_NEXT_BLOCK,
+        };
+        frame[0x08..0x0A].copy_from_slice(&next.to_le_bytes());
+
+        let filename = self.filename.as_bytes();
+        let len = filename.len().min(FILENAME_LEN);
+        frame[0x0A..0x0A + len].copy_from_slice(&filename[..len]);
+    }
+
+    /// Pointer to a block that does not exist
+    pub fn has_invalid_next(&self) -> bool {
+        self.next.is_some_and(|block| !SAVE_BLOCKS.contains(&block))
+    }
+}
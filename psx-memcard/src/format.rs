@@ -0,0 +1,122 @@
+use crate::{Card, Error};
+use aes::Aes128;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use hmac::{Hmac, Mac};
+use psx_core::sio::memcard::MEMORY_CARD_SIZE;
+use sha1::Sha1;
+use std::path::Path;
+
+// DexDrive: "123-456-STD" signature, 15 save comments of 256 bytes, then the raw card
+const GME_MAGIC: &[u8] = b"123-456-STD";
+const GME_HEADER_SIZE: usize = 0xF40;
+
+// PSP virtual memory card: "\0PMV" signature, header size, salt seed and signature, then the raw card
+const VMP_MAGIC: &[u8] = b"\0PMV";
+const VMP_HEADER_SIZE: usize = 0x80;
+const VMP_SALT_SEED_OFFSET: usize = 0x0C;
+const VMP_SIGNATURE_OFFSET: usize = 0x20;
+const VMP_SIGNATURE_SIZE: usize = 0x14;
+
+// Key and IV the PSP derives the signing salt of PS1 cards with, the same ones MemcardRex uses
+const VMP_KEY: [u8; 16] = [
+    0xAB, 0x5A, 0xBC, 0x9F, 0xC1, 0xF4, 0x9D, 0xE6, 0xA0, 0x51, 0xDB, 0xAE, 0xFA, 0x51, 0x88, 0x59,
+];
+const VMP_IV: [u8; 16] = [
+    0xB3, 0x0F, 0xFE, 0xED, 0xB7, 0xDC, 0x5E, 0xB7, 0x13, 0x3D, 0xA6, 0x0D, 0x1B, 0x6B, 0x2C, 0xDC,
+];
+
+/// Container around the raw 128 KiB card image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardFormat {
+    Raw, // .mcr, .mcd, .mem, .srm, ...
+    Gme, // DexDrive
+    Vmp, // PSP/PS3 virtual memory card
+}
+
+impl CardFormat {
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("gme") => CardFormat::Gme,
+            Some("vmp") => CardFormat::Vmp,
+            _ => CardFormat::Raw,
+        }
+    }
+
+    /// Strip the container, returns the raw card image
+    pub fn decode(&self, image: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let (magic, header_size) = match self {
+            CardFormat::Raw => return Ok(image),
+            CardFormat::Gme => (GME_MAGIC, GME_HEADER_SIZE),
+            CardFormat::Vmp => (VMP_MAGIC, VMP_HEADER_SIZE),
+        };
+
+        if !image.starts_with(magic) {
+            return Err(Error::InvalidImage(format!("Missing {:?} signature", self)));
+        }
+
+        if image.len() < header_size + MEMORY_CARD_SIZE {
+            return Err(Error::InvalidImage(format!(
+                "{:?} image is truncated ({} bytes)",
+                self,
+                image.len()
+            )));
+        }
+
+        Ok(image[header_size..header_size + MEMORY_CARD_SIZE].to_vec())
+    }
+
+    /// Wrap the raw card image in the container
+    pub fn encode(&self, card: &Card) -> Result<Vec<u8>, Error> {
+        match self {
+            CardFormat::Raw => Ok(card.data().to_vec()),
+            CardFormat::Gme => {
+                // Save comments are left empty
+                let mut image = vec![0; GME_HEADER_SIZE];
+                image[..GME_MAGIC.len()].copy_from_slice(GME_MAGIC);
+                image.extend_from_slice(card.data());
+                Ok(image)
+            }
+            CardFormat::Vmp => {
+                // The salt seed is left at zero, the PSP only checks that the signature matches it
+                let mut image = vec![0; VMP_HEADER_SIZE];
+                image[..VMP_MAGIC.len()].copy_from_slice(VMP_MAGIC);
+                image[0x04..0x08].copy_from_slice(&(VMP_HEADER_SIZE as u32).to_le_bytes());
+                image.extend_from_slice(card.data());
+
+                let signature = vmp_signature(&image);
+                image[VMP_SIGNATURE_OFFSET..VMP_SIGNATURE_OFFSET + VMP_SIGNATURE_SIZE].copy_from_slice(&signature);
+                Ok(image)
+            }
+        }
+    }
+}
+
+/// HMAC-SHA1 of the whole image with the signature zeroed, keyed with a salt derived from the header's seed
+pub fn vmp_signature(image: &[u8]) -> [u8; VMP_SIGNATURE_SIZE] {
+    let seed = &image[VMP_SALT_SEED_OFFSET..VMP_SALT_SEED_OFFSET + 0x14];
+    let cipher = Aes128::new(&VMP_KEY.into());
+
+    let mut decrypted = aes::Block::clone_from_slice(&seed[..0x10]);
+    cipher.decrypt_block(&mut decrypted);
+    let mut encrypted = aes::Block::clone_from_slice(&seed[..0x10]);
+    cipher.encrypt_block(&mut encrypted);
+
+    let mut salt = [0u8; 0x14];
+    for i in 0..0x10 {
+        salt[i] = decrypted[i] ^ VMP_IV[i];
+    }
+    for i in 0..4 {
+        salt[0x10 + i] = encrypted[i] ^ seed[0x10 + i];
+    }
+
+    let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(&salt).expect("HMAC takes keys of any length");
+    mac.update(&image[..VMP_SIGNATURE_OFFSET]);
+    mac.update(&[0; VMP_SIGNATURE_SIZE]);
+    mac.update(&image[VMP_SIGNATURE_OFFSET + VMP_SIGNATURE_SIZE..]);
+    mac.finalize().into_bytes().into()
+}
@@ -0,0 +1,195 @@
+pub mod directory;
+pub mod format;
+pub mod links;
+pub mod save;
+
+use crate::directory::{BlockState, DirectoryEntry};
+use crate::save::Save;
+use psx_core::sio::memcard::{MEMORY_CARD_SECTOR_SIZE as FRAME_SIZE, MemoryCard};
+use std::path::Path;
+
+pub const BLOCK_SIZE: usize = 8 * 1024;
+pub const BLOCK_COUNT: usize = 16;
+pub const FRAMES_PER_BLOCK: usize = BLOCK_SIZE / FRAME_SIZE;
+
+/// Block 0 holds the header and the directory, saves live in block 1..15
+pub const SAVE_BLOCKS: std::ops::RangeInclusive<usize> = 1..=15;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    InvalidImage(String),
+    InvalidBlock(usize),
+    NotEnoughSpace { needed: usize, free: usize },
+    AlreadyExists(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::InvalidImage(reason) => write!(f, "Invalid image: {}", reason),
+            Error::InvalidBlock(block) => write!(f, "Block {} is out of range or does not start a save", block),
+            Error::NotEnoughSpace { needed, free } => {
+                write!(f, "Not enough space on card: {} blocks needed, {} free", needed, free)
+            }
+            Error::AlreadyExists(filename) => write!(f, "A save named {} already exists on the card", filename),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Save manager view of a memory card image, built on the image the SIO device uses
+pub struct Card {
+    card: MemoryCard,
+}
+
+impl Card {
+    /// Freshly formatted card
+    pub fn new() -> Self {
+        Self {
+            card: MemoryCard::new(),
+        }
+    }
+
+    pub fn from_memory_card(card: MemoryCard) -> Self {
+        Self { card }
+    }
+
+    /// Open a card image, the container format is picked from the file extension
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let image = std::fs::read(path)?;
+        let raw = format::CardFormat::from_path(path).decode(image)?;
+
+        Ok(Self {
+            card: MemoryCard::from_image(raw)?,
+        })
+    }
+
+    /// Write the card image, the container format is picked from the file extension
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let image = format::CardFormat::from_path(path).encode(self)?;
+        std::fs::write(path, image)?;
+        Ok(())
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.card.data()
+    }
+
+    pub fn into_memory_card(self) -> MemoryCard {
+        self.card
+    }
+
+    pub fn block(&self, block: usize) -> Result<&[u8], Error> {
+        let block = check_block(block)?;
+        Ok(&self.card.data()[block * BLOCK_SIZE..(block + 1) * BLOCK_SIZE])
+    }
+
+    pub fn block_mut(&mut self, block: usize) -> Result<&mut [u8], Error> {
+        let block = check_block(block)?;
+        Ok(&mut self.card.data_mut()[block * BLOCK_SIZE..(block + 1) * BLOCK_SIZE])
+    }
+
+    /// Directory frame of a save block, PSX-SPX: "Block 0, Frame 1..15"
+    pub fn directory_frame(&self, block: usize) -> Result<&[u8], Error> {
+        Ok(self.frame_at(check_save_block(block)?))
+    }
+
+    pub fn entry(&self, block: usize) -> Result<DirectoryEntry, Error> {
+        Ok(self.entry_at(check_save_block(block)?))
+    }
+
+    pub fn entries(&self) -> Vec<DirectoryEntry> {
+        SAVE_BLOCKS.map(|block| self.entry_at(block)).collect()
+    }
+
+    /// Write a directory frame back, the checksum is updated
+    pub fn set_entry(&mut self, entry: &DirectoryEntry) -> Result<(), Error> {
+        check_save_block(entry.block)?;
+        self.write_entry(entry);
+        Ok(())
+    }
+
+    /// Blocks of the chain that starts at `first_block`, stops at the first broken link
+    pub fn chain(&self, first_block: usize) -> Result<Vec<usize>, Error> {
+        let mut blocks = vec![check_save_block(first_block)?];
+
+        let mut next = self.entry_at(first_block).next;
+        while let Some(block) = next {
+            if !SAVE_BLOCKS.contains(&block) || blocks.contains(&block) {
+                break;
+            }
+
+            blocks.push(block);
+            next = self.entry_at(block).next;
+        }
+
+        Ok(blocks)
+    }
+
+    // The accessors below take blocks that are already known to be in SAVE_BLOCKS
+
+    pub(crate) fn frame_at(&self, block: usize) -> &[u8] {
+        &self.card.data()[block * FRAME_SIZE..(block + 1) * FRAME_SIZE]
+    }
+
+    pub(crate) fn entry_at(&self, block: usize) -> DirectoryEntry {
+        DirectoryEntry::parse(block, self.frame_at(block))
+    }
+
+    pub(crate) fn write_entry(&mut self, entry: &DirectoryEntry) {
+        let offset = entry.block * FRAME_SIZE;
+        let frame = &mut self.card.data_mut()[offset..offset + FRAME_SIZE];
+        entry.write(frame);
+        MemoryCard::update_frame_checksum(frame);
+    }
+
+    /// Every save on the card, in directory order
+    pub fn saves(&self) -> Vec<Save> {
+        self.entries()
+            .into_iter()
+            .filter(|entry| entry.state == BlockState::InUseFirst)
+            .filter_map(|entry| Save::from_card(self, entry).ok()) // Directory entries are always in range
+            .collect()
+    }
+
+    pub fn free_blocks(&self) -> Vec<usize> {
+        self.entries()
+            .into_iter()
+            .filter(|entry| entry.state.is_free())
+            .map(|entry| entry.block)
+            .collect()
+    }
+}
+
+fn check_block(block: usize) -> Result<usize, Error> {
+    if block < BLOCK_COUNT {
+        Ok(block)
+    } else {
+        Err(Error::InvalidBlock(block))
+    }
+}
+
+fn check_save_block(block: usize) -> Result<usize, Error> {
+    if SAVE_BLOCKS.contains(&block) {
+        Ok(block)
+    } else {
+        Err(Error::InvalidBlock(block))
+    }
+}
+
+impl Default for Card {
+    fn default() -> Self {
+        Self::new()
+    }
+}
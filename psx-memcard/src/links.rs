@@ -0,0 +1,235 @@
+use crate::directory::{BlockState, DirectoryEntry};
+use crate::{BLOCK_COUNT, BLOCK_SIZE, Card, SAVE_BLOCKS};
+use psx_core::sio::memcard::MemoryCard;
+
+/// Inconsistency in the directory that ties blocks together into saves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    BadChecksum {
+        block: usize,
+    },
+    UnknownState {
+        block: usize,
+        state: BlockState,
+    },
+    InvalidPointer {
+        block: usize,
+        next: usize,
+    },
+    BadTarget {
+        block: usize,
+        next: usize,
+        state: BlockState,
+    },
+    CrossLinked {
+        block: usize,
+        next: usize,
+    },
+    Loop {
+        block: usize,
+        next: usize,
+    },
+    MissingEnd {
+        first: usize,
+        block: usize,
+    },
+    SizeMismatch {
+        first: usize,
+        size: u32,
+        blocks: usize,
+    },
+    Orphan {
+        block: usize,
+        state: BlockState,
+    },
+}
+
+impl std::fmt::Display for LinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkError::BadChecksum { block } => write!(f, "Block {}: directory frame checksum mismatch", block),
+            LinkError::UnknownState { block, state } => write!(f, "Block {}: {}", block, state),
+            LinkError::InvalidPointer { block, next } => {
+                write!(f, "Block {}: points to block {}, which does not exist", block, next)
+            }
+            LinkError::BadTarget { block, next, state } => {
+                write!(f, "Block {}: points to block {}, which is {}", block, next, state)
+            }
+            LinkError::CrossLinked { block, next } => {
+                write!(
+                    f,
+                    "Block {}: points to block {}, which belongs to another save",
+                    block, next
+                )
+            }
+            LinkError::Loop { block, next } => write!(f, "Block {}: points back to block {}", block, next),
+            LinkError::MissingEnd { first, block } => {
+                write!(
+                    f,
+                    "Save at block {}: chain stops at block {} without a last block",
+                    first, block
+                )
+            }
+            LinkError::SizeMismatch { first, size, blocks } => {
+                write!(
+                    f,
+                    "Save at block {}: size is {} bytes, but {} blocks are linked",
+                    first, size, blocks
+                )
+            }
+            LinkError::Orphan { block, state } => write!(f, "Block {}: {} but not part of any save", block, state),
+        }
+    }
+}
+
+impl Card {
+    /// Walk every save and report broken block links
+    pub fn check_links(&self) -> Vec<LinkError> {
+        let mut errors = Vec::new();
+        let mut claimed = [false; BLOCK_COUNT];
+
+        for block in SAVE_BLOCKS {
+            let frame = self.frame_at(block);
+            if MemoryCard::frame_checksum(frame) != frame[frame.len() - 1] {
+                errors.push(LinkError::BadChecksum { block });
+            }
+
+            if let state @ BlockState::Unknown(_) = self.entry_at(block).state {
+                errors.push(LinkError::UnknownState { block, state });
+            }
+        }
+
+        for first in SAVE_BLOCKS {
+            let entry = self.entry_at(first);
+            if entry.state != BlockState::InUseFirst {
+                continue;
+            }
+
+            let chain = self.walk_chain(first, &claimed, &mut errors);
+            for &block in &chain {
+                claimed[block] = true;
+            }
+
+            if entry.size as usize != chain.len() * BLOCK_SIZE {
+                errors.push(LinkError::SizeMismatch {
+                    first,
+                    size: entry.size,
+                    blocks: chain.len(),
+                });
+            }
+        }
+
+        for block in SAVE_BLOCKS {
+            let state = self.entry_at(block).state;
+            if !claimed[block] && matches!(state, BlockState::InUseMiddle | BlockState::InUseLast) {
+                errors.push(LinkError::Orphan { block, state });
+            }
+        }
+
+        errors
+    }
+
+    /// Rebuild the directory from the intact part of every chain, returns what was broken
+    ///
+    /// Saves are cut at their first broken link, blocks that no save reaches anymore are freed
+    pub fn repair_links(&mut self) -> Vec<LinkError> {
+        let errors = self.check_links();
+        if errors.is_empty() {
+            return errors;
+        }
+
+        let mut claimed = [false; BLOCK_COUNT];
+        let mut chains = Vec::new();
+
+        for first in SAVE_BLOCKS {
+            if self.entry_at(first).state != BlockState::InUseFirst {
+                continue;
+            }
+
+            let chain = self.walk_chain(first, &claimed, &mut Vec::new());
+            for &block in &chain {
+                claimed[block] = true;
+            }
+            chains.push(chain);
+        }
+
+        for chain in chains {
+            let first = self.entry_at(chain[0]);
+
+            for (i, &block) in chain.iter().enumerate() {
+                let is_last = i == chain.len() - 1;
+
+                self.write_entry(&DirectoryEntry {
+                    block,
+                    state: match (i, is_last) {
+                        (0, _) => BlockState::InUseFirst,
+                        (_, true) => BlockState::InUseLast,
+                        _ => BlockState::InUseMiddle,
+                    },
+                    size: if i == 0 { (chain.len() * BLOCK_SIZE) as u32 } else { 0 },
+                    next: chain.get(i + 1).copied(),
+                    filename: if i == 0 { first.filename.clone() } else { String::new() },
+                });
+            }
+        }
+
+        for block in SAVE_BLOCKS {
+            if claimed[block] {
+                continue;
+            }
+
+            let entry = self.entry_at(block);
+            if entry.state.is_free() {
+                // Deleted saves keep their links so they can still be restored
+                self.write_entry(&entry);
+            } else {
+                self.write_entry(&DirectoryEntry::free(block));
+            }
+        }
+
+        errors
+    }
+
+    /// Follow the links of the save at `first` until the chain ends or a link is broken
+    fn walk_chain(&self, first: usize, claimed: &[bool], errors: &mut Vec<LinkError>) -> Vec<usize> {
+        let mut chain = vec![first];
+        let mut block = first;
+
+        loop {
+            let entry = self.entry_at(block);
+
+            let Some(next) = entry.next else {
+                if chain.len() > 1 && entry.state != BlockState::InUseLast {
+                    errors.push(LinkError::MissingEnd { first, block });
+                }
+                break;
+            };
+
+            if entry.has_invalid_next() {
+                errors.push(LinkError::InvalidPointer { block, next });
+                break;
+            }
+
+            if chain.contains(&next) {
+                errors.push(LinkError::Loop { block, next });
+                break;
+            }
+
+            if claimed[next] {
+                errors.push(LinkError::CrossLinked { block, next });
+                break;
+            }
+
+            let state = self.entry_at(next).state;
+            if !matches!(state, BlockState::InUseMiddle | BlockState::InUseLast) {
+                errors.push(LinkError::BadTarget { block, next, state });
+                break;
+            }
+
+            chain.push(next);
+            block = next;
+        }
+
+        chain
+    }
+}
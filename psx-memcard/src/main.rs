@@ -0,0 +1,194 @@
+use clap::{Parser, Subcommand};
+use psx_memcard::Card;
+use psx_memcard::save::{ICON_HEIGHT, ICON_WIDTH};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(name = "psx-memcard")]
+#[command(about = "Manage saves on psx memory card images (.mcr, .gme, .vmp)", long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create a freshly formatted card
+    New { card: PathBuf },
+
+    /// List the saves on a card
+    List { card: PathBuf },
+
+    /// Export the save starting at a block as .mcs
+    Export {
+        card: PathBuf,
+        block: usize,
+        output: PathBuf,
+    },
+
+    /// Import a .mcs save into the free blocks of a card
+    Import { card: PathBuf, save: PathBuf },
+
+    /// Delete the save starting at a block
+    Delete { card: PathBuf, block: usize },
+
+    /// Convert a card between formats, picked from the file extensions
+    Convert { input: PathBuf, output: PathBuf },
+
+    /// Write the icon frames of a save as a PNG strip
+    Icon {
+        card: PathBuf,
+        block: usize,
+        output: PathBuf,
+    },
+
+    /// Report broken block links
+    Check { card: PathBuf },
+
+    /// Repair broken block links in place
+    Repair { card: PathBuf },
+}
+
+fn open_card(path: &PathBuf) -> Card {
+    Card::open(path).unwrap_or_else(|e| {
+        eprintln!("Failed to open card {:?}: {}", path, e);
+        std::process::exit(1);
+    })
+}
+
+fn save_card(card: &Card, path: &PathBuf) {
+    if let Err(e) = card.save(path) {
+        eprintln!("Failed to write card {:?}: {}", path, e);
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    match args.command {
+        Command::New { card: path } => {
+            save_card(&Card::new(), &path);
+            println!("Created {:?}", path);
+        }
+        Command::List { card: path } => {
+            let card = open_card(&path);
+
+            for save in card.saves() {
+                println!(
+                    "{:>2}: {:<20} {:>2} block(s)  {}",
+                    save.first_block(),
+                    save.entry.filename,
+                    save.blocks.len(),
+                    save.title
+                );
+            }
+
+            println!("{} free block(s)", card.free_blocks().len());
+        }
+        Command::Export {
+            card: path,
+            block,
+            output,
+        } => {
+            let card = open_card(&path);
+
+            match card.export_mcs(block) {
+                Ok(mcs) => {
+                    std::fs::write(&output, mcs).expect("Failed to write save file");
+                    println!("Exported block {} to {:?}", block, output);
+                }
+                Err(e) => {
+                    eprintln!("Failed to export save: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Import { card: path, save } => {
+            let mut card = open_card(&path);
+            let mcs = std::fs::read(&save).expect("Failed to read save file");
+
+            match card.import_mcs(&mcs) {
+                Ok(block) => {
+                    save_card(&card, &path);
+                    println!("Imported {:?} at block {}", save, block);
+                }
+                Err(e) => {
+                    eprintln!("Failed to import save: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Delete { card: path, block } => {
+            let mut card = open_card(&path);
+
+            if let Err(e) = card.delete(block) {
+                eprintln!("Failed to delete save: {}", e);
+                std::process::exit(1);
+            }
+
+            save_card(&card, &path);
+            println!("Deleted save at block {}", block);
+        }
+        Command::Convert { input, output } => {
+            let card = open_card(&input);
+            save_card(&card, &output);
+            println!("Converted {:?} to {:?}", input, output);
+        }
+        Command::Icon {
+            card: path,
+            block,
+            output,
+        } => {
+            let card = open_card(&path);
+
+            let Some(save) = card.saves().into_iter().find(|save| save.first_block() == block) else {
+                eprintln!("Block {} does not start a save", block);
+                std::process::exit(1);
+            };
+
+            // Animation frames are placed next to each other
+            let frame_count = save.icon.frames.len();
+            let mut strip = image::RgbaImage::new((ICON_WIDTH * frame_count) as u32, ICON_HEIGHT as u32);
+            for frame in 0..frame_count {
+                let rgba = save.icon.to_rgba(frame);
+                for (i, pixel) in rgba.chunks_exact(4).enumerate() {
+                    let x = (frame * ICON_WIDTH + i % ICON_WIDTH) as u32;
+                    let y = (i / ICON_WIDTH) as u32;
+                    strip.put_pixel(x, y, image::Rgba(pixel.try_into().unwrap()));
+                }
+            }
+
+            strip.save(&output).expect("Failed to save icon");
+            println!("Saved {} icon frame(s) to {:?}", frame_count, output);
+        }
+        Command::Check { card: path } => {
+            let card = open_card(&path);
+            let errors = card.check_links();
+
+            for error in &errors {
+                println!("{}", error);
+            }
+
+            if errors.is_empty() {
+                println!("No broken links");
+            } else {
+                std::process::exit(1);
+            }
+        }
+        Command::Repair { card: path } => {
+            let mut card = open_card(&path);
+            let errors = card.repair_links();
+
+            for error in &errors {
+                println!("Fixed: {}", error);
+            }
+
+            if errors.is_empty() {
+                println!("No broken links");
+            } else {
+                save_card(&card, &path);
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate::directory::{BlockState, DirectoryEntry};
+use crate::{BLOCK_SIZE, Card, Error};
+use psx_core::sio::memcard::{MEMORY_CARD_SECTOR_SIZE as FRAME_SIZE, MemoryCard};
+
+pub const ICON_WIDTH: usize = 16;
+pub const ICON_HEIGHT: usize = 16;
+
+/// 16x16 4bpp icon with up to 3 animation frames
+#[derive(Debug, Clone)]
+pub struct Icon {
+    pub palette: [u16; 16],            // 15-bit BGR colors, 0000h is transparent
+    pub frames: Vec<[u8; FRAME_SIZE]>, // 4bpp bitmaps, low nibble is the left pixel
+}
+
+impl Icon {
+    /// PSX-SPX: "02h Icon Display Flag (11h..13h = 1..3 frames)", "60h-7Fh Icon 16 Color Palette Data"
+    fn parse(block: &[u8]) -> Self {
+        let frame_count = match block[0x02] {
+            0x11 => 1,
+            0x12 => 2,
+            0x13 => 3,
+            _ => 1,
+        };
+
+        let mut palette = [0u16; 16];
+        for (i, color) in palette.iter_mut().enumerate() {
+            *color = u16::from_le_bytes([block[0x60 + i * 2], block[0x61 + i * 2]]);
+        }
+
+        // Icon bitmaps follow the title frame
+        let frames = (1..=frame_count)
+            .map(|frame| block[frame * FRAME_SIZE..(frame + 1) * FRAME_SIZE].try_into().unwrap())
+            .collect();
+
+        Self { palette, frames }
+    }
+
+    /// RGBA8 pixels of one animation frame
+    pub fn to_rgba(&self, frame: usize) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(ICON_WIDTH * ICON_HEIGHT * 4);
+
+        for byte in self.frames[frame] {
+            for index in [byte & 0x0F, byte >> 4] {
+                let color = self.palette[index as usize];
+                let r = ((color & 0x1F) << 3) as u8;
+                let g = (((color >> 5) & 0x1F) << 3) as u8;
+                let b = (((color >> 10) & 0x1F) << 3) as u8;
+                let a = if color == 0 { 0 } else { 0xFF };
+                rgba.extend_from_slice(&[r, g, b, a]);
+            }
+        }
+
+        rgba
+    }
+}
+
+/// A save on the card, the blocks of its chain and the data of its title frame
+#[derive(Debug, Clone)]
+pub struct Save {
+    pub entry: DirectoryEntry,
+    pub blocks: Vec<usize>,
+    pub title: String,
+    pub icon: Icon,
+}
+
+impl Save {
+    pub fn from_card(card: &Card, entry: DirectoryEntry) -> Result<Self, Error> {
+        let blocks = card.chain(entry.block)?;
+        let block = card.block(entry.block)?;
+
+        Ok(Self {
+            title: decode_title(&block[0x04..0x44]),
+            icon: Icon::parse(block),
+            entry,
+            blocks,
+        })
+    }
+
+    pub fn first_block(&self) -> usize {
+        self.entry.block
+    }
+}
+
+/// PSX-SPX: "04h-43h Title in Shift-JIS format (64 bytes = max 32 characters)"
+pub fn decode_title(title: &[u8]) -> String {
+    let title = title.split(|&byte| byte == 0).next().unwrap_or_default();
+    let (title, _, _) = encoding_rs::SHIFT_JIS.decode(title);
+
+    // Most titles are written with full-width characters, fold those back to ASCII
+    title
+        .chars()
+        .map(|c| match c {
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFF01 + 0x21).unwrap(),
+            _ => c,
+        })
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+impl Card {
+    /// Export the save starting at `first_block` as `.mcs`: its directory frame followed by its blocks
+    pub fn export_mcs(&self, first_block: usize) -> Result<Vec<u8>, Error> {
+        let entry = self.entry(first_block)?;
+        if entry.state != BlockState::InUseFirst {
+            return Err(Error::InvalidBlock(first_block));
+        }
+
+        let blocks = self.chain(first_block)?;
+
+        let mut mcs = Vec::with_capacity(FRAME_SIZE + blocks.len() * BLOCK_SIZE);
+        mcs.extend_from_slice(self.directory_frame(first_block)?);
+
+        // The header describes a standalone save, so it does not point into this card
+        let mut header = entry.clone();
+        header.next = None;
+        header.write(&mut mcs[..FRAME_SIZE]);
+        MemoryCard::update_frame_checksum(&mut mcs[..FRAME_SIZE]);
+
+        for block in blocks {
+            mcs.extend_from_slice(self.block(block)?);
+        }
+
+        Ok(mcs)
+    }
+
+    /// Import a `.mcs` save into free blocks, returns the first block of the new save
+    pub fn import_mcs(&mut self, mcs: &[u8]) -> Result<usize, Error> {
+        if mcs.len() < FRAME_SIZE + BLOCK_SIZE || !(mcs.len() - FRAME_SIZE).is_multiple_of(BLOCK_SIZE) {
+            return Err(Error::InvalidImage(format!(
+                "{} bytes is not a directory frame followed by whole blocks",
+                mcs.len()
+            )));
+        }
+
+        let header = DirectoryEntry::parse(0, &mcs[..FRAME_SIZE]);
+        let data = &mcs[FRAME_SIZE..];
+        let block_count = data.len() / BLOCK_SIZE;
+
+        if self.saves().iter().any(|save| save.entry.filename == header.filename) {
+            return Err(Error::AlreadyExists(header.filename));
+        }
+
+        let free_blocks = self.free_blocks();
+        if free_blocks.len() < block_count {
+            return Err(Error::NotEnoughSpace {
+                needed: block_count,
+                free: free_blocks.len(),
+            });
+        }
+
+        let blocks = &free_blocks[..block_count];
+        for (i, &block) in blocks.iter().enumerate() {
+            let is_first = i == 0;
+            let is_last = i == block_count - 1;
+
+            let entry = DirectoryEntry {
+                block,
+                state: match (is_first, is_last) {
+                    (true, _) => BlockState::InUseFirst,
+                    (false, true) => BlockState::InUseLast,
+                    (false, false) => BlockState::InUseMiddle,
+                },
+                size: if is_first { (block_count * BLOCK_SIZE) as u32 } else { 0 },
+                next: blocks.get(i + 1).copied(),
+                filename: if is_first {
+                    header.filename.clone()
+                } else {
+                    String::new()
+                },
+            };
+
+            self.set_entry(&entry)?;
+            self.block_mut(block)?
+                .copy_from_slice(&data[i * BLOCK_SIZE..(i + 1) * BLOCK_SIZE]);
+        }
+
+        Ok(blocks[0])
+    }
+
+    /// Mark every block of a save as deleted, the data stays on the card
+    pub fn delete(&mut self, first_block: usize) -> Result<(), Error> {
+        if self.entry(first_block)?.state != BlockState::InUseFirst {
+            return Err(Error::InvalidBlock(first_block));
+        }
+
+        let blocks = self.chain(first_block)?;
+        for (i, &block) in blocks.iter().enumerate() {
+            let mut entry = self.entry(block)?;
+            entry.state = match i {
+                0 => BlockState::DeletedFirst,
+                _ if i == blocks.len() - 1 => BlockState::DeletedLast,
+                _ => BlockState::DeletedMiddle,
+            };
+            self.set_entry(&entry)?;
+        }
+
+        Ok(())
+    }
+}
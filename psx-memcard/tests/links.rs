@@ -0,0 +1,103 @@
+//! Checking and repairing the block links of the directory.
+
+use psx_core::sio::memcard::{MEMORY_CARD_SECTOR_SIZE as FRAME_SIZE, MemoryCard};
+use psx_memcard::directory::{BlockState, DirectoryEntry};
+use psx_memcard::links::LinkError;
+use psx_memcard::{BLOCK_SIZE, Card};
+
+/// Card with one save per entry of `sizes`, in blocks
+fn card_with_saves(sizes: &[usize]) -> Card {
+    let mut card = Card::new();
+
+    for (index, &blocks) in sizes.iter().enumerate() {
+        let mut mcs = vec![0; FRAME_SIZE];
+        DirectoryEntry {
+            block: 0,
+            state: BlockState::InUseFirst,
+            size: (blocks * BLOCK_SIZE) as u32,
+            next: None,
+            filename: format!("BASLUS-0000{}", index),
+        }
+        .write(&mut mcs);
+        MemoryCard::update_frame_checksum(&mut mcs);
+        mcs.resize(FRAME_SIZE + blocks * BLOCK_SIZE, 0);
+
+        card.import_mcs(&mcs).unwrap();
+    }
+
+    card
+}
+
+#[test]
+fn test_repair_invalid_pointer() {
+    let mut card = card_with_saves(&[3]);
+
+    let mut entry = card.entry(2).unwrap();
+    entry.next = Some(0x40);
+    card.set_entry(&entry).unwrap();
+
+    let errors = card.check_links();
+    assert!(errors.contains(&LinkError::InvalidPointer { block: 2, next: 0x40 }));
+
+    assert_eq!(card.repair_links(), errors);
+    assert!(card.check_links().is_empty());
+
+    // The save is cut at the broken link, the block it lost is free again
+    assert_eq!(card.chain(1).unwrap(), [1, 2]);
+    assert_eq!(card.entry(1).unwrap().size, 2 * BLOCK_SIZE as u32);
+    assert_eq!(card.entry(2).unwrap().state, BlockState::InUseLast);
+    assert_eq!(card.entry(3).unwrap().state, BlockState::Free);
+}
+
+#[test]
+fn test_repair_cross_link_and_orphan() {
+    let mut card = card_with_saves(&[2, 2]);
+
+    // The first save points into the second one, which leaves its own last block orphaned
+    let mut entry = card.entry(1).unwrap();
+    entry.next = Some(4);
+    card.set_entry(&entry).unwrap();
+
+    let errors = card.check_links();
+    assert!(errors.contains(&LinkError::Orphan {
+        block: 2,
+        state: BlockState::InUseLast
+    }));
+
+    card.repair_links();
+    assert!(card.check_links().is_empty());
+    assert_eq!(card.entry(2).unwrap().state, BlockState::Free);
+    assert_eq!(card.saves().len(), 2);
+}
+
+#[test]
+fn test_repair_keeps_deleted_saves() {
+    let mut card = card_with_saves(&[2, 2]);
+    card.delete(3).unwrap();
+
+    let mut entry = card.entry(2).unwrap();
+    entry.state = BlockState::InUseMiddle;
+    card.set_entry(&entry).unwrap();
+
+    assert!(
+        card.check_links()
+            .contains(&LinkError::MissingEnd { first: 1, block: 2 })
+    );
+
+    card.repair_links();
+    assert!(card.check_links().is_empty());
+    assert_eq!(card.entry(2).unwrap().state, BlockState::InUseLast);
+    assert_eq!(card.entry(3).unwrap().state, BlockState::DeletedFirst);
+    assert_eq!(card.entry(3).unwrap().next, Some(4));
+}
+
+#[test]
+fn test_bad_checksum() {
+    let mut card = card_with_saves(&[1]);
+    card.block_mut(0).unwrap()[FRAME_SIZE + 0x20] ^= 0xFF;
+
+    assert_eq!(card.check_links(), [LinkError::BadChecksum { block: 1 }]);
+
+    card.repair_links();
+    assert!(card.check_links().is_empty());
+}
@@ -0,0 +1,107 @@
+//! Exporting and importing single saves as `.mcs`.
+
+use psx_core::sio::memcard::{MEMORY_CARD_SECTOR_SIZE as FRAME_SIZE, MemoryCard};
+use psx_memcard::directory::{BlockState, DirectoryEntry};
+use psx_memcard::{BLOCK_SIZE, Card, Error};
+
+/// `.mcs` of a save named `filename` spanning `blocks` blocks, every block filled with its index
+fn build_mcs(filename: &str, blocks: usize) -> Vec<u8> {
+    let mut mcs = vec![0; FRAME_SIZE];
+    let header = DirectoryEntry {
+        block: 0,
+        state: BlockState::InUseFirst,
+        size: (blocks * BLOCK_SIZE) as u32,
+        next: None,
+        filename: filename.to_string(),
+    };
+    header.write(&mut mcs);
+    MemoryCard::update_frame_checksum(&mut mcs);
+
+    for block in 0..blocks {
+        mcs.extend(std::iter::repeat_n(block as u8 + 1, BLOCK_SIZE));
+    }
+
+    mcs
+}
+
+#[test]
+fn test_import_export_round_trip() {
+    let mcs = build_mcs("BESLES-12345SAVE", 3);
+    let mut card = Card::new();
+
+    let first = card.import_mcs(&mcs).unwrap();
+    assert_eq!(first, 1);
+    assert_eq!(card.chain(first).unwrap(), [1, 2, 3]);
+    assert_eq!(card.entry(3).unwrap().state, BlockState::InUseLast);
+    assert!(card.check_links().is_empty());
+
+    let saves = card.saves();
+    assert_eq!(saves.len(), 1);
+    assert_eq!(saves[0].entry.filename, "BESLES-12345SAVE");
+    assert_eq!(saves[0].entry.size, 3 * BLOCK_SIZE as u32);
+
+    assert_eq!(card.export_mcs(first).unwrap(), mcs);
+}
+
+#[test]
+fn test_import_fills_free_blocks() {
+    let mut card = Card::new();
+    let first = card.import_mcs(&build_mcs("BASLUS-00001A", 2)).unwrap();
+    let second = card.import_mcs(&build_mcs("BASLUS-00002B", 2)).unwrap();
+    card.delete(first).unwrap();
+
+    // The deleted save's blocks are reused, the rest goes after the second save
+    let third = card.import_mcs(&build_mcs("BASLUS-00003C", 3)).unwrap();
+    assert_eq!(card.chain(second).unwrap(), [3, 4]);
+    assert_eq!(card.chain(third).unwrap(), [1, 2, 5]);
+    assert!(card.check_links().is_empty());
+
+    let exported = card.export_mcs(third).unwrap();
+    assert_eq!(exported, build_mcs("BASLUS-00003C", 3));
+}
+
+#[test]
+fn test_import_rejects() {
+    let mut card = Card::new();
+    card.import_mcs(&build_mcs("BASLUS-00001A", 10)).unwrap();
+
+    assert!(matches!(
+        card.import_mcs(&build_mcs("BASLUS-00001A", 1)),
+        Err(Error::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        card.import_mcs(&build_mcs("BASLUS-00002B", 6)),
+        Err(Error::NotEnoughSpace { needed: 6, free: 5 })
+    ));
+    assert!(matches!(
+        card.import_mcs(&build_mcs("BASLUS-00003C", 1)[..FRAME_SIZE + 100]),
+        Err(Error::InvalidImage(_))
+    ));
+
+    assert!(matches!(card.export_mcs(2), Err(Error::InvalidBlock(2))));
+}
+
+#[test]
+fn test_out_of_range_blocks() {
+    let mut card = Card::new();
+    card.import_mcs(&build_mcs("BASLUS-00001A", 1)).unwrap();
+
+    // Block 0 is the directory, 16 is past the end of the card
+    for block in [0, 16, usize::MAX] {
+        assert!(matches!(card.export_mcs(block), Err(Error::InvalidBlock(b)) if b == block));
+        assert!(matches!(card.delete(block), Err(Error::InvalidBlock(b)) if b == block));
+        assert!(matches!(card.entry(block), Err(Error::InvalidBlock(_))));
+        assert!(matches!(card.directory_frame(block), Err(Error::InvalidBlock(_))));
+        assert!(matches!(card.chain(block), Err(Error::InvalidBlock(_))));
+    }
+
+    assert!(card.block(0).is_ok());
+    assert!(card.block(15).is_ok());
+    assert!(matches!(card.block(16), Err(Error::InvalidBlock(16))));
+    assert!(matches!(card.block_mut(16), Err(Error::InvalidBlock(16))));
+
+    let mut entry = card.entry(1).unwrap();
+    entry.block = 16;
+    assert!(matches!(card.set_entry(&entry), Err(Error::InvalidBlock(16))));
+    assert_eq!(card.saves().len(), 1);
+}
@@ -235,14 +235,14 @@ fn main() {
                     if !buttons_pressed {
                         buttons_pressed = true;
                         
-                        psx.set_controller_state(ControllerState {
+                        psx.set_controller_state(0, ControllerState {
                             circle: true,
                             start: true,
                             ..Default::default()
                         });
                     } else {
                         buttons_pressed = false;
-                        psx.set_controller_state(ControllerState::default());
+                        psx.set_controller_state(0, ControllerState::default());
                     }
                 }
             }